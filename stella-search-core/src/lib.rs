@@ -5,15 +5,107 @@
 
 use serde::{Deserialize, Serialize};
 
+mod top_k;
+
+pub use top_k::{ResultSort, TopK};
+
 /// Indexed file record
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IndexedFile {
     pub id: i64,
+    /// Path as it exists on disk, original case. Backends may match against
+    /// normalized or case-folded keys, but never return them here.
     pub path: String,
+    /// Final path component as it exists on disk (same guarantee as `path`)
     pub name: String,
     pub extension: Option<String>,
     pub size: i64,
     pub is_directory: bool,
+    /// Last modification time (Unix seconds, 0 if unknown)
+    #[serde(default)]
+    pub modified: i64,
+    /// Creation time (Unix seconds). None where the filesystem doesn't record it,
+    /// and for directories indexed by a directory walk
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created: Option<i64>,
+    /// Original path when `path` was made relative by `strip_path_prefix`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub full_path: Option<String>,
+    /// Where a symbolic link points, when the search asked for symlink resolution
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    /// Whether the entry was still on disk when the search asked to verify results;
+    /// None when not checked (not asked, or past the verification time budget)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exists: Option<bool>,
+    /// Drive (`C:`), network share (`\\server\share`) or mount point (`/mnt/data`)
+    /// holding the entry, when the search asked for it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub volume: Option<String>,
+}
+
+/// Default cap on the estimated serialized size of one result set (64 MiB)
+pub const DEFAULT_MAX_RESULT_BYTES: usize = 64 * 1024 * 1024;
+
+impl IndexedFile {
+    /// Approximate serialized JSON size in bytes, used to cap result memory
+    pub fn estimated_size(&self) -> usize {
+        // Field names, punctuation and numbers take about 100 bytes
+        100 + self.path.len()
+            + self.name.len()
+            + self.extension.as_ref().map_or(0, String::len)
+            + self.full_path.as_ref().map_or(0, String::len)
+            + self.target.as_ref().map_or(0, String::len)
+            + self.volume.as_ref().map_or(0, String::len)
+    }
+
+    /// Make `path` relative to `prefix` if it lies under it, keeping the original in
+    /// `full_path`. Paths outside the prefix (or equal to it) are left unchanged.
+    pub fn strip_path_prefix(&mut self, prefix: &str) {
+        if let Some(relative) = relative_path(&self.path, prefix) {
+            let relative = relative.to_string();
+            self.full_path = Some(std::mem::replace(&mut self.path, relative));
+        }
+    }
+}
+
+/// Remainder of `path` after the directory `prefix`, or None if it isn't under it.
+/// `/` and `\` match each other, the prefix only matches whole components, and on
+/// Windows the comparison ignores ASCII case.
+pub fn relative_path<'a>(path: &'a str, prefix: &str) -> Option<&'a str> {
+    let is_sep = |c: char| c == '/' || c == '\\';
+    let prefix = prefix.trim_end_matches(is_sep);
+    let (head, rest) = path.split_at_checked(prefix.len())?;
+
+    let same = |(a, b): (char, char)| {
+        a == b || (is_sep(a) && is_sep(b)) || (cfg!(windows) && a.eq_ignore_ascii_case(&b))
+    };
+    if !head.chars().zip(prefix.chars()).all(same) {
+        return None;
+    }
+
+    let rest = rest.strip_prefix(is_sep)?;
+    (!rest.is_empty()).then_some(rest)
+}
+
+/// Collect files until their estimated total size would pass `max_bytes`.
+/// Returns the collected files and whether any were left out.
+pub fn collect_capped(
+    files: impl IntoIterator<Item = IndexedFile>,
+    max_bytes: usize,
+) -> (Vec<IndexedFile>, bool) {
+    let mut collected = Vec::new();
+    let mut total = 0usize;
+
+    for file in files {
+        total += file.estimated_size();
+        if total > max_bytes {
+            return (collected, true);
+        }
+        collected.push(file);
+    }
+
+    (collected, false)
 }
 
 /// Search results returned by both native library and daemon
@@ -22,6 +114,9 @@ pub struct SearchResults {
     pub files: Vec<IndexedFile>,
     pub total_found: usize,
     pub query_time_ms: u64,
+    /// Collection stopped at the result size cap, before `max_results` was reached
+    #[serde(default)]
+    pub truncated: bool,
 }
 
 /// Index statistics (used by daemon only, but shared for IPC)
@@ -30,9 +125,20 @@ pub struct IndexStats {
     pub indexed_files: u64,
     pub indexed_dirs: u64,
     pub database_size_bytes: u64,
+    /// Combined size of the indexed files (directories excluded)
+    #[serde(default)]
+    pub total_size_bytes: u64,
     pub is_scanning: bool,
     pub scan_progress: f64,
     pub current_scan_path: Option<String>,
+    /// Indexing is paused because the database volume is below the free-space minimum
+    pub low_disk_space: bool,
+    /// When the last full scan completed (Unix seconds); None if none has
+    #[serde(default)]
+    pub last_full_scan: Option<i64>,
+    /// Why the last full scan looks incomplete (far fewer entries than expected)
+    #[serde(default)]
+    pub index_warning: Option<String>,
 }
 
 /// Search error types
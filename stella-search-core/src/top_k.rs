@@ -0,0 +1,169 @@
+//! Bounded top-K selection for locally sorted results
+//!
+//! Backends that can only stream matches in their own order (Windows Search streams
+//! by rank) use this to re-sort by another key while holding at most K rows.
+
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::IndexedFile;
+
+/// Order in which results are returned
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResultSort {
+    /// Backend order (relevance rank from the index)
+    #[default]
+    Rank,
+    /// Largest files first
+    Size,
+    /// Case-insensitive name order
+    Name,
+    /// Most recently created first; files without a creation time last
+    Created,
+}
+
+impl ResultSort {
+    /// Parse a sort name: `rank`, `size`, `name` or `created`
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "rank" | "" => Some(ResultSort::Rank),
+            "size" => Some(ResultSort::Size),
+            "name" => Some(ResultSort::Name),
+            "created" => Some(ResultSort::Created),
+            _ => None,
+        }
+    }
+
+    fn key(self, file: &IndexedFile) -> SortKey {
+        match self {
+            ResultSort::Rank => SortKey::Rank,
+            ResultSort::Size => SortKey::Size(Reverse(file.size)),
+            ResultSort::Name => SortKey::Name(file.name.to_lowercase()),
+            // Reversed, None (smallest) sorts last
+            ResultSort::Created => SortKey::Created(Reverse(file.created)),
+        }
+    }
+}
+
+/// Smaller keys sort first; only one variant is used per collector
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum SortKey {
+    Rank,
+    Size(Reverse<i64>),
+    Name(String),
+    Created(Reverse<Option<i64>>),
+}
+
+/// Heap entry: ties keep arrival order, so equal keys stay in backend order
+struct Entry {
+    key: SortKey,
+    seq: u64,
+    file: IndexedFile,
+}
+
+impl Entry {
+    fn order(&self) -> (&SortKey, u64) {
+        (&self.key, self.seq)
+    }
+}
+
+impl PartialEq for Entry {
+    fn eq(&self, other: &Self) -> bool {
+        self.order() == other.order()
+    }
+}
+
+impl Eq for Entry {}
+
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Entry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.order().cmp(&other.order())
+    }
+}
+
+/// Keeps the first `limit` files under a sort order out of any number pushed.
+///
+/// The heap's top is the worst row kept, so each push costs O(log K) and memory
+/// never holds more than K rows (fewer if their estimated size passes `max_bytes`).
+pub struct TopK {
+    sort: ResultSort,
+    limit: usize,
+    max_bytes: usize,
+    bytes: usize,
+    seq: u64,
+    heap: BinaryHeap<Entry>,
+    truncated: bool,
+}
+
+impl TopK {
+    pub fn new(sort: ResultSort, limit: usize, max_bytes: usize) -> Self {
+        Self {
+            sort,
+            limit,
+            max_bytes,
+            bytes: 0,
+            seq: 0,
+            heap: BinaryHeap::with_capacity(limit.min(4096)),
+            truncated: false,
+        }
+    }
+
+    /// Offer a file, dropping the worst kept row if over the limit
+    pub fn push(&mut self, file: IndexedFile) {
+        if self.limit == 0 {
+            return;
+        }
+
+        let entry = Entry {
+            key: self.sort.key(&file),
+            seq: self.seq,
+            file,
+        };
+        self.seq += 1;
+
+        // Full and no better than the worst kept row: skip without touching the heap
+        if self.heap.len() >= self.limit
+            && let Some(worst) = self.heap.peek()
+            && entry >= *worst
+        {
+            return;
+        }
+
+        self.bytes += entry.file.estimated_size();
+        self.heap.push(entry);
+
+        if self.heap.len() > self.limit {
+            self.pop_worst();
+        }
+        while self.bytes > self.max_bytes && !self.heap.is_empty() {
+            self.pop_worst();
+            self.truncated = true;
+        }
+    }
+
+    fn pop_worst(&mut self) {
+        if let Some(worst) = self.heap.pop() {
+            self.bytes -= worst.file.estimated_size();
+        }
+    }
+
+    /// Kept files in sort order, and whether the size cap dropped any
+    pub fn into_sorted(self) -> (Vec<IndexedFile>, bool) {
+        let files = self
+            .heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|entry| entry.file)
+            .collect();
+        (files, self.truncated)
+    }
+}
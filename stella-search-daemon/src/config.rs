@@ -5,10 +5,14 @@
 use anyhow::{Context, Result};
 use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::PathBuf;
 use std::sync::{Arc, RwLock};
-use tracing::info;
+use tracing::{info, warn};
+use crate::search::expansion::{self, QueryExpansion};
+use unicode_normalization::{is_nfc_quick, is_nfkc_quick, IsNormalized, UnicodeNormalization as _};
 
 /// Main configuration structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -46,36 +50,253 @@ pub struct IndexingConfig {
     /// Mode: "everything" or "selected"
     #[serde(default = "default_mode")]
     pub mode: String,
+
+    /// Scan the watch paths when the daemon starts with an empty index. When false,
+    /// only files created or changed while the watcher runs (or reindexed on request)
+    /// get indexed, so searches miss untouched files until a reindex.
+    #[serde(default = "default_true")]
+    pub initial_scan: bool,
+
+    /// Store each file's owner (uid on Unix, SID on Windows) and permission bits, for
+    /// the `owner` and `world_writable` search filters. Costs an extra lookup per file
+    /// on Windows and a stat per directory everywhere; reindex after turning it on.
+    #[serde(default)]
+    pub track_ownership: bool,
 }
 
 /// Search backend configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchConfig {
-    /// Search backend: "auto", "windows", or "sqlite"
+    /// Search backend: "auto", "windows", "sqlite", "everything", or the name of a
+    /// registered backend
     /// - "auto" = Windows Search if available, else SQLite (default)
     /// - "windows" = Force Windows Search (falls back if unavailable)
     /// - "sqlite" = Force custom SQLite indexer
+    /// - "everything" = A running Everything instance (Windows only, falls back to SQLite)
+    /// - "spotlight" = The Spotlight index via mdfind (macOS only, the default there)
+    /// - any other name = that backend from the registry, falling back to SQLite
     #[serde(default)]
     pub backend: SearchBackendType,
+
+    /// Unicode form that file names and queries are normalized to before matching.
+    /// Changing it only affects newly indexed names; run a reindex afterwards.
+    #[serde(default)]
+    pub unicode_normalization: UnicodeNormalization,
+
+    /// How often the daemon re-checks primary backend availability and switches
+    /// between primary and fallback (seconds, 0 = only on `backend --refresh`)
+    #[serde(default = "default_reprobe_interval")]
+    pub reprobe_interval_secs: u64,
+
+    /// Time a search may spend checking that its results still exist when asked
+    /// to (ms, 0 = no limit). Results past the budget are returned unchecked
+    #[serde(default = "default_verify_budget")]
+    pub verify_budget_ms: u64,
+
+    /// Boundaries of the size classes behind `--size`
+    #[serde(default)]
+    pub size_categories: SizeCategoryBounds,
+
+    /// Named extension sets a search can filter by (`--group documents`). Setting
+    /// this section replaces the default groups rather than adding to them.
+    #[serde(default = "default_extension_groups")]
+    pub extension_groups: HashMap<String, Vec<String>>,
+
+    /// Also match the extensions and names in `query_expansions` when a query equals
+    /// one of its keys ("doc" finds .docx files). Off by default: it adds matches the
+    /// query text doesn't contain.
+    #[serde(default)]
+    pub expand_queries: bool,
+
+    /// Abbreviations for `expand_queries`: key -> extensions (".docx") and name
+    /// substrings. Setting this section replaces the defaults.
+    #[serde(default = "default_query_expansions")]
+    pub query_expansions: HashMap<String, Vec<String>>,
+
+    /// Everything SDK DLL for the "everything" backend (default: Everything64.dll
+    /// next to the daemon or in the system directories)
+    #[serde(default)]
+    pub everything_dll: Option<PathBuf>,
+
+    /// When the primary backend isn't SQLite (Everything, Spotlight, a registered
+    /// backend), also search the local SQLite index and merge the results. Off by
+    /// default: every search then runs twice, and the local index is kept up even
+    /// where the primary would let the daemon skip indexing.
+    #[serde(default)]
+    pub union_with_local: bool,
+}
+
+/// Upper bounds of the size classes; each class starts where the previous one ends
+/// and "huge" is everything from `large_below_mb` up
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SizeCategoryBounds {
+    /// "tiny" files are smaller than this (KB)
+    #[serde(default = "default_tiny_below_kb")]
+    pub tiny_below_kb: u64,
+
+    /// "small" files are smaller than this (MB)
+    #[serde(default = "default_small_below_mb")]
+    pub small_below_mb: u64,
+
+    /// "medium" files are smaller than this (MB)
+    #[serde(default = "default_medium_below_mb")]
+    pub medium_below_mb: u64,
+
+    /// "large" files are smaller than this (MB)
+    #[serde(default = "default_large_below_mb")]
+    pub large_below_mb: u64,
+}
+
+/// Unicode normalization form for names and queries
+///
+/// The same accented name can be stored composed ("é" as U+00E9, NFC: Windows, most
+/// Linux input) or decomposed ("e" + U+0301, NFD: macOS HFS+, files copied from
+/// macOS). The two look identical but don't match byte-for-byte, so both sides
+/// are folded to one form.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum UnicodeNormalization {
+    /// Canonical composition: NFD and NFC spellings match
+    #[default]
+    Nfc,
+    /// Compatibility composition: additionally folds ligatures, full-width and
+    /// superscript forms ("ﬁle" matches "file")
+    Nfkc,
+    /// Match names exactly as stored
+    None,
+}
+
+impl UnicodeNormalization {
+    /// Normalize text to this form (borrows when it's already normalized)
+    pub fn normalize<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        match self {
+            Self::Nfc if is_nfc_quick(text.chars()) != IsNormalized::Yes => {
+                Cow::Owned(text.nfc().collect())
+            }
+            Self::Nfkc if is_nfkc_quick(text.chars()) != IsNormalized::Yes => {
+                Cow::Owned(text.nfkc().collect())
+            }
+            _ => Cow::Borrowed(text),
+        }
+    }
+}
+
+impl SearchConfig {
+    /// Extensions of a group, lowercased with a leading dot (".pdf").
+    /// Group names are matched case-insensitively.
+    pub fn extension_group(&self, name: &str) -> Result<Vec<String>> {
+        let Some((_, extensions)) = self
+            .extension_groups
+            .iter()
+            .find(|(group, _)| group.eq_ignore_ascii_case(name.trim()))
+        else {
+            let mut names: Vec<&str> = self.extension_groups.keys().map(String::as_str).collect();
+            names.sort_unstable();
+            anyhow::bail!("Unknown extension group '{}' (groups: {})", name, names.join(", "));
+        };
+
+        Ok(extensions
+            .iter()
+            .map(|ext| ext.trim().trim_start_matches('.').to_lowercase())
+            .filter(|ext| !ext.is_empty())
+            .map(|ext| format!(".{}", ext))
+            .collect())
+    }
+
+    /// Expansion of a query's abbreviation, if `expand_queries` is on and it has one
+    pub fn query_expansion(&self, query: &str) -> Option<QueryExpansion> {
+        if !self.expand_queries {
+            return None;
+        }
+        expansion::expand(query, &self.query_expansions)
+    }
+
+    /// Reject group names that couldn't be typed as `--group <name>` or that clash
+    /// ignoring case, and groups without extensions
+    fn validate_extension_groups(&self) -> Result<()> {
+        for (name, extensions) in &self.extension_groups {
+            if name.is_empty()
+                || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            {
+                anyhow::bail!(
+                    "Invalid extension group name '{}': use letters, digits, '-' and '_'",
+                    name
+                );
+            }
+            if self.extension_groups.keys().filter(|other| other.eq_ignore_ascii_case(name)).count() > 1 {
+                anyhow::bail!("Extension group names differ only in case: '{}'", name);
+            }
+            if extensions.iter().all(|ext| ext.trim().trim_start_matches('.').is_empty()) {
+                anyhow::bail!("Extension group '{}' has no extensions", name);
+            }
+        }
+        Ok(())
+    }
 }
 
 /// Search backend type
 #[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum SearchBackendType {
-    /// Auto-detect: Windows Search if available, else SQLite
+    /// Auto-detect: Spotlight on macOS, Windows Search if available, else SQLite
     #[default]
     Auto,
     /// Force Windows Search (on Windows only)
     Windows,
     /// Force SQLite-based search
     Sqlite,
+    /// Search a running Everything (voidtools) instance (Windows only)
+    Everything,
+    /// Search the Spotlight index with mdfind (macOS only)
+    Spotlight,
+    /// A backend registered under this name (see `search::registry`)
+    #[serde(untagged)]
+    Custom(String),
+}
+
+impl SearchBackendType {
+    /// Name to look up in the backend registry; None where the SQLite index
+    /// is the backend
+    pub fn registry_name(&self) -> Option<&str> {
+        match self {
+            SearchBackendType::Auto if cfg!(target_os = "macos") => Some("spotlight"),
+            SearchBackendType::Auto | SearchBackendType::Sqlite => None,
+            SearchBackendType::Windows => Some("windows"),
+            SearchBackendType::Everything => Some("everything"),
+            SearchBackendType::Spotlight => Some("spotlight"),
+            SearchBackendType::Custom(name) if name.trim().eq_ignore_ascii_case("auto") => {
+                cfg!(target_os = "macos").then_some("spotlight")
+            }
+            SearchBackendType::Custom(name) if name.trim().eq_ignore_ascii_case("sqlite") => None,
+            SearchBackendType::Custom(name) => Some(name),
+        }
+    }
 }
 
 impl Default for SearchConfig {
     fn default() -> Self {
         Self {
             backend: SearchBackendType::default(),
+            unicode_normalization: UnicodeNormalization::default(),
+            reprobe_interval_secs: default_reprobe_interval(),
+            verify_budget_ms: default_verify_budget(),
+            size_categories: SizeCategoryBounds::default(),
+            extension_groups: default_extension_groups(),
+            expand_queries: false,
+            query_expansions: default_query_expansions(),
+            everything_dll: None,
+            union_with_local: false,
+        }
+    }
+}
+
+impl Default for SizeCategoryBounds {
+    fn default() -> Self {
+        Self {
+            tiny_below_kb: default_tiny_below_kb(),
+            small_below_mb: default_small_below_mb(),
+            medium_below_mb: default_medium_below_mb(),
+            large_below_mb: default_large_below_mb(),
         }
     }
 }
@@ -95,13 +316,124 @@ pub struct WatchConfig {
     #[serde(default = "default_exclude_patterns")]
     pub exclude_patterns: Vec<String>,
 
-    /// File extensions to exclude
+    /// Built-in exclude paths or patterns to drop (e.g. "/var/log"), so one default can
+    /// be lifted without re-listing the rest. Applied when the config is loaded
+    #[serde(default)]
+    pub remove_default_excludes: Vec<String>,
+
+    /// File extensions to exclude (".log" or "log", any case). Directories are never
+    /// excluded by extension
     #[serde(default)]
     pub exclude_extensions: Vec<String>,
 
+    /// Only index files with one of these extensions (same form); empty = any.
+    /// Applies to live updates as well as scans
+    #[serde(default)]
+    pub include_extensions: Vec<String>,
+
+    /// Exact file or directory names to exclude, matched case-sensitively (e.g. "desktop.ini")
+    #[serde(default = "default_exclude_names")]
+    pub exclude_names: HashSet<String>,
+
     /// Include hidden files/directories
     #[serde(default)]
     pub include_hidden: bool,
+
+    /// Index and descend into symlinks, junctions and volume mount points.
+    /// Off by default: their targets are usually indexed under their own path already.
+    #[serde(default)]
+    pub follow_reparse_points: bool,
+
+    /// Directories scanned before everything else, in this order. They may lie inside a
+    /// watch path (then scanned ahead of the rest of it) or hold watch paths; others are
+    /// ignored
+    #[serde(default)]
+    pub scan_priority_paths: Vec<String>,
+
+    /// Scan the users' home directories (after `scan_priority_paths`) before other
+    /// directories and drives
+    #[serde(default = "default_true")]
+    pub scan_home_first: bool,
+}
+
+impl WatchConfig {
+    /// Drop the `remove_default_excludes` entries from `exclude` and `exclude_patterns`.
+    /// Only built-in defaults are dropped, compared with `\\` read as `/` and without a
+    /// trailing slash (ignoring case on Windows). Other entries are logged and ignored.
+    fn apply_remove_default_excludes(&mut self) {
+        let normalize = |entry: &str| {
+            let entry = entry.replace('\\', "/");
+            let entry = entry.trim_end_matches('/');
+            if cfg!(windows) {
+                entry.to_lowercase()
+            } else {
+                entry.to_string()
+            }
+        };
+
+        let defaults: HashSet<String> = default_exclude_paths()
+            .iter()
+            .chain(&default_exclude_patterns())
+            .map(|entry| normalize(entry))
+            .collect();
+        let mut removed = HashSet::new();
+        for entry in &self.remove_default_excludes {
+            let entry = normalize(entry);
+            if defaults.contains(&entry) {
+                removed.insert(entry);
+            } else {
+                warn!("remove_default_excludes: {:?} is not a default exclude, ignoring it", entry);
+            }
+        }
+
+        if !removed.is_empty() {
+            self.exclude.retain(|entry| !removed.contains(&normalize(entry)));
+            self.exclude_patterns.retain(|entry| !removed.contains(&normalize(entry)));
+        }
+    }
+}
+
+/// `watch.include_extensions` and `watch.exclude_extensions`, ready for lookups
+/// (lowercase, no leading dot). The scanner, the MFT scanner and the watcher all
+/// check files against it, so a file a scan leaves out isn't added by a later
+/// create event either.
+#[derive(Debug, Clone, Default)]
+pub struct ExtensionFilter {
+    /// Empty = any extension
+    include: HashSet<String>,
+    exclude: HashSet<String>,
+}
+
+impl ExtensionFilter {
+    pub fn new(watch: &WatchConfig) -> Self {
+        let normalize = |extensions: &[String]| {
+            extensions
+                .iter()
+                .map(|ext| ext.trim().trim_start_matches('.').to_lowercase())
+                .filter(|ext| !ext.is_empty())
+                .collect()
+        };
+        Self {
+            include: normalize(&watch.include_extensions),
+            exclude: normalize(&watch.exclude_extensions),
+        }
+    }
+
+    /// Whether a file with this name (or path) is indexed. A file without an extension
+    /// only passes when there is no include list. Don't call it for directories.
+    pub fn allows_file(&self, name: &str) -> bool {
+        if self.include.is_empty() && self.exclude.is_empty() {
+            return true;
+        }
+
+        match std::path::Path::new(name).extension() {
+            Some(ext) => {
+                let ext = ext.to_string_lossy().to_lowercase();
+                !self.exclude.contains(&ext) && (self.include.is_empty() || self.include.contains(&ext))
+            }
+            None => self.include.is_empty(),
+        }
+    }
 }
 
 /// File watcher configuration
@@ -118,6 +450,16 @@ pub struct WatcherConfig {
     /// Auto-watch new drives/mount points
     #[serde(default = "default_true")]
     pub auto_watch_new_drives: bool,
+
+    /// Re-stat newly created files once they've gone this long without a write,
+    /// so files written over time get their final size (0 = disabled)
+    #[serde(default = "default_settle_ms")]
+    pub settle_ms: u64,
+
+    /// Most events waiting to be processed. Past this, events are dropped and the
+    /// affected watch paths get a reconciliation scan once the burst is over.
+    #[serde(default = "default_max_queued_events")]
+    pub max_queued_events: usize,
 }
 
 /// Service configuration
@@ -127,9 +469,53 @@ pub struct ServiceConfig {
     #[serde(default = "default_log_level")]
     pub log_level: String,
 
+    /// Log output format
+    #[serde(default)]
+    pub log_format: LogFormat,
+
     /// Custom socket path (optional)
     #[serde(default)]
     pub socket_path: Option<String>,
+
+    /// Custom database path (optional, defaults to the platform data directory)
+    #[serde(default)]
+    pub db_path: Option<String>,
+
+    /// When the database is missing but one exists at an older default location,
+    /// move it over instead of starting with an empty index
+    #[serde(default = "default_true")]
+    pub migrate_legacy_db: bool,
+
+    /// For a shared service indexing several users: identify each client from the
+    /// socket/pipe peer and limit its searches to its own home directory
+    #[serde(default)]
+    pub per_user_scope: bool,
+
+    /// Close IPC connections that send no complete request within this many seconds
+    /// (0 = wait forever). A client that connects and stalls would otherwise hold one
+    /// of the daemon's limited connection slots for good.
+    #[serde(default = "default_connection_idle")]
+    pub connection_idle_secs: u64,
+
+    /// Remember recent search queries for `stella-search history` (in memory only,
+    /// never written to disk). Off by default for privacy
+    #[serde(default)]
+    pub record_query_history: bool,
+
+    /// Most distinct queries kept in the history
+    #[serde(default = "default_query_history_size")]
+    pub query_history_size: usize,
+}
+
+/// Log output format
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Compact human-readable lines
+    #[default]
+    Text,
+    /// One JSON object per line, for log aggregators
+    Json,
 }
 
 /// Performance configuration
@@ -142,6 +528,88 @@ pub struct PerformanceConfig {
     /// Memory limit for batch operations (MB)
     #[serde(default = "default_memory_limit")]
     pub memory_limit_mb: usize,
+
+    /// Minimum free space (MB) on the database volume before indexing pauses (0 = disabled)
+    #[serde(default = "default_min_free_disk")]
+    pub min_free_disk_mb: u64,
+
+    /// Index file names by trigram so substring searches skip the full table scan.
+    /// Grows the database roughly 3-5x; changing it rebuilds or drops the index on startup.
+    #[serde(default)]
+    pub trigram_index: bool,
+
+    /// Read the name index into cache at startup, so the first search isn't slowed
+    /// by cold disk reads (`stella-search warmup` does the same on demand)
+    #[serde(default)]
+    pub warmup_on_start: bool,
+
+    /// Maximum number of searches running at once (0 = unlimited)
+    #[serde(default = "default_max_concurrent_searches")]
+    pub max_concurrent_searches: usize,
+
+    /// How long a search waits for a free slot before failing as busy (0 = fail immediately)
+    #[serde(default = "default_search_queue_timeout")]
+    pub search_queue_timeout_ms: u64,
+
+    /// Cap on the estimated size of one result set (MB); results past it are cut off
+    /// and marked truncated, however large `max_results` is
+    #[serde(default = "default_max_result_mb")]
+    pub max_result_mb: usize,
+
+    /// Minimum time between scan progress/path updates (ms, 0 = every batch).
+    /// Keeps fast scans from rewriting the status path thousands of times per second.
+    #[serde(default = "default_progress_interval")]
+    pub progress_interval_ms: u64,
+
+    /// How often to refresh query planner statistics (`PRAGMA optimize`) while the
+    /// index keeps changing (seconds, 0 = only after scans). Skipped during scans and
+    /// when nothing was written since the last run.
+    #[serde(default = "default_optimize_interval")]
+    pub optimize_interval_secs: u64,
+
+    /// Drive type used to schedule scans: "auto" detects it per disk
+    #[serde(default)]
+    pub disk_type: DiskType,
+
+    /// Watch paths scanned at once on one solid-state disk
+    #[serde(default = "default_ssd_scan_concurrency")]
+    pub ssd_scan_concurrency: usize,
+
+    /// Watch paths scanned at once on one spinning (or undetected) disk.
+    /// Concurrent walks make an HDD seek back and forth, so this defaults to 1.
+    #[serde(default = "default_hdd_scan_concurrency")]
+    pub hdd_scan_concurrency: usize,
+}
+
+/// Drive type assumed when scheduling scans
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DiskType {
+    /// Detect per disk (seek penalty on Windows, `queue/rotational` on Linux)
+    #[default]
+    Auto,
+    /// Treat every disk as solid-state
+    Ssd,
+    /// Treat every disk as spinning
+    Hdd,
+}
+
+impl PerformanceConfig {
+    /// How many watch paths on one disk may be scanned at once. Disks of unknown
+    /// type count as spinning, which keeps their scan sequential.
+    pub fn scan_concurrency(&self, rotational: Option<bool>) -> usize {
+        let solid_state = match self.disk_type {
+            DiskType::Auto => rotational == Some(false),
+            DiskType::Ssd => true,
+            DiskType::Hdd => false,
+        };
+        let limit = if solid_state {
+            self.ssd_scan_concurrency
+        } else {
+            self.hdd_scan_concurrency
+        };
+        limit.max(1)
+    }
 }
 
 // Default value functions
@@ -157,6 +625,14 @@ fn default_batch_size() -> usize {
     100
 }
 
+fn default_settle_ms() -> u64 {
+    2000
+}
+
+fn default_max_queued_events() -> usize {
+    100_000
+}
+
 fn default_true() -> bool {
     true
 }
@@ -169,6 +645,96 @@ fn default_memory_limit() -> usize {
     50
 }
 
+fn default_min_free_disk() -> u64 {
+    500
+}
+
+fn default_max_concurrent_searches() -> usize {
+    8
+}
+
+fn default_connection_idle() -> u64 {
+    30
+}
+
+fn default_query_history_size() -> usize {
+    100
+}
+
+fn default_reprobe_interval() -> u64 {
+    60
+}
+
+fn default_verify_budget() -> u64 {
+    200
+}
+
+fn default_search_queue_timeout() -> u64 {
+    250
+}
+
+fn default_optimize_interval() -> u64 {
+    3600
+}
+
+fn default_progress_interval() -> u64 {
+    250
+}
+
+fn default_tiny_below_kb() -> u64 {
+    100
+}
+
+fn default_small_below_mb() -> u64 {
+    1
+}
+
+fn default_medium_below_mb() -> u64 {
+    100
+}
+
+fn default_large_below_mb() -> u64 {
+    1024
+}
+
+fn default_ssd_scan_concurrency() -> usize {
+    4
+}
+
+fn default_hdd_scan_concurrency() -> usize {
+    1
+}
+
+fn default_max_result_mb() -> usize {
+    stella_search_core::DEFAULT_MAX_RESULT_BYTES / (1024 * 1024)
+}
+
+fn default_extension_groups() -> HashMap<String, Vec<String>> {
+    let group = |extensions: &[&str]| extensions.iter().map(|ext| ext.to_string()).collect();
+    HashMap::from([
+        ("documents".to_string(), group(&["pdf", "doc", "docx", "odt", "rtf", "txt", "md"])),
+        ("spreadsheets".to_string(), group(&["xls", "xlsx", "ods", "csv"])),
+        ("presentations".to_string(), group(&["ppt", "pptx", "odp"])),
+        ("images".to_string(), group(&["jpg", "jpeg", "png", "gif", "webp", "bmp", "svg", "heic"])),
+        ("audio".to_string(), group(&["mp3", "flac", "wav", "ogg", "m4a", "aac"])),
+        ("video".to_string(), group(&["mp4", "mkv", "avi", "mov", "webm", "wmv"])),
+        ("archives".to_string(), group(&["zip", "rar", "7z", "tar", "gz", "xz", "bz2"])),
+        ("code".to_string(), group(&["rs", "py", "js", "ts", "c", "cpp", "h", "java", "go", "cs"])),
+    ])
+}
+
+fn default_query_expansions() -> HashMap<String, Vec<String>> {
+    let targets = |targets: &[&str]| targets.iter().map(|target| target.to_string()).collect();
+    HashMap::from([
+        ("doc".to_string(), targets(&[".doc", ".docx", ".odt", ".rtf"])),
+        ("xls".to_string(), targets(&[".xls", ".xlsx", ".ods"])),
+        ("ppt".to_string(), targets(&[".ppt", ".pptx", ".odp"])),
+        ("pic".to_string(), targets(&[".jpg", ".jpeg", ".png", ".gif", ".webp", ".heic"])),
+        ("img".to_string(), targets(&[".jpg", ".jpeg", ".png", ".gif", ".webp", ".heic"])),
+        ("vid".to_string(), targets(&[".mp4", ".mkv", ".avi", ".mov", ".webm"])),
+    ])
+}
+
 fn default_exclude_paths() -> Vec<String> {
     let mut paths = Vec::new();
 
@@ -177,7 +743,6 @@ fn default_exclude_paths() -> Vec<String> {
         paths.extend(vec![
             "C:/Windows".to_string(),
             "C:/Windows.old".to_string(),
-            "C:/$Recycle.Bin".to_string(),
             "C:/System Volume Information".to_string(),
             "C:/Recovery".to_string(),
             "C:/PerfLogs".to_string(),
@@ -203,6 +768,23 @@ fn default_exclude_paths() -> Vec<String> {
     paths
 }
 
+fn default_exclude_names() -> HashSet<String> {
+    [
+        // Windows shell metadata
+        "desktop.ini",
+        "Thumbs.db",
+        "ehthumbs.db",
+        // macOS Finder metadata
+        ".DS_Store",
+        ".localized",
+        // KDE folder settings
+        ".directory",
+    ]
+    .into_iter()
+    .map(String::from)
+    .collect()
+}
+
 fn default_exclude_patterns() -> Vec<String> {
     vec![
         // Development folders
@@ -234,6 +816,15 @@ fn default_exclude_patterns() -> Vec<String> {
         "**/*.lock".to_string(),
         "**/Thumbs.db".to_string(),
         "**/.DS_Store".to_string(),
+        // Recycle bin / trash on every drive and mount. The "/**" forms also
+        // catch files the watcher sees being moved into the trash on delete.
+        "**/$Recycle.Bin".to_string(),
+        "**/$Recycle.Bin/**".to_string(),
+        "**/$RECYCLE.BIN".to_string(),
+        "**/$RECYCLE.BIN/**".to_string(),
+        "**/.Trash-*".to_string(),
+        "**/.local/share/Trash".to_string(),
+        "**/.local/share/Trash/**".to_string(),
     ]
 }
 
@@ -241,6 +832,8 @@ impl Default for IndexingConfig {
     fn default() -> Self {
         Self {
             mode: default_mode(),
+            initial_scan: true,
+            track_ownership: false,
         }
     }
 }
@@ -251,8 +844,14 @@ impl Default for WatchConfig {
             include: Vec::new(),
             exclude: default_exclude_paths(),
             exclude_patterns: default_exclude_patterns(),
+            remove_default_excludes: Vec::new(),
             exclude_extensions: Vec::new(),
+            include_extensions: Vec::new(),
+            exclude_names: default_exclude_names(),
             include_hidden: false,
+            follow_reparse_points: false,
+            scan_priority_paths: Vec::new(),
+            scan_home_first: true,
         }
     }
 }
@@ -263,6 +862,8 @@ impl Default for WatcherConfig {
             debounce_ms: default_debounce_ms(),
             batch_size: default_batch_size(),
             auto_watch_new_drives: true,
+            settle_ms: default_settle_ms(),
+            max_queued_events: default_max_queued_events(),
         }
     }
 }
@@ -271,7 +872,14 @@ impl Default for ServiceConfig {
     fn default() -> Self {
         Self {
             log_level: default_log_level(),
+            log_format: LogFormat::default(),
             socket_path: None,
+            db_path: None,
+            migrate_legacy_db: true,
+            per_user_scope: false,
+            connection_idle_secs: default_connection_idle(),
+            record_query_history: false,
+            query_history_size: default_query_history_size(),
         }
     }
 }
@@ -281,6 +889,17 @@ impl Default for PerformanceConfig {
         Self {
             threads: 0,
             memory_limit_mb: default_memory_limit(),
+            min_free_disk_mb: default_min_free_disk(),
+            trigram_index: false,
+            warmup_on_start: false,
+            max_concurrent_searches: default_max_concurrent_searches(),
+            search_queue_timeout_ms: default_search_queue_timeout(),
+            max_result_mb: default_max_result_mb(),
+            progress_interval_ms: default_progress_interval(),
+            optimize_interval_secs: default_optimize_interval(),
+            disk_type: DiskType::default(),
+            ssd_scan_concurrency: default_ssd_scan_concurrency(),
+            hdd_scan_concurrency: default_hdd_scan_concurrency(),
         }
     }
 }
@@ -304,40 +923,50 @@ impl Default for Config {
 impl Config {
     /// Get default paths for config and database
     fn get_default_paths() -> (PathBuf, PathBuf) {
-        if let Some(proj_dirs) = ProjectDirs::from("com", "stella", "stella-search") {
-            let config_dir = proj_dirs.config_dir();
-            let data_dir = proj_dirs.data_dir();
+        let (config_dir, data_dir) = match ProjectDirs::from("com", "stella", "stella-search") {
+            Some(proj_dirs) => (
+                proj_dirs.config_dir().to_path_buf(),
+                proj_dirs.data_dir().to_path_buf(),
+            ),
+            None => Self::fallback_dirs(),
+        };
 
-            // Create directories if they don't exist
-            let _ = fs::create_dir_all(config_dir);
-            let _ = fs::create_dir_all(data_dir);
+        // Create directories if they don't exist
+        let _ = fs::create_dir_all(&config_dir);
+        let _ = fs::create_dir_all(&data_dir);
+
+        (config_dir.join("config.toml"), data_dir.join("stella-search.db"))
+    }
 
+    /// Config and data directories used when `ProjectDirs` can't resolve a home
+    fn fallback_dirs() -> (PathBuf, PathBuf) {
+        #[cfg(windows)]
+        {
+            let appdata = std::env::var("APPDATA").unwrap_or_else(|_| ".".to_string());
+            let base = PathBuf::from(appdata).join("StellaSearch");
+            (base.clone(), base)
+        }
+
+        #[cfg(unix)]
+        {
+            let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
             (
-                config_dir.join("config.toml"),
-                data_dir.join("stella-search.db"),
+                PathBuf::from(&home).join(".config/stella-search"),
+                PathBuf::from(&home).join(".local/share/stella-search"),
             )
-        } else {
-            // Fallback paths
-            #[cfg(windows)]
-            {
-                let appdata = std::env::var("APPDATA").unwrap_or_else(|_| ".".to_string());
-                let base = PathBuf::from(appdata).join("StellaSearch");
-                let _ = fs::create_dir_all(&base);
-                (base.join("config.toml"), base.join("stella-search.db"))
-            }
+        }
+    }
 
-            #[cfg(unix)]
-            {
-                let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
-                let config_dir = PathBuf::from(&home).join(".config/stella-search");
-                let data_dir = PathBuf::from(&home).join(".local/share/stella-search");
-                let _ = fs::create_dir_all(&config_dir);
-                let _ = fs::create_dir_all(&data_dir);
-                (
-                    config_dir.join("config.toml"),
-                    data_dir.join("stella-search.db"),
-                )
-            }
+    /// Database locations earlier setups may have used. That is the fallback directory,
+    /// which applied whenever `ProjectDirs` failed; on Unix it is also the plain XDG
+    /// location, which `ProjectDirs` moves for `XDG_DATA_HOME` or the macOS Library folder.
+    pub fn legacy_db_paths(&self) -> Vec<PathBuf> {
+        let legacy = Self::fallback_dirs().1.join("stella-search.db");
+        // A relative fallback (no HOME/APPDATA) depends on the working directory
+        if legacy.is_absolute() && legacy != self.db_path {
+            vec![legacy]
+        } else {
+            Vec::new()
         }
     }
 
@@ -360,6 +989,19 @@ impl Config {
             config
         };
 
+        if let Some(custom_path) = &config.service.db_path {
+            config.db_path = PathBuf::from(custom_path);
+        }
+
+        config.watch.apply_remove_default_excludes();
+
+        config
+            .search
+            .validate_extension_groups()
+            .with_context(|| format!("Invalid config file: {:?}", config.config_path))?;
+        expansion::validate(&config.search.query_expansions)
+            .with_context(|| format!("Invalid config file: {:?}", config.config_path))?;
+
         Ok(config)
     }
 
@@ -403,8 +1045,20 @@ impl Config {
         }
     }
 
-    /// Check if a path should be excluded
+    /// Check if a file or directory name is listed in `watch.exclude_names`
+    pub fn is_excluded_name(&self, name: &str) -> bool {
+        self.watch.exclude_names.contains(name)
+    }
+
+    /// Check if a path should be excluded. Extensions are checked separately, by
+    /// `ExtensionFilter`, since they only apply to files.
     pub fn should_exclude(&self, path: &str) -> bool {
+        // Check exact name exclusions (cheapest check first)
+        let name = path.rsplit(['/', '\\']).next().unwrap_or(path);
+        if self.is_excluded_name(name) {
+            return true;
+        }
+
         // Check absolute exclusions
         for excluded in &self.watch.exclude {
             let excluded_normalized = excluded.replace('\\', "/");
@@ -425,16 +1079,6 @@ impl Config {
             }
         }
 
-        // Check extension exclusions
-        if !self.watch.exclude_extensions.is_empty() {
-            if let Some(ext) = std::path::Path::new(path).extension() {
-                let ext_str = format!(".{}", ext.to_string_lossy());
-                if self.watch.exclude_extensions.contains(&ext_str) {
-                    return true;
-                }
-            }
-        }
-
         // Check hidden files
         if !self.watch.include_hidden {
             let path_obj = std::path::Path::new(path);
@@ -489,6 +1133,40 @@ impl Config {
             }
         }
     }
+
+    /// Directories to scan first, most important first: `watch.scan_priority_paths`,
+    /// then with `watch.scan_home_first` the home directories (the current user's and
+    /// the folder holding every user's)
+    pub fn scan_priority_paths(&self) -> Vec<PathBuf> {
+        // Rebuilt from components, which drops trailing separators ("/srv/a/" is walked,
+        // and its row stored, as "/srv/a")
+        let mut paths: Vec<PathBuf> = self
+            .watch
+            .scan_priority_paths
+            .iter()
+            .map(|path| std::path::Path::new(path).components().collect())
+            .collect();
+
+        if self.watch.scan_home_first {
+            if let Some(dirs) = directories::UserDirs::new() {
+                paths.push(dirs.home_dir().to_path_buf());
+            }
+
+            #[cfg(windows)]
+            {
+                let drive = std::env::var("SystemDrive").unwrap_or_else(|_| "C:".to_string());
+                paths.push(PathBuf::from(format!("{}\\Users", drive)));
+            }
+
+            #[cfg(target_os = "macos")]
+            paths.push(PathBuf::from("/Users"));
+
+            #[cfg(all(unix, not(target_os = "macos")))]
+            paths.push(PathBuf::from("/home"));
+        }
+
+        paths
+    }
 }
 
 /// Thread-safe configuration wrapper
@@ -519,3 +1197,32 @@ impl SharedConfig {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NFC: &str = "caf\u{e9}";
+    const NFD: &str = "cafe\u{301}";
+
+    #[test]
+    fn nfc_folds_decomposed_names_and_borrows_composed_ones() {
+        assert_eq!(UnicodeNormalization::Nfc.normalize(NFD), NFC);
+        assert!(matches!(UnicodeNormalization::Nfc.normalize(NFC), Cow::Borrowed(_)));
+        // Compatibility forms are left alone
+        assert_eq!(UnicodeNormalization::Nfc.normalize("\u{fb01}le"), "\u{fb01}le");
+    }
+
+    #[test]
+    fn nfkc_also_folds_ligatures_and_full_width_forms() {
+        assert_eq!(UnicodeNormalization::Nfkc.normalize("\u{fb01}le"), "file");
+        assert_eq!(UnicodeNormalization::Nfkc.normalize("\u{ff52}\u{ff45}\u{ff50}\u{ff4f}\u{ff52}\u{ff54}"), "report");
+        assert_eq!(UnicodeNormalization::Nfkc.normalize(NFD), NFC);
+    }
+
+    #[test]
+    fn none_keeps_names_as_stored() {
+        assert_eq!(UnicodeNormalization::None.normalize(NFD), NFD);
+        assert_eq!(UnicodeNormalization::None.normalize("\u{fb01}le"), "\u{fb01}le");
+    }
+}
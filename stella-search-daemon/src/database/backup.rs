@@ -0,0 +1,126 @@
+//! Online backup and restore of the index database
+
+use anyhow::{Context, Result};
+use rusqlite::backup::Progress;
+use rusqlite::{Connection, DatabaseName, OpenFlags};
+use std::path::Path;
+use tracing::info;
+
+use super::Database;
+
+/// Log backup/restore progress roughly every 100 MB (25,600 pages of 4 KB)
+const PROGRESS_LOG_PAGES: i32 = 25_600;
+
+fn log_progress(progress: Progress) {
+    let copied = progress.pagecount - progress.remaining;
+    if progress.remaining == 0 || copied % PROGRESS_LOG_PAGES == 0 {
+        let percent = if progress.pagecount > 0 {
+            copied as f64 / progress.pagecount as f64 * 100.0
+        } else {
+            100.0
+        };
+        info!(
+            "Backup progress: {}/{} pages ({:.0}%)",
+            copied, progress.pagecount, percent
+        );
+    }
+}
+
+impl Database {
+    /// Write a consistent copy of the live database to `dest` using SQLite's online backup API.
+    /// Reads through a separate connection so searches and indexing keep running (WAL readers
+    /// don't block writers). Returns the number of pages copied.
+    pub fn backup_to(&self, dest: &Path) -> Result<u64> {
+        let src = Connection::open_with_flags(self.path(), OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .with_context(|| format!("Failed to open database for backup: {}", self.path()))?;
+
+        info!("Backing up database to {:?}", dest);
+        src.backup(DatabaseName::Main, dest, Some(log_progress))
+            .with_context(|| format!("Failed to back up database to {:?}", dest))?;
+
+        let page_count: u64 = src.query_row("PRAGMA page_count", [], |row| row.get(0))?;
+        info!("Backup complete: {} pages written to {:?}", page_count, dest);
+        Ok(page_count)
+    }
+
+    /// Replace the live database contents with a backup created by `backup_to`.
+    /// Holds the connection lock for the whole restore so no writes can interleave;
+    /// callers must make sure no scan is running.
+    pub fn restore_from(&self, src: &Path) -> Result<()> {
+        if !src.exists() {
+            anyhow::bail!("Backup file does not exist: {:?}", src);
+        }
+
+        info!("Restoring database from {:?}", src);
+        let mut conn = self.connection();
+        conn.restore(DatabaseName::Main, src, Some(log_progress))
+            .with_context(|| format!("Failed to restore database from {:?}", src))?;
+
+        // The backup may have been taken with the trigram index toggled the other way
+        self.sync_trigram_index(&conn)?;
+        self.bump_generation();
+
+        info!("Restore complete");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::search::SearchQuery;
+    use crate::test_support::{seed, test_config, test_database};
+
+    /// Every indexed path, sorted
+    fn all_paths(conn: &Connection) -> Vec<String> {
+        let mut stmt = conn.prepare("SELECT path FROM files ORDER BY path").unwrap();
+        stmt.query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn backup_and_restore_round_trip_every_row() {
+        let dir = TempDir::new().unwrap();
+        let db = test_database(&test_config(&dir));
+        let paths: Vec<String> = (0..5_000).map(|i| format!("/data/dir{}/file{}.txt", i % 50, i)).collect();
+        let paths: Vec<&str> = paths.iter().map(String::as_str).collect();
+        // Still in the WAL, not checkpointed: the backup has to read it from there
+        seed(&db, &paths);
+        let original = all_paths(&db.connection());
+        assert_eq!(original.len(), 5_000);
+
+        let backup = dir.path().join("backup.db");
+        assert!(db.backup_to(&backup).unwrap() > 0);
+        let copy = Connection::open_with_flags(&backup, OpenFlags::SQLITE_OPEN_READ_ONLY).unwrap();
+        assert_eq!(all_paths(&copy), original);
+        drop(copy);
+
+        // Change the live index, then put the backup back
+        db.connection().execute("DELETE FROM files WHERE path LIKE '/data/dir1/%'", []).unwrap();
+        seed(&db, &["/data/added.txt"]);
+        assert_ne!(all_paths(&db.connection()), original);
+
+        db.restore_from(&backup).unwrap();
+        assert_eq!(all_paths(&db.connection()), original);
+
+        // Searches read through the separate reader connection
+        let results = db.search(&SearchQuery::new("file4999", 10)).unwrap();
+        assert_eq!(results.total_found, 1);
+        let results = db.search(&SearchQuery::new("added", 10)).unwrap();
+        assert_eq!(results.total_found, 0);
+    }
+
+    #[test]
+    fn restoring_a_missing_backup_fails_and_keeps_the_index() {
+        let dir = TempDir::new().unwrap();
+        let db = test_database(&test_config(&dir));
+        seed(&db, &["/data/kept.txt"]);
+
+        assert!(db.restore_from(&dir.path().join("missing.db")).is_err());
+        assert_eq!(all_paths(&db.connection()), ["/data/kept.txt"]);
+    }
+}
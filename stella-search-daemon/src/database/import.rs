@@ -0,0 +1,256 @@
+//! Import of JSONL index dumps
+//!
+//! Each non-empty line is one `IndexedFile` as serialized over IPC. The `id`
+//! field is ignored; rows are keyed by path. The whole import runs in one
+//! transaction, so a malformed line leaves the database untouched. A leading UTF-8
+//! byte order mark and `\r\n` line endings (see `stella-search export`) are accepted.
+
+use anyhow::{Context, Result};
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use tracing::info;
+
+use super::queries::IndexedFile;
+use super::trigrams;
+use super::Database;
+
+/// Plain insert used when the table was emptied first
+const INSERT_FILE_SQL: &str = r#"
+    INSERT INTO files (path, name, extension, size, is_directory, modified, created)
+    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+    RETURNING id
+"#;
+
+/// Insert that leaves already-indexed paths alone (no row returned when skipped)
+const INSERT_OR_IGNORE_FILE_SQL: &str = r#"
+    INSERT OR IGNORE INTO files (path, name, extension, size, is_directory, modified, created)
+    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+    RETURNING id
+"#;
+
+/// Insert that overwrites the row of an already-indexed path
+const MERGE_FILE_SQL: &str = r#"
+    INSERT INTO files (path, name, extension, size, is_directory, modified, created)
+    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+    ON CONFLICT(path) DO UPDATE SET
+        name = excluded.name,
+        extension = excluded.extension,
+        size = excluded.size,
+        is_directory = excluded.is_directory,
+        modified = excluded.modified,
+        created = excluded.created,
+        raw_path = NULL
+    RETURNING id
+"#;
+
+/// What to do with rows whose path is already indexed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportPolicy {
+    /// Upsert: existing paths are updated, new paths inserted (default)
+    #[default]
+    Merge,
+    /// Clear the whole index before importing
+    Replace,
+    /// Keep existing rows, only insert new paths
+    Skip,
+}
+
+/// Row counts reported by an import
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportSummary {
+    pub inserted: u64,
+    pub updated: u64,
+    pub skipped: u64,
+}
+
+impl fmt::Display for ImportSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} inserted, {} updated, {} skipped",
+            self.inserted, self.updated, self.skipped
+        )
+    }
+}
+
+impl Database {
+    /// Import a JSONL dump into the index using the given policy
+    pub fn import_jsonl(&self, src: &Path, policy: ImportPolicy) -> Result<ImportSummary> {
+        let file = File::open(src).with_context(|| format!("Failed to open import file: {:?}", src))?;
+        let reader = BufReader::new(file);
+
+        info!("Importing {:?} with policy {:?}", src, policy);
+
+        let mut conn = self.connection();
+        let tx = conn.transaction()?;
+        let mut summary = ImportSummary::default();
+
+        if policy == ImportPolicy::Replace {
+            if self.trigram_index {
+                tx.execute("DELETE FROM name_trigrams", [])?;
+            }
+            tx.execute("DELETE FROM files", [])?;
+        }
+
+        {
+            let sql = match policy {
+                ImportPolicy::Merge => MERGE_FILE_SQL,
+                ImportPolicy::Replace => INSERT_FILE_SQL,
+                ImportPolicy::Skip => INSERT_OR_IGNORE_FILE_SQL,
+            };
+            let mut stmt = tx.prepare(sql)?;
+            let mut exists_stmt = tx.prepare("SELECT 1 FROM files WHERE path = ?1")?;
+
+            for (index, line) in reader.lines().enumerate() {
+                let line = line.with_context(|| format!("Failed to read import file: {:?}", src))?;
+                let line = if index == 0 { line.trim_start_matches('\u{FEFF}') } else { &line };
+                if line.trim().is_empty() {
+                    continue;
+                }
+
+                let file: IndexedFile = serde_json::from_str(line)
+                    .with_context(|| format!("Invalid record on line {}", index + 1))?;
+                let name = self.normalization.normalize(&file.name);
+
+                // Only merges need to tell inserts from updates, the other policies know up front
+                let existed = policy == ImportPolicy::Merge && exists_stmt.exists(params![file.path])?;
+
+                let id: Option<i64> = stmt
+                    .query_row(
+                        params![
+                            file.path,
+                            name,
+                            file.extension,
+                            file.size,
+                            file.is_directory as i32,
+                            file.modified,
+                            file.created,
+                        ],
+                        |row| row.get(0),
+                    )
+                    .optional()?;
+
+                match id {
+                    Some(id) => {
+                        if existed {
+                            summary.updated += 1;
+                        } else {
+                            summary.inserted += 1;
+                        }
+                        if self.trigram_index {
+                            trigrams::index_name(&tx, id, &name)?;
+                        }
+                    }
+                    None => summary.skipped += 1,
+                }
+            }
+        }
+
+        tx.commit()?;
+        self.bump_generation();
+        info!("Import complete: {}", summary);
+        Ok(summary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::search::SearchQuery;
+    use crate::test_support::{seed, test_config, test_database};
+
+    /// One overlapping path (a.txt, size 500 instead of 100) and one new path
+    const DUMP: &str = concat!(
+        r#"{"id":7,"path":"/data/a.txt","name":"a.txt","extension":".txt","size":500,"is_directory":false,"modified":1}"#,
+        "\n",
+        r#"{"id":8,"path":"/data/c.txt","name":"c.txt","extension":".txt","size":300,"is_directory":false,"modified":1}"#,
+        "\n",
+    );
+
+    /// Database holding a.txt and b.txt (size 100), with `dump` written next to it
+    fn populated(dump: &str) -> (Arc<Database>, std::path::PathBuf, TempDir) {
+        let dir = TempDir::new().unwrap();
+        let db = test_database(&test_config(&dir));
+        seed(&db, &["/data/a.txt", "/data/b.txt"]);
+        let path = dir.path().join("dump.jsonl");
+        std::fs::write(&path, dump).unwrap();
+        (db, path, dir)
+    }
+
+    /// (path, size) of every row, by path
+    fn rows(db: &Database) -> Vec<(String, i64)> {
+        let results = db.search(&SearchQuery::new("", 50)).unwrap();
+        let mut rows: Vec<_> = results.files.into_iter().map(|f| (f.path, f.size)).collect();
+        rows.sort();
+        rows
+    }
+
+    fn row(path: &str, size: i64) -> (String, i64) {
+        (path.to_string(), size)
+    }
+
+    #[test]
+    fn merge_updates_existing_paths_and_inserts_new_ones() {
+        let (db, dump, _dir) = populated(DUMP);
+        let summary = db.import_jsonl(&dump, ImportPolicy::Merge).unwrap();
+        assert_eq!(summary, ImportSummary { inserted: 1, updated: 1, skipped: 0 });
+        assert_eq!(rows(&db), [row("/data/a.txt", 500), row("/data/b.txt", 100), row("/data/c.txt", 300)]);
+    }
+
+    #[test]
+    fn skip_keeps_existing_rows() {
+        let (db, dump, _dir) = populated(DUMP);
+        let summary = db.import_jsonl(&dump, ImportPolicy::Skip).unwrap();
+        assert_eq!(summary, ImportSummary { inserted: 1, updated: 0, skipped: 1 });
+        assert_eq!(rows(&db), [row("/data/a.txt", 100), row("/data/b.txt", 100), row("/data/c.txt", 300)]);
+    }
+
+    #[test]
+    fn replace_clears_the_index_first() {
+        let (db, dump, _dir) = populated(DUMP);
+        let summary = db.import_jsonl(&dump, ImportPolicy::Replace).unwrap();
+        assert_eq!(summary, ImportSummary { inserted: 2, updated: 0, skipped: 0 });
+        assert_eq!(rows(&db), [row("/data/a.txt", 500), row("/data/c.txt", 300)]);
+    }
+
+    #[test]
+    fn a_malformed_line_leaves_the_index_untouched() {
+        let (db, dump, _dir) = populated(&format!("{}{{\"path\": \n", DUMP));
+        for policy in [ImportPolicy::Merge, ImportPolicy::Skip, ImportPolicy::Replace] {
+            let error = db.import_jsonl(&dump, policy).unwrap_err();
+            assert_eq!(error.to_string(), "Invalid record on line 3");
+            assert_eq!(rows(&db), [row("/data/a.txt", 100), row("/data/b.txt", 100)]);
+        }
+    }
+
+    #[test]
+    fn a_byte_order_mark_and_crlf_line_endings_are_accepted() {
+        let (db, dump, _dir) = populated(&format!("\u{FEFF}{}\r\n", DUMP.replace('\n', "\r\n")));
+        let summary = db.import_jsonl(&dump, ImportPolicy::Merge).unwrap();
+        assert_eq!(summary, ImportSummary { inserted: 1, updated: 1, skipped: 0 });
+    }
+
+    #[test]
+    fn imported_names_reach_the_trigram_index() {
+        let dir = TempDir::new().unwrap();
+        let mut config = test_config(&dir);
+        config.performance.trigram_index = true;
+        let db = test_database(&config);
+        seed(&db, &["/data/a.txt", "/data/b.txt"]);
+        let dump = dir.path().join("dump.jsonl");
+        std::fs::write(&dump, DUMP).unwrap();
+
+        db.import_jsonl(&dump, ImportPolicy::Replace).unwrap();
+        let found = |query: &str| db.search(&SearchQuery::new(query, 50)).unwrap().total_found;
+        assert_eq!((found("a.txt"), found("b.txt"), found("c.txt")), (1, 0, 1));
+    }
+}
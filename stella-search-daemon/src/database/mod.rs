@@ -4,6 +4,10 @@
 
 mod schema;
 mod queries;
+mod backup;
+mod import;
+mod trigrams;
 
 pub use schema::Database;
 pub use queries::*;
+pub use import::ImportPolicy;
@@ -3,13 +3,21 @@
 //! Uses simple LIKE queries instead of FTS5 (fast enough for filename search)
 
 use anyhow::Result;
-use rusqlite::params;
-use std::path::Path;
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 
+use super::trigrams::{self, name_trigrams};
 use super::Database;
+use crate::platform::{self, Ownership};
+use crate::search::path_encoding::{percent_encode_bytes, percent_encode_str};
+use crate::search::{QueryKind, SearchQuery};
 
 // Re-export shared types from core crate
 pub use stella_search_core::{IndexedFile, IndexStats, SearchResults};
+use stella_search_core::{collect_capped, ResultSort};
 
 /// Pre-computed file metadata from MFT or filesystem
 /// Used for efficient batch inserts without per-file stat() calls
@@ -19,15 +27,338 @@ pub struct FileMetadata {
     pub name: String,
     pub size: i64,
     pub is_directory: bool,
+    pub modified: i64,
+    pub created: Option<i64>,
+    pub is_symlink: bool,
+}
+
+/// Match count for one extension (None: directories and files without one)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExtensionCount {
+    pub extension: Option<String>,
+    pub count: u64,
+}
+
+/// An index entry with the time it was last written, for staleness checks
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StaleEntry {
+    pub path: String,
+    pub is_directory: bool,
+    /// The file's own modification time (Unix seconds)
+    pub modified: i64,
+    /// When a scan or the watcher last wrote the entry (Unix seconds); None if never
+    /// (imported, or indexed before this was recorded)
+    pub indexed_at: Option<i64>,
+}
+
+/// What adding an exclude pattern would remove from the index
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExcludePreview {
+    pub files: u64,
+    pub directories: u64,
+    /// Some of the paths, in path order
+    pub sample: Vec<String>,
+}
+
+/// Number of matching files directly inside one directory
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DirectoryCount {
+    pub directory: String,
+    pub count: u64,
+}
+
+/// Files with one extension and the space they take (None: files without one)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExtensionUsage {
+    pub extension: Option<String>,
+    pub files: u64,
+    pub total_size: u64,
+}
+
+/// Indexed files on one drive or mount point; files on a volume mounted inside
+/// another count only toward the inner one
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VolumeCount {
+    pub root: String,
+    pub files: u64,
+}
+
+/// Composition of the index for status dashboards (see `Database::detailed_stats`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DetailedStats {
+    pub indexed_files: u64,
+    pub average_file_size: u64,
+    /// Most common file extensions, most files first
+    pub top_extensions: Vec<ExtensionUsage>,
+    /// Files per drive or mount point, most files first
+    pub volumes: Vec<VolumeCount>,
+    /// Largest files, biggest first
+    pub largest_files: Vec<IndexedFile>,
+}
+
+/// Number of extensions in `DetailedStats::top_extensions`
+const DETAILED_TOP_EXTENSIONS: usize = 20;
+
+/// Number of files in `DetailedStats::largest_files`
+const DETAILED_LARGEST_FILES: usize = 10;
+
+/// FROM source, ORDER BY column and WHERE terms shared by search queries
+struct SearchFilter {
+    source: &'static str,
+    order_column: &'static str,
+    conditions: Vec<String>,
+    values: Vec<Value>,
+}
+
+/// Insert a file row, updating it in place if the path is already indexed
+const UPSERT_FILE_SQL: &str = r#"
+    INSERT INTO files (path, name, extension, size, is_directory, modified, raw_path, owner, mode, created, is_symlink, indexed_at)
+    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, unixepoch())
+    ON CONFLICT(path) DO UPDATE SET
+        name = excluded.name,
+        extension = excluded.extension,
+        size = excluded.size,
+        is_directory = excluded.is_directory,
+        modified = excluded.modified,
+        raw_path = excluded.raw_path,
+        owner = excluded.owner,
+        mode = excluded.mode,
+        created = excluded.created,
+        is_symlink = excluded.is_symlink,
+        indexed_at = excluded.indexed_at
+    RETURNING id
+"#;
+
+/// Columns selected for every IndexedFile query (order matches `row_to_file`)
+const FILE_COLUMNS: &str = "id, path, name, extension, size, is_directory, modified, created";
+
+/// An entry's parent directory, keeping its trailing separator: rtrim strips every
+/// trailing character that is neither `/` nor `\`, so "/a/b/c" gives "/a/b/" and
+/// "C:\a\c" gives "C:\a\"
+const PARENT_PATH_SQL: &str = r"rtrim(path, replace(replace(path, '/', ''), '\', ''))";
+
+/// Extract an IndexedFile from a row selected with `FILE_COLUMNS`
+///
+/// The `name` column is a search key (Unicode-normalized, and matched under
+/// case-insensitive collations), so the returned name is taken from the stored
+/// path, which is kept exactly as on disk. The path is split on both separators,
+/// whatever the daemon's platform: an index can hold Windows paths on Unix (an
+/// import, a merged backend). Only volume roots, which have no final component,
+/// fall back to the column.
+fn row_to_file(row: &rusqlite::Row) -> rusqlite::Result<IndexedFile> {
+    let path: String = row.get(1)?;
+    let name = match path.rsplit(['/', '\\']).next() {
+        Some(name) if !name.is_empty() => name.to_string(),
+        _ => row.get(2)?,
+    };
+
+    Ok(IndexedFile {
+        id: row.get(0)?,
+        path,
+        name,
+        extension: row.get(3)?,
+        size: row.get(4)?,
+        is_directory: row.get::<_, i32>(5)? != 0,
+        modified: row.get(6)?,
+        created: row.get(7)?,
+        full_path: None,
+        target: None,
+        exists: None,
+        volume: None,
+    })
+}
+
+/// Raw bytes of a path that isn't valid UTF-8 (None for UTF-8 paths).
+/// Windows paths are UTF-16 and always stored as text.
+fn raw_path_bytes(path: &Path) -> Option<Vec<u8>> {
+    if path.to_str().is_some() {
+        return None;
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        Some(path.as_os_str().as_bytes().to_vec())
+    }
+
+    #[cfg(not(unix))]
+    {
+        None
+    }
+}
+
+/// Escape `%`, `_` and `\` so a user string matches literally inside a
+/// `LIKE ... ESCAPE '\'` pattern ("a_b" must not match "aXb")
+pub(crate) fn escape_like(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '\\' | '%' | '_') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// LIKE prefix (before escaping) for a drive or mount point filter.
+/// `E:`, `E:\` and `e:/` all become `E:`; `/mnt/data` becomes `/mnt/data/`
+/// so sibling mounts like "/mnt/data2" are not matched.
+fn drive_path_prefix(drive: &str) -> String {
+    let drive = drive.trim().replace('\\', "/");
+    let bytes = drive.as_bytes();
+
+    if (bytes.len() == 2 || (bytes.len() == 3 && bytes[2] == b'/'))
+        && bytes[0].is_ascii_alphabetic()
+        && bytes[1] == b':'
+    {
+        return drive[..2].to_ascii_uppercase();
+    }
+
+    format!("{}/", drive.trim_end_matches('/'))
+}
+
+/// Separator that indexed paths under `path` are stored with: `\` below a drive
+/// letter or UNC share (as Windows scans write them), `/` everywhere else
+fn stored_separator(path: &str) -> char {
+    let bytes = path.as_bytes();
+    let drive = bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':';
+    if drive || path.starts_with("\\\\") { '\\' } else { '/' }
+}
+
+/// A directory in the form its indexed entries start with, without the trailing
+/// separator ("C:/Users/" becomes `C:\Users`, "/" becomes ""), and that separator
+fn stored_directory(directory: &str) -> (String, char) {
+    let separator = stored_separator(directory);
+    let directory = match separator {
+        '\\' => directory.replace('/', "\\"),
+        _ => directory.to_string(),
+    };
+    (directory.trim_end_matches(separator).to_string(), separator)
+}
+
+/// Half-open range `[low, high)` of paths strictly under `directory`, so a scope is
+/// a seek on the path index. The range uses the separator the rows are stored with
+/// (`C:/Users/bob` is read as `C:\Users\bob`). Comparison is binary: "/home/bob"
+/// excludes "/home/Bob" and the sibling "/home/bob2".
+fn scope_path_range(directory: &str) -> (String, String) {
+    let (directory, separator) = stored_directory(directory);
+    // The byte after the separator: '0' after '/', ']' after '\'
+    let next = (separator as u8 + 1) as char;
+    (format!("{}{}", directory, separator), format!("{}{}", directory, next))
+}
+
+/// Parent path from `PARENT_PATH_SQL` as a directory path: "/a/b/" becomes "/a/b"
+/// and "C:\a\" becomes "C:\a", while roots such as "/" and "C:\" keep their separator
+fn trim_directory_separator(mut parent: String) -> String {
+    if parent.len() > 1 && !parent.ends_with(":/") && !parent.ends_with(":\\") {
+        parent.pop();
+    }
+    parent
+}
+
+/// Extra WHERE term and its values limiting a query to paths under `scope`, if any
+fn scope_condition(scope: Option<&str>) -> (&'static str, Vec<Value>) {
+    match scope {
+        Some(scope) => {
+            let (low, high) = scope_path_range(scope);
+            (" AND path >= ? AND path < ?", vec![low.into(), high.into()])
+        }
+        None => ("", Vec::new()),
+    }
+}
+
+/// Files under each volume root, with volumes mounted inside another counted
+/// only toward the innermost one. Volumes without indexed files are left out.
+/// With a `scope`, only files under it are counted.
+fn volume_counts(conn: &Connection, roots: &[String], scope: Option<&str>) -> Result<Vec<VolumeCount>> {
+    let mut roots: Vec<String> = roots.iter().map(|root| stored_directory(root).0).collect();
+    roots.sort();
+    roots.dedup();
+    // Deepest first, so nested volumes are counted before the ones holding them
+    roots.sort_by_key(|root| std::cmp::Reverse(root.len()));
+
+    let (scope_sql, scope_values) = scope_condition(scope);
+    let mut stmt = conn.prepare(&format!(
+        "SELECT COUNT(*) FROM files WHERE is_directory = 0 AND path >= ? AND path < ?{}",
+        scope_sql
+    ))?;
+    let mut counts: Vec<VolumeCount> = Vec::new();
+    for root in roots {
+        let (low, high) = scope_path_range(&root);
+        let values = [low.into(), high.into()].into_iter().chain(scope_values.iter().cloned());
+        let under: u64 = stmt.query_row(params_from_iter(values), |row| row.get(0))?;
+        let nested: u64 = counts
+            .iter()
+            .filter(|inner| {
+                inner.root.len() > root.len()
+                    && inner.root.starts_with(&root)
+                    && inner.root.as_bytes()[root.len()] == stored_separator(&root) as u8
+            })
+            .map(|inner| inner.files)
+            .sum();
+        counts.push(VolumeCount {
+            root,
+            files: under.saturating_sub(nested),
+        });
+    }
+
+    counts.retain(|volume| volume.files > 0);
+    // Put back the separator of roots left empty or as a bare drive
+    for volume in &mut counts {
+        if volume.root.is_empty() {
+            volume.root = "/".to_string();
+        } else if volume.root.ends_with(':') {
+            volume.root.push('\\');
+        }
+    }
+    counts.sort_by(|a, b| b.files.cmp(&a.files).then_with(|| a.root.cmp(&b.root)));
+    Ok(counts)
+}
+
+/// Owner and mode column values for an upsert (both NULL if unknown)
+fn ownership_params(ownership: Option<Ownership>) -> (Option<String>, Option<i64>) {
+    match ownership {
+        Some(ownership) => (Some(ownership.owner), Some(ownership.mode as i64)),
+        None => (None, None),
+    }
+}
+
+/// Modification time of a file in Unix seconds (0 if unavailable)
+pub fn modified_secs(metadata: &std::fs::Metadata) -> i64 {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// Creation time of a file in Unix seconds. Always set on Windows and macOS; on
+/// Linux only where the filesystem records a birth time (ext4, btrfs, xfs with
+/// statx), so None is common there.
+pub fn created_secs(metadata: &std::fs::Metadata) -> Option<i64> {
+    metadata
+        .created()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs() as i64)
 }
 
 impl Database {
     /// Insert or update a file in the index (simplified schema)
-    pub fn upsert_file(&self, path: &str, is_directory: bool, size: i64) -> Result<()> {
-        let path_obj = Path::new(path);
+    pub fn upsert_file(
+        &self,
+        path_obj: &Path,
+        is_directory: bool,
+        size: i64,
+        modified: i64,
+        created: Option<i64>,
+    ) -> Result<()> {
+        let path = path_obj.to_string_lossy();
+        let raw_path = raw_path_bytes(path_obj);
         let name = path_obj
             .file_name()
-            .map(|n| n.to_string_lossy().to_string())
+            .map(|n| self.normalization.normalize(&n.to_string_lossy()).into_owned())
             .unwrap_or_default();
         let extension = if is_directory {
             None
@@ -35,20 +366,26 @@ impl Database {
             path_obj.extension().map(|e| format!(".{}", e.to_string_lossy()))
         };
 
+        let (owner, mode) = if self.track_ownership {
+            ownership_params(platform::file_ownership(path_obj, None))
+        } else {
+            (None, None)
+        };
+
+        let is_symlink = path_obj.is_symlink();
+
         let conn = self.connection();
-        conn.execute(
-            r#"
-            INSERT INTO files (path, name, extension, size, is_directory)
-            VALUES (?1, ?2, ?3, ?4, ?5)
-            ON CONFLICT(path) DO UPDATE SET
-                name = excluded.name,
-                extension = excluded.extension,
-                size = excluded.size,
-                is_directory = excluded.is_directory
-            "#,
-            params![path, name, extension, size, is_directory as i32],
+        let id: i64 = conn.query_row(
+            UPSERT_FILE_SQL,
+            params![path, name, extension, size, is_directory as i32, modified, raw_path, owner, mode, created, is_symlink],
+            |row| row.get(0),
         )?;
 
+        if self.trigram_index {
+            trigrams::index_name(&conn, id, &name)?;
+        }
+
+        self.bump_generation();
         Ok(())
     }
 
@@ -59,17 +396,7 @@ impl Database {
         let tx = conn.transaction()?;
 
         {
-            let mut stmt = tx.prepare(
-                r#"
-                INSERT INTO files (path, name, extension, size, is_directory)
-                VALUES (?1, ?2, ?3, ?4, ?5)
-                ON CONFLICT(path) DO UPDATE SET
-                    name = excluded.name,
-                    extension = excluded.extension,
-                    size = excluded.size,
-                    is_directory = excluded.is_directory
-                "#,
-            )?;
+            let mut stmt = tx.prepare(UPSERT_FILE_SQL)?;
 
             for file in files {
                 let extension = if file.is_directory {
@@ -79,67 +406,107 @@ impl Database {
                         .extension()
                         .map(|e| format!(".{}", e.to_string_lossy()))
                 };
+                let name = self.normalization.normalize(&file.name);
+                // MFT records only carry an index into $Secure, so each path is looked up
+                let (owner, mode) = if self.track_ownership {
+                    ownership_params(platform::file_ownership(Path::new(&file.path), None))
+                } else {
+                    (None, None)
+                };
+
+                let id: i64 = stmt.query_row(
+                    params![
+                        file.path,
+                        name,
+                        extension,
+                        file.size,
+                        file.is_directory as i32,
+                        file.modified,
+                        Option::<Vec<u8>>::None,
+                        owner,
+                        mode,
+                        file.created,
+                        file.is_symlink,
+                    ],
+                    |row| row.get(0),
+                )?;
 
-                stmt.execute(params![
-                    file.path,
-                    file.name,
-                    extension,
-                    file.size,
-                    file.is_directory as i32,
-                ])?;
+                if self.trigram_index {
+                    trigrams::index_name(&tx, id, &name)?;
+                }
             }
         }
 
         tx.commit()?;
+        self.bump_generation();
         Ok(())
     }
 
-    /// Batch insert files for walkdir scanner (computes metadata from path)
-    pub fn batch_upsert_files(&self, files: &[(String, bool)]) -> Result<()> {
+    /// Batch insert files for walkdir scanner (computes metadata from path).
+    /// Entries are (path, is_directory, is_symlink).
+    pub fn batch_upsert_files(&self, files: &[(PathBuf, bool, bool)]) -> Result<()> {
         let mut conn = self.connection();
         let tx = conn.transaction()?;
 
         {
-            let mut stmt = tx.prepare(
-                r#"
-                INSERT INTO files (path, name, extension, size, is_directory)
-                VALUES (?1, ?2, ?3, ?4, ?5)
-                ON CONFLICT(path) DO UPDATE SET
-                    name = excluded.name,
-                    extension = excluded.extension,
-                    size = excluded.size,
-                    is_directory = excluded.is_directory
-                "#,
-            )?;
-
-            for (path, is_directory) in files {
-                let path_obj = Path::new(path);
+            let mut stmt = tx.prepare(UPSERT_FILE_SQL)?;
+
+            for (path_obj, is_directory, is_symlink) in files {
+                let path = path_obj.to_string_lossy();
                 let name = path_obj
                     .file_name()
-                    .map(|n| n.to_string_lossy().to_string())
+                    .map(|n| self.normalization.normalize(&n.to_string_lossy()).into_owned())
                     .unwrap_or_default();
 
-                let (extension, size) = if *is_directory {
-                    (None, 0i64)
+                // Directories skip the stat() call unless ownership is tracked
+                // (their size and mtime stay 0 either way)
+                let metadata = if *is_directory && !self.track_ownership {
+                    None
+                } else {
+                    std::fs::metadata(path_obj).ok()
+                };
+                let (extension, size, modified, created) = if *is_directory {
+                    (None, 0i64, 0i64, None)
                 } else {
                     let ext = path_obj.extension().map(|e| format!(".{}", e.to_string_lossy()));
-                    let size = std::fs::metadata(path)
-                        .map(|m| m.len() as i64)
-                        .unwrap_or(0);
-                    (ext, size)
+                    let (size, modified, created) = metadata
+                        .as_ref()
+                        .map(|m| (m.len() as i64, modified_secs(m), created_secs(m)))
+                        .unwrap_or((0, 0, None));
+                    (ext, size, modified, created)
+                };
+                let (owner, mode) = match &metadata {
+                    Some(m) if self.track_ownership => {
+                        ownership_params(platform::file_ownership(path_obj, Some(m)))
+                    }
+                    _ => (None, None),
                 };
 
-                stmt.execute(params![
-                    path,
-                    name,
-                    extension,
-                    size,
-                    *is_directory as i32,
-                ])?;
+                let id: i64 = stmt.query_row(
+                    params![
+                        path,
+                        name,
+                        extension,
+                        size,
+                        *is_directory as i32,
+                        modified,
+                        raw_path_bytes(path_obj),
+                        owner,
+                        mode,
+                        created,
+                        is_symlink,
+                    ],
+                    |row| row.get(0),
+                )?;
+
+                if self.trigram_index {
+                    trigrams::index_name(&tx, id, &name)?;
+                }
             }
         }
 
         tx.commit()?;
+        self.bump_generation();
         Ok(())
     }
 
@@ -147,6 +514,7 @@ impl Database {
     pub fn delete_file(&self, path: &str) -> Result<()> {
         let conn = self.connection();
         conn.execute("DELETE FROM files WHERE path = ?1", params![path])?;
+        self.bump_generation();
         Ok(())
     }
 
@@ -154,60 +522,307 @@ impl Database {
     pub fn delete_directory(&self, directory: &str) -> Result<()> {
         let conn = self.connection();
         // Delete the directory itself and all files/subdirs under it
-        let like_pattern = format!("{}%", directory.replace('\\', "/"));
+        let like_pattern = format!("{}%", escape_like(&directory.replace('\\', "/")));
         conn.execute(
-            "DELETE FROM files WHERE path LIKE ?1",
+            "DELETE FROM files WHERE path LIKE ?1 ESCAPE '\\'",
             params![like_pattern],
         )?;
+        self.bump_generation();
         Ok(())
     }
 
-    /// Search files using simple LIKE queries (fast enough for filename search)
-    /// No FTS5 - Everything proves this approach works for billions of files
-    pub fn search(&self, query: &str, max_results: usize, extension: Option<&str>) -> Result<SearchResults> {
-        let start = std::time::Instant::now();
+    /// Delete every entry on a Windows drive, leaving other drives alone. The letter
+    /// matches either case and must be followed by a separator or nothing, so 'D'
+    /// covers `D:`, `D:\` and `d:/x` but not `D.txt` or `Data:`. Returns how many
+    /// were removed.
+    pub fn delete_drive(&self, letter: char) -> Result<usize> {
+        let root = format!("{}:", letter.to_ascii_uppercase());
         let conn = self.connection();
+        // LIKE ignores ASCII case, and the root has no wildcards to escape
+        let removed = conn.execute(
+            "DELETE FROM files WHERE path LIKE ?1 ESCAPE '\\' OR path LIKE ?2 ESCAPE '\\' OR path LIKE ?3 ESCAPE '\\'",
+            params![root, format!("{}/%", root), format!("{}%", escape_like(&format!("{}\\", root)))],
+        )?;
+        if removed > 0 {
+            self.bump_generation();
+        }
+        Ok(removed)
+    }
 
-        // Build LIKE pattern for substring matching
-        let like_pattern = format!("%{}%", query);
-
-        // Helper to extract IndexedFile from a row
-        fn row_to_file(row: &rusqlite::Row) -> rusqlite::Result<IndexedFile> {
-            Ok(IndexedFile {
-                id: row.get(0)?,
-                path: row.get(1)?,
-                name: row.get(2)?,
-                extension: row.get(3)?,
-                size: row.get(4)?,
-                is_directory: row.get::<_, i32>(5)? != 0,
-            })
+    /// Delete entries under a directory last written before `before` (Unix seconds):
+    /// after a rescan, these are files that went away without an event. The range is
+    /// built in the separator the rows use, so a watch path written `C:/Users`
+    /// still covers `C:\Users\...`. Returns how many were removed.
+    pub fn delete_unrefreshed(&self, directory: &str, before: i64) -> Result<usize> {
+        let (low, high) = scope_path_range(directory);
+        let conn = self.connection();
+        let removed = conn.execute(
+            "DELETE FROM files WHERE path >= ?1 AND path < ?2 AND (indexed_at IS NULL OR indexed_at < ?3)",
+            params![low, high, before],
+        )?;
+        if removed > 0 {
+            self.bump_generation();
         }
+        Ok(removed)
+    }
 
-        let files: Vec<IndexedFile> = if let Some(ext) = extension {
-            // Filter by extension first (uses index), then LIKE on name
-            let sql = r#"
-                SELECT id, path, name, extension, size, is_directory
-                FROM files
-                WHERE extension = ?1 AND name LIKE ?2
-                LIMIT ?3
-            "#;
-            let mut stmt = conn.prepare(sql)?;
-            stmt.query_map(params![ext, like_pattern, max_results as i64], row_to_file)?
-                .filter_map(|r| r.ok())
-                .collect()
-        } else {
-            // General search on name
-            let sql = r#"
-                SELECT id, path, name, extension, size, is_directory
-                FROM files
-                WHERE name LIKE ?1
-                LIMIT ?2
-            "#;
-            let mut stmt = conn.prepare(sql)?;
-            stmt.query_map(params![like_pattern, max_results as i64], row_to_file)?
-                .filter_map(|r| r.ok())
-                .collect()
+    /// Count the entries an exclude pattern would remove, matching it the way
+    /// `Config::should_exclude` does (against the whole path, `\\` read as `/`).
+    /// Entries under a matching directory count too, since scans never descend
+    /// into it. Reads the whole index; nothing is deleted.
+    pub fn preview_exclude(&self, pattern: &glob::Pattern, sample_size: usize) -> Result<ExcludePreview> {
+        let conn = self.reader();
+        // Path order puts each directory before everything under it
+        let mut stmt = conn.prepare("SELECT path, is_directory FROM files ORDER BY path")?;
+        let mut rows = stmt.query([])?;
+
+        let mut preview = ExcludePreview {
+            files: 0,
+            directories: 0,
+            sample: Vec::new(),
+        };
+        let mut excluded_dirs = HashSet::new();
+
+        while let Some(row) = rows.next()? {
+            let path: String = row.get(0)?;
+            let is_directory = row.get::<_, i32>(1)? != 0;
+            let normalized = path.replace('\\', "/");
+
+            let under_excluded = normalized
+                .match_indices('/')
+                .any(|(i, _)| excluded_dirs.contains(&normalized[..i]));
+            if !under_excluded && !pattern.matches(&normalized) {
+                continue;
+            }
+
+            if is_directory {
+                preview.directories += 1;
+                excluded_dirs.insert(normalized);
+            } else {
+                preview.files += 1;
+            }
+            if preview.sample.len() < sample_size {
+                preview.sample.push(path);
+            }
+        }
+
+        Ok(preview)
+    }
+
+    /// FROM and WHERE clauses matching a search query
+    fn search_filter(&self, conn: &Connection, query: &SearchQuery) -> Result<SearchFilter> {
+        // Names are stored normalized, so the query must be in the same form
+        let text = self.normalization.normalize(&query.query);
+
+        let (mut conditions, mut values): (Vec<String>, Vec<Value>) = match query.kind {
+            // Substring match on name (user wildcards match literally)
+            QueryKind::Substring => (
+                vec!["name LIKE ? ESCAPE '\\'".to_string()],
+                vec![format!("%{}%", escape_like(&text)).into()],
+            ),
+            // Seek on idx_files_name_nocase
+            QueryKind::Exact => (
+                vec!["name = ? COLLATE NOCASE".to_string()],
+                vec![text.to_string().into()],
+            ),
+        };
+
+        // An expanded abbreviation adds alternatives to the literal name match
+        let expansion = query.expansion.as_ref().filter(|_| query.kind == QueryKind::Substring);
+        if let Some(expansion) = expansion {
+            let mut alternatives = vec![conditions.remove(0)];
+            if !expansion.extensions.is_empty() {
+                let placeholders = vec!["?"; expansion.extensions.len()].join(", ");
+                alternatives.push(format!("extension COLLATE NOCASE IN ({})", placeholders));
+                values.extend(expansion.extensions.iter().map(|ext| Value::from(ext.clone())));
+            }
+            for name in &expansion.names {
+                alternatives.push("name LIKE ? ESCAPE '\\'".to_string());
+                values.push(format!("%{}%", escape_like(&self.normalization.normalize(name))).into());
+            }
+            conditions = vec![format!("({})", alternatives.join(" OR "))];
+        }
+
+        // Filter by extension first (idx_files_extension_name), then match the name
+        if let Some(ext) = &query.extension {
+            conditions.insert(0, "extension = ?".to_string());
+            values.insert(0, ext.clone().into());
+        }
+
+        if let Some(extensions) = &query.extensions {
+            let placeholders = vec!["?"; extensions.len()].join(", ");
+            conditions.push(format!("extension COLLATE NOCASE IN ({})", placeholders));
+            values.extend(extensions.iter().map(|ext| Value::from(ext.clone())));
+        }
+
+        let mut source = "files";
+        let mut order_column = "id";
+        if query.kind == QueryKind::Exact {
+            // Without this the planner prefers the extension index for its id order
+            // and scans every file of that extension
+            source = "files INDEXED BY idx_files_name_nocase";
+        } else if self.trigram_index
+            // Expanded alternatives needn't contain the query's trigrams
+            && expansion.is_none()
+            && let Some(trigram) = trigrams::rarest_trigram(conn, &name_trigrams(&text))?
+        {
+            // Drive the query from the postings of the query's rarest trigram; the LIKE still
+            // verifies. CROSS JOIN pins the join order so the planner can't fall back to a scan.
+            source = "name_trigrams CROSS JOIN files ON files.id = name_trigrams.file_id";
+            // Postings are stored in file_id order, so this needs no sort
+            order_column = "name_trigrams.file_id";
+            conditions.insert(0, "name_trigrams.trigram = ?".to_string());
+            values.insert(0, trigram.into());
+        }
+
+        // One JSON array parameter rather than a placeholder per path, so the list
+        // isn't bounded by SQLite's variable limit
+        if let Some(paths) = &query.restrict_to_paths {
+            conditions.push("path IN (SELECT value FROM json_each(?))".to_string());
+            values.push(serde_json::to_string(paths)?.into());
+        }
+
+        if let Some(drive) = &query.drive {
+            conditions.push("path LIKE ? ESCAPE '\\'".to_string());
+            values.push(format!("{}%", escape_like(&drive_path_prefix(drive))).into());
+        }
+
+        if let Some(scope) = &query.scope {
+            let (low, high) = scope_path_range(scope);
+            conditions.push("path >= ? AND path < ?".to_string());
+            values.push(low.into());
+            values.push(high.into());
+        }
+
+        // A dot at the start of any path component marks it (or a parent) hidden;
+        // matches the indexing-time `include_hidden` rule. Without ESCAPE, LIKE reads
+        // '\' literally, so the second pattern covers Windows paths.
+        if query.include_hidden == Some(false) {
+            conditions.push("path NOT LIKE '%/.%' AND path NOT LIKE '%\\.%'".to_string());
+        }
+
+        if let Some(category) = query.size_category {
+            let (low, high) = category.size_range(&self.size_categories);
+            conditions.push("is_directory = 0 AND size >= ?".to_string());
+            values.push((low as i64).into());
+            if let Some(high) = high {
+                conditions.push("size < ?".to_string());
+                values.push((high as i64).into());
+            }
+        }
+
+        // Entries without a creation time match neither bound
+        if let Some(after) = query.created_after {
+            conditions.push("created >= ?".to_string());
+            values.push(after.into());
+        }
+        if let Some(before) = query.created_before {
+            conditions.push("created < ?".to_string());
+            values.push(before.into());
+        }
+
+        if let Some(owner) = &query.owner {
+            conditions.push("owner = ?".to_string());
+            values.push(owner.clone().into());
+        }
+
+        // Bit 0o002 is "others may write" (on Windows: Everyone has write access)
+        match query.world_writable {
+            Some(true) => conditions.push("(mode & 2) != 0".to_string()),
+            Some(false) => conditions.push("(mode & 2) = 0".to_string()),
+            None => {}
+        }
+
+        if let Some(filter) = &query.filter {
+            conditions.push(filter.sql.clone());
+            values.extend(filter.values.iter().cloned());
+        }
+
+        Ok(SearchFilter {
+            source,
+            order_column,
+            conditions,
+            values,
+        })
+    }
+
+    /// Search files using simple LIKE queries (fast enough for filename search)
+    /// No FTS5 - Everything proves this approach works for billions of files
+    pub fn search(&self, query: &SearchQuery) -> Result<SearchResults> {
+        let start = std::time::Instant::now();
+        let conn = self.reader();
+        let SearchFilter {
+            source,
+            order_column,
+            conditions,
+            mut values,
+        } = self.search_filter(&conn, query)?;
+
+        // Ordering by id is total and matches the natural scan order, so pages never
+        // overlap or skip rows and the index paths need no extra sort. Other orders
+        // sort the matches, with id breaking ties. SQLite puts NULLs last for DESC.
+        let boost_names =
+            query.boost_name_matches && query.kind == QueryKind::Substring && !query.query.is_empty();
+        let mut order_values: Vec<Value> = Vec::new();
+        let order = match query.sort {
+            ResultSort::Rank if boost_names => {
+                // Names equal to the query, with or without their extension, then names
+                // starting with it, then the rest; shorter names first within each tier.
+                // Sorts every match instead of stopping at the limit.
+                let text = self.normalization.normalize(&query.query);
+                order_values.push(text.to_string().into());
+                order_values.push(text.to_string().into());
+                order_values.push(format!("{}%", escape_like(&text)).into());
+                format!(
+                    "CASE WHEN name = ? COLLATE NOCASE \
+                     OR substr(name, 1, length(name) - length(extension)) = ? COLLATE NOCASE THEN 0 \
+                     WHEN name LIKE ? ESCAPE '\\' THEN 1 ELSE 2 END, length(name), {}",
+                    order_column
+                )
+            }
+            ResultSort::Rank => order_column.to_string(),
+            ResultSort::Size => format!("size DESC, {}", order_column),
+            ResultSort::Name => format!("name COLLATE NOCASE, {}", order_column),
+            ResultSort::Created => format!("created DESC, {}", order_column),
         };
+        let sql = match query.max_per_directory {
+            None => {
+                values.extend(order_values);
+                format!(
+                    "SELECT {} FROM {} WHERE {} ORDER BY {} LIMIT ? OFFSET ?",
+                    FILE_COLUMNS,
+                    source,
+                    conditions.join(" AND "),
+                    order
+                )
+            }
+            // Number the matches within each parent directory and keep the first few.
+            // The order appears twice, ahead of the conditions, and so do its values.
+            Some(max) => {
+                values.splice(0..0, order_values.iter().chain(&order_values).cloned());
+                values.push((max as i64).into());
+                format!(
+                    "SELECT {cols} FROM (\
+                         SELECT {cols}, ROW_NUMBER() OVER (ORDER BY {order}) AS sort_key, ROW_NUMBER() OVER (\
+                             PARTITION BY {parent} ORDER BY {order}\
+                         ) AS dir_rank FROM {source} WHERE {conditions}\
+                     ) WHERE dir_rank <= ? ORDER BY sort_key LIMIT ? OFFSET ?",
+                    cols = FILE_COLUMNS,
+                    parent = PARENT_PATH_SQL,
+                    order = order,
+                    source = source,
+                    conditions = conditions.join(" AND "),
+                )
+            }
+        };
+        values.push((query.max_results as i64).into());
+        values.push((query.offset as i64).into());
+
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt
+            .query_map(params_from_iter(values), row_to_file)?
+            .filter_map(|r| r.ok());
+        let (files, truncated) = collect_capped(rows, self.max_result_bytes);
 
         let total_found = files.len();
         let query_time_ms = start.elapsed().as_millis() as u64;
@@ -216,12 +831,200 @@ impl Database {
             files,
             total_found,
             query_time_ms,
+            truncated,
+        })
+    }
+
+    /// Number of matches per extension, most common first, for filter UIs.
+    /// The query's own extension filter is ignored so every extension keeps its count;
+    /// paging fields don't apply. Directories and extensionless files count under None.
+    pub fn search_facets(&self, query: &SearchQuery) -> Result<Vec<ExtensionCount>> {
+        let conn = self.reader();
+        let query = SearchQuery {
+            extension: None,
+            ..query.clone()
+        };
+        let filter = self.search_filter(&conn, &query)?;
+
+        let sql = format!(
+            "SELECT extension, COUNT(*) AS matches FROM {} WHERE {} \
+             GROUP BY extension ORDER BY matches DESC, extension",
+            filter.source,
+            filter.conditions.join(" AND ")
+        );
+
+        let mut stmt = conn.prepare(&sql)?;
+        let counts = stmt
+            .query_map(params_from_iter(filter.values), |row| {
+                Ok(ExtensionCount {
+                    extension: row.get(0)?,
+                    count: row.get(1)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(counts)
+    }
+
+    /// Directories holding files that match the query, with how many each holds,
+    /// most matches first. Matching directories themselves aren't counted.
+    /// `max_results` and `offset` page through the directories.
+    pub fn containing_directories(&self, query: &SearchQuery) -> Result<Vec<DirectoryCount>> {
+        let conn = self.reader();
+        let SearchFilter {
+            source,
+            conditions,
+            mut values,
+            ..
+        } = self.search_filter(&conn, query)?;
+
+        let sql = format!(
+            "SELECT {} AS parent, COUNT(*) AS matches \
+             FROM {} WHERE {} AND is_directory = 0 \
+             GROUP BY parent ORDER BY matches DESC, parent LIMIT ? OFFSET ?",
+            PARENT_PATH_SQL,
+            source,
+            conditions.join(" AND ")
+        );
+        values.push((query.max_results as i64).into());
+        values.push((query.offset as i64).into());
+
+        let mut stmt = conn.prepare(&sql)?;
+        let directories = stmt
+            .query_map(params_from_iter(values), |row| {
+                let parent: String = row.get(0)?;
+                Ok(DirectoryCount {
+                    directory: trim_directory_separator(parent),
+                    count: row.get(1)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(directories)
+    }
+
+    /// Rewrite result paths and names using the lossless percent-encoding.
+    /// Rows whose text path holds U+FFFD are looked up for their original bytes.
+    pub fn percent_encode_paths(&self, files: &mut [IndexedFile]) -> Result<()> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare("SELECT raw_path FROM files WHERE id = ?1")?;
+
+        for file in files.iter_mut() {
+            let raw_path: Option<Vec<u8>> = if file.path.contains('\u{FFFD}') {
+                stmt.query_row(params![file.id], |row| row.get(0)).ok().flatten()
+            } else {
+                None
+            };
+
+            match raw_path {
+                Some(bytes) => {
+                    let name_start = bytes.iter().rposition(|&b| b == b'/').map_or(0, |i| i + 1);
+                    file.name = percent_encode_bytes(&bytes[name_start..]);
+                    file.path = percent_encode_bytes(&bytes);
+                }
+                None => {
+                    file.name = percent_encode_str(&file.name);
+                    file.path = percent_encode_str(&file.path);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Fill in `target` for results that were symbolic links when indexed: the fully
+    /// resolved path, or the link's own contents if that doesn't exist. Costs a lookup
+    /// per result and a few filesystem calls per link.
+    pub fn resolve_symlink_targets(&self, files: &mut [IndexedFile]) -> Result<()> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare_cached("SELECT is_symlink FROM files WHERE id = ?1")?;
+
+        for file in files.iter_mut() {
+            let is_symlink: bool = stmt.query_row(params![file.id], |row| row.get(0)).unwrap_or(false);
+            if !is_symlink {
+                continue;
+            }
+
+            let path = Path::new(&file.path);
+            file.target = std::fs::canonicalize(path)
+                .or_else(|_| std::fs::read_link(path))
+                .ok()
+                .map(|target| target.to_string_lossy().into_owned());
+        }
+
+        Ok(())
+    }
+
+    /// Get the most recently modified files, newest first (directories excluded),
+    /// optionally only those under the `scope` directory
+    pub fn newest(&self, limit: usize, scope: Option<&str>) -> Result<SearchResults> {
+        self.modified_since(None, limit, scope)
+    }
+
+    /// Files modified after `since` (Unix seconds; None = any time), newest first,
+    /// optionally only those under the `scope` directory. Seeks the modified-time index.
+    pub fn modified_since(&self, since: Option<i64>, limit: usize, scope: Option<&str>) -> Result<SearchResults> {
+        let start = std::time::Instant::now();
+        let conn = self.reader();
+
+        let mut conditions = vec!["is_directory = 0"];
+        let mut values: Vec<Value> = Vec::new();
+        if let Some(since) = since {
+            conditions.push("modified > ?");
+            values.push(since.into());
+        }
+        if let Some(scope) = scope {
+            let (low, high) = scope_path_range(scope);
+            conditions.push("path >= ? AND path < ?");
+            values.push(low.into());
+            values.push(high.into());
+        }
+        values.push((limit as i64).into());
+
+        let sql = format!(
+            "SELECT {} FROM files WHERE {} ORDER BY modified DESC, id DESC LIMIT ?",
+            FILE_COLUMNS,
+            conditions.join(" AND ")
+        );
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt
+            .query_map(params_from_iter(values), row_to_file)?
+            .filter_map(|r| r.ok());
+        let (files, truncated) = collect_capped(rows, self.max_result_bytes);
+
+        let total_found = files.len();
+        Ok(SearchResults {
+            files,
+            total_found,
+            query_time_ms: start.elapsed().as_millis() as u64,
+            truncated,
         })
     }
 
+    /// Entries the index has gone longest without refreshing, never-refreshed first.
+    /// Reads the whole table; meant for diagnosing stale entries, not for searches.
+    pub fn oldest_indexed(&self, limit: usize) -> Result<Vec<StaleEntry>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare(
+            "SELECT path, is_directory, modified, indexed_at FROM files
+             ORDER BY indexed_at, id LIMIT ?1",
+        )?;
+        let entries = stmt
+            .query_map(params![limit as i64], |row| {
+                Ok(StaleEntry {
+                    path: row.get(0)?,
+                    is_directory: row.get::<_, i32>(1)? != 0,
+                    modified: row.get(2)?,
+                    indexed_at: row.get(3)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(entries)
+    }
+
     /// Get index statistics
     pub fn get_stats(&self) -> Result<IndexStats> {
-        let conn = self.connection();
+        let conn = self.reader();
 
         let indexed_files: u64 = conn.query_row(
             "SELECT COUNT(*) FROM files WHERE is_directory = 0",
@@ -238,21 +1041,795 @@ impl Database {
         drop(conn);
 
         let database_size_bytes = self.get_size().unwrap_or(0);
+        let total_size_bytes = self.total_indexed_size()?;
+        let last_full_scan = self.last_full_scan()?;
+        let index_warning = self.index_warning()?;
 
         Ok(IndexStats {
             indexed_files,
             indexed_dirs,
             database_size_bytes,
+            total_size_bytes,
             is_scanning: false,  // Will be updated by indexer
             scan_progress: 0.0,
             current_scan_path: None,
+            low_disk_space: false,
+            last_full_scan,
+            index_warning,
+        })
+    }
+
+    /// Record that a full scan completed at `at` (Unix seconds)
+    pub fn record_full_scan(&self, at: i64) -> Result<()> {
+        let conn = self.connection();
+        conn.execute(
+            "INSERT OR REPLACE INTO stats (key, value) VALUES ('last_full_scan', ?1)",
+            params![at.to_string()],
+        )?;
+        Ok(())
+    }
+
+    /// When the last full scan completed (Unix seconds), if one has
+    pub fn last_full_scan(&self) -> Result<Option<i64>> {
+        let conn = self.reader();
+        let value: Option<String> = conn
+            .query_row("SELECT value FROM stats WHERE key = 'last_full_scan'", [], |row| row.get(0))
+            .optional()?;
+        // The schema seeds the key with 0
+        Ok(value.and_then(|value| value.parse().ok()).filter(|&at: &i64| at > 0))
+    }
+
+    /// Store why the last full scan looks incomplete, or clear it (None)
+    pub fn record_index_warning(&self, warning: Option<&str>) -> Result<()> {
+        let conn = self.connection();
+        match warning {
+            Some(warning) => conn.execute(
+                "INSERT OR REPLACE INTO stats (key, value) VALUES ('index_warning', ?1)",
+                params![warning],
+            )?,
+            None => conn.execute("DELETE FROM stats WHERE key = 'index_warning'", [])?,
+        };
+        Ok(())
+    }
+
+    /// Why the last full scan looks incomplete, if it does
+    pub fn index_warning(&self) -> Result<Option<String>> {
+        let conn = self.reader();
+        let warning = conn
+            .query_row("SELECT value FROM stats WHERE key = 'index_warning'", [], |row| row.get(0))
+            .optional()?;
+        Ok(warning)
+    }
+
+    /// Combined size in bytes of every indexed file (directories excluded)
+    pub fn total_indexed_size(&self) -> Result<u64> {
+        let conn = self.reader();
+        let total: i64 = conn.query_row(
+            "SELECT COALESCE(SUM(size), 0) FROM files WHERE is_directory = 0",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(total.max(0) as u64)
+    }
+
+    /// Breakdown of the index by extension, volume and size. Each part is one bounded
+    /// query, but they read the whole table, so this is kept out of `get_stats`.
+    /// `roots` are the drives or mount points to count files under. With a `scope`
+    /// directory, only the files under it are described.
+    pub fn detailed_stats(&self, roots: &[String], scope: Option<&str>) -> Result<DetailedStats> {
+        let conn = self.reader();
+        let (scope_sql, scope_values) = scope_condition(scope);
+
+        let (indexed_files, average_file_size): (u64, f64) = conn.query_row(
+            &format!(
+                "SELECT COUNT(*), COALESCE(AVG(size), 0) FROM files WHERE is_directory = 0{}",
+                scope_sql
+            ),
+            params_from_iter(&scope_values),
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+
+        let sql = format!(
+            "SELECT extension, COUNT(*) AS files, COALESCE(SUM(size), 0) FROM files \
+             WHERE is_directory = 0{} GROUP BY extension ORDER BY files DESC, extension LIMIT ?",
+            scope_sql
+        );
+        let values = scope_values.iter().cloned().chain([(DETAILED_TOP_EXTENSIONS as i64).into()]);
+        let top_extensions = conn
+            .prepare(&sql)?
+            .query_map(params_from_iter(values), |row| {
+                Ok(ExtensionUsage {
+                    extension: row.get(0)?,
+                    files: row.get(1)?,
+                    total_size: row.get(2)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let sql = format!(
+            "SELECT {} FROM files WHERE is_directory = 0{} ORDER BY size DESC, id LIMIT ?",
+            FILE_COLUMNS, scope_sql
+        );
+        let values = scope_values.iter().cloned().chain([(DETAILED_LARGEST_FILES as i64).into()]);
+        let largest_files = conn
+            .prepare(&sql)?
+            .query_map(params_from_iter(values), row_to_file)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let volumes = volume_counts(&conn, roots, scope)?;
+
+        Ok(DetailedStats {
+            indexed_files,
+            average_file_size: average_file_size as u64,
+            top_extensions,
+            volumes,
+            largest_files,
         })
     }
 
     /// Clear all indexed files
     pub fn clear_all(&self) -> Result<()> {
         let conn = self.connection();
+        if self.trigram_index {
+            // Empty the postings in one pass instead of row by row through the trigger
+            conn.execute("DELETE FROM name_trigrams", [])?;
+        }
         conn.execute("DELETE FROM files", [])?;
+        self.bump_generation();
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::config::UnicodeNormalization;
+    use crate::search::SizeCategory;
+    use crate::test_support::{seed, test_config, test_database};
+
+    /// Database holding `paths` (see `test_support::seed`)
+    fn seeded(paths: &[&str]) -> (Arc<Database>, TempDir) {
+        let dir = TempDir::new().unwrap();
+        let db = test_database(&test_config(&dir));
+        seed(&db, paths);
+        (db, dir)
+    }
+
+    fn paths(results: SearchResults) -> Vec<String> {
+        results.files.into_iter().map(|file| file.path).collect()
+    }
+
+    #[test]
+    fn hidden_entries_are_left_out_on_both_separators() {
+        let (db, _dir) = seeded(&[
+            "/home/bob/.config/app/settings.toml",
+            "/home/bob/.settings",
+            "/home/bob/settings.toml",
+            "C:\\Users\\bob\\.vscode\\settings.json",
+            "C:\\Users\\bob\\.settings",
+            "C:\\Users\\bob\\settings.json",
+        ]);
+
+        let query = SearchQuery::new("settings", 50).with_include_hidden(false);
+        let mut found = paths(db.search(&query).unwrap());
+        found.sort();
+        assert_eq!(found, ["/home/bob/settings.toml", "C:\\Users\\bob\\settings.json"]);
+
+        let query = SearchQuery::new("settings", 50).with_include_hidden(true);
+        assert_eq!(db.search(&query).unwrap().total_found, 6);
+    }
+
+    #[test]
+    fn max_per_directory_caps_each_directory_on_both_separators() {
+        let (db, _dir) = seeded(&[
+            "/data/a/log1.txt",
+            "/data/a/log2.txt",
+            "/data/a/log3.txt",
+            "/data/b/log1.txt",
+            "C:\\data\\a\\log1.txt",
+            "C:\\data\\a\\log2.txt",
+            "C:\\data\\a\\log3.txt",
+            "C:\\data\\b\\log1.txt",
+            "C:\\data\\b\\log2.txt",
+        ]);
+
+        let query = SearchQuery::new("log", 50).with_max_per_directory(2);
+        let mut found = paths(db.search(&query).unwrap());
+        found.sort();
+        assert_eq!(
+            found,
+            [
+                "/data/a/log1.txt",
+                "/data/a/log2.txt",
+                "/data/b/log1.txt",
+                "C:\\data\\a\\log1.txt",
+                "C:\\data\\a\\log2.txt",
+                "C:\\data\\b\\log1.txt",
+                "C:\\data\\b\\log2.txt",
+            ]
+        );
+    }
+
+    #[test]
+    fn containing_directories_group_by_parent_on_both_separators() {
+        let (db, _dir) = seeded(&[
+            "/data/a/log1.txt",
+            "/data/a/log2.txt",
+            "/log.txt",
+            "C:\\data\\a\\log1.txt",
+            "C:\\data\\a\\log2.txt",
+            "C:\\data\\a\\log3.txt",
+            "C:\\data\\b\\log1.txt",
+            "C:\\log.txt",
+        ]);
+
+        let query = SearchQuery::new("log", 50);
+        let directories: Vec<(String, u64)> = db
+            .containing_directories(&query)
+            .unwrap()
+            .into_iter()
+            .map(|dir| (dir.directory, dir.count))
+            .collect();
+        assert_eq!(
+            directories,
+            [
+                ("C:\\data\\a".to_string(), 3),
+                ("/data/a".to_string(), 2),
+                ("/".to_string(), 1),
+                ("C:\\".to_string(), 1),
+                ("C:\\data\\b".to_string(), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn volume_counts_cover_drive_letters_and_nested_mounts() {
+        let (db, _dir) = seeded(&[
+            "C:\\Windows\\notepad.exe",
+            "C:\\Users\\bob\\report.txt",
+            "D:\\backup\\a.zip",
+            "/etc/hosts",
+            "/mnt/data/a.bin",
+            "/mnt/data/b.bin",
+        ]);
+
+        let roots = ["C:\\", "D:\\", "E:\\", "/", "/mnt/data"].map(String::from);
+        let volumes: Vec<(String, u64)> = db
+            .detailed_stats(&roots, None)
+            .unwrap()
+            .volumes
+            .into_iter()
+            .map(|volume| (volume.root, volume.files))
+            .collect();
+        assert_eq!(
+            volumes,
+            [
+                ("/mnt/data".to_string(), 2),
+                ("C:\\".to_string(), 2),
+                ("/".to_string(), 1),
+                ("D:\\".to_string(), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn delete_unrefreshed_removes_stale_rows_under_the_directory() {
+        let (db, _dir) = seeded(&[
+            "C:\\Users\\bob\\",
+            "C:\\Users\\bob\\kept.txt",
+            "C:\\Users\\bob\\gone.txt",
+            "C:\\Users\\bob\\old\\gone.txt",
+            "C:\\Users\\bobby\\stale.txt",
+            "/home/bob/kept.txt",
+            "/home/bob/gone.txt",
+            "/home/bobby/stale.txt",
+        ]);
+        // Everything but the kept files was last written before the rescan started
+        db.connection()
+            .execute("UPDATE files SET indexed_at = 1000 WHERE name != 'kept.txt'", [])
+            .unwrap();
+
+        // Scans pass the watch path as configured, which may use either separator
+        assert_eq!(db.delete_unrefreshed("C:/Users/bob/", 2000).unwrap(), 2);
+        assert_eq!(db.delete_unrefreshed("/home/bob", 2000).unwrap(), 1);
+
+        let query = SearchQuery::new("", 50);
+        let mut left = paths(db.search(&query).unwrap());
+        left.sort();
+        assert_eq!(
+            left,
+            [
+                "/home/bob/kept.txt",
+                "/home/bobby/stale.txt",
+                "C:\\Users\\bob",
+                "C:\\Users\\bob\\kept.txt",
+                "C:\\Users\\bobby\\stale.txt",
+            ]
+        );
+    }
+
+    #[test]
+    fn like_wildcards_in_the_query_match_literally() {
+        let (db, _dir) = seeded(&[
+            "/srv/50%_off.pdf",
+            "/srv/50Xoff.pdf",
+            "/srv/50%Yoff.pdf",
+            "C:\\Sales\\a_b.txt",
+            "C:\\Sales\\aXb.txt",
+        ]);
+
+        assert_eq!(paths(db.search(&SearchQuery::new("50%_off", 50)).unwrap()), ["/srv/50%_off.pdf"]);
+        assert_eq!(paths(db.search(&SearchQuery::new("a_b", 50)).unwrap()), ["C:\\Sales\\a_b.txt"]);
+    }
+
+    #[test]
+    fn detailed_stats_sum_extensions_within_the_scope() {
+        let (db, _dir) = seeded(&[
+            "/home/bob/",
+            "/home/bob/a.pdf",
+            "/home/bob/b.pdf",
+            "/home/bob/notes",
+            "/home/alice/c.pdf",
+            "/home/alice/d.txt",
+        ]);
+        let usage = |stats: &DetailedStats| -> Vec<(Option<String>, u64, u64)> {
+            let extensions = stats.top_extensions.iter();
+            extensions.map(|usage| (usage.extension.clone(), usage.files, usage.total_size)).collect()
+        };
+        let roots = ["/".to_string()];
+
+        let stats = db.detailed_stats(&roots, None).unwrap();
+        assert_eq!(stats.indexed_files, 5);
+        assert_eq!(
+            usage(&stats),
+            [(Some(".pdf".to_string()), 3, 300), (None, 1, 100), (Some(".txt".to_string()), 1, 100)]
+        );
+
+        let stats = db.detailed_stats(&roots, Some("/home/bob")).unwrap();
+        assert_eq!(stats.indexed_files, 3);
+        assert_eq!(usage(&stats), [(Some(".pdf".to_string()), 2, 200), (None, 1, 100)]);
+        assert!(stats.largest_files.iter().all(|file| file.path.starts_with("/home/bob/")));
+        assert_eq!(stats.volumes, [VolumeCount { root: "/".to_string(), files: 3 }]);
+    }
+
+    #[test]
+    fn searches_run_while_a_write_transaction_is_open() {
+        let (db, _dir) = seeded(&["/data/report.txt"]);
+        db.begin_bulk_insert().unwrap();
+
+        // A scan batch holds the writer and an uncommitted transaction
+        let writer = db.connection();
+        let journal_mode: String = writer.query_row("PRAGMA journal_mode", [], |row| row.get(0)).unwrap();
+        assert_eq!(journal_mode, "wal");
+        writer.execute_batch("BEGIN IMMEDIATE; DELETE FROM files;").unwrap();
+
+        // The search must neither wait for the writer nor see its uncommitted changes
+        let (tx, rx) = std::sync::mpsc::channel();
+        let reader = db.clone();
+        std::thread::spawn(move || {
+            let _ = tx.send(reader.search(&SearchQuery::new("report", 50)).map(paths));
+        });
+        let found = rx.recv_timeout(std::time::Duration::from_secs(5)).expect("search blocked by the writer");
+        assert_eq!(found.unwrap(), ["/data/report.txt"]);
+
+        writer.execute_batch("COMMIT").unwrap();
+        drop(writer);
+        db.end_bulk_insert().unwrap();
+        assert!(db.search(&SearchQuery::new("report", 50)).unwrap().files.is_empty());
+    }
+
+    #[test]
+    fn trigram_index_finds_the_same_rows_as_a_scan() {
+        let names = [
+            "/data/report_2024.txt",
+            "/data/Quarterly REPORT.pdf",
+            "/data/reports/",
+            "/data/rep.txt",
+            "/srv/50%_off.pdf",
+            "/srv/50Xoff.pdf",
+            "/srv/100%.txt",
+            "C:\\Sales\\a_b.txt",
+            "C:\\Sales\\aXb.txt",
+            "/home/bob/Résumé.odt",
+        ];
+        let (scanned, _scanned_dir) = seeded(&names);
+        let dir = TempDir::new().unwrap();
+        let mut config = test_config(&dir);
+        config.performance.trigram_index = true;
+        let indexed = test_database(&config);
+        seed(&indexed, &names);
+        let postings: i64 =
+            indexed.connection().query_row("SELECT COUNT(*) FROM name_trigrams", [], |row| row.get(0)).unwrap();
+        assert!(postings > 0);
+
+        let search = |db: &Database, query: &str| {
+            let mut found = paths(db.search(&SearchQuery::new(query, 100)).unwrap());
+            found.sort();
+            found
+        };
+        // Under three characters there is no trigram to look up, and the scan is used
+        for query in ["report", "REPORT", "port_", "rep", "re", "r", "50%_off", "0%", "%", "_", "a_b", "umé", "zzz"] {
+            assert_eq!(search(&indexed, query), search(&scanned, query), "query {:?}", query);
+        }
+        assert_eq!(search(&indexed, "port").len(), 3);
+        assert_eq!(search(&indexed, "50%_off"), ["/srv/50%_off.pdf"]);
+        assert_eq!(search(&indexed, "a_b"), ["C:\\Sales\\a_b.txt"]);
+        assert_eq!(search(&indexed, "0%").len(), 2);
+    }
+
+    #[test]
+    fn pages_continue_without_gaps_or_overlaps() {
+        // Ties in every ordering: equal tiers, name lengths, names and sizes
+        let names = [
+            "/b/report_b.txt",
+            "/a/report",
+            "/a/report_a.txt",
+            "/c/my report.txt",
+            "/b/report",
+            "/a/report.txt",
+            "/c/report_c.txt",
+            "/c/old report.txt",
+            "/b/reports/",
+            "/a/reporting.md",
+            "/c/report",
+        ];
+        let (db, _dir) = seeded(&names);
+        let dir = TempDir::new().unwrap();
+        let mut config = test_config(&dir);
+        config.performance.trigram_index = true;
+        let indexed = test_database(&config);
+        seed(&indexed, &names);
+
+        for db in [&db, &indexed] {
+            for sort in [ResultSort::Rank, ResultSort::Size, ResultSort::Name, ResultSort::Created] {
+                let query = |max_results| SearchQuery::new("report", max_results).with_sort(sort);
+                let all = paths(db.search(&query(100)).unwrap());
+                assert_eq!(all.len(), names.len());
+
+                let mut paged = Vec::new();
+                for page in 0.. {
+                    let files = paths(db.search(&query(3).with_offset(page * 3)).unwrap());
+                    if files.is_empty() {
+                        break;
+                    }
+                    paged.extend(files);
+                }
+                assert_eq!(paged, all, "{:?}", sort);
+            }
+
+            // The boost puts the exact names first, ties in insertion order
+            let first = paths(db.search(&SearchQuery::new("report", 3)).unwrap());
+            assert_eq!(first, ["/a/report", "/b/report", "/c/report"]);
+        }
+    }
+
+    #[test]
+    fn composed_and_decomposed_names_find_each_other() {
+        let nfc = "/linux/caf\u{e9}.txt";
+        let nfd = "/mac/cafe\u{301}.txt";
+        let ligature = "/data/\u{fb01}le.txt";
+        let with = |normalization| {
+            let dir = TempDir::new().unwrap();
+            let mut config = test_config(&dir);
+            config.search.unicode_normalization = normalization;
+            let db = test_database(&config);
+            seed(&db, &[nfc, nfd, ligature]);
+            (db, dir)
+        };
+        let search = |db: &Database, query: &str| {
+            let mut found = paths(db.search(&SearchQuery::new(query, 50)).unwrap());
+            found.sort();
+            found
+        };
+
+        let (db, _dir) = with(UnicodeNormalization::Nfc);
+        assert_eq!(search(&db, "caf\u{e9}"), [nfc, nfd]);
+        assert_eq!(search(&db, "cafe\u{301}"), [nfc, nfd]);
+        assert!(search(&db, "file").is_empty());
+
+        let (db, _dir) = with(UnicodeNormalization::Nfkc);
+        assert_eq!(search(&db, "cafe\u{301}"), [nfc, nfd]);
+        assert_eq!(search(&db, "file"), [ligature]);
+
+        let (db, _dir) = with(UnicodeNormalization::None);
+        assert_eq!(search(&db, "caf\u{e9}"), [nfc]);
+        assert_eq!(search(&db, "cafe\u{301}"), [nfd]);
+    }
+
+    #[test]
+    fn exact_names_rank_above_prefix_and_substring_matches() {
+        let (db, _dir) = seeded(&[
+            "/a/app-config-old.yaml",
+            "/a/myconfig",
+            "/a/config_x",
+            "/a/config.json",
+            "/b/config",
+            "/a/CONFIG",
+            "/a/configure.sh",
+        ]);
+
+        let ranked = paths(db.search(&SearchQuery::new("config", 50)).unwrap());
+        assert_eq!(
+            ranked,
+            [
+                // Equal to the query, with or without the extension; shorter first
+                "/b/config",
+                "/a/CONFIG",
+                "/a/config.json",
+                // Starting with it
+                "/a/config_x",
+                "/a/configure.sh",
+                // Containing it
+                "/a/myconfig",
+                "/a/app-config-old.yaml",
+            ]
+        );
+
+        // Without the boost, plain index order
+        let query = SearchQuery::new("config", 50).with_boost_name_matches(false);
+        assert_eq!(paths(db.search(&query).unwrap())[..2], ["/a/app-config-old.yaml", "/a/myconfig"]);
+
+        // The tiers hold within an extension filter and a per-directory cap too
+        let query = SearchQuery::new("config", 50).with_extension(".json");
+        assert_eq!(paths(db.search(&query).unwrap()), ["/a/config.json"]);
+        let query = SearchQuery::new("config", 50).with_max_per_directory(2);
+        assert_eq!(paths(db.search(&query).unwrap()), ["/b/config", "/a/CONFIG", "/a/config.json"]);
+    }
+
+    #[test]
+    fn exact_queries_match_whole_names_in_any_case() {
+        let names = [
+            "/a/Config.JSON",
+            "C:\\b\\config.json",
+            "/a/config.json.bak",
+            "/a/my config.json",
+            "/a/config%json",
+            "/a/config",
+        ];
+        let (db, _dir) = seeded(&names);
+        let dir = TempDir::new().unwrap();
+        let mut config = test_config(&dir);
+        config.performance.trigram_index = true;
+        let indexed = test_database(&config);
+        seed(&indexed, &names);
+
+        for db in [&db, &indexed] {
+            let exact = |query: &str| SearchQuery::new(query, 50).with_kind(QueryKind::Exact);
+            assert_eq!(paths(db.search(&exact("config.json")).unwrap()), ["/a/Config.JSON", "C:\\b\\config.json"]);
+            assert_eq!(paths(db.search(&exact("config")).unwrap()), ["/a/config"]);
+            // No wildcards: % is an ordinary character
+            assert_eq!(paths(db.search(&exact("config%json")).unwrap()), ["/a/config%json"]);
+            assert!(db.search(&exact("config.js")).unwrap().files.is_empty());
+        }
+    }
+
+    #[test]
+    fn size_categories_filter_on_half_open_ranges() {
+        let (db, _dir) = seeded(&["/d/below.bin", "/d/at.bin", "/d/top.bin", "/d/next.bin", "/d/small/"]);
+        let sizes = [
+            ("below.bin", 100 * 1024 - 1),
+            ("at.bin", 100 * 1024),
+            ("top.bin", 1024 * 1024 - 1),
+            ("next.bin", 1024 * 1024),
+        ];
+        for (name, size) in sizes {
+            db.connection().execute("UPDATE files SET size = ?1 WHERE name = ?2", params![size, name]).unwrap();
+        }
+
+        let search = |category| {
+            let mut found = paths(db.search(&SearchQuery::new("", 50).with_size_category(category)).unwrap());
+            found.sort();
+            found
+        };
+        // Directories have size 0 but never match a category
+        assert_eq!(search(SizeCategory::Tiny), ["/d/below.bin"]);
+        assert_eq!(search(SizeCategory::Small), ["/d/at.bin", "/d/top.bin"]);
+        assert_eq!(search(SizeCategory::Medium), ["/d/next.bin"]);
+    }
+
+    #[test]
+    fn every_upsert_refreshes_indexed_at() {
+        let root = crate::test_support::scan_root();
+        crate::test_support::create_files(root.path(), &["single.txt", "batch.txt"]);
+        let single = root.path().join("single.txt");
+        let batch = root.path().join("batch.txt");
+        let (db, _dir) = seeded(&[&single.to_string_lossy(), &batch.to_string_lossy(), "/data/metadata.txt"]);
+        let ids = |db: &Database| {
+            let mut rows: Vec<(String, i64)> =
+                db.search(&SearchQuery::new("", 50)).unwrap().files.into_iter().map(|f| (f.path, f.id)).collect();
+            rows.sort();
+            rows
+        };
+        let before = ids(&db);
+        db.connection().execute("UPDATE files SET indexed_at = 1000", []).unwrap();
+
+        // The watcher's, the walkdir scanner's and the MFT scanner's write paths
+        let started = chrono::Utc::now().timestamp();
+        db.upsert_file(&single, false, 4, 1, None).unwrap();
+        db.batch_upsert_files(&[(batch.clone(), false, false)]).unwrap();
+        seed(&db, &["/data/metadata.txt"]);
+
+        let stale = db.oldest_indexed(10).unwrap();
+        assert_eq!(stale.len(), 3);
+        assert!(stale.iter().all(|entry| entry.indexed_at >= Some(started)), "{:?}", stale);
+        assert_eq!(ids(&db), before, "rows are updated in place");
+    }
+
+    #[test]
+    fn names_come_from_the_path_on_either_separator() {
+        let (db, _dir) = seeded(&["C:\\Users\\bob\\Report.TXT", "/home/bob/Notes.md", "/"]);
+        let conn = db.connection();
+        conn.execute(r"INSERT INTO files (path, name, size, is_directory, modified) VALUES ('C:\', '', 0, 1, 0)", [])
+            .unwrap();
+        // The column is a search key; the returned name must not be
+        conn.execute("UPDATE files SET name = lower(name)", []).unwrap();
+        conn.execute(r"UPDATE files SET name = 'root' WHERE path IN ('/', 'C:\')", []).unwrap();
+        drop(conn);
+
+        let mut names: Vec<(String, String)> = db
+            .search(&SearchQuery::new("", 50))
+            .unwrap()
+            .files
+            .into_iter()
+            .map(|f| (f.path, f.name))
+            .collect();
+        names.sort();
+        let expected = [
+            ("/", "root"),
+            ("/home/bob/Notes.md", "Notes.md"),
+            ("C:\\", "root"),
+            ("C:\\Users\\bob\\Report.TXT", "Report.TXT"),
+        ];
+        assert_eq!(names, expected.map(|(path, name)| (path.to_string(), name.to_string())));
+    }
+
+    #[test]
+    fn newest_orders_by_modified_time_and_cuts_off_after_since() {
+        let (db, _dir) = seeded(&[
+            "/data/old.txt",
+            "/data/mid.txt",
+            "/data/tie_a.txt",
+            "/data/tie_b.txt",
+            "/data/new.txt",
+            "/data/fresh/",
+            "/other/newest.txt",
+        ]);
+        let conn = db.connection();
+        for (path, modified) in [
+            ("/data/old.txt", 1_000),
+            ("/data/mid.txt", 2_000),
+            ("/data/tie_a.txt", 3_000),
+            ("/data/tie_b.txt", 3_000),
+            ("/data/new.txt", 4_000),
+            // Directories never show up, however recent
+            ("/data/fresh", 9_000),
+            ("/other/newest.txt", 5_000),
+        ] {
+            conn.execute("UPDATE files SET modified = ?1 WHERE path = ?2", params![modified, path]).unwrap();
+        }
+        drop(conn);
+
+        // Ties go to the most recently inserted row
+        assert_eq!(
+            paths(db.newest(10, None).unwrap()),
+            [
+                "/other/newest.txt",
+                "/data/new.txt",
+                "/data/tie_b.txt",
+                "/data/tie_a.txt",
+                "/data/mid.txt",
+                "/data/old.txt",
+            ]
+        );
+        assert_eq!(paths(db.newest(2, None).unwrap()), ["/other/newest.txt", "/data/new.txt"]);
+        assert_eq!(paths(db.newest(10, Some("/data")).unwrap())[0], "/data/new.txt");
+
+        // The cutoff is exclusive
+        assert_eq!(
+            paths(db.modified_since(Some(3_000), 10, None).unwrap()),
+            ["/other/newest.txt", "/data/new.txt"]
+        );
+        assert_eq!(paths(db.modified_since(Some(2_999), 10, Some("/data")).unwrap()).len(), 3);
+        assert!(db.modified_since(Some(5_000), 10, None).unwrap().files.is_empty());
+    }
+
+    #[test]
+    fn restrict_to_paths_searches_only_the_listed_files() {
+        let (db, _dir) = seeded(&[
+            "/data/a/report.txt",
+            "/data/a/notes.txt",
+            "/data/b/report.md",
+            "/data/b/summary.txt",
+            "/data/c/report.pdf",
+            "C:\\data\\report.txt",
+        ]);
+        let listed = vec![
+            "/data/a/report.txt".to_string(),
+            "/data/b/report.md".to_string(),
+            "C:\\data\\report.txt".to_string(),
+            "/data/not/indexed.txt".to_string(),
+        ];
+        let search = |query: SearchQuery| {
+            let mut found = paths(db.search(&query).unwrap());
+            found.sort();
+            found
+        };
+
+        assert_eq!(
+            search(SearchQuery::new("", 50).with_restrict_to_paths(listed.clone())),
+            ["/data/a/report.txt", "/data/b/report.md", "C:\\data\\report.txt"]
+        );
+        // Combined with the other conditions
+        assert_eq!(
+            search(SearchQuery::new("report", 50).with_extension(".txt").with_restrict_to_paths(listed.clone())),
+            ["/data/a/report.txt", "C:\\data\\report.txt"]
+        );
+        assert!(search(SearchQuery::new("", 50).with_restrict_to_paths(Vec::new())).is_empty());
+
+        // Far past SQLite's variable limit
+        let mut many: Vec<String> = (0..40_000).map(|i| format!("/data/none/{}.txt", i)).collect();
+        many.push("/data/c/report.pdf".to_string());
+        assert_eq!(search(SearchQuery::new("report", 50).with_restrict_to_paths(many)), ["/data/c/report.pdf"]);
+    }
+
+    #[test]
+    fn preview_exclude_counts_matches_without_deleting_them() {
+        let (db, _dir) = seeded(&[
+            "/proj/node_modules/",
+            "/proj/node_modules/lib/",
+            "/proj/node_modules/lib/index.js",
+            "/proj/node_modules/readme.md",
+            "/proj/src/main.js",
+            "/proj/build.log",
+            "/proj/src/debug.log",
+            "C:\\proj\\trace.log",
+        ]);
+
+        // Everything under a matching directory counts, matched or not
+        let preview = db.preview_exclude(&glob::Pattern::new("**/node_modules").unwrap(), 10).unwrap();
+        assert_eq!((preview.files, preview.directories), (2, 2));
+        assert_eq!(
+            preview.sample,
+            [
+                "/proj/node_modules",
+                "/proj/node_modules/lib",
+                "/proj/node_modules/lib/index.js",
+                "/proj/node_modules/readme.md",
+            ]
+        );
+
+        // Windows paths are matched with '/' separators; the sample is capped
+        let preview = db.preview_exclude(&glob::Pattern::new("**/*.log").unwrap(), 2).unwrap();
+        assert_eq!((preview.files, preview.directories), (3, 0));
+        assert_eq!(preview.sample, ["/proj/build.log", "/proj/src/debug.log"]);
+
+        let preview = db.preview_exclude(&glob::Pattern::new("**/*.tmp").unwrap(), 10).unwrap();
+        assert_eq!((preview.files, preview.directories), (0, 0));
+        assert!(preview.sample.is_empty());
+
+        assert_eq!(db.search(&SearchQuery::new("", 50)).unwrap().total_found, 8);
+    }
+
+    #[test]
+    fn total_indexed_size_follows_inserts_and_deletes() {
+        let (db, _dir) = seeded(&["/data/a.bin", "/data/b.bin", "/data/sub/"]);
+        assert_eq!(db.total_indexed_size().unwrap(), 200);
+
+        // Directories don't count, whatever size they carry
+        db.connection().execute("UPDATE files SET size = 4096 WHERE path = '/data/sub'", []).unwrap();
+        assert_eq!(db.total_indexed_size().unwrap(), 200);
+
+        db.upsert_file(Path::new("/data/sub/c.bin"), false, 5_000_000_000, 0, None).unwrap();
+        assert_eq!(db.total_indexed_size().unwrap(), 5_000_000_200);
+
+        db.delete_file("/data/a.bin").unwrap();
+        assert_eq!(db.total_indexed_size().unwrap(), 5_000_000_100);
+
+        db.delete_directory("/data").unwrap();
+        assert_eq!(db.total_indexed_size().unwrap(), 0);
+    }
+}
@@ -1,17 +1,86 @@
 //! Database schema and initialization
 
 use anyhow::{Context, Result};
-use rusqlite::Connection;
+use rusqlite::{Connection, OpenFlags};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
-use tracing::info;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
 
-use crate::config::Config;
+use crate::config::{Config, SizeCategoryBounds, UnicodeNormalization};
 
 /// Database wrapper with connection pooling
 #[derive(Clone)]
 pub struct Database {
+    /// Connection for writes (indexing, watcher updates, maintenance)
     conn: Arc<Mutex<Connection>>,
+    /// Read-only connection for searches, so they run alongside a bulk scan
+    reader: Arc<Mutex<Connection>>,
     db_path: String,
+    /// Maintain the `name_trigrams` table (see trigrams.rs)
+    pub(super) trigram_index: bool,
+    /// Fill the `owner` and `mode` columns when indexing
+    pub(super) track_ownership: bool,
+    /// Form that stored names and queries are normalized to
+    pub(super) normalization: UnicodeNormalization,
+    /// Boundaries of the `size_category` search classes
+    pub(super) size_categories: SizeCategoryBounds,
+    /// Cap on the estimated size of one result set
+    pub(super) max_result_bytes: usize,
+    /// Bumped on every write, so clients can tell when cached results went stale
+    generation: Arc<AtomicU64>,
+}
+
+/// Memory map set on the search connection by `warm_up`
+const WARMUP_MMAP_BYTES: i64 = 256 * 1024 * 1024;
+
+/// Rows read from the files table and from the name index by `warm_up`
+const WARMUP_ROWS: i64 = 100_000;
+
+/// SQLite files making up one database in WAL mode
+const DB_FILE_SUFFIXES: [&str; 3] = ["", "-wal", "-shm"];
+
+/// Pick up a database left at an older default location, so a changed data
+/// directory doesn't look like a lost index. Moves it when `service.migrate_legacy_db`
+/// is on, otherwise only says where it is.
+fn adopt_legacy_db(config: &Config) -> Result<()> {
+    let Some(legacy) = config.legacy_db_paths().into_iter().find(|path| path.is_file()) else {
+        return Ok(());
+    };
+
+    if !config.service.migrate_legacy_db {
+        warn!(
+            "No database at {:?}, but one exists at {:?}. Move it there (with its -wal/-shm files) \
+             to keep the existing index, or set service.db_path to use it in place.",
+            config.db_path, legacy
+        );
+        return Ok(());
+    }
+
+    info!("Migrating database from {:?} to {:?}", legacy, config.db_path);
+    for suffix in DB_FILE_SUFFIXES {
+        let from = with_suffix(&legacy, suffix);
+        if !from.exists() {
+            continue;
+        }
+        let to = with_suffix(&config.db_path, suffix);
+        // Rename fails across volumes: copy, then remove the original
+        if std::fs::rename(&from, &to).is_err() {
+            std::fs::copy(&from, &to)
+                .with_context(|| format!("Failed to migrate {:?} to {:?}", from, to))?;
+            if let Err(e) = std::fs::remove_file(&from) {
+                warn!("Migrated {:?} but could not remove it: {}", from, e);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> std::path::PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    name.into()
 }
 
 impl Database {
@@ -25,15 +94,39 @@ impl Database {
                 .with_context(|| format!("Failed to create database directory: {:?}", parent))?;
         }
 
+        if !config.db_path.exists() {
+            adopt_legacy_db(config)?;
+        }
+
         let conn = Connection::open(&config.db_path)
             .with_context(|| format!("Failed to open database: {:?}", config.db_path))?;
 
         // Enable WAL mode for better concurrent access
         conn.execute_batch("PRAGMA journal_mode=WAL; PRAGMA synchronous=NORMAL;")?;
 
+        // WAL lets this connection read committed data while the writer holds a transaction
+        let reader = Connection::open_with_flags(
+            &config.db_path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )
+        .with_context(|| format!("Failed to open database reader: {:?}", config.db_path))?;
+
         Ok(Self {
             conn: Arc::new(Mutex::new(conn)),
+            reader: Arc::new(Mutex::new(reader)),
             db_path,
+            trigram_index: config.performance.trigram_index,
+            track_ownership: config.indexing.track_ownership,
+            normalization: config.search.unicode_normalization,
+            size_categories: config.search.size_categories,
+            max_result_bytes: config.performance.max_result_mb.saturating_mul(1024 * 1024),
+            // Seeded from the clock so generations never repeat across daemon restarts
+            generation: Arc::new(AtomicU64::new(
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_millis() as u64)
+                    .unwrap_or(0),
+            )),
         })
     }
 
@@ -41,11 +134,11 @@ impl Database {
     /// Call this before starting a large batch insert operation
     pub fn begin_bulk_insert(&self) -> Result<()> {
         let conn = self.conn.lock().unwrap();
-        // Use journal_mode=OFF for fast writes without journal overhead
+        // synchronous=OFF skips fsync per batch; journal_mode stays WAL so the
+        // reader connection keeps serving searches while the scan runs
         // Reduced cache_size to 50MB to limit RAM usage
         conn.execute_batch(
             "PRAGMA synchronous = OFF;
-             PRAGMA journal_mode = OFF;
              PRAGMA cache_size = -50000;
              PRAGMA temp_store = MEMORY;"
         )?;
@@ -59,10 +152,7 @@ impl Database {
         let conn = self.conn.lock().unwrap();
 
         // Restore normal settings
-        conn.execute_batch(
-            "PRAGMA synchronous = NORMAL;
-             PRAGMA journal_mode = WAL;"
-        )?;
+        conn.execute_batch("PRAGMA synchronous = NORMAL;")?;
         info!("Bulk insert mode disabled, normal settings restored");
 
         // Note: VACUUM removed - it takes too long on large databases
@@ -71,6 +161,73 @@ impl Database {
         Ok(())
     }
 
+    /// Let SQLite refresh query planner statistics for tables that changed enough
+    /// to need it. Cheap when nothing did; run after scans and periodically.
+    pub fn optimize(&self) -> Result<()> {
+        self.conn.lock().unwrap().execute_batch("PRAGMA optimize;")?;
+        Ok(())
+    }
+
+    /// Rebuild query planner statistics from scratch. Slower than `optimize`, for
+    /// after a full scan, when the statistics describe a much smaller table.
+    pub fn analyze(&self) -> Result<()> {
+        self.conn.lock().unwrap().execute_batch("ANALYZE;")?;
+        Ok(())
+    }
+
+    /// Flush the WAL into the main file and refresh query planner statistics.
+    /// Call on graceful shutdown so the next startup doesn't replay a large WAL.
+    pub fn shutdown(&self) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute_batch("PRAGMA optimize;")?;
+
+        // Returns (busy, wal frames, frames checkpointed); busy means a reader blocked truncation
+        let (busy, frames): (i64, i64) = conn.query_row(
+            "PRAGMA wal_checkpoint(TRUNCATE)",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+
+        if busy != 0 {
+            warn!("WAL checkpoint incomplete at shutdown ({} frames pending)", frames);
+        } else {
+            info!("Database checkpointed and closed");
+        }
+        Ok(())
+    }
+
+    /// Pull the start of the files table and the name index into cache, so the first
+    /// search after startup doesn't pay for cold reads, then refresh planner statistics.
+    /// Memory-maps the database on the search connection, so warmed pages live in the
+    /// OS page cache. Returns the number of rows read.
+    pub fn warm_up(&self) -> Result<usize> {
+        let mut rows = 0;
+        {
+            let reader = self.reader();
+            // Returns the size actually granted, which may be lower than asked
+            let _: i64 = reader.query_row(
+                &format!("PRAGMA mmap_size = {}", WARMUP_MMAP_BYTES),
+                [],
+                |row| row.get(0),
+            )?;
+
+            // Substring searches scan the table; exact ones seek the NOCASE index
+            for sql in [
+                "SELECT name FROM files LIMIT ?1",
+                "SELECT name FROM files INDEXED BY idx_files_name_nocase ORDER BY name COLLATE NOCASE LIMIT ?1",
+            ] {
+                let mut stmt = reader.prepare(sql)?;
+                let mut result = stmt.query([WARMUP_ROWS])?;
+                while result.next()?.is_some() {
+                    rows += 1;
+                }
+            }
+        }
+
+        self.conn.lock().unwrap().execute_batch("PRAGMA optimize;")?;
+        Ok(rows)
+    }
+
     /// Initialize the database schema
     pub fn init_schema(&self) -> Result<()> {
         let conn = self.conn.lock().unwrap();
@@ -93,6 +250,26 @@ impl Database {
 
         conn.execute_batch(SCHEMA_SQL)?;
 
+        // Add columns introduced after the table was first created
+        for (column, definition) in ADDED_COLUMNS {
+            let exists: bool = conn
+                .query_row(
+                    "SELECT COUNT(*) FROM pragma_table_info('files') WHERE name = ?1",
+                    [column],
+                    |row| row.get::<_, i64>(0),
+                )
+                .map(|count| count > 0)
+                .unwrap_or(false);
+
+            if !exists {
+                info!("Adding column '{}' to files table", column);
+                conn.execute_batch(&format!("ALTER TABLE files ADD COLUMN {} {}", column, definition))?;
+            }
+        }
+
+        conn.execute_batch(INDEX_SQL)?;
+        self.sync_trigram_index(&conn)?;
+
         info!("Database schema initialized");
         Ok(())
     }
@@ -102,6 +279,26 @@ impl Database {
         self.conn.lock().unwrap()
     }
 
+    /// Get the read-only connection used by searches
+    pub fn reader(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.reader.lock().unwrap()
+    }
+
+    /// Current index generation; changes whenever indexed data changes
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    /// Mark the indexed data as changed
+    pub(super) fn bump_generation(&self) {
+        self.generation.fetch_add(1, Ordering::AcqRel);
+    }
+
+    /// Get the database file path
+    pub fn path(&self) -> &str {
+        &self.db_path
+    }
+
     /// Get database file size in bytes
     pub fn get_size(&self) -> Result<u64> {
         let metadata = std::fs::metadata(&self.db_path)?;
@@ -113,20 +310,19 @@ impl Database {
 /// Optimized for fast bulk inserts and small database size
 /// No FTS5 - uses simple LIKE queries which are fast enough for filename search
 const SCHEMA_SQL: &str = r#"
--- Main files table (simplified - removed directory, modified, indexed_at)
+-- Main files table (simplified - removed directory, indexed_at)
 CREATE TABLE IF NOT EXISTS files (
     id INTEGER PRIMARY KEY,
     path TEXT NOT NULL UNIQUE,
     name TEXT NOT NULL,
     extension TEXT,
     size INTEGER NOT NULL DEFAULT 0,
-    is_directory INTEGER NOT NULL DEFAULT 0
+    is_directory INTEGER NOT NULL DEFAULT 0,
+    modified INTEGER NOT NULL DEFAULT 0,
+    -- Original path bytes, only set when the path is not valid UTF-8
+    raw_path BLOB
 );
 
--- Only 2 indexes needed for search
-CREATE INDEX IF NOT EXISTS idx_files_name ON files(name);
-CREATE INDEX IF NOT EXISTS idx_files_extension ON files(extension);
-
 -- Index statistics table
 CREATE TABLE IF NOT EXISTS stats (
     key TEXT PRIMARY KEY,
@@ -138,3 +334,38 @@ INSERT OR IGNORE INTO stats (key, value) VALUES ('last_full_scan', '0');
 INSERT OR IGNORE INTO stats (key, value) VALUES ('total_files', '0');
 INSERT OR IGNORE INTO stats (key, value) VALUES ('total_dirs', '0');
 "#;
+
+/// Columns added to `files` after the initial schema: (name, definition).
+/// Existing databases get them via ALTER TABLE in `init_schema`.
+const ADDED_COLUMNS: &[(&str, &str)] = &[
+    ("modified", "INTEGER NOT NULL DEFAULT 0"),
+    ("raw_path", "BLOB"),
+    // Set only with `indexing.track_ownership`: uid or SID string, and Unix mode
+    // bits (on Windows only 0o002, "writable by Everyone", is meaningful)
+    ("owner", "TEXT"),
+    ("mode", "INTEGER"),
+    // Creation time in Unix seconds; NULL where the filesystem doesn't record one
+    ("created", "INTEGER"),
+    // When the row was last written by a scan or the watcher (Unix seconds), as
+    // opposed to the file's own mtime; NULL for imported rows
+    ("indexed_at", "INTEGER"),
+    // Symbolic links (and on Windows junctions and mount points), so result target
+    // resolution only touches actual links
+    ("is_symlink", "INTEGER NOT NULL DEFAULT 0"),
+];
+
+/// Indexes, created after column migration so they can reference added columns
+const INDEX_SQL: &str = r#"
+-- Exact name lookups; NOCASE matches the ASCII case folding of LIKE
+CREATE INDEX IF NOT EXISTS idx_files_name_nocase ON files(name COLLATE NOCASE);
+-- Superseded by idx_files_name_nocase (substring LIKE can't use either)
+DROP INDEX IF EXISTS idx_files_name;
+-- Extension-filtered searches: rows of one extension in id order (the result order, so
+-- no sort), carrying the name so the LIKE is checked on the index entry and only matches
+-- read the table row. 2M files: ~2.5x faster, +37 MB (~13%) over the plain extension index.
+CREATE INDEX IF NOT EXISTS idx_files_extension_name ON files(extension, id, name);
+-- Superseded by idx_files_extension_name
+DROP INDEX IF EXISTS idx_files_extension;
+-- Newest-first queries (modified time in Unix seconds)
+CREATE INDEX IF NOT EXISTS idx_files_modified ON files(modified);
+"#;
@@ -0,0 +1,247 @@
+//! Writers for exported search results
+//!
+//! `stella-search export` writes JSONL by default: one `IndexedFile` per line, in
+//! the shape `stella-search import` reads back. Other consumers want a single JSON
+//! array or CSV, Windows tools often want `\r\n` line endings, and some only detect
+//! UTF-8 when the file starts with a byte order mark.
+
+use std::io::Write;
+
+use anyhow::Result;
+
+use crate::database::IndexedFile;
+
+/// UTF-8 byte order mark
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Layout of exported results
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExportFormat {
+    /// One JSON object per line (default; readable by `import`)
+    #[default]
+    Jsonl,
+    /// A single JSON array, one object per line
+    JsonArray,
+    /// CSV with a header row (path,name,extension,size,is_directory)
+    Csv,
+}
+
+/// Line terminator written after each record
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum LineEnding {
+    /// `\n` (default)
+    #[default]
+    Lf,
+    /// `\r\n`
+    Crlf,
+}
+
+impl LineEnding {
+    fn as_bytes(self) -> &'static [u8] {
+        match self {
+            LineEnding::Lf => b"\n",
+            LineEnding::Crlf => b"\r\n",
+        }
+    }
+}
+
+/// How results are written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExportOptions {
+    pub format: ExportFormat,
+    pub line_ending: LineEnding,
+    /// Start the output with a UTF-8 byte order mark
+    pub bom: bool,
+}
+
+/// Writes files one at a time in the chosen format
+pub struct Exporter<W: Write> {
+    sink: Sink<W>,
+    line_ending: LineEnding,
+    written: usize,
+}
+
+enum Sink<W: Write> {
+    Json { writer: W, array: bool },
+    /// Boxed: the CSV writer carries its own buffer
+    Csv(Box<csv::Writer<W>>),
+}
+
+impl<W: Write> Exporter<W> {
+    /// Start an export, writing the BOM and any header or opening bracket
+    pub fn new(mut writer: W, options: ExportOptions) -> Result<Self> {
+        if options.bom {
+            writer.write_all(UTF8_BOM)?;
+        }
+
+        let sink = match options.format {
+            ExportFormat::Jsonl => Sink::Json { writer, array: false },
+            ExportFormat::JsonArray => {
+                writer.write_all(b"[")?;
+                Sink::Json { writer, array: true }
+            }
+            ExportFormat::Csv => {
+                let terminator = match options.line_ending {
+                    LineEnding::Lf => csv::Terminator::Any(b'\n'),
+                    LineEnding::Crlf => csv::Terminator::CRLF,
+                };
+                let mut csv = csv::WriterBuilder::new().terminator(terminator).from_writer(writer);
+                csv.write_record(["path", "name", "extension", "size", "is_directory"])?;
+                Sink::Csv(Box::new(csv))
+            }
+        };
+
+        Ok(Self {
+            sink,
+            line_ending: options.line_ending,
+            written: 0,
+        })
+    }
+
+    /// Write one file. CSV fields holding commas, quotes or newlines are quoted;
+    /// files without an extension get an empty field.
+    pub fn write(&mut self, file: &IndexedFile) -> Result<()> {
+        match &mut self.sink {
+            Sink::Json { writer, array } => {
+                if *array {
+                    if self.written > 0 {
+                        writer.write_all(b",")?;
+                    }
+                    writer.write_all(self.line_ending.as_bytes())?;
+                }
+                serde_json::to_writer(&mut *writer, file)?;
+                if !*array {
+                    writer.write_all(self.line_ending.as_bytes())?;
+                }
+            }
+            Sink::Csv(csv) => csv.serialize((
+                &file.path,
+                &file.name,
+                file.extension.as_deref().unwrap_or(""),
+                file.size,
+                file.is_directory,
+            ))?,
+        }
+        self.written += 1;
+        Ok(())
+    }
+
+    /// Close the array if any and flush; returns the number of files written
+    pub fn finish(self) -> Result<usize> {
+        match self.sink {
+            Sink::Json { mut writer, array } => {
+                if array {
+                    if self.written > 0 {
+                        writer.write_all(self.line_ending.as_bytes())?;
+                    }
+                    writer.write_all(b"]")?;
+                    writer.write_all(self.line_ending.as_bytes())?;
+                }
+                writer.flush()?;
+            }
+            Sink::Csv(mut csv) => csv.flush()?,
+        }
+        Ok(self.written)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::search::SearchQuery;
+    use crate::test_support::{seed, test_config, test_database};
+
+    /// Search results for `paths`, in index order
+    fn seeded_results(paths: &[&str]) -> Vec<IndexedFile> {
+        let dir = TempDir::new().unwrap();
+        let db = test_database(&test_config(&dir));
+        seed(&db, paths);
+        db.search(&SearchQuery::new("", 50).with_boost_name_matches(false)).unwrap().files
+    }
+
+    /// `files` exported with `options`
+    fn export(files: &[IndexedFile], options: ExportOptions) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut exporter = Exporter::new(&mut out, options).unwrap();
+        for file in files {
+            exporter.write(file).unwrap();
+        }
+        assert_eq!(exporter.finish().unwrap(), files.len());
+        out
+    }
+
+    #[test]
+    fn csv_fields_with_commas_quotes_and_newlines_parse_back_intact() {
+        let files = seeded_results(&[
+            "/data/budget, final.xlsx",
+            "/data/the \"real\" one.txt",
+            "/data/two\nlines.md",
+            "/data/Makefile",
+            "/data/reports/",
+        ]);
+        let csv = export(&files, ExportOptions { format: ExportFormat::Csv, ..Default::default() });
+        let text = String::from_utf8(csv.clone()).unwrap();
+        assert!(text.starts_with("path,name,extension,size,is_directory\n"));
+        assert!(text.contains("\"/data/budget, final.xlsx\","));
+        assert!(text.contains("\"/data/the \"\"real\"\" one.txt\","));
+        assert!(text.contains("\"/data/two\nlines.md\","));
+
+        let mut reader = csv::Reader::from_reader(&csv[..]);
+        assert_eq!(reader.headers().unwrap(), vec!["path", "name", "extension", "size", "is_directory"]);
+        let records: Vec<Vec<String>> = reader
+            .records()
+            .map(|record| record.unwrap().iter().map(String::from).collect())
+            .collect();
+        let expected = [
+            ["/data/budget, final.xlsx", "budget, final.xlsx", ".xlsx", "100", "false"],
+            ["/data/the \"real\" one.txt", "the \"real\" one.txt", ".txt", "100", "false"],
+            ["/data/two\nlines.md", "two\nlines.md", ".md", "100", "false"],
+            ["/data/Makefile", "Makefile", "", "100", "false"],
+            ["/data/reports", "reports", "", "0", "true"],
+        ];
+        assert_eq!(records, expected);
+    }
+
+    #[test]
+    fn each_format_line_ending_and_bom_writes_the_expected_bytes() {
+        let files = seeded_results(&["/data/a.txt", "/data/docs/"]);
+        let a = r#"{"id":1,"path":"/data/a.txt","name":"a.txt","extension":".txt","size":100,"is_directory":false,"modified":1700000000}"#;
+        let docs = r#"{"id":2,"path":"/data/docs","name":"docs","extension":null,"size":0,"is_directory":true,"modified":1700000000}"#;
+        // With \n endings; no field holds a newline, so \r\n output only differs there
+        let golden = [
+            (ExportFormat::Jsonl, format!("{a}\n{docs}\n")),
+            (ExportFormat::JsonArray, format!("[\n{a},\n{docs}\n]\n")),
+            (
+                ExportFormat::Csv,
+                "path,name,extension,size,is_directory\n/data/a.txt,a.txt,.txt,100,false\n/data/docs,docs,,0,true\n"
+                    .to_string(),
+            ),
+        ];
+
+        for (format, lf) in golden {
+            for line_ending in [LineEnding::Lf, LineEnding::Crlf] {
+                for bom in [false, true] {
+                    let mut expected = if bom { UTF8_BOM.to_vec() } else { Vec::new() };
+                    expected.extend(lf.replace('\n', std::str::from_utf8(line_ending.as_bytes()).unwrap()).bytes());
+                    let options = ExportOptions { format, line_ending, bom };
+                    assert_eq!(
+                        String::from_utf8_lossy(&export(&files, options)),
+                        String::from_utf8_lossy(&expected),
+                        "{:?}",
+                        options
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn an_empty_export_is_still_well_formed() {
+        let export = |format| export(&[], ExportOptions { format, ..Default::default() });
+        assert_eq!(export(ExportFormat::Jsonl), b"");
+        assert_eq!(export(ExportFormat::JsonArray), b"[]\n");
+        assert_eq!(export(ExportFormat::Csv), b"path,name,extension,size,is_directory\n");
+    }
+}
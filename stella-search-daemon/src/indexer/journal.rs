@@ -0,0 +1,184 @@
+//! Crash journal for the file watcher
+//!
+//! Events wait in the watcher channel, and new files wait in the settle queue, before
+//! they reach the database. A crash loses both, and the changes stay missing until the
+//! next reindex. Each event's paths are appended to a journal file next to the database
+//! before the event is queued. The journal is emptied whenever the watcher is idle with
+//! nothing in flight. On the next start, any paths still in it are checked against the
+//! disk and re-indexed or removed.
+//!
+//! Only paths are logged, not operations: replaying a path means indexing whatever is on
+//! disk now, which is right whatever happened to it. Paths that aren't valid UTF-8 are
+//! not logged.
+
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::{Context, Result};
+use tracing::{debug, warn};
+
+/// Most paths kept in the journal; later ones are left to the next reindex
+const MAX_JOURNAL_ENTRIES: usize = 100_000;
+
+/// Journal of paths whose changes may not have reached the database yet
+pub(super) struct WatchJournal {
+    inner: Mutex<JournalFile>,
+}
+
+struct JournalFile {
+    /// None if the journal couldn't be opened; events are then only counted
+    file: Option<File>,
+    /// Paths written since the journal was last emptied
+    entries: usize,
+    /// Events queued to the watcher loop and not yet processed
+    in_flight: usize,
+}
+
+impl WatchJournal {
+    /// Open the journal at `path`, returning it along with the paths a previous run
+    /// left unprocessed
+    pub(super) fn open(path: &Path) -> Result<(Self, Vec<PathBuf>)> {
+        let leftover = read_entries(path)?;
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open watch journal {:?}", path))?;
+
+        let journal = Self {
+            inner: Mutex::new(JournalFile {
+                file: Some(file),
+                entries: leftover.len(),
+                in_flight: 0,
+            }),
+        };
+        Ok((journal, leftover))
+    }
+
+    /// A journal that records nothing, for when the file can't be opened
+    pub(super) fn disabled() -> Self {
+        Self {
+            inner: Mutex::new(JournalFile {
+                file: None,
+                entries: 0,
+                in_flight: 0,
+            }),
+        }
+    }
+
+    /// Log an event's paths, then queue the event with `send` while the journal is
+    /// still locked, so `clear_if_idle` can never see the paths without the event
+    pub(super) fn record<T>(&self, paths: &[PathBuf], send: impl FnOnce() -> T) -> T {
+        let mut journal = self.inner.lock().unwrap();
+
+        let mut lines = String::new();
+        for path in paths {
+            if journal.file.is_none() || journal.entries >= MAX_JOURNAL_ENTRIES {
+                break;
+            }
+            if let Some(path) = path.to_str()
+                && let Ok(line) = serde_json::to_string(path)
+            {
+                lines.push_str(&line);
+                lines.push('\n');
+                journal.entries += 1;
+            }
+        }
+        if !lines.is_empty()
+            && let Some(file) = &mut journal.file
+            && let Err(e) = file.write_all(lines.as_bytes())
+        {
+            debug!("Failed to write watch journal: {}", e);
+        }
+
+        journal.in_flight += 1;
+        send()
+    }
+
+    /// The watcher loop finished processing one recorded event
+    pub(super) fn event_done(&self) {
+        let mut journal = self.inner.lock().unwrap();
+        journal.in_flight = journal.in_flight.saturating_sub(1);
+    }
+
+    /// Empty the journal if no recorded event is still queued and `settled` (nothing
+    /// waits in the settle queue either)
+    pub(super) fn clear_if_idle(&self, settled: bool) {
+        let mut journal = self.inner.lock().unwrap();
+        if journal.entries == 0 || journal.in_flight > 0 || !settled {
+            return;
+        }
+
+        if let Some(file) = &journal.file {
+            match file.set_len(0) {
+                Ok(()) => journal.entries = 0,
+                Err(e) => debug!("Failed to clear watch journal: {}", e),
+            }
+        }
+    }
+}
+
+/// Paths in a journal file, without duplicates (missing file: none)
+fn read_entries(path: &Path) -> Result<Vec<PathBuf>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read watch journal {:?}", path)),
+    };
+
+    let mut entries = Vec::new();
+    let mut seen = std::collections::HashSet::new();
+    let mut lines = 0;
+    for line in BufReader::new(file).lines() {
+        lines += 1;
+        // A torn last line from a crash mid-write is skipped
+        let Ok(path) = serde_json::from_str::<String>(&line?) else {
+            continue;
+        };
+        if seen.insert(path.clone()) {
+            entries.push(PathBuf::from(path));
+        }
+    }
+
+    if lines >= MAX_JOURNAL_ENTRIES {
+        warn!("Watch journal was full; run a reindex to pick up every change made before the last shutdown");
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn the_journal_is_kept_while_events_are_in_flight() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("index.watch-journal");
+        let (journal, _) = WatchJournal::open(&path).unwrap();
+        journal.record(&[PathBuf::from("/data/a.txt")], || ());
+
+        // Queued but not processed, or processed but still settling: not yet safe to forget
+        journal.clear_if_idle(true);
+        journal.event_done();
+        journal.clear_if_idle(false);
+        assert_eq!(read_entries(&path).unwrap(), [PathBuf::from("/data/a.txt")]);
+
+        journal.clear_if_idle(true);
+        assert!(read_entries(&path).unwrap().is_empty());
+    }
+
+    #[test]
+    fn a_torn_last_line_is_skipped() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("index.watch-journal");
+        std::fs::write(&path, "\"/data/a.txt\"\n\"/data/b.txt\"\n\"/data/a.txt\"\n\"/data/c.t").unwrap();
+
+        let (_journal, leftover) = WatchJournal::open(&path).unwrap();
+        assert_eq!(leftover, [PathBuf::from("/data/a.txt"), PathBuf::from("/data/b.txt")]);
+    }
+}
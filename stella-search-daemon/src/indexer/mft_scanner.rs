@@ -6,13 +6,14 @@
 #[cfg(windows)]
 use anyhow::{Context, Result};
 #[cfg(windows)]
+use ntfs_reader::file::NtfsFile;
+#[cfg(windows)]
 use ntfs_reader::file_info::FileInfo;
 #[cfg(windows)]
 use ntfs_reader::mft::Mft;
 #[cfg(windows)]
 use ntfs_reader::volume::Volume;
 #[cfg(windows)]
-use std::sync::atomic::Ordering;
 #[cfg(windows)]
 use tracing::{error, info, warn};
 
@@ -53,6 +54,7 @@ pub async fn scan_volume_mft(
     // Use large batch size for bulk inserts (50,000 files per transaction)
     let batch_size = 50_000;
     let include_hidden = config.watch.include_hidden;
+    let follow_reparse_points = config.watch.follow_reparse_points;
 
     let mut batch: Vec<FileMetadata> = Vec::with_capacity(batch_size);
     let mut indexed_count = 0u64;
@@ -65,15 +67,23 @@ pub async fn scan_volume_mft(
     // Iterate through all MFT entries
     info!("Starting MFT iteration...");
     mft.iterate_files(|file| {
-        if indexer.should_stop() {
+        if indexer.scan_cancelled() {
             return; // Exit iteration early
         }
 
-        let info = FileInfo::new(&mft, file);
         processed += 1;
 
+        // Junctions and mount points to other volumes: their targets are indexed
+        // by that volume's own scan, indexing the link too shows up as a duplicate
+        let is_symlink = is_link(file);
+        if !follow_reparse_points && is_symlink {
+            return;
+        }
+
+        let info = FileInfo::new(&mft, file);
+
         // Skip system files and special entries
-        if should_skip_mft_entry(&info, include_hidden, config) {
+        if should_skip_mft_entry(&info, include_hidden, config, indexer.extension_filter()) {
             return;
         }
 
@@ -100,12 +110,17 @@ pub async fn scan_volume_mft(
             name: info.name.clone(),
             size: if info.is_directory { 0 } else { info.size as i64 },
             is_directory: info.is_directory,
+            modified: info.modified.map(|t| t.unix_timestamp()).unwrap_or(0),
+            // $STANDARD_INFORMATION creation time
+            created: info.created.map(|t| t.unix_timestamp()),
+            is_symlink,
         };
 
         batch.push(metadata);
 
         // Flush batch when full
         if batch.len() >= batch_size {
+            indexer.wait_for_disk_space();
             if let Err(e) = indexer.db().batch_upsert_files_with_metadata(&batch) {
                 warn!("Failed to batch insert: {}", e);
             } else {
@@ -132,25 +147,79 @@ pub async fn scan_volume_mft(
     }
 
     info!(
-        "MFT scan complete for {}: indexed {} files from {} MFT records",
-        volume_path, indexed_count, processed
+        volume = %volume_path,
+        indexed = indexed_count,
+        records = processed,
+        "MFT scan complete for volume"
     );
 
+    // The record count includes free records, but a healthy volume still indexes a
+    // good share of it; a handful of entries means the iteration went wrong
+    let too_few = indexed_count.saturating_mul(100) < total_estimate.saturating_mul(MIN_INDEXED_PERCENT_OF_MFT);
+    if too_few && !indexer.scan_cancelled() {
+        indexer.flag_small_scan(format!(
+            "{}: indexed {} entries of about {} MFT records",
+            drive_letter, indexed_count, total_estimate
+        ));
+    }
+
     Ok(indexed_count)
 }
 
+/// Below this share of a volume's MFT records indexed, the scan is flagged as incomplete
+#[cfg(windows)]
+const MIN_INDEXED_PERCENT_OF_MFT: u64 = 5;
+
+/// $REPARSE_POINT attribute type (missing from ntfs_reader's NtfsAttributeType)
+#[cfg(windows)]
+const REPARSE_POINT_ATTRIBUTE: u32 = 0xC0;
+
+/// Reparse tag bit of tags that redirect to another path (symlinks, junctions, volume
+/// mount points). Other reparse points, like OneDrive placeholders, are real files.
+#[cfg(windows)]
+const REPARSE_TAG_NAME_SURROGATE: u32 = 0x2000_0000;
+
+/// Whether an MFT record is a symlink, junction or volume mount point.
+/// Only the entry itself is affected: the MFT holds each volume's own records,
+/// so the scan never descends through a link either way.
+#[cfg(windows)]
+fn is_link(file: &NtfsFile) -> bool {
+    let mut tag = None;
+    file.attributes(|att| {
+        if att.header.type_id == REPARSE_POINT_ATTRIBUTE {
+            // The reparse data starts with the little-endian tag
+            tag = att
+                .get_resident()
+                .and_then(|data| data.first_chunk::<4>())
+                .map(|bytes| u32::from_le_bytes(*bytes));
+        }
+    });
+    tag.is_some_and(|tag| tag & REPARSE_TAG_NAME_SURROGATE != 0)
+}
+
 /// Check if an MFT entry should be skipped
 #[cfg(windows)]
 fn should_skip_mft_entry(
     info: &FileInfo,
     include_hidden: bool,
-    _config: &crate::config::Config,
+    config: &crate::config::Config,
+    extensions: &crate::config::ExtensionFilter,
 ) -> bool {
     // Skip entries with empty names (deleted or system metadata)
     if info.name.is_empty() {
         return true;
     }
 
+    // Skip files whose extension isn't indexed (the watcher skips them too)
+    if !info.is_directory && !extensions.allows_file(&info.name) {
+        return true;
+    }
+
+    // Skip exact names from watch.exclude_names (desktop.ini, Thumbs.db, ...)
+    if config.is_excluded_name(&info.name) {
+        return true;
+    }
+
     // Skip common system files/directories
     let name_lower = info.name.to_lowercase();
     if name_lower == "$mft"
@@ -172,6 +241,11 @@ fn should_skip_mft_entry(
         return true;
     }
 
+    // Skip the per-volume recycle bin and everything in it
+    if is_in_recycle_bin(&info.path.to_string_lossy()) {
+        return true;
+    }
+
     // Skip hidden files if configured
     if !include_hidden && info.name.starts_with('.') {
         return true;
@@ -180,6 +254,17 @@ fn should_skip_mft_entry(
     false
 }
 
+/// Check if a volume-relative MFT path (e.g. `\$Recycle.Bin\S-1-5-21\$R1.txt`) is in the recycle bin.
+/// Every NTFS volume has its own `$Recycle.Bin` at the root.
+#[cfg(windows)]
+fn is_in_recycle_bin(raw_path: &str) -> bool {
+    raw_path
+        .trim_start_matches(['\\', '/'])
+        .split(['\\', '/'])
+        .next()
+        .is_some_and(|first| first.eq_ignore_ascii_case("$recycle.bin"))
+}
+
 /// Get list of NTFS drives on the system
 #[cfg(windows)]
 pub fn get_ntfs_drives() -> Vec<char> {
@@ -230,17 +315,39 @@ pub fn get_ntfs_drives() -> Vec<char> {
 /// Start MFT-based initial scan for all NTFS volumes
 #[cfg(windows)]
 pub async fn start_mft_scan(indexer: &Indexer) -> Result<()> {
-    indexer.state.is_scanning.store(true, Ordering::Relaxed);
-    indexer.state.scan_progress.store(0, Ordering::Relaxed);
-
-    let ntfs_drives = get_ntfs_drives();
+    let mut ntfs_drives = get_ntfs_drives();
     info!("Found {} NTFS drives: {:?}", ntfs_drives.len(), ntfs_drives);
 
+    // A volume is read whole, so priority directories can only put their drive first
+    let priority_drives: Vec<char> = indexer
+        .config()
+        .scan_priority_paths()
+        .iter()
+        .filter_map(|path| {
+            let path = path.to_string_lossy();
+            let mut chars = path.chars();
+            match (chars.next(), chars.next()) {
+                (Some(letter), Some(':')) if letter.is_ascii_alphabetic() => Some(letter.to_ascii_uppercase()),
+                _ => None,
+            }
+        })
+        .collect();
+    ntfs_drives.sort_by_key(|drive| {
+        priority_drives
+            .iter()
+            .position(|letter| letter == drive)
+            .unwrap_or(usize::MAX)
+    });
+
     if ntfs_drives.is_empty() {
         warn!("No NTFS drives found, falling back to walkdir scanner");
         return super::scanner::start_initial_scan(indexer).await;
     }
 
+    if !indexer.begin_scan() {
+        return Err(super::ScanInProgress.into());
+    }
+
     // Enable bulk insert mode for maximum speed
     if let Err(e) = indexer.db().begin_bulk_insert() {
         warn!("Failed to enable bulk insert mode: {}", e);
@@ -250,11 +357,13 @@ pub async fn start_mft_scan(indexer: &Indexer) -> Result<()> {
     let mut total_indexed = 0u64;
 
     for (i, drive) in ntfs_drives.iter().enumerate() {
-        if indexer.should_stop() {
+        if indexer.scan_cancelled() {
             info!("MFT scan stopped by request");
             break;
         }
 
+        indexer.wait_for_disk_space_async().await;
+
         let base_progress = i as f64 / total_drives as f64;
         let progress_range = 1.0 / total_drives as f64;
 
@@ -286,8 +395,16 @@ pub async fn start_mft_scan(indexer: &Indexer) -> Result<()> {
         warn!("Failed to disable bulk insert mode: {}", e);
     }
 
-    indexer.state.is_scanning.store(false, Ordering::Relaxed);
-    indexer.set_progress(1.0, None);
+    // Statistics gathered on a near-empty table would steer searches away from the indexes
+    if let Err(e) = indexer.db().analyze() {
+        warn!("Failed to analyze database after full scan: {}", e);
+    }
+
+    indexer.finish_scan();
+
+    if !indexer.scan_cancelled() {
+        indexer.record_full_scan();
+    }
 
     info!("MFT scan complete: indexed {} total files", total_indexed);
     Ok(())
@@ -2,6 +2,7 @@
 //!
 //! Handles directory scanning and file watching.
 
+mod journal;
 mod scanner;
 mod watcher;
 #[cfg(windows)]
@@ -10,40 +11,133 @@ mod mft_scanner;
 #[allow(unused_imports)]
 pub use scanner::scan_directory_public;
 
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, atomic::{AtomicBool, AtomicU64, Ordering}};
 use std::sync::RwLock;
+use std::time::{Duration, Instant};
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::{debug, info, warn};
 
-use crate::config::Config;
+use crate::config::{Config, ExtensionFilter};
 use crate::database::Database;
+use crate::platform;
+
+/// `last_progress_ms` value before the first progress update
+const NO_PROGRESS_YET: u64 = u64::MAX;
+
+/// How often to re-check free disk space while indexing is paused
+const DISK_SPACE_RECHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How often a scan paused for disk space looks for a cancel or shutdown
+const DISK_SPACE_CANCEL_POLL: Duration = Duration::from_millis(200);
 
 /// Shared indexer state
 #[derive(Clone)]
 pub struct Indexer {
     db: Database,
     config: Config,
+    /// Built once from `config.watch`
+    extensions: ExtensionFilter,
+    disk_space: DiskSpaceProbe,
     state: Arc<IndexerState>,
 }
 
+/// Free bytes available on the volume holding a path
+type FreeSpaceFn = dyn Fn(&Path) -> Result<u64> + Send + Sync;
+
+/// Free space lookup for the low-disk pause, and how often it is repeated while
+/// paused. Tests swap in their own lookup.
+#[derive(Clone)]
+struct DiskSpaceProbe {
+    free_bytes: Arc<FreeSpaceFn>,
+    recheck_interval: Duration,
+}
+
+impl Default for DiskSpaceProbe {
+    fn default() -> Self {
+        Self {
+            free_bytes: Arc::new(platform::free_disk_space),
+            recheck_interval: DISK_SPACE_RECHECK_INTERVAL,
+        }
+    }
+}
+
+/// A scan or reindex was asked to start while another one was running
+#[derive(Error, Debug)]
+#[error("a scan or reindex is already running")]
+pub struct ScanInProgress;
+
 /// Indexer runtime state
 pub struct IndexerState {
     pub is_scanning: AtomicBool,
     pub scan_progress: AtomicU64,  // Stored as progress * 10000 for precision
     pub current_scan_path: RwLock<Option<String>>,
+    /// Reference point for `last_progress_ms`
+    pub created: Instant,
+    /// When progress was last published (ms since `created`), for throttling
+    pub last_progress_ms: AtomicU64,
     pub should_stop: AtomicBool,
+    /// Stops the running scan only; the watcher keeps going. Cleared when a scan starts.
+    pub cancel_scan: AtomicBool,
+    pub low_disk_space: AtomicBool,
+    /// Per watch root state, keyed by the configured path
+    pub watches: RwLock<BTreeMap<PathBuf, WatchPathStatus>>,
+    /// Watcher implementation in use ("native" or "poll"), once started
+    pub watcher_kind: RwLock<Option<String>>,
+    /// Roots the running scan indexed implausibly little of (see `flag_small_scan`)
+    pub small_scans: RwLock<Vec<String>>,
+}
+
+/// Live state of one watch root
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WatchPathStatus {
+    pub path: String,
+    /// The watcher is registered on this path
+    pub watching: bool,
+    /// The initial scan of this path finished (or was skipped because it was already indexed)
+    pub initial_scan_complete: bool,
+    /// Last error from watching or scanning this path
+    pub error: Option<String>,
+    /// The watcher lost events under this path; a reconciliation scan is pending
+    #[serde(default)]
+    pub needs_rescan: bool,
 }
 
 impl Indexer {
     /// Create a new indexer
     pub fn new(db: Database, config: Config) -> Self {
+        let watches = config
+            .get_watch_paths()
+            .into_iter()
+            .map(|path| {
+                let status = WatchPathStatus {
+                    path: path.to_string_lossy().to_string(),
+                    ..Default::default()
+                };
+                (path, status)
+            })
+            .collect();
+
         Self {
             db,
+            extensions: ExtensionFilter::new(&config.watch),
+            disk_space: DiskSpaceProbe::default(),
             config,
             state: Arc::new(IndexerState {
                 is_scanning: AtomicBool::new(false),
                 scan_progress: AtomicU64::new(0),
                 current_scan_path: RwLock::new(None),
+                created: Instant::now(),
+                last_progress_ms: AtomicU64::new(NO_PROGRESS_YET),
                 should_stop: AtomicBool::new(false),
+                cancel_scan: AtomicBool::new(false),
+                low_disk_space: AtomicBool::new(false),
+                watches: RwLock::new(watches),
+                watcher_kind: RwLock::new(None),
+                small_scans: RwLock::new(Vec::new()),
             }),
         }
     }
@@ -63,24 +157,259 @@ impl Indexer {
         self.state.current_scan_path.read().unwrap().clone()
     }
 
-    /// Set scan progress
+    /// Set scan progress, at most once per `progress_interval_ms`.
+    /// Completion (1.0) is always published so a finished scan never shows stale progress.
     fn set_progress(&self, progress: f64, path: Option<&str>) {
+        if progress < 1.0 && !self.progress_update_due() {
+            return;
+        }
+
         self.state.scan_progress.store((progress * 10000.0) as u64, Ordering::Relaxed);
         if let Some(p) = path {
             *self.state.current_scan_path.write().unwrap() = Some(p.to_string());
         }
     }
 
+    /// Claim the next progress update: true at most once per interval
+    fn progress_update_due(&self) -> bool {
+        let interval = self.config.performance.progress_interval_ms;
+        if interval == 0 {
+            return true;
+        }
+
+        let now = self.state.created.elapsed().as_millis() as u64;
+        let last = self.state.last_progress_ms.load(Ordering::Relaxed);
+        if last != NO_PROGRESS_YET && now.saturating_sub(last) < interval {
+            return false;
+        }
+
+        // Concurrent scans (a reindex during a scan) race for the slot; one wins
+        self.state
+            .last_progress_ms
+            .compare_exchange(last, now, Ordering::Relaxed, Ordering::Relaxed)
+            .is_ok()
+    }
+
     /// Request stop
     pub fn request_stop(&self) {
         self.state.should_stop.store(true, Ordering::Relaxed);
     }
 
-    /// Check if should stop
+    /// Check if should stop (set once, at shutdown)
     fn should_stop(&self) -> bool {
         self.state.should_stop.load(Ordering::Relaxed)
     }
 
+    /// Cancel the running scan or reindex, leaving the watcher running.
+    /// Returns false if no scan was in progress.
+    pub fn cancel_scan(&self) -> bool {
+        if !self.is_scanning() {
+            return false;
+        }
+        self.state.cancel_scan.store(true, Ordering::Relaxed);
+        true
+    }
+
+    /// Check if the running scan should stop (shutdown or cancellation)
+    fn scan_cancelled(&self) -> bool {
+        self.should_stop() || self.state.cancel_scan.load(Ordering::Relaxed)
+    }
+
+    /// Claim the scan and mark it started. Returns false, changing nothing, if another
+    /// scan or reindex holds it: one runs at a time, so `is_scanning`, progress and
+    /// cancellation always describe that one.
+    /// Cancellation is per scan: a cancel aimed at an earlier scan (even one that bailed
+    /// out with an error) must not stop this one. `should_stop` is left alone, since it
+    /// means the daemon is shutting down.
+    fn begin_scan(&self) -> bool {
+        if self
+            .state
+            .is_scanning
+            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+            .is_err()
+        {
+            return false;
+        }
+        self.state.cancel_scan.store(false, Ordering::Relaxed);
+        self.state.scan_progress.store(0, Ordering::Relaxed);
+        self.state.last_progress_ms.store(NO_PROGRESS_YET, Ordering::Relaxed);
+        self.state.small_scans.write().unwrap().clear();
+        true
+    }
+
+    /// Mark the scan finished and refresh planner statistics, which a large batch
+    /// of index changes leaves describing a different table
+    fn finish_scan(&self) {
+        self.set_progress(1.0, None);
+        if let Err(e) = self.db.optimize() {
+            warn!("Failed to optimize database after scan: {}", e);
+        }
+        self.state.is_scanning.store(false, Ordering::Release);
+    }
+
+    /// Note the time a full scan completed, and whether it looked incomplete, for `status`
+    fn record_full_scan(&self) {
+        if let Err(e) = self.db.record_full_scan(chrono::Utc::now().timestamp()) {
+            warn!("Failed to record full scan time: {}", e);
+        }
+
+        let small_scans = std::mem::take(&mut *self.state.small_scans.write().unwrap());
+        let warning = (!small_scans.is_empty()).then(|| small_scans.join("; "));
+        if let Err(e) = self.db.record_index_warning(warning.as_deref()) {
+            warn!("Failed to record index size warning: {}", e);
+        }
+    }
+
+    /// Flag a scanned root (watch path or volume) that indexed far fewer entries than it
+    /// should have, e.g. because a privilege or volume error cut the scan short without
+    /// failing it. Logged now; kept for `status` if the scan is a full one.
+    fn flag_small_scan(&self, warning: String) {
+        warn!("Index looks incomplete: {}", warning);
+        self.state.small_scans.write().unwrap().push(warning);
+    }
+
+    /// Get the state of every watch root
+    pub fn watch_status(&self) -> Vec<WatchPathStatus> {
+        self.state.watches.read().unwrap().values().cloned().collect()
+    }
+
+    /// Get the watcher implementation in use, if the watcher has started
+    pub fn watcher_kind(&self) -> Option<String> {
+        self.state.watcher_kind.read().unwrap().clone()
+    }
+
+    /// Update the state of a watch root, creating it if needed
+    fn update_watch(&self, path: &Path, update: impl FnOnce(&mut WatchPathStatus)) {
+        let mut watches = self.state.watches.write().unwrap();
+        let status = watches.entry(path.to_path_buf()).or_insert_with(|| WatchPathStatus {
+            path: path.to_string_lossy().to_string(),
+            ..Default::default()
+        });
+        update(status);
+    }
+
+    /// Record an error against the watch root containing `path`
+    fn record_watch_error(&self, path: &Path, error: String) {
+        let mut watches = self.state.watches.write().unwrap();
+        if let Some((_, status)) = watches.iter_mut().find(|(root, _)| path.starts_with(root)) {
+            status.error = Some(error);
+        }
+    }
+
+    /// Schedule a reconciliation scan of the watch roots holding `paths`, or of every
+    /// root if the lost events carried no paths
+    fn mark_needs_rescan(&self, paths: &[PathBuf]) {
+        let mut watches = self.state.watches.write().unwrap();
+        for (root, status) in watches.iter_mut() {
+            if paths.is_empty() || paths.iter().any(|path| path.starts_with(root)) {
+                status.needs_rescan = true;
+            }
+        }
+    }
+
+    /// Take the watch roots waiting for a reconciliation scan, clearing their flag
+    fn take_pending_rescans(&self) -> Vec<PathBuf> {
+        let mut watches = self.state.watches.write().unwrap();
+        watches
+            .iter_mut()
+            .filter(|(_, status)| status.needs_rescan)
+            .map(|(root, status)| {
+                status.needs_rescan = false;
+                root.clone()
+            })
+            .collect()
+    }
+
+    /// A reindex of `path` (None: everything) supersedes pending reconciliation
+    /// scans of the watch roots it covers
+    fn clear_pending_rescans(&self, path: Option<&Path>) {
+        let mut watches = self.state.watches.write().unwrap();
+        for (root, status) in watches.iter_mut() {
+            if path.is_none_or(|path| root.starts_with(path)) {
+                status.needs_rescan = false;
+            }
+        }
+    }
+
+    /// Check if indexing is paused because the database volume is low on space
+    pub fn is_low_disk_space(&self) -> bool {
+        self.state.low_disk_space.load(Ordering::Relaxed)
+    }
+
+    /// Block while free space on the database volume is below `performance.min_free_disk_mb`.
+    /// Called before a scan starts and between batches so a full index can't fill the disk.
+    /// A cancel or shutdown ends the wait within `DISK_SPACE_CANCEL_POLL`. For scan
+    /// threads; async code uses `wait_for_disk_space_async`.
+    fn wait_for_disk_space(&self) {
+        while !self.scan_cancelled() && !self.has_disk_space() {
+            let recheck_at = Instant::now() + self.disk_space.recheck_interval;
+            while let Some(step) = self.disk_space_pause(recheck_at) {
+                std::thread::sleep(step);
+            }
+        }
+    }
+
+    /// `wait_for_disk_space` for async code, sleeping without holding a runtime thread
+    #[cfg_attr(not(windows), allow(dead_code))] // Used by the MFT scan
+    async fn wait_for_disk_space_async(&self) {
+        while !self.scan_cancelled() && !self.has_disk_space() {
+            let recheck_at = Instant::now() + self.disk_space.recheck_interval;
+            while let Some(step) = self.disk_space_pause(recheck_at) {
+                tokio::time::sleep(step).await;
+            }
+        }
+    }
+
+    /// Next sleep of a pause for disk space, or None once the scan is cancelled or
+    /// it is time to check the free space again
+    fn disk_space_pause(&self, recheck_at: Instant) -> Option<Duration> {
+        if self.scan_cancelled() {
+            return None;
+        }
+        let left = recheck_at.saturating_duration_since(Instant::now());
+        (!left.is_zero()).then(|| left.min(DISK_SPACE_CANCEL_POLL))
+    }
+
+    /// Check free space on the database volume once, updating `low_disk_space`.
+    /// True if indexing may go on: enough space, no minimum set, or the check failed.
+    fn has_disk_space(&self) -> bool {
+        let min_free_mb = self.config.performance.min_free_disk_mb;
+        if min_free_mb == 0 {
+            return true;
+        }
+
+        let min_free_bytes = min_free_mb * 1024 * 1024;
+        let db_dir = self
+            .config
+            .db_path
+            .parent()
+            .unwrap_or_else(|| std::path::Path::new("."));
+
+        match (self.disk_space.free_bytes)(db_dir) {
+            Ok(free_bytes) if free_bytes < min_free_bytes => {
+                if !self.state.low_disk_space.swap(true, Ordering::Relaxed) {
+                    warn!(
+                        "Low disk space on {:?}: {} MB free, {} MB required. Indexing paused.",
+                        db_dir,
+                        free_bytes / (1024 * 1024),
+                        min_free_mb
+                    );
+                }
+                false
+            }
+            Ok(_) => {
+                if self.state.low_disk_space.swap(false, Ordering::Relaxed) {
+                    info!("Disk space recovered on {:?}, resuming indexing", db_dir);
+                }
+                true
+            }
+            Err(e) => {
+                debug!("Failed to check free disk space on {:?}: {}", db_dir, e);
+                true
+            }
+        }
+    }
+
     /// Start initial scan
     /// Uses MFT scanner on Windows NTFS volumes for maximum speed,
     /// falls back to walkdir for non-NTFS volumes or other platforms.
@@ -93,6 +422,7 @@ impl Indexer {
                 "Database already has {} files indexed, skipping initial scan. Use 'reindex' command to force re-scan.",
                 stats.indexed_files
             );
+            self.mark_all_scanned();
             return Ok(());
         }
 
@@ -100,7 +430,11 @@ impl Indexer {
         {
             // Try MFT scanner first on Windows (requires admin)
             match mft_scanner::start_mft_scan(self).await {
-                Ok(()) => return Ok(()),
+                Ok(()) => {
+                    // MFT scans whole volumes, which covers every watch root
+                    self.mark_all_scanned();
+                    return Ok(());
+                }
                 Err(e) => {
                     tracing::warn!("MFT scan failed, falling back to walkdir: {}", e);
                     // Fall through to walkdir
@@ -111,6 +445,15 @@ impl Indexer {
         scanner::start_initial_scan(self).await
     }
 
+    /// Mark the initial scan of every existing watch root as complete
+    fn mark_all_scanned(&self) {
+        for path in self.config.get_watch_paths() {
+            if path.exists() {
+                self.update_watch(&path, |status| status.initial_scan_complete = true);
+            }
+        }
+    }
+
     /// Start file watcher
     pub async fn start_watcher(&self) -> Result<()> {
         watcher::start_watcher(self).await
@@ -121,6 +464,49 @@ impl Indexer {
         scanner::reindex_path(self, path).await
     }
 
+    /// Refresh planner statistics every `interval` until the daemon exits. Ticks
+    /// during a scan (which refreshes them when it finishes) or with no writes
+    /// since the last run are skipped.
+    pub async fn run_optimize(&self, interval: Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        // The first tick completes immediately; startup has nothing new to measure
+        ticker.tick().await;
+
+        let mut optimized_generation = self.db.generation();
+        loop {
+            ticker.tick().await;
+            let generation = self.db.generation();
+            if self.is_scanning() || generation == optimized_generation {
+                continue;
+            }
+            match self.db.optimize() {
+                Ok(()) => {
+                    debug!("Query planner statistics refreshed");
+                    optimized_generation = generation;
+                }
+                Err(e) => warn!("Failed to optimize database: {}", e),
+            }
+        }
+    }
+
+    /// Rebuild the index of one Windows drive, e.g. 'D'
+    pub async fn reindex_drive(&self, letter: char) -> Result<()> {
+        scanner::reindex_drive(self, letter).await
+    }
+
+    /// Watch paths on a Windows drive ("D:\\..." or "d:/..." for 'D')
+    pub fn watch_paths_on_drive(&self, letter: char) -> Vec<PathBuf> {
+        self.config
+            .get_watch_paths()
+            .into_iter()
+            .filter(|path| {
+                let path = path.to_string_lossy();
+                let bytes = path.as_bytes();
+                bytes.len() >= 2 && bytes[0].eq_ignore_ascii_case(&(letter as u8)) && bytes[1] == b':'
+            })
+            .collect()
+    }
+
     /// Get database reference
     pub fn db(&self) -> &Database {
         &self.db
@@ -130,4 +516,141 @@ impl Indexer {
     pub fn config(&self) -> &Config {
         &self.config
     }
+
+    /// Which file extensions get indexed
+    pub fn extension_filter(&self) -> &ExtensionFilter {
+        &self.extensions
+    }
+
+    /// Read free disk space from `free_bytes` instead of the filesystem, re-checking
+    /// every `recheck_interval` while paused
+    #[cfg(test)]
+    fn with_free_disk_space(
+        mut self,
+        free_bytes: impl Fn(&Path) -> Result<u64> + Send + Sync + 'static,
+        recheck_interval: Duration,
+    ) -> Self {
+        self.disk_space = DiskSpaceProbe {
+            free_bytes: Arc::new(free_bytes),
+            recheck_interval,
+        };
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::test_support::{test_config, test_database};
+
+    const MB: u64 = 1024 * 1024;
+
+    /// Indexer requiring 100 MB free, reading free space from `free`
+    fn indexer(dir: &TempDir, free: &Arc<AtomicU64>, recheck_interval: Duration) -> Indexer {
+        let mut config = test_config(dir);
+        config.performance.min_free_disk_mb = 100;
+        let db = test_database(&config);
+        let free = free.clone();
+        Indexer::new((*db).clone(), config)
+            .with_free_disk_space(move |_| Ok(free.load(Ordering::SeqCst)), recheck_interval)
+    }
+
+    /// Wait for `done` to be true, failing after `limit`
+    fn wait_until(limit: Duration, done: impl Fn() -> bool) {
+        let deadline = Instant::now() + limit;
+        while !done() {
+            assert!(Instant::now() < deadline, "timed out");
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn indexing_pauses_below_the_minimum_and_resumes_once_space_frees() {
+        let dir = TempDir::new().unwrap();
+        let free = Arc::new(AtomicU64::new(50 * MB));
+        let indexer = indexer(&dir, &free, Duration::from_millis(20));
+
+        let waiter = {
+            let indexer = indexer.clone();
+            std::thread::spawn(move || indexer.wait_for_disk_space())
+        };
+        wait_until(Duration::from_secs(5), || indexer.is_low_disk_space());
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(!waiter.is_finished(), "indexing went on below the minimum");
+
+        free.store(150 * MB, Ordering::SeqCst);
+        wait_until(Duration::from_secs(5), || waiter.is_finished());
+        waiter.join().unwrap();
+        assert!(!indexer.is_low_disk_space());
+    }
+
+    #[test]
+    fn enough_space_does_not_pause() {
+        let dir = TempDir::new().unwrap();
+        let free = Arc::new(AtomicU64::new(100 * MB));
+        let indexer = indexer(&dir, &free, Duration::from_secs(30));
+
+        indexer.wait_for_disk_space();
+        assert!(!indexer.is_low_disk_space());
+    }
+
+    #[test]
+    fn no_minimum_never_checks() {
+        let dir = TempDir::new().unwrap();
+        let checks = Arc::new(AtomicUsize::new(0));
+        let counted = checks.clone();
+        let mut config = test_config(&dir);
+        config.performance.min_free_disk_mb = 0;
+        let db = test_database(&config);
+        let indexer = Indexer::new((*db).clone(), config).with_free_disk_space(
+            move |_| {
+                counted.fetch_add(1, Ordering::SeqCst);
+                Ok(0)
+            },
+            Duration::from_secs(30),
+        );
+
+        indexer.wait_for_disk_space();
+        assert_eq!(checks.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn cancelling_the_scan_ends_the_pause_without_waiting_for_a_recheck() {
+        let dir = TempDir::new().unwrap();
+        let free = Arc::new(AtomicU64::new(0));
+        let indexer = indexer(&dir, &free, Duration::from_secs(30));
+        assert!(indexer.begin_scan());
+
+        let waiter = {
+            let indexer = indexer.clone();
+            std::thread::spawn(move || indexer.wait_for_disk_space())
+        };
+        wait_until(Duration::from_secs(5), || indexer.is_low_disk_space());
+
+        assert!(indexer.cancel_scan());
+        wait_until(Duration::from_secs(2), || waiter.is_finished());
+        waiter.join().unwrap();
+    }
+
+    #[tokio::test]
+    async fn the_async_wait_stops_at_shutdown() {
+        let dir = TempDir::new().unwrap();
+        let free = Arc::new(AtomicU64::new(0));
+        let indexer = indexer(&dir, &free, Duration::from_secs(30));
+
+        let waiter = {
+            let indexer = indexer.clone();
+            tokio::spawn(async move { indexer.wait_for_disk_space_async().await })
+        };
+        while !indexer.is_low_disk_space() {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+
+        indexer.request_stop();
+        tokio::time::timeout(Duration::from_secs(2), waiter).await.unwrap().unwrap();
+    }
 }
@@ -1,20 +1,101 @@
 //! Directory scanner for initial indexing
 
 use anyhow::Result;
-use std::path::Path;
-use std::sync::atomic::Ordering;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Instant;
 use tracing::{info, warn, debug};
 use walkdir::WalkDir;
 
-use super::Indexer;
+use super::{Indexer, ScanInProgress};
+use crate::config::Config;
+use crate::platform::{self, DiskInfo};
+
+/// Watch paths on one disk, taken in order by up to `concurrency` workers
+struct DiskQueue {
+    disk: DiskInfo,
+    concurrency: usize,
+    /// (position among all watch paths, path), for progress
+    paths: Mutex<VecDeque<(usize, PathBuf)>>,
+}
+
+/// Order the walks of a full scan. Each priority directory comes first, in order: one
+/// inside a watch path becomes a walk of its own (returned as carved out, so the walk
+/// of its watch path skips it), and watch paths inside one move up. The remaining
+/// watch paths follow in their configured order. Priority directories outside every
+/// watch path are dropped.
+fn scan_order(watch_paths: &[PathBuf], priority: &[PathBuf]) -> (Vec<PathBuf>, Vec<PathBuf>) {
+    let mut order: Vec<PathBuf> = Vec::new();
+    let mut carved = Vec::new();
+    let add = |order: &mut Vec<PathBuf>, path: &Path| {
+        if !order.iter().any(|p| p == path) {
+            order.push(path.to_path_buf());
+        }
+    };
+
+    for dir in priority {
+        if let Some(root) = watch_paths.iter().find(|root| dir.starts_with(root)) {
+            if dir != root && !carved.contains(dir) {
+                carved.push(dir.clone());
+            }
+            add(&mut order, dir);
+        }
+        for root in watch_paths.iter().filter(|root| root.starts_with(dir)) {
+            add(&mut order, root);
+        }
+    }
+    for root in watch_paths {
+        add(&mut order, root);
+    }
+
+    (order, carved)
+}
+
+/// Group watch paths by the disk they live on. Disks are scanned in parallel;
+/// within a disk, `PerformanceConfig::scan_concurrency` caps the walks at once.
+fn plan_scan(config: &Config, watch_paths: &[PathBuf]) -> Vec<DiskQueue> {
+    let mut queues: Vec<DiskQueue> = Vec::new();
+    for (i, path) in watch_paths.iter().enumerate() {
+        let disk = platform::disk_of(path);
+        match queues.iter_mut().find(|queue| queue.disk.id == disk.id) {
+            Some(queue) => queue.paths.get_mut().unwrap().push_back((i, path.clone())),
+            None => queues.push(DiskQueue {
+                concurrency: config.performance.scan_concurrency(disk.rotational),
+                disk,
+                paths: Mutex::new(VecDeque::from([(i, path.clone())])),
+            }),
+        }
+    }
+    queues
+}
 
 /// Start the initial directory scan
 pub async fn start_initial_scan(indexer: &Indexer) -> Result<()> {
-    indexer.state.is_scanning.store(true, Ordering::Relaxed);
-    indexer.state.scan_progress.store(0, Ordering::Relaxed);
+    if !indexer.begin_scan() {
+        return Err(ScanInProgress.into());
+    }
+    scan_watch_paths(indexer);
+    indexer.finish_scan();
 
-    let watch_paths = indexer.config().get_watch_paths();
-    info!("Starting initial scan of {} paths", watch_paths.len());
+    if !indexer.scan_cancelled() {
+        indexer.record_full_scan();
+    }
+    Ok(())
+}
+
+/// Walk every watch path into the index. The caller owns the scan: it calls
+/// `begin_scan` before and `finish_scan` after.
+fn scan_watch_paths(indexer: &Indexer) {
+    let started = Instant::now();
+    let (watch_paths, carved) = scan_order(
+        &indexer.config().get_watch_paths(),
+        &indexer.config().scan_priority_paths(),
+    );
+    info!(paths = watch_paths.len(), "Starting initial scan");
+    if !carved.is_empty() {
+        info!(paths = ?carved, "Scanning priority directories first");
+    }
 
     // Enable bulk insert mode for faster indexing
     if let Err(e) = indexer.db().begin_bulk_insert() {
@@ -22,19 +103,29 @@ pub async fn start_initial_scan(indexer: &Indexer) -> Result<()> {
     }
 
     let total_paths = watch_paths.len();
-    for (i, path) in watch_paths.iter().enumerate() {
-        if indexer.should_stop() {
-            info!("Scan stopped by request");
-            break;
-        }
-
-        let base_progress = i as f64 / total_paths as f64;
-        indexer.set_progress(base_progress, Some(&path.to_string_lossy()));
+    let queues = plan_scan(indexer.config(), &watch_paths);
+    for queue in &queues {
+        info!(
+            disk = %queue.disk.id,
+            rotational = ?queue.disk.rotational,
+            paths = queue.paths.lock().unwrap().len(),
+            concurrency = queue.concurrency,
+            "Scan plan"
+        );
+    }
 
-        info!("Scanning: {:?}", path);
-        if let Err(e) = scan_directory(indexer, path, base_progress, 1.0 / total_paths as f64).await {
-            warn!("Error scanning {:?}: {}", path, e);
+    std::thread::scope(|scope| {
+        for queue in &queues {
+            let workers = queue.concurrency.min(queue.paths.lock().unwrap().len());
+            for _ in 0..workers {
+                let carved = &carved;
+                scope.spawn(move || scan_worker(indexer, queue, total_paths, carved));
+            }
         }
+    });
+
+    if indexer.scan_cancelled() {
+        info!("Scan stopped by request");
     }
 
     // Restore normal database settings
@@ -42,58 +133,176 @@ pub async fn start_initial_scan(indexer: &Indexer) -> Result<()> {
         warn!("Failed to disable bulk insert mode: {}", e);
     }
 
-    indexer.state.is_scanning.store(false, Ordering::Relaxed);
-    indexer.set_progress(1.0, None);
+    // Statistics gathered on a near-empty table would steer searches away from the indexes
+    if let Err(e) = indexer.db().analyze() {
+        warn!("Failed to analyze database after full scan: {}", e);
+    }
 
-    info!("Initial scan complete");
-    Ok(())
+    info!(
+        paths = total_paths,
+        duration_ms = started.elapsed().as_millis() as u64,
+        "Initial scan complete"
+    );
+}
+
+/// Scan paths from one disk's queue until it is empty or the scan is cancelled.
+/// `carved` are priority directories walked on their own, not as part of their watch path.
+fn scan_worker(indexer: &Indexer, queue: &DiskQueue, total_paths: usize, carved: &[PathBuf]) {
+    while !indexer.scan_cancelled() {
+        let Some((i, path)) = queue.paths.lock().unwrap().pop_front() else {
+            break;
+        };
+
+        indexer.wait_for_disk_space();
+
+        let base_progress = i as f64 / total_paths as f64;
+        indexer.set_progress(base_progress, Some(&path.to_string_lossy()));
+
+        info!(path = %path.display(), "Scanning");
+        match scan_directory(indexer, &path, base_progress, 1.0 / total_paths as f64, carved) {
+            // A cancelled scan returns early without covering the whole path
+            Ok(()) if indexer.scan_cancelled() => {}
+            // Not a watch path: its watch path's status covers it
+            Ok(()) if carved.contains(&path) => {}
+            Ok(()) => indexer.update_watch(&path, |status| status.initial_scan_complete = true),
+            Err(e) => {
+                warn!(path = %path.display(), error = %e, "Error scanning path");
+                indexer.update_watch(&path, |status| status.error = Some(format!("Scan failed: {}", e)));
+            }
+        }
+    }
 }
 
 /// Reindex a specific path or all paths
 pub async fn reindex_path(indexer: &Indexer, path: Option<&str>) -> Result<()> {
-    indexer.state.is_scanning.store(true, Ordering::Relaxed);
+    if !indexer.begin_scan() {
+        return Err(ScanInProgress.into());
+    }
+    indexer.clear_pending_rescans(path.map(Path::new));
 
-    match path {
-        Some(p) => {
-            info!("Reindexing path: {}", p);
-            indexer.set_progress(0.0, Some(p));
+    let result = async {
+        match path {
+            Some(p) => {
+                info!("Reindexing path: {}", p);
+                indexer.set_progress(0.0, Some(p));
 
-            // Clear existing entries under this path
-            indexer.db().delete_directory(p)?;
+                // Clear existing entries under this path
+                indexer.db().delete_directory(p)?;
 
-            // Rescan
-            scan_directory(indexer, Path::new(p), 0.0, 1.0).await?;
+                // Rescan
+                scan_directory(indexer, Path::new(p), 0.0, 1.0, &[])
+            }
+            None => {
+                info!("Full reindex requested");
+
+                // Clear all entries
+                indexer.db().clear_all()?;
+
+                // Rescan everything, within this reindex's scan
+                scan_watch_paths(indexer);
+                Ok(())
+            }
         }
-        None => {
-            info!("Full reindex requested");
+    }
+    .await;
 
-            // Clear all entries
-            indexer.db().clear_all()?;
+    // Also on error, so status doesn't report a scan that is no longer running
+    indexer.finish_scan();
 
-            // Rescan everything
-            start_initial_scan(indexer).await?;
-            return Ok(());
+    if path.is_none() && result.is_ok() && !indexer.scan_cancelled() {
+        indexer.record_full_scan();
+    }
+    result
+}
+
+/// Rebuild the entries on one Windows drive: clear them, then rescan the volume
+/// through its MFT, or walk the watch paths on it when the MFT can't be read.
+/// Other drives are left as they are.
+pub async fn reindex_drive(indexer: &Indexer, letter: char) -> Result<()> {
+    let letter = letter.to_ascii_uppercase();
+    let drive = format!("{}:", letter);
+    let watch_paths = indexer.watch_paths_on_drive(letter);
+
+    if !indexer.begin_scan() {
+        return Err(ScanInProgress.into());
+    }
+    indexer.clear_pending_rescans(Some(Path::new(&format!("{}\\", drive))));
+
+    let result = async {
+        info!("Reindexing drive {}", drive);
+        indexer.set_progress(0.0, Some(&drive));
+
+        let removed = indexer.db().delete_drive(letter)?;
+        debug!("Cleared {} entries on drive {}", removed, drive);
+
+        #[cfg(windows)]
+        match super::mft_scanner::scan_volume_mft(indexer, letter, 0.0, 1.0).await {
+            Ok(count) => {
+                info!("Indexed {} files from drive {}", count, drive);
+                return Ok(());
+            }
+            Err(e) => warn!("MFT scan of drive {} failed, falling back to walkdir: {}", drive, e),
+        }
+
+        let total_paths = watch_paths.len();
+        for (i, path) in watch_paths.iter().enumerate() {
+            if indexer.scan_cancelled() {
+                break;
+            }
+            let base_progress = i as f64 / total_paths as f64;
+            scan_directory(indexer, path, base_progress, 1.0 / total_paths as f64, &[])?;
         }
+        Ok(())
+    }
+    .await;
+
+    indexer.finish_scan();
+    result
+}
+
+/// Bring a watch root back in line with the disk after the watcher lost events under it.
+/// Unlike a reindex, the existing entries stay searchable meanwhile: everything found is
+/// upserted (refreshing `indexed_at`), then entries the scan didn't refresh are swept.
+/// Fails with `ScanInProgress` while another scan runs.
+pub(super) fn reconcile_path(indexer: &Indexer, root: &Path) -> Result<()> {
+    if !indexer.begin_scan() {
+        return Err(ScanInProgress.into());
     }
 
-    indexer.state.is_scanning.store(false, Ordering::Relaxed);
-    indexer.set_progress(1.0, None);
+    let started = chrono::Utc::now().timestamp();
+    let root_str = root.to_string_lossy();
+    info!("Reconciling {} after lost watcher events", root_str);
 
+    indexer.wait_for_disk_space();
+    indexer.set_progress(0.0, Some(&root_str));
+    let result = scan_directory(indexer, root, 0.0, 1.0, &[]).and_then(|()| {
+        // A partial scan would sweep entries it never reached
+        if indexer.scan_cancelled() {
+            anyhow::bail!("cancelled; run a reindex of {} to pick up lost changes", root_str);
+        }
+        indexer.db().delete_unrefreshed(&root_str, started)
+    });
+    indexer.finish_scan();
+
+    let removed = result?;
+    info!("Reconciled {} ({} stale entries removed)", root_str, removed);
     Ok(())
 }
 
-/// Scan a single directory recursively
-async fn scan_directory(
+/// Scan a single directory recursively (blocking), leaving out the `skip` directories
+/// below it
+fn scan_directory(
     indexer: &Indexer,
     path: &Path,
     base_progress: f64,
     progress_range: f64,
+    skip: &[PathBuf],
 ) -> Result<()> {
     let config = indexer.config();
     // Use large batch size for bulk inserts (50,000 files per transaction)
     let batch_size = 50_000;
 
-    let mut batch: Vec<(String, bool)> = Vec::with_capacity(batch_size);
+    let mut batch: Vec<(PathBuf, bool, bool)> = Vec::with_capacity(batch_size);
     let mut processed = 0u64;
     let mut total_estimate = 1000u64; // Initial estimate, will be updated
 
@@ -103,11 +312,14 @@ async fn scan_directory(
     }
 
     for entry in WalkDir::new(path)
-        .follow_links(false)
+        .follow_links(config.watch.follow_reparse_points)
         .into_iter()
-        .filter_entry(|e| !should_skip_entry(e, config))
+        .filter_entry(|e| {
+            !should_skip_entry(e, config, indexer.extension_filter())
+                && (e.depth() == 0 || !skip.iter().any(|dir| dir == e.path()))
+        })
     {
-        if indexer.should_stop() {
+        if indexer.scan_cancelled() {
             // Flush remaining batch before stopping
             if !batch.is_empty() {
                 let _ = indexer.db().batch_upsert_files(&batch);
@@ -117,17 +329,16 @@ async fn scan_directory(
 
         match entry {
             Ok(entry) => {
-                let path_str = entry.path().to_string_lossy().to_string();
                 let is_dir = entry.file_type().is_dir();
 
-                // Skip the root path itself
-                if entry.depth() == 0 {
-                    continue;
-                }
-
-                batch.push((path_str, is_dir));
+                // The root itself gets a row too (depth 0), so a watched folder is found
+                // by its name and counted in directory summaries. Overlapping roots
+                // upsert the same row, and the MFT scan writes drive roots as "C:\\",
+                // the path a walk of "C:\\" yields.
+                batch.push((entry.path().to_path_buf(), is_dir, entry.path_is_symlink()));
 
                 if batch.len() >= batch_size {
+                    indexer.wait_for_disk_space();
                     if let Err(e) = indexer.db().batch_upsert_files(&batch) {
                         warn!("Failed to batch insert: {}", e);
                     }
@@ -144,6 +355,9 @@ async fn scan_directory(
         }
     }
 
+    // Not counting the root's own row
+    let indexed = (processed + batch.len() as u64).saturating_sub(1);
+
     // Flush remaining entries
     if !batch.is_empty() {
         if let Err(e) = indexer.db().batch_upsert_files(&batch) {
@@ -151,9 +365,40 @@ async fn scan_directory(
         }
     }
 
+    // Fewer entries than the root's own listing holds means the walk lost whole
+    // subtrees, e.g. to errors it only logs at debug level
+    let expected = indexable_top_level_entries(indexer, path, skip);
+    if indexed.saturating_mul(2) < expected {
+        indexer.flag_small_scan(format!(
+            "{} indexed {} entries, but its top level alone holds {}",
+            path.display(),
+            indexed,
+            expected
+        ));
+    }
+
     Ok(())
 }
 
+/// Entries directly under `path` that the exclusion rules let through, other than the
+/// `skip` directories: a lower bound on what a walk of it indexes
+fn indexable_top_level_entries(indexer: &Indexer, path: &Path, skip: &[PathBuf]) -> u64 {
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return 0;
+    };
+
+    entries
+        .flatten()
+        .filter(|entry| {
+            let path_str = entry.path().to_string_lossy().into_owned();
+            let is_dir = entry.file_type().is_ok_and(|t| t.is_dir());
+            !indexer.config().should_exclude(&path_str)
+                && (is_dir || indexer.extension_filter().allows_file(&path_str))
+                && !skip.contains(&entry.path())
+        })
+        .count() as u64
+}
+
 /// Quick count of entries in a directory (for progress estimation)
 fn quick_count_entries(path: &Path) -> Result<u64> {
     let mut count = 0u64;
@@ -172,7 +417,11 @@ fn quick_count_entries(path: &Path) -> Result<u64> {
 }
 
 /// Check if a directory entry should be skipped
-fn should_skip_entry(entry: &walkdir::DirEntry, config: &crate::config::Config) -> bool {
+fn should_skip_entry(
+    entry: &walkdir::DirEntry,
+    config: &crate::config::Config,
+    extensions: &crate::config::ExtensionFilter,
+) -> bool {
     let path = entry.path();
     let path_str = path.to_string_lossy();
 
@@ -181,6 +430,11 @@ fn should_skip_entry(entry: &walkdir::DirEntry, config: &crate::config::Config)
         return true;
     }
 
+    // Files whose extension isn't indexed (directories are always walked)
+    if !entry.file_type().is_dir() && !extensions.allows_file(&path_str) {
+        return true;
+    }
+
     // Skip hidden files if configured
     if !config.watch.include_hidden {
         if let Some(name) = path.file_name() {
@@ -200,5 +454,115 @@ pub async fn scan_directory_public(
     base_progress: f64,
     progress_range: f64,
 ) -> Result<()> {
-    scan_directory(indexer, path, base_progress, progress_range).await
+    scan_directory(indexer, path, base_progress, progress_range, &[])
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::test_support::{create_files, scan_root, test_indexer, watch_config};
+
+    const FILES: &[&str] = &["a.txt", "b.txt", "sub/c.txt"];
+
+    /// Indexer watching a fresh root holding `FILES`
+    fn indexer_with_files() -> (Indexer, TempDir, TempDir) {
+        let dir = TempDir::new().unwrap();
+        let root = scan_root();
+        create_files(root.path(), FILES);
+        (test_indexer(watch_config(&dir, &[root.path()])), root, dir)
+    }
+
+    #[tokio::test]
+    async fn no_scan_work_happens_after_a_stop() {
+        let (indexer, root, _dir) = indexer_with_files();
+        indexer.request_stop();
+
+        indexer.reindex_path(Some(&root.path().to_string_lossy())).await.unwrap();
+        indexer.reindex_path(None).await.unwrap();
+
+        let stats = indexer.db().get_stats().unwrap();
+        assert_eq!((stats.indexed_files, stats.indexed_dirs), (0, 0));
+        assert_eq!(stats.last_full_scan, None, "a stopped scan is not a full scan");
+        assert!(!indexer.is_scanning());
+    }
+
+    #[tokio::test]
+    async fn a_cancelled_scan_does_not_stop_the_next_one() {
+        let (indexer, root, _dir) = indexer_with_files();
+        indexer.begin_scan();
+        assert!(indexer.cancel_scan());
+        indexer.finish_scan();
+
+        indexer.reindex_path(Some(&root.path().to_string_lossy())).await.unwrap();
+        assert_eq!(indexer.db().get_stats().unwrap().indexed_files, 3);
+        assert!(!indexer.cancel_scan(), "no scan is running any more");
+    }
+
+    #[tokio::test]
+    async fn a_full_reindex_is_one_scan() {
+        let (indexer, _root, _dir) = indexer_with_files();
+
+        indexer.reindex_path(None).await.unwrap();
+
+        let stats = indexer.db().get_stats().unwrap();
+        assert_eq!(stats.indexed_files, 3);
+        assert!(stats.last_full_scan.is_some());
+        assert!(!indexer.is_scanning());
+        assert_eq!(indexer.get_scan_progress(), 1.0);
+    }
+
+    #[test]
+    fn a_reindex_cannot_start_during_a_reconcile() {
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicU64, Ordering};
+        use std::time::Duration;
+
+        let dir = TempDir::new().unwrap();
+        let root = scan_root();
+        create_files(root.path(), FILES);
+        let mut config = watch_config(&dir, &[root.path()]);
+        config.performance.min_free_disk_mb = 1;
+
+        // No free space until released, so the reconcile holds its scan meanwhile
+        let free = Arc::new(AtomicU64::new(0));
+        let indexer = test_indexer(config).with_free_disk_space(
+            {
+                let free = free.clone();
+                move |_| Ok(free.load(Ordering::SeqCst))
+            },
+            Duration::from_millis(10),
+        );
+
+        let reconcile = {
+            let indexer = indexer.clone();
+            let root = root.path().to_path_buf();
+            std::thread::spawn(move || reconcile_path(&indexer, &root))
+        };
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !indexer.is_low_disk_space() {
+            assert!(Instant::now() < deadline, "reconcile never started");
+            std::thread::sleep(Duration::from_millis(5));
+        }
+
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        let root_str = root.path().to_string_lossy().into_owned();
+        for result in [
+            runtime.block_on(indexer.reindex_path(Some(&root_str))),
+            runtime.block_on(indexer.reindex_path(None)),
+            reconcile_path(&indexer, root.path()),
+        ] {
+            assert!(result.unwrap_err().is::<ScanInProgress>());
+        }
+        assert!(indexer.is_scanning(), "the refused reindex left the reconcile's scan alone");
+
+        free.store(u64::MAX, Ordering::SeqCst);
+        reconcile.join().unwrap().unwrap();
+        assert!(!indexer.is_scanning());
+        assert_eq!(indexer.db().get_stats().unwrap().indexed_files, 3);
+
+        // With the reconcile done, a reindex runs
+        runtime.block_on(indexer.reindex_path(Some(&root_str))).unwrap();
+    }
 }
@@ -1,46 +1,201 @@
 //! File system watcher for real-time index updates
 
 use anyhow::Result;
-use notify::{Config, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
-use std::path::PathBuf;
+use notify::{Config, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher, WatcherKind};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tracing::{info, warn, debug, error};
 
-use super::Indexer;
+use super::journal::WatchJournal;
+use super::{scanner, Indexer, ScanInProgress};
+use crate::database::{created_secs, modified_secs};
+
+/// Longest wait for an event before checking `should_stop` again
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Newly created files waiting for their writes to settle.
+/// A file is indexed as soon as it's created, then re-stat'ed once it has gone
+/// `delay` without a modify event; modifies in between only push the deadline back.
+struct SettleQueue {
+    delay: Duration,
+    deadlines: HashMap<PathBuf, Instant>,
+}
+
+impl SettleQueue {
+    fn new(delay: Duration) -> Self {
+        Self {
+            delay,
+            deadlines: HashMap::new(),
+        }
+    }
+
+    fn is_enabled(&self) -> bool {
+        !self.delay.is_zero()
+    }
+
+    /// Start or restart the settle period for a path
+    fn schedule(&mut self, path: &Path) {
+        self.deadlines.insert(path.to_path_buf(), Instant::now() + self.delay);
+    }
+
+    /// Restart the settle period if the path is waiting; returns whether it was
+    fn postpone(&mut self, path: &Path) -> bool {
+        match self.deadlines.get_mut(path) {
+            Some(deadline) => {
+                *deadline = Instant::now() + self.delay;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn cancel(&mut self, path: &Path) {
+        self.deadlines.remove(path);
+    }
+
+    fn is_empty(&self) -> bool {
+        self.deadlines.is_empty()
+    }
+
+    /// How long to wait for the next event without missing a deadline
+    fn next_timeout(&self) -> Duration {
+        let now = Instant::now();
+        self.deadlines
+            .values()
+            .map(|deadline| deadline.saturating_duration_since(now))
+            .min()
+            .map_or(POLL_INTERVAL, |wait| wait.min(POLL_INTERVAL))
+    }
+
+    /// Remove and return the paths whose settle period is over
+    fn take_due(&mut self) -> Vec<PathBuf> {
+        let now = Instant::now();
+        let due: Vec<PathBuf> = self
+            .deadlines
+            .iter()
+            .filter(|(_, deadline)| **deadline <= now)
+            .map(|(path, _)| path.clone())
+            .collect();
+        for path in &due {
+            self.deadlines.remove(path);
+        }
+        due
+    }
+}
 
 /// Start the file system watcher
 pub async fn start_watcher(indexer: &Indexer) -> Result<()> {
     let config = indexer.config();
     let watch_paths = config.get_watch_paths();
     let debounce_ms = config.watcher.debounce_ms;
+    let mut settle = SettleQueue::new(Duration::from_millis(config.watcher.settle_ms));
 
     info!("Starting file watcher for {} paths", watch_paths.len());
 
-    // Create channel for events
-    let (tx, rx) = mpsc::channel();
+    let journal_path = config.db_path.with_extension("watch-journal");
+    let (journal, leftover) = match WatchJournal::open(&journal_path) {
+        Ok(opened) => opened,
+        Err(e) => {
+            warn!("Watcher changes won't survive a crash: {:#}", e);
+            (WatchJournal::disabled(), Vec::new())
+        }
+    };
+    let journal = std::sync::Arc::new(journal);
+
+    // Bounded, so a burst (unpacking a huge archive) can't grow memory without limit
+    let max_queued = config.watcher.max_queued_events.max(1);
+    let (tx, rx) = mpsc::sync_channel(max_queued);
+    // Set at the first dropped event of a burst, cleared once the queue goes idle,
+    // so each burst is logged once
+    let saturated = std::sync::Arc::new(AtomicBool::new(false));
+    let event_saturated = saturated.clone();
 
     // Create watcher with debouncing
     let watcher_config = Config::default()
         .with_poll_interval(Duration::from_millis(debounce_ms));
 
+    let error_indexer = indexer.clone();
+    let event_journal = journal.clone();
     let mut watcher: RecommendedWatcher = Watcher::new(
-        move |res: Result<Event, notify::Error>| {
-            if let Ok(event) = res {
-                let _ = tx.send(event);
+        move |res: Result<Event, notify::Error>| match res {
+            Ok(event) => {
+                // The OS queue overflowed (inotify, FSEvents): changes were missed
+                if event.need_rescan() {
+                    warn!("File watcher lost events; scheduling a reconciliation scan");
+                    error_indexer.mark_needs_rescan(&event.paths);
+                    return;
+                }
+
+                // Opens and reads change nothing, and walking a tree (as a reconciliation
+                // scan does) makes one per directory: keep them out of the bounded queue
+                if let EventKind::Access(_) = event.kind {
+                    return;
+                }
+
+                // Journal only the events process_event acts on
+                let paths = match event.kind {
+                    EventKind::Other => Vec::new(),
+                    _ => event.paths.clone(),
+                };
+                match event_journal.record(&paths, || tx.try_send(event)) {
+                    Ok(()) => {}
+                    Err(mpsc::TrySendError::Full(event)) => {
+                        event_journal.event_done();
+                        if !event_saturated.swap(true, Ordering::Relaxed) {
+                            warn!(
+                                "Watcher event queue is full ({} events); dropping events and scheduling a reconciliation scan",
+                                max_queued
+                            );
+                        }
+                        error_indexer.mark_needs_rescan(&event.paths);
+                    }
+                    Err(mpsc::TrySendError::Disconnected(_)) => event_journal.event_done(),
+                }
+            }
+            Err(e) => {
+                for path in &e.paths {
+                    error_indexer.record_watch_error(path, e.to_string());
+                }
             }
         },
         watcher_config,
     )?;
 
+    let kind = match RecommendedWatcher::kind() {
+        WatcherKind::PollWatcher => "poll",
+        _ => "native",
+    };
+    *indexer.state.watcher_kind.write().unwrap() = Some(kind.to_string());
+
     // Add watch paths
     for path in &watch_paths {
         match watcher.watch(path, RecursiveMode::Recursive) {
-            Ok(_) => info!("Watching: {:?}", path),
-            Err(e) => warn!("Failed to watch {:?}: {}", path, e),
+            Ok(_) => {
+                info!("Watching: {:?}", path);
+                indexer.update_watch(path, |status| {
+                    status.watching = true;
+                    status.error = None;
+                });
+            }
+            Err(e) => {
+                warn!("Failed to watch {:?}: {}", path, e);
+                indexer.update_watch(path, |status| {
+                    status.watching = false;
+                    status.error = Some(format!("Watch failed: {}", e));
+                });
+            }
         }
     }
 
+    // Watches are in place, so anything changed from here on is seen again
+    if !leftover.is_empty() {
+        replay_journal(indexer, &leftover);
+        journal.clear_if_idle(settle.is_empty());
+    }
+
     // Process events
     info!("File watcher started, processing events...");
 
@@ -50,28 +205,92 @@ pub async fn start_watcher(indexer: &Indexer) -> Result<()> {
             break;
         }
 
-        // Use recv_timeout to allow checking should_stop periodically
-        match rx.recv_timeout(Duration::from_secs(1)) {
+        // Use recv_timeout to allow checking should_stop and settle deadlines periodically
+        match rx.recv_timeout(settle.next_timeout()) {
             Ok(event) => {
-                if let Err(e) = process_event(indexer, &event).await {
+                if let Err(e) = process_event(indexer, &event, &mut settle).await {
                     debug!("Error processing event: {}", e);
                 }
+                journal.event_done();
             }
             Err(mpsc::RecvTimeoutError::Timeout) => {
-                // No event, continue loop
+                // Idle, so any burst that lost events is over. A running scan or
+                // reindex goes first; pending reconciliations wait for it.
+                saturated.store(false, Ordering::Relaxed);
+                if !indexer.is_scanning() {
+                    for root in indexer.take_pending_rescans() {
+                        if let Err(e) = scanner::reconcile_path(indexer, &root) {
+                            // A reindex started meanwhile; try again once it is done
+                            if e.is::<ScanInProgress>() {
+                                indexer.mark_needs_rescan(std::slice::from_ref(&root));
+                                continue;
+                            }
+                            warn!("Reconciliation of {:?} failed: {:#}", root, e);
+                            indexer.update_watch(&root, |status| {
+                                status.error = Some(format!("Reconciliation failed: {:#}", e));
+                            });
+                        }
+                    }
+                }
             }
             Err(mpsc::RecvTimeoutError::Disconnected) => {
                 error!("Watcher channel disconnected");
                 break;
             }
         }
+
+        index_settled(indexer, &mut settle);
+        journal.clear_if_idle(settle.is_empty());
     }
 
     Ok(())
 }
 
+/// Re-index the files whose settle period is over, at their final size
+fn index_settled(indexer: &Indexer, settle: &mut SettleQueue) {
+    for path in settle.take_due() {
+        // Gone or renamed while settling: the remove/rename event already handled it
+        if path.exists() {
+            debug!("File settled: {:?}", path);
+            let (size, modified, created) = file_size_and_times(&path, false);
+            if let Err(e) = indexer.db().upsert_file(&path, false, size, modified, created) {
+                debug!("Error indexing settled file: {}", e);
+            }
+        }
+    }
+}
+
+/// Bring the paths a previous run left in the journal up to date with the disk
+fn replay_journal(indexer: &Indexer, leftover: &[PathBuf]) {
+    info!("Replaying {} watcher changes left over from the last run", leftover.len());
+    for path in leftover {
+        if let Err(e) = replay_path(indexer, path) {
+            debug!("Error replaying {:?}: {}", path, e);
+        }
+    }
+}
+
+/// Bring one path from the journal up to date with the disk
+fn replay_path(indexer: &Indexer, path: &Path) -> Result<()> {
+    let path_str = path.to_string_lossy();
+    if indexer.config().should_exclude(&path_str) {
+        return Ok(());
+    }
+
+    if path.exists() {
+        let is_dir = path.is_dir();
+        if !is_indexable(indexer, &path_str, is_dir) {
+            return Ok(());
+        }
+        let (size, modified, created) = file_size_and_times(path, is_dir);
+        indexer.db().upsert_file(path, is_dir, size, modified, created)
+    } else {
+        indexer.db().delete_file(&path_str)
+    }
+}
+
 /// Process a file system event
-async fn process_event(indexer: &Indexer, event: &Event) -> Result<()> {
+async fn process_event(indexer: &Indexer, event: &Event, settle: &mut SettleQueue) -> Result<()> {
     let config = indexer.config();
 
     for path in &event.paths {
@@ -84,28 +303,39 @@ async fn process_event(indexer: &Indexer, event: &Event) -> Result<()> {
 
         match &event.kind {
             EventKind::Create(_) => {
-                info!("File created: {}", path_str);
                 let is_dir = path.is_dir();
-                let size = if is_dir { 0 } else {
-                    std::fs::metadata(&path).map(|m| m.len() as i64).unwrap_or(0)
-                };
-                indexer.db().upsert_file(&path_str, is_dir, size)?;
+                if !is_indexable(indexer, &path_str, is_dir) {
+                    continue;
+                }
+                info!("File created: {}", path_str);
+                let (size, modified, created) = file_size_and_times(path, is_dir);
+                indexer.db().upsert_file(path, is_dir, size, modified, created)?;
+                if !is_dir && settle.is_enabled() {
+                    settle.schedule(path);
+                }
             }
 
             EventKind::Modify(_) => {
+                // Still being written after creation: recorded once it settles
+                if settle.postpone(path) {
+                    continue;
+                }
+
                 info!("File modified: {}", path_str);
                 // Only update if it exists (might be a temporary file)
                 if path.exists() {
                     let is_dir = path.is_dir();
-                    let size = if is_dir { 0 } else {
-                        std::fs::metadata(&path).map(|m| m.len() as i64).unwrap_or(0)
-                    };
-                    indexer.db().upsert_file(&path_str, is_dir, size)?;
+                    if !is_indexable(indexer, &path_str, is_dir) {
+                        continue;
+                    }
+                    let (size, modified, created) = file_size_and_times(path, is_dir);
+                    indexer.db().upsert_file(path, is_dir, size, modified, created)?;
                 }
             }
 
             EventKind::Remove(_) => {
                 info!("File removed: {}", path_str);
+                settle.cancel(path);
                 indexer.db().delete_file(&path_str)?;
             }
 
@@ -125,3 +355,163 @@ async fn process_event(indexer: &Indexer, event: &Event) -> Result<()> {
 
     Ok(())
 }
+
+/// Whether the watcher may add or update a path: directories always, files only with
+/// an extension the scans index too. Removals aren't checked: deleting a path that
+/// was never indexed does nothing.
+fn is_indexable(indexer: &Indexer, path: &str, is_dir: bool) -> bool {
+    is_dir || indexer.extension_filter().allows_file(path)
+}
+
+/// Size, modification and creation time of a path (directories report size 0)
+fn file_size_and_times(path: &std::path::Path, is_dir: bool) -> (i64, i64, Option<i64>) {
+    match std::fs::metadata(path) {
+        Ok(m) => (if is_dir { 0 } else { m.len() as i64 }, modified_secs(&m), created_secs(&m)),
+        Err(_) => (0, 0, None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::search::SearchQuery;
+    use crate::test_support::{scan_root, seed, test_indexer, watch_config};
+
+    /// Indexer watching a fresh, empty root, with `*.tmp` excluded
+    fn watched_root() -> (Indexer, TempDir, TempDir) {
+        let dir = TempDir::new().unwrap();
+        let root = scan_root();
+        let mut config = watch_config(&dir, &[root.path()]);
+        config.watch.exclude_patterns = vec!["*.tmp".to_string()];
+        (test_indexer(config), root, dir)
+    }
+
+    /// Id, path and size of every indexed row, by path
+    fn indexed(indexer: &Indexer) -> Vec<(i64, String, i64)> {
+        let results = indexer.db().search(&SearchQuery::new("", 100)).unwrap();
+        let mut rows: Vec<_> = results.files.into_iter().map(|f| (f.id, f.path, f.size)).collect();
+        rows.sort_by(|a, b| a.1.cmp(&b.1));
+        rows
+    }
+
+    fn path_str(path: &Path) -> String {
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn journaled_changes_are_replayed_after_a_restart() {
+        let (indexer, root, _dir) = watched_root();
+        let created = root.path().join("created.txt");
+        let deleted = root.path().join("deleted.txt");
+        let scratch = root.path().join("scratch.tmp");
+        std::fs::write(&created, b"created while the event was queued").unwrap();
+        std::fs::write(&scratch, b"excluded").unwrap();
+        // Indexed earlier, then deleted while its event was still queued
+        seed(indexer.db(), &[&path_str(&deleted)]);
+
+        let journal_path = indexer.config().db_path.with_extension("watch-journal");
+        {
+            let (journal, leftover) = WatchJournal::open(&journal_path).unwrap();
+            assert!(leftover.is_empty());
+            journal.record(&[created.clone(), deleted.clone()], || ());
+            journal.record(&[created.clone(), scratch.clone()], || ());
+            // The daemon dies here, before the events are processed
+        }
+
+        let (journal, leftover) = WatchJournal::open(&journal_path).unwrap();
+        assert_eq!(leftover, [created.clone(), deleted, scratch]);
+        replay_journal(&indexer, &leftover);
+        let replayed = indexed(&indexer);
+        let size = "created while the event was queued".len() as i64;
+        assert!(matches!(&replayed[..], [(_, path, s)] if *path == path_str(&created) && *s == size), "{:?}", replayed);
+
+        // A crash before the journal was emptied replays it again, to the same rows
+        replay_journal(&indexer, &leftover);
+        assert_eq!(indexed(&indexer), replayed);
+
+        journal.clear_if_idle(true);
+        drop(journal);
+        let (_journal, leftover) = WatchJournal::open(&journal_path).unwrap();
+        assert!(leftover.is_empty());
+    }
+
+    fn event(kind: EventKind, path: &Path) -> Event {
+        Event::new(kind).add_path(path.to_path_buf())
+    }
+
+    #[tokio::test]
+    async fn a_burst_of_writes_to_a_new_file_is_indexed_once_settled() {
+        use notify::event::{CreateKind, DataChange, ModifyKind};
+
+        let (indexer, root, _dir) = watched_root();
+        let mut settle = SettleQueue::new(Duration::from_millis(300));
+        let path = root.path().join("download.txt");
+        std::fs::write(&path, b"").unwrap();
+        let generation = || indexer.db().generation();
+        let start = generation();
+
+        process_event(&indexer, &event(EventKind::Create(CreateKind::File), &path), &mut settle).await.unwrap();
+        assert_eq!(generation(), start + 1, "a new file is indexed as soon as it appears");
+
+        let modify = EventKind::Modify(ModifyKind::Data(DataChange::Content));
+        for chunk in 1..=5 {
+            std::fs::write(&path, vec![b'x'; chunk * 1000]).unwrap();
+            process_event(&indexer, &event(modify, &path), &mut settle).await.unwrap();
+            index_settled(&indexer, &mut settle);
+        }
+        assert_eq!(generation(), start + 1, "writes while settling are not indexed one by one");
+
+        std::thread::sleep(Duration::from_millis(400));
+        index_settled(&indexer, &mut settle);
+        assert_eq!(generation(), start + 2);
+        assert!(settle.is_empty());
+        assert_eq!(indexed(&indexer)[0].2, 5000);
+    }
+
+    #[tokio::test]
+    async fn excluded_extensions_are_not_indexed_on_create_or_rename() {
+        use notify::event::{CreateKind, ModifyKind, RenameMode};
+
+        let dir = TempDir::new().unwrap();
+        let root = scan_root();
+        let mut config = watch_config(&dir, &[root.path()]);
+        config.watch.exclude_extensions = vec!["LOG".to_string(), ".tmp".to_string()];
+        let indexer = test_indexer(config);
+        let mut settle = SettleQueue::new(Duration::ZERO);
+        let created = EventKind::Create(CreateKind::Any);
+
+        // Matched in any case; directories are never filtered by extension
+        let new_log = root.path().join("new.Log");
+        let archive = root.path().join("archive.log");
+        let notes = root.path().join("notes.txt");
+        std::fs::write(&new_log, b"log").unwrap();
+        std::fs::create_dir(&archive).unwrap();
+        std::fs::write(&notes, b"notes").unwrap();
+        for path in [&new_log, &archive, &notes] {
+            process_event(&indexer, &event(created, path), &mut settle).await.unwrap();
+        }
+
+        // Renamed to an excluded extension: the new name is not added
+        let draft = root.path().join("draft.txt");
+        let renamed = root.path().join("draft.tmp");
+        std::fs::write(&draft, b"draft").unwrap();
+        std::fs::rename(&draft, &renamed).unwrap();
+        let rename = Event::new(EventKind::Modify(ModifyKind::Name(RenameMode::Both)))
+            .add_path(draft)
+            .add_path(renamed.clone());
+        process_event(&indexer, &rename, &mut settle).await.unwrap();
+        let moved = root.path().join("moved.LOG");
+        std::fs::rename(&renamed, &moved).unwrap();
+        process_event(&indexer, &event(EventKind::Modify(ModifyKind::Name(RenameMode::To)), &moved), &mut settle)
+            .await
+            .unwrap();
+
+        // Nor does a journal replay add them
+        replay_journal(&indexer, &[new_log, moved]);
+
+        let paths: Vec<String> = indexed(&indexer).into_iter().map(|(_, path, _)| path).collect();
+        assert_eq!(paths, [path_str(&archive), path_str(&notes)]);
+    }
+}
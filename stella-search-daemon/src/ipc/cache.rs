@@ -0,0 +1,69 @@
+//! Client-side search result cache
+//!
+//! Every search response carries the index generation it was read at, and the
+//! generation changes on any index write. Cached results are only returned while
+//! the newest generation the client has seen matches theirs, so a response from a
+//! changed index (a search, newest or status call) drops the whole cache.
+//!
+//! The client can't learn about writes without talking to the daemon: a hit may be
+//! stale until the next uncached request or status poll reports the new generation.
+
+use std::collections::{HashMap, VecDeque};
+
+use crate::database::SearchResults;
+
+/// Search results of the current generation, keyed by serialized request
+pub(super) struct ResultCache {
+    capacity: usize,
+    generation: Option<u64>,
+    entries: HashMap<String, SearchResults>,
+    /// Insertion order, oldest first, for eviction
+    order: VecDeque<String>,
+}
+
+impl ResultCache {
+    pub(super) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            generation: None,
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// Cached results for a request
+    pub(super) fn get(&self, key: &str) -> Option<SearchResults> {
+        self.entries.get(key).cloned()
+    }
+
+    /// Record the generation reported by the daemon, dropping entries from older ones
+    pub(super) fn observe_generation(&mut self, generation: u64) {
+        if self.generation != Some(generation) {
+            self.clear();
+            self.generation = Some(generation);
+        }
+    }
+
+    /// Store results read at `generation`
+    pub(super) fn insert(&mut self, key: String, generation: u64, results: &SearchResults) {
+        self.observe_generation(generation);
+        if self.capacity == 0 {
+            return;
+        }
+
+        if self.entries.insert(key.clone(), results.clone()).is_none() {
+            self.order.push_back(key);
+        }
+        while self.entries.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+    }
+
+    /// Drop all entries
+    pub(super) fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+}
@@ -1,45 +1,184 @@
 //! IPC client for communicating with the service
 
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
 use anyhow::{Context, Result, bail};
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
 use serde_json;
 
-use crate::database::SearchResults;
-use super::protocol::{Request, Response, ConfigResponse, StatusResponse};
+use crate::config::Config;
+use crate::database::{DetailedStats, DirectoryCount, ExcludePreview, ExtensionCount, ImportPolicy, IndexedFile, SearchResults, StaleEntry};
+use super::cache::ResultCache;
+use super::compression;
+use super::history::QueryHistoryEntry;
+use super::protocol::{Request, Response, BackendStatusResponse, ConfigResponse, ExtensionGroup, SearchOptions, StatusResponse, WatchStatusResponse};
+
+/// Connection a response is read from
+#[cfg(windows)]
+type ResponseReader = BufReader<tokio::net::windows::named_pipe::NamedPipeClient>;
+#[cfg(unix)]
+type ResponseReader = BufReader<tokio::net::unix::OwnedReadHalf>;
+
+/// Longest a client keeps retrying while every pipe instance is serving someone else
+#[cfg(windows)]
+const PIPE_BUSY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Delay between attempts to open a busy pipe
+#[cfg(windows)]
+const PIPE_BUSY_RETRY_DELAY: std::time::Duration = std::time::Duration::from_millis(50);
+
+/// Open the daemon's pipe. ERROR_PIPE_BUSY only means all instances are taken for
+/// now, so it is retried for a while. Retries sleep instead of calling
+/// WaitNamedPipeW, which would block a runtime thread.
+#[cfg(windows)]
+async fn open_pipe(name: &Path) -> Result<tokio::net::windows::named_pipe::NamedPipeClient> {
+    use tokio::net::windows::named_pipe::ClientOptions;
+    use windows_sys::Win32::Foundation::ERROR_PIPE_BUSY;
+
+    let deadline = std::time::Instant::now() + PIPE_BUSY_TIMEOUT;
+    loop {
+        match ClientOptions::new().open(name) {
+            Ok(client) => return Ok(client),
+            Err(e) if e.raw_os_error() == Some(ERROR_PIPE_BUSY as i32) => {
+                if std::time::Instant::now() >= deadline {
+                    bail!(
+                        "StellaSearch service is busy: no free connection within {}s",
+                        PIPE_BUSY_TIMEOUT.as_secs()
+                    );
+                }
+            }
+            Err(e) => {
+                return Err(e).context("Failed to connect to StellaSearch service. Is it running?");
+            }
+        }
+        tokio::time::sleep(PIPE_BUSY_RETRY_DELAY).await;
+    }
+}
+
+/// Read one response line, and the body after it if the response came compressed
+async fn read_response(reader: &mut ResponseReader) -> Result<Response> {
+    let mut line = String::new();
+    reader.read_line(&mut line).await?;
+
+    let response: Response = serde_json::from_str(&line)?;
+    if let Response::Compressed { encoding, length } = response {
+        let mut data = vec![0; length];
+        reader.read_exact(&mut data).await.context("Compressed response ended early")?;
+        let json = compression::decompress(encoding, &data)?;
+        return Ok(serde_json::from_slice(&json)?);
+    }
+    Ok(response)
+}
+
+/// Search results read one file at a time (see `IpcClient::search_stream`)
+pub struct SearchStream {
+    pub total_found: usize,
+    pub query_time_ms: u64,
+    pub truncated: bool,
+    files: StreamedFiles,
+}
+
+/// Where a `SearchStream` takes its files from
+enum StreamedFiles {
+    /// The daemon sent the whole (small) result at once
+    Received(std::vec::IntoIter<IndexedFile>),
+    /// `remaining` more `search_file` lines are still to be read
+    Connection { reader: ResponseReader, remaining: usize },
+}
+
+impl SearchStream {
+    /// Next result file, or None once all have been read
+    pub async fn next_file(&mut self) -> Result<Option<IndexedFile>> {
+        match &mut self.files {
+            StreamedFiles::Received(files) => Ok(files.next()),
+            StreamedFiles::Connection { reader, remaining } => {
+                if *remaining == 0 {
+                    return Ok(None);
+                }
+                match read_response(reader).await.context("Search result stream ended early")? {
+                    Response::SearchFile(file) => {
+                        *remaining -= 1;
+                        Ok(Some(file))
+                    }
+                    _ => bail!("Unexpected response type in search result stream"),
+                }
+            }
+        }
+    }
+}
 
 /// IPC client for communicating with the StellaSearch service
 pub struct IpcClient {
     // Connection will be established per-request
+    socket_path: PathBuf,
+    /// Opt-in search result cache (see cache.rs)
+    cache: Option<Mutex<ResultCache>>,
 }
 
 impl IpcClient {
-    /// Connect to the IPC server
+    /// Connect to the IPC server at the configured socket path. Fails if the config
+    /// file can't be read, rather than guessing at the socket path.
     pub async fn connect() -> Result<Self> {
-        Ok(Self {})
+        let socket_path = Config::load()
+            .context("Failed to load config to find the StellaSearch service")?
+            .get_socket_path();
+        Self::connect_to(socket_path).await
+    }
+
+    /// Connect to the IPC server listening on a specific socket path (or pipe name on Windows)
+    pub async fn connect_to(socket_path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self {
+            socket_path: socket_path.as_ref().to_path_buf(),
+            cache: None,
+        })
+    }
+
+    /// Cache up to `capacity` search results, reusing them for identical searches
+    /// until the daemon reports that the index changed
+    #[allow(dead_code)] // For long-lived clients; the CLI makes one request per run
+    pub fn with_cache(mut self, capacity: usize) -> Self {
+        self.cache = Some(Mutex::new(ResultCache::new(capacity)));
+        self
+    }
+
+    /// Drop all cached search results
+    #[allow(dead_code)]
+    pub fn clear_cache(&self) {
+        if let Some(cache) = &self.cache {
+            cache.lock().unwrap().clear();
+        }
+    }
+
+    /// Record the index generation from a response
+    fn observe_generation(&self, generation: u64) {
+        if let Some(cache) = &self.cache {
+            cache.lock().unwrap().observe_generation(generation);
+        }
     }
 
     /// Send a request and receive a response
     async fn send_request(&self, request: &Request) -> Result<Response> {
+        let mut reader = self.open_request(request).await?;
+        read_response(&mut reader).await
+    }
+
+    /// Send a request, returning the connection to read the response from
+    async fn open_request(&self, request: &Request) -> Result<ResponseReader> {
         #[cfg(windows)]
         {
-            self.send_request_windows(request).await
+            self.open_request_windows(request).await
         }
 
         #[cfg(unix)]
         {
-            self.send_request_unix(request).await
+            self.open_request_unix(request).await
         }
     }
 
     #[cfg(windows)]
-    async fn send_request_windows(&self, request: &Request) -> Result<Response> {
-        use tokio::net::windows::named_pipe::ClientOptions;
-
-        let pipe_name = r"\\.\pipe\stella-search";
-
-        let client = ClientOptions::new()
-            .open(pipe_name)
-            .context("Failed to connect to StellaSearch service. Is it running?")?;
+    async fn open_request_windows(&self, request: &Request) -> Result<ResponseReader> {
+        let client = open_pipe(&self.socket_path).await?;
 
         let request_json = serde_json::to_string(request)?;
 
@@ -49,27 +188,14 @@ impl IpcClient {
         writer.write_all(b"\n").await?;
         writer.flush().await?;
 
-        // Read response
-        let mut reader = BufReader::new(writer);
-        let mut line = String::new();
-        reader.read_line(&mut line).await?;
-
-        let response: Response = serde_json::from_str(&line)?;
-        Ok(response)
+        Ok(BufReader::new(writer))
     }
 
     #[cfg(unix)]
-    async fn send_request_unix(&self, request: &Request) -> Result<Response> {
+    async fn open_request_unix(&self, request: &Request) -> Result<ResponseReader> {
         use tokio::net::UnixStream;
 
-        // Try XDG_RUNTIME_DIR first, then /tmp
-        let socket_path = if let Ok(runtime_dir) = std::env::var("XDG_RUNTIME_DIR") {
-            std::path::PathBuf::from(runtime_dir).join("stella-search.sock")
-        } else {
-            std::path::PathBuf::from("/tmp/stella-search.sock")
-        };
-
-        let stream = UnixStream::connect(&socket_path)
+        let stream = UnixStream::connect(&self.socket_path)
             .await
             .context("Failed to connect to StellaSearch service. Is it running?")?;
 
@@ -83,13 +209,7 @@ impl IpcClient {
         writer.write_all(b"\n").await?;
         writer.flush().await?;
 
-        // Read response
-        let mut reader = BufReader::new(reader);
-        let mut line = String::new();
-        reader.read_line(&mut line).await?;
-
-        let response: Response = serde_json::from_str(&line)?;
-        Ok(response)
+        Ok(BufReader::new(reader))
     }
 
     /// Search for files
@@ -98,12 +218,29 @@ impl IpcClient {
         query: &str,
         max_results: usize,
         extension: Option<&str>,
+        options: SearchOptions,
     ) -> Result<SearchResults> {
         let request = Request::Search {
             query: query.to_string(),
             max_results: Some(max_results),
             extensions: extension.map(|e| vec![e.to_string()]),
             directories: None,
+            options: SearchOptions {
+                compress: options.compress.or(compression::SUPPORTED.then_some(true)),
+                ..options
+            },
+        };
+
+        // The serialized request covers every parameter that affects the results
+        let key = match &self.cache {
+            Some(cache) => {
+                let key = serde_json::to_string(&request)?;
+                if let Some(results) = cache.lock().unwrap().get(&key) {
+                    return Ok(results);
+                }
+                Some(key)
+            }
+            None => None,
         };
 
         match self.send_request(&request).await? {
@@ -111,16 +248,213 @@ impl IpcClient {
                 files,
                 total_found,
                 query_time_ms,
-            } => Ok(SearchResults {
+                generation,
+                truncated,
+            } => {
+                let results = SearchResults {
+                    files,
+                    total_found,
+                    query_time_ms,
+                    truncated,
+                };
+                if let (Some(cache), Some(key), Some(generation)) = (&self.cache, key, generation) {
+                    cache.lock().unwrap().insert(key, generation, &results);
+                }
+                Ok(results)
+            }
+            Response::Error { message } => bail!("Search failed: {}", message),
+            _ => bail!("Unexpected response type"),
+        }
+    }
+
+    /// Count matches of a query per extension
+    pub async fn search_facets(&self, query: &str, options: SearchOptions) -> Result<Vec<ExtensionCount>> {
+        let request = Request::SearchFacets {
+            query: query.to_string(),
+            options,
+        };
+
+        match self.send_request(&request).await? {
+            Response::SearchFacets { facets } => Ok(facets),
+            Response::Error { message } => bail!("Facets failed: {}", message),
+            _ => bail!("Unexpected response type"),
+        }
+    }
+
+    /// Search for files, reading a large result from the daemon one file at a time
+    /// instead of as a single response. Bypasses the result cache.
+    pub async fn search_stream(
+        &self,
+        query: &str,
+        max_results: usize,
+        extension: Option<&str>,
+        options: SearchOptions,
+    ) -> Result<SearchStream> {
+        let request = Request::Search {
+            query: query.to_string(),
+            max_results: Some(max_results),
+            extensions: extension.map(|e| vec![e.to_string()]),
+            directories: None,
+            options: SearchOptions {
+                stream: Some(true),
+                ..options
+            },
+        };
+
+        let mut reader = self.open_request(&request).await?;
+        match read_response(&mut reader).await? {
+            Response::SearchStream {
+                count,
+                total_found,
+                query_time_ms,
+                truncated,
+                ..
+            } => Ok(SearchStream {
+                total_found,
+                query_time_ms,
+                truncated,
+                files: StreamedFiles::Connection {
+                    reader,
+                    remaining: count,
+                },
+            }),
+            Response::SearchResult {
                 files,
                 total_found,
                 query_time_ms,
+                truncated,
+                ..
+            } => Ok(SearchStream {
+                total_found,
+                query_time_ms,
+                truncated,
+                files: StreamedFiles::Received(files.into_iter()),
             }),
             Response::Error { message } => bail!("Search failed: {}", message),
             _ => bail!("Unexpected response type"),
         }
     }
 
+    /// Directories holding files that match a query, with per-directory match counts
+    pub async fn search_containing_dirs(
+        &self,
+        query: &str,
+        max_results: usize,
+        extension: Option<&str>,
+        options: SearchOptions,
+    ) -> Result<Vec<DirectoryCount>> {
+        let request = Request::Search {
+            query: query.to_string(),
+            max_results: Some(max_results),
+            extensions: extension.map(|e| vec![e.to_string()]),
+            directories: None,
+            options: SearchOptions {
+                return_containing_dirs: Some(true),
+                ..options
+            },
+        };
+
+        match self.send_request(&request).await? {
+            Response::ContainingDirs { directories, .. } => Ok(directories),
+            Response::Error { message } => bail!("Search failed: {}", message),
+            _ => bail!("Unexpected response type"),
+        }
+    }
+
+    /// Get the most recently modified files
+    pub async fn newest(&self, limit: usize) -> Result<SearchResults> {
+        let request = Request::Newest { limit: Some(limit) };
+
+        match self.send_request(&request).await? {
+            Response::SearchResult {
+                files,
+                total_found,
+                query_time_ms,
+                generation,
+                truncated,
+            } => {
+                if let Some(generation) = generation {
+                    self.observe_generation(generation);
+                }
+                Ok(SearchResults {
+                    files,
+                    total_found,
+                    query_time_ms,
+                    truncated,
+                })
+            }
+            Response::Error { message } => bail!("Newest failed: {}", message),
+            _ => bail!("Unexpected response type"),
+        }
+    }
+
+    /// Get the files modified after `since` (Unix seconds), newest first
+    pub async fn session_changes(&self, since: i64, limit: usize) -> Result<SearchResults> {
+        let request = Request::SessionChanges { since, limit: Some(limit) };
+
+        match self.send_request(&request).await? {
+            Response::SearchResult {
+                files,
+                total_found,
+                query_time_ms,
+                generation,
+                truncated,
+            } => {
+                if let Some(generation) = generation {
+                    self.observe_generation(generation);
+                }
+                Ok(SearchResults {
+                    files,
+                    total_found,
+                    query_time_ms,
+                    truncated,
+                })
+            }
+            Response::Error { message } => bail!("Session changes failed: {}", message),
+            _ => bail!("Unexpected response type"),
+        }
+    }
+
+    /// Get the entries the index has gone longest without refreshing
+    pub async fn oldest_indexed(&self, limit: usize) -> Result<Vec<StaleEntry>> {
+        let request = Request::OldestIndexed { limit: Some(limit) };
+
+        match self.send_request(&request).await? {
+            Response::OldestIndexed { entries } => Ok(entries),
+            Response::Error { message } => bail!("Oldest indexed failed: {}", message),
+            _ => bail!("Unexpected response type"),
+        }
+    }
+
+    /// Recent search queries, most recent first
+    pub async fn query_history(&self, limit: usize) -> Result<Vec<QueryHistoryEntry>> {
+        let request = Request::QueryHistory { limit: Some(limit) };
+
+        match self.send_request(&request).await? {
+            Response::QueryHistory { entries } => Ok(entries),
+            Response::Error { message } => bail!("Query history failed: {}", message),
+            _ => bail!("Unexpected response type"),
+        }
+    }
+
+    /// Forget the recorded search queries; the message says how many there were
+    pub async fn clear_query_history(&self) -> Result<String> {
+        match self.send_request(&Request::ClearQueryHistory).await? {
+            Response::Ok { message } => Ok(message),
+            Response::Error { message } => bail!("Clear query history failed: {}", message),
+            _ => bail!("Unexpected response type"),
+        }
+    }
+
+    /// Check that the daemon is answering; returns its process id
+    pub async fn ping(&self) -> Result<u32> {
+        match self.send_request(&Request::Ping).await? {
+            Response::Pong { pid } => Ok(pid),
+            Response::Error { message } => bail!("Ping failed: {}", message),
+            _ => bail!("Unexpected response type"),
+        }
+    }
+
     /// Get index status
     pub async fn get_status(&self) -> Result<StatusResponse> {
         let request = Request::Status;
@@ -131,18 +465,37 @@ impl IpcClient {
                 indexed_files,
                 indexed_dirs,
                 database_size_bytes,
+                total_size_bytes,
                 is_scanning,
                 scan_progress,
                 current_scan_path,
-            } => Ok(StatusResponse {
-                search_backend,
-                indexed_files,
-                indexed_dirs,
-                database_size_bytes,
-                is_scanning,
-                scan_progress,
-                current_scan_path,
-            }),
+                low_disk_space,
+                active_searches,
+                generation,
+                privilege,
+                started_at,
+                last_full_scan,
+                index_warning,
+            } => {
+                self.observe_generation(generation);
+                Ok(StatusResponse {
+                    search_backend,
+                    indexed_files,
+                    indexed_dirs,
+                    database_size_bytes,
+                    total_size_bytes,
+                    is_scanning,
+                    scan_progress,
+                    current_scan_path,
+                    low_disk_space,
+                    active_searches,
+                    generation,
+                    privilege,
+                    started_at,
+                    last_full_scan,
+                    index_warning,
+                })
+            }
             Response::Error { message } => bail!("Status failed: {}", message),
             _ => bail!("Unexpected response type"),
         }
@@ -238,6 +591,20 @@ impl IpcClient {
         }
     }
 
+    /// Count the indexed entries an exclude pattern would remove
+    pub async fn preview_exclude(&self, pattern: &str, sample: usize) -> Result<ExcludePreview> {
+        let request = Request::PreviewExclude {
+            pattern: pattern.to_string(),
+            sample: Some(sample),
+        };
+
+        match self.send_request(&request).await? {
+            Response::ExcludePreview(preview) => Ok(preview),
+            Response::Error { message } => bail!("Preview exclude failed: {}", message),
+            _ => bail!("Unexpected response type"),
+        }
+    }
+
     /// Trigger reindex
     pub async fn reindex(&self, path: Option<&str>) -> Result<()> {
         let request = Request::Reindex {
@@ -250,4 +617,274 @@ impl IpcClient {
             _ => bail!("Unexpected response type"),
         }
     }
+
+    /// Clear and rescan one Windows drive, e.g. 'D'
+    pub async fn reindex_drive(&self, drive: char) -> Result<()> {
+        match self.send_request(&Request::ReindexDrive { drive }).await? {
+            Response::Ok { .. } => Ok(()),
+            Response::Error { message } => bail!("Reindex drive failed: {}", message),
+            _ => bail!("Unexpected response type"),
+        }
+    }
+
+    /// Cancel the running scan or reindex; the message says whether one was running
+    pub async fn cancel_reindex(&self) -> Result<String> {
+        match self.send_request(&Request::CancelReindex).await? {
+            Response::Ok { message } => Ok(message),
+            Response::Error { message } => bail!("Cancel reindex failed: {}", message),
+            _ => bail!("Unexpected response type"),
+        }
+    }
+
+    /// List the extension groups searches can filter by, sorted by name
+    pub async fn extension_groups(&self) -> Result<Vec<ExtensionGroup>> {
+        match self.send_request(&Request::ExtensionGroups).await? {
+            Response::ExtensionGroups { groups } => Ok(groups),
+            Response::Error { message } => bail!("Listing extension groups failed: {}", message),
+            _ => bail!("Unexpected response type"),
+        }
+    }
+
+    /// Get the live state of each watch path
+    pub async fn watch_status(&self) -> Result<WatchStatusResponse> {
+        match self.send_request(&Request::WatchStatus).await? {
+            Response::WatchStatus { watcher, paths } => Ok(WatchStatusResponse { watcher, paths }),
+            Response::Error { message } => bail!("Watch status failed: {}", message),
+            _ => bail!("Unexpected response type"),
+        }
+    }
+
+    /// Get the index breakdown by extension, volume and size
+    pub async fn detailed_status(&self) -> Result<DetailedStats> {
+        match self.send_request(&Request::DetailedStatus).await? {
+            Response::DetailedStatus(stats) => Ok(stats),
+            Response::Error { message } => bail!("Detailed status failed: {}", message),
+            _ => bail!("Unexpected response type"),
+        }
+    }
+
+    /// Get search backend status
+    pub async fn backend_status(&self) -> Result<BackendStatusResponse> {
+        self.request_backend_status(&Request::BackendStatus).await
+    }
+
+    /// Re-check primary backend availability; `switched_to_primary` reports whether it switched back
+    pub async fn refresh_backend(&self) -> Result<BackendStatusResponse> {
+        self.request_backend_status(&Request::RefreshBackend).await
+    }
+
+    async fn request_backend_status(&self, request: &Request) -> Result<BackendStatusResponse> {
+        match self.send_request(request).await? {
+            Response::BackendStatus {
+                primary,
+                fallback,
+                active,
+                primary_available,
+                using_fallback,
+                switched_to_primary,
+            } => Ok(BackendStatusResponse {
+                primary,
+                fallback,
+                active,
+                primary_available,
+                using_fallback,
+                switched_to_primary,
+            }),
+            Response::Error { message } => bail!("Backend status failed: {}", message),
+            _ => bail!("Unexpected response type"),
+        }
+    }
+
+    /// Back up the database to a file
+    pub async fn backup(&self, path: &str) -> Result<String> {
+        let request = Request::Backup {
+            path: path.to_string(),
+        };
+
+        match self.send_request(&request).await? {
+            Response::Ok { message } => Ok(message),
+            Response::Error { message } => bail!("Backup failed: {}", message),
+            _ => bail!("Unexpected response type"),
+        }
+    }
+
+    /// Restore the database from a backup file
+    pub async fn restore(&self, path: &str) -> Result<String> {
+        let request = Request::Restore {
+            path: path.to_string(),
+        };
+
+        match self.send_request(&request).await? {
+            Response::Ok { message } => Ok(message),
+            Response::Error { message } => bail!("Restore failed: {}", message),
+            _ => bail!("Unexpected response type"),
+        }
+    }
+
+    /// Import a JSONL dump into the index
+    pub async fn import(&self, path: &str, policy: ImportPolicy) -> Result<String> {
+        let request = Request::Import {
+            path: path.to_string(),
+            policy,
+        };
+
+        match self.send_request(&request).await? {
+            Response::Ok { message } => Ok(message),
+            Response::Error { message } => bail!("Import failed: {}", message),
+            _ => bail!("Unexpected response type"),
+        }
+    }
+
+    /// Pull the index into the daemon's cache
+    pub async fn warmup(&self) -> Result<String> {
+        match self.send_request(&Request::Warmup).await? {
+            Response::Ok { message } => Ok(message),
+            Response::Error { message } => bail!("Warm-up failed: {}", message),
+            _ => bail!("Unexpected response type"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestDaemon;
+
+    const FILES: &[&str] = &[
+        "/data/",
+        "/data/reports/",
+        "/data/reports/report_final.txt",
+        "/data/reports/budget.xlsx",
+        "/data/notes.md",
+    ];
+
+    #[tokio::test]
+    async fn ping_answers_with_the_daemon_pid() {
+        let daemon = TestDaemon::start(FILES).await;
+        let pid = daemon.client().await.ping().await.unwrap();
+        assert_eq!(pid, std::process::id());
+    }
+
+    #[tokio::test]
+    async fn search_finds_seeded_files() {
+        let daemon = TestDaemon::start(FILES).await;
+        let client = daemon.client().await;
+
+        let results = client.search("report", 10, None, SearchOptions::default()).await.unwrap();
+        let paths: Vec<_> = results.files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, ["/data/reports", "/data/reports/report_final.txt"]);
+
+        let results = client.search("report", 10, Some(".txt"), SearchOptions::default()).await.unwrap();
+        assert_eq!(results.total_found, 1);
+
+        let results = client.search("missing", 10, None, SearchOptions::default()).await.unwrap();
+        assert!(results.files.is_empty());
+
+        // Rows written while the server runs are visible to the next search
+        crate::test_support::seed(&daemon.db, &["/data/missing.txt"]);
+        let results = client.search("missing", 10, None, SearchOptions::default()).await.unwrap();
+        assert_eq!(results.total_found, 1);
+    }
+
+    #[tokio::test]
+    async fn cached_searches_are_reused_until_the_generation_changes() {
+        let daemon = TestDaemon::start(FILES).await;
+        let client = daemon.client().await.with_cache(8);
+        let search = || client.search("budget", 10, None, SearchOptions::default());
+
+        assert_eq!(search().await.unwrap().total_found, 1);
+
+        // The index changes, but nothing has told the client yet: the cached answer stands
+        crate::test_support::seed(&daemon.db, &["/data/budget_2025.xlsx"]);
+        assert_eq!(search().await.unwrap().total_found, 1);
+
+        // Any response carrying the new generation drops the cache, so the search goes
+        // to the daemon again
+        let status = client.get_status().await.unwrap();
+        assert_eq!(status.generation, daemon.db.generation());
+        assert_eq!(search().await.unwrap().total_found, 2);
+
+        // A different query is never answered from another one's entry
+        let results = client.search("budget", 1, None, SearchOptions::default()).await.unwrap();
+        assert_eq!(results.files.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn non_ascii_names_come_back_intact() {
+        let daemon = TestDaemon::start(&["/data/Лето_2024/", "/data/Лето_2024/café résumé 日本.pdf"]).await;
+        let client = daemon.client().await;
+
+        let results = client.search("résumé 日本", 10, None, SearchOptions::default()).await.unwrap();
+        assert_eq!(results.files.len(), 1);
+        assert_eq!(results.files[0].name, "café résumé 日本.pdf");
+        assert_eq!(results.files[0].path, "/data/Лето_2024/café résumé 日本.pdf");
+    }
+
+    #[cfg(feature = "compression")]
+    #[tokio::test]
+    async fn large_results_arrive_compressed_and_intact() {
+        let paths: Vec<String> = (0..900).map(|i| format!("/data/report_{:05}.txt", i)).collect();
+        let daemon = TestDaemon::start(&paths.iter().map(String::as_str).collect::<Vec<_>>()).await;
+        let client = daemon.client().await;
+
+        // The client asks for compression by default in this build
+        let results = client.search("report", 900, None, SearchOptions::default()).await.unwrap();
+        let mut found: Vec<String> = results.files.into_iter().map(|f| f.path).collect();
+        found.sort();
+        assert_eq!(found, paths);
+    }
+
+    #[tokio::test]
+    async fn status_counts_seeded_files_and_directories() {
+        let daemon = TestDaemon::start(FILES).await;
+        let status = daemon.client().await.get_status().await.unwrap();
+
+        assert_eq!(status.indexed_files, 3);
+        assert_eq!(status.indexed_dirs, 2);
+        assert_eq!(status.search_backend, "SQLite");
+        assert!(!status.is_scanning);
+        assert_eq!(status.active_searches, 0);
+    }
+
+    #[tokio::test]
+    async fn config_reports_the_daemon_config() {
+        let daemon = TestDaemon::start_with(&[], |config| {
+            config.watch.exclude_patterns = vec!["*.tmp".to_string()];
+        })
+        .await;
+        let config = daemon.client().await.get_config().await.unwrap();
+        assert_eq!(config.exclude_patterns, ["*.tmp"]);
+    }
+
+    #[tokio::test]
+    async fn streamed_results_match_a_plain_search() {
+        let paths: Vec<String> = (0..1200).map(|i| format!("/data/report_{:05}.txt", i)).collect();
+        let daemon = TestDaemon::start(&paths.iter().map(String::as_str).collect::<Vec<_>>()).await;
+        let client = daemon.client().await;
+
+        let plain = client.search("report", 2000, None, SearchOptions::default()).await.unwrap();
+        let mut stream = client.search_stream("report", 2000, None, SearchOptions::default()).await.unwrap();
+        assert_eq!(stream.total_found, 1200);
+        assert!(matches!(stream.files, StreamedFiles::Connection { remaining: 1200, .. }));
+
+        let mut streamed = Vec::new();
+        while let Some(file) = stream.next_file().await.unwrap() {
+            streamed.push(file);
+        }
+        assert_eq!(serde_json::to_value(&streamed).unwrap(), serde_json::to_value(&plain.files).unwrap());
+    }
+
+    #[tokio::test]
+    async fn small_streamed_searches_arrive_whole() {
+        let daemon = TestDaemon::start(FILES).await;
+        let client = daemon.client().await;
+
+        let mut stream = client.search_stream("report", 10, None, SearchOptions::default()).await.unwrap();
+        assert!(matches!(stream.files, StreamedFiles::Received(_)));
+        let mut paths = Vec::new();
+        while let Some(file) = stream.next_file().await.unwrap() {
+            paths.push(file.path);
+        }
+        assert_eq!(paths, ["/data/reports", "/data/reports/report_final.txt"]);
+    }
 }
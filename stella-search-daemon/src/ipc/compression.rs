@@ -0,0 +1,58 @@
+//! Compression of large IPC responses (cargo feature `compression`)
+//!
+//! A client that can decode asks with `compress` in its search options. A response
+//! of at least `COMPRESS_MIN_BYTES` of JSON then goes out as a `compressed` header
+//! line naming the codec and body length, followed by that many bytes of
+//! zstd-compressed JSON. Level 1 is used: the socket is local, so speed matters
+//! more than ratio. A daemon built without the feature answers uncompressed.
+
+use anyhow::Result;
+
+use super::protocol::Compression;
+
+/// Responses with less JSON than this are sent as plain lines
+pub(super) const COMPRESS_MIN_BYTES: usize = 64 * 1024;
+
+/// Whether this build can decode compressed responses (and so may ask for them)
+pub(super) const SUPPORTED: bool = cfg!(feature = "compression");
+
+#[cfg(feature = "compression")]
+const ZSTD_LEVEL: i32 = 1;
+
+/// Compress a serialized response; None if this build can't, or compression failed
+pub(super) fn compress(json: &[u8]) -> Option<(Compression, Vec<u8>)> {
+    #[cfg(feature = "compression")]
+    {
+        match zstd::bulk::compress(json, ZSTD_LEVEL) {
+            Ok(data) => Some((Compression::Zstd, data)),
+            Err(e) => {
+                tracing::warn!("Failed to compress response, sending it uncompressed: {}", e);
+                None
+            }
+        }
+    }
+
+    #[cfg(not(feature = "compression"))]
+    {
+        let _ = json;
+        None
+    }
+}
+
+/// Decode the body of a `compressed` response back to JSON
+pub(super) fn decompress(encoding: Compression, data: &[u8]) -> Result<Vec<u8>> {
+    match encoding {
+        Compression::Zstd => {
+            #[cfg(feature = "compression")]
+            {
+                Ok(zstd::stream::decode_all(data)?)
+            }
+
+            #[cfg(not(feature = "compression"))]
+            {
+                let _ = data;
+                anyhow::bail!("Response is zstd-compressed, but this build has no compression support")
+            }
+        }
+    }
+}
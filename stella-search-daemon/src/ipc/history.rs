@@ -0,0 +1,74 @@
+//! Recent search queries, for a search-history dropdown
+//!
+//! Off unless `service.record_query_history` is set, and kept in memory only: the
+//! history is gone when the daemon stops. Repeating a query moves it to the front
+//! instead of adding a second entry. Searches from per-user scoped clients are not
+//! recorded, since every client reading the history would see them.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+/// One remembered query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryHistoryEntry {
+    pub query: String,
+    /// When the query was last searched (Unix seconds)
+    pub last_searched: i64,
+    /// Matches found by the last search
+    pub result_count: usize,
+    /// Times the query was searched while remembered
+    pub times_searched: u64,
+}
+
+/// Most recent distinct queries, newest first, capped at `capacity`
+pub(super) struct QueryHistory {
+    capacity: usize,
+    entries: Mutex<VecDeque<QueryHistoryEntry>>,
+}
+
+impl QueryHistory {
+    /// A history holding up to `capacity` queries (0 records nothing)
+    pub(super) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Record a search. Queries are compared after trimming, so "a " and "a"
+    /// are one entry; empty queries are not recorded.
+    pub(super) fn record(&self, query: &str, result_count: usize) {
+        let query = query.trim();
+        if query.is_empty() || self.capacity == 0 {
+            return;
+        }
+
+        let mut entries = self.entries.lock().unwrap();
+        let times_searched = match entries.iter().position(|entry| entry.query == query) {
+            Some(i) => entries.remove(i).map_or(0, |entry| entry.times_searched),
+            None => 0,
+        };
+        entries.push_front(QueryHistoryEntry {
+            query: query.to_string(),
+            last_searched: chrono::Utc::now().timestamp(),
+            result_count,
+            times_searched: times_searched + 1,
+        });
+        entries.truncate(self.capacity);
+    }
+
+    /// Up to `limit` queries, most recently searched first
+    pub(super) fn recent(&self, limit: usize) -> Vec<QueryHistoryEntry> {
+        self.entries.lock().unwrap().iter().take(limit).cloned().collect()
+    }
+
+    /// Forget every query; returns how many there were
+    pub(super) fn clear(&self) -> usize {
+        let mut entries = self.entries.lock().unwrap();
+        let count = entries.len();
+        entries.clear();
+        count
+    }
+}
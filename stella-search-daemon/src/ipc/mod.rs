@@ -6,6 +6,10 @@
 mod protocol;
 mod server;
 mod client;
+mod cache;
+mod peer;
+mod history;
+mod compression;
 
 pub use protocol::*;
 pub use server::IpcServer;
@@ -0,0 +1,223 @@
+//! Identification of IPC clients for per-user scoping
+//!
+//! With `service.per_user_scope`, a shared daemon (root / LocalSystem) indexes every
+//! user's files into one database. Each connection is mapped to the home directory of
+//! the user on the other end: from `SO_PEERCRED` on Unix, and by briefly impersonating
+//! the named-pipe client on Windows. Searches from that client only match files under
+//! it. The service's own account (and root) keeps the unscoped view.
+
+use anyhow::Result;
+
+/// What a connected client may see
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum ClientScope {
+    /// The whole index (scoping disabled, or the service's own account)
+    All,
+    /// Only files under this home directory
+    Home(String),
+}
+
+impl ClientScope {
+    /// Directory results must lie under, if any
+    pub(super) fn directory(&self) -> Option<&str> {
+        match self {
+            ClientScope::All => None,
+            ClientScope::Home(home) => Some(home),
+        }
+    }
+}
+
+/// Scope of the user on the other end of a Unix socket
+#[cfg(unix)]
+pub(super) fn unix_peer_scope(stream: &tokio::net::UnixStream) -> Result<ClientScope> {
+    use nix::unistd::{geteuid, Uid};
+
+    uid_scope(Uid::from_raw(stream.peer_cred()?.uid()), geteuid())
+}
+
+/// Scope of a client running as `uid`, for a service running as `service`
+#[cfg(unix)]
+fn uid_scope(uid: nix::unistd::Uid, service: nix::unistd::Uid) -> Result<ClientScope> {
+    use anyhow::Context;
+    use nix::unistd::User;
+
+    if uid.is_root() || uid == service {
+        return Ok(ClientScope::All);
+    }
+
+    let user = User::from_uid(uid)?.with_context(|| format!("No user with uid {}", uid))?;
+    Ok(ClientScope::Home(user.dir.to_string_lossy().into_owned()))
+}
+
+/// Scope of the user on the other end of a connected named pipe
+#[cfg(windows)]
+pub(super) fn pipe_client_scope(
+    pipe: &tokio::net::windows::named_pipe::NamedPipeServer,
+) -> Result<ClientScope> {
+    use std::os::windows::io::{AsRawHandle, FromRawHandle, OwnedHandle};
+    use windows_sys::Win32::Security::{EqualSid, RevertToSelf, TOKEN_QUERY};
+    use windows_sys::Win32::System::Pipes::ImpersonateNamedPipeClient;
+    use windows_sys::Win32::System::Threading::{
+        GetCurrentProcess, GetCurrentThread, OpenProcessToken, OpenThreadToken,
+    };
+    use windows_sys::Win32::UI::Shell::GetUserProfileDirectoryW;
+
+    // Impersonation is per thread: take the client's token and revert right away,
+    // with no await in between
+    let mut raw = std::ptr::null_mut();
+    let client = unsafe {
+        if ImpersonateNamedPipeClient(pipe.as_raw_handle()) == 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        let opened = OpenThreadToken(GetCurrentThread(), TOKEN_QUERY, 1, &mut raw);
+        let open_error = std::io::Error::last_os_error();
+        RevertToSelf();
+        if opened == 0 {
+            return Err(open_error.into());
+        }
+        OwnedHandle::from_raw_handle(raw)
+    };
+
+    let mut raw = std::ptr::null_mut();
+    let service = unsafe {
+        if OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut raw) == 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        OwnedHandle::from_raw_handle(raw)
+    };
+
+    let client_user = token_user(&client)?;
+    let service_user = token_user(&service)?;
+    if unsafe { EqualSid(sid_of(&client_user), sid_of(&service_user)) } != 0 {
+        return Ok(ClientScope::All);
+    }
+
+    let mut len = 0u32;
+    unsafe { GetUserProfileDirectoryW(client.as_raw_handle(), std::ptr::null_mut(), &mut len) };
+    let mut buf = vec![0u16; len as usize];
+    if unsafe { GetUserProfileDirectoryW(client.as_raw_handle(), buf.as_mut_ptr(), &mut len) } == 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    let end = buf.iter().position(|&c| c == 0).unwrap_or(buf.len());
+
+    Ok(ClientScope::Home(String::from_utf16_lossy(&buf[..end])))
+}
+
+/// TOKEN_USER of an access token, in a buffer aligned for its pointer fields
+#[cfg(windows)]
+fn token_user(token: &std::os::windows::io::OwnedHandle) -> Result<Vec<usize>> {
+    use std::os::windows::io::AsRawHandle;
+    use windows_sys::Win32::Security::{GetTokenInformation, TokenUser};
+
+    let mut len = 0u32;
+    unsafe { GetTokenInformation(token.as_raw_handle(), TokenUser, std::ptr::null_mut(), 0, &mut len) };
+    let mut buf = vec![0usize; (len as usize).div_ceil(size_of::<usize>())];
+    let ok = unsafe {
+        GetTokenInformation(token.as_raw_handle(), TokenUser, buf.as_mut_ptr().cast(), len, &mut len)
+    };
+    if ok == 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    Ok(buf)
+}
+
+#[cfg(windows)]
+fn sid_of(token_user: &[usize]) -> windows_sys::Win32::Security::PSID {
+    let user = token_user.as_ptr().cast::<windows_sys::Win32::Security::TOKEN_USER>();
+    unsafe { (*user).User.Sid }
+}
+
+/// Pipe DACL letting every authenticated user connect, read and write.
+/// Only SYSTEM and administrators get FILE_CREATE_PIPE_INSTANCE, so users can't
+/// create competing instances of the pipe; 0x12019b is generic read plus write data/attributes.
+#[cfg(windows)]
+const SCOPED_PIPE_SDDL: &str = "D:(A;;GA;;;SY)(A;;GA;;;BA)(A;;0x12019b;;;AU)";
+
+/// Security attributes for pipe instances created with per-user scoping on
+#[cfg(windows)]
+pub(super) struct PipeSecurity {
+    attributes: windows_sys::Win32::Security::SECURITY_ATTRIBUTES,
+}
+
+#[cfg(windows)]
+unsafe impl Send for PipeSecurity {}
+#[cfg(windows)]
+unsafe impl Sync for PipeSecurity {}
+
+#[cfg(windows)]
+impl PipeSecurity {
+    pub(super) fn new() -> Result<Self> {
+        use windows_sys::Win32::Security::Authorization::{
+            ConvertStringSecurityDescriptorToSecurityDescriptorW, SDDL_REVISION_1,
+        };
+        use windows_sys::Win32::Security::SECURITY_ATTRIBUTES;
+
+        let sddl: Vec<u16> = SCOPED_PIPE_SDDL.encode_utf16().chain(std::iter::once(0)).collect();
+        let mut descriptor = std::ptr::null_mut();
+        let ok = unsafe {
+            ConvertStringSecurityDescriptorToSecurityDescriptorW(
+                sddl.as_ptr(),
+                SDDL_REVISION_1,
+                &mut descriptor,
+                std::ptr::null_mut(),
+            )
+        };
+        if ok == 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+
+        Ok(Self {
+            attributes: SECURITY_ATTRIBUTES {
+                nLength: size_of::<SECURITY_ATTRIBUTES>() as u32,
+                lpSecurityDescriptor: descriptor,
+                bInheritHandle: 0,
+            },
+        })
+    }
+
+    /// Pointer for `ServerOptions::create_with_security_attributes_raw`
+    pub(super) fn as_ptr(&self) -> *mut std::ffi::c_void {
+        (&self.attributes as *const windows_sys::Win32::Security::SECURITY_ATTRIBUTES)
+            .cast_mut()
+            .cast()
+    }
+}
+
+#[cfg(windows)]
+impl Drop for PipeSecurity {
+    fn drop(&mut self) {
+        unsafe { windows_sys::Win32::Foundation::LocalFree(self.attributes.lpSecurityDescriptor) };
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use nix::unistd::{Uid, User};
+
+    use super::*;
+
+    #[test]
+    fn root_and_the_service_account_see_everything() {
+        let service = Uid::from_raw(4321);
+        assert_eq!(uid_scope(Uid::from_raw(0), service).unwrap(), ClientScope::All);
+        assert_eq!(uid_scope(service, service).unwrap(), ClientScope::All);
+    }
+
+    #[test]
+    fn other_users_are_scoped_to_their_home() {
+        let nobody = User::from_name("nobody").unwrap().expect("a 'nobody' account");
+        let scope = uid_scope(nobody.uid, Uid::from_raw(0)).unwrap();
+        assert_eq!(scope, ClientScope::Home(nobody.dir.to_string_lossy().into_owned()));
+    }
+
+    #[test]
+    fn unknown_users_are_refused() {
+        assert!(uid_scope(Uid::from_raw(3_999_999_999), Uid::from_raw(0)).is_err());
+    }
+
+    #[tokio::test]
+    async fn a_client_of_the_same_user_sees_everything() {
+        let (client, _server) = tokio::net::UnixStream::pair().unwrap();
+        assert_eq!(unix_peer_scope(&client).unwrap(), ClientScope::All);
+    }
+}
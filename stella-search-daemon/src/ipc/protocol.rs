@@ -1,7 +1,12 @@
 //! IPC protocol definitions
 
 use serde::{Deserialize, Serialize};
-use crate::database::{IndexedFile, SearchResults, IndexStats};
+use crate::database::{DetailedStats, DirectoryCount, ExcludePreview, ExtensionCount, ImportPolicy, IndexedFile, SearchResults, IndexStats, StaleEntry};
+use crate::indexer::WatchPathStatus;
+use crate::ipc::history::QueryHistoryEntry;
+use crate::platform::PrivilegeLevel;
+use crate::search::{PathEncoding, QueryKind, SizeCategory};
+use stella_search_core::ResultSort;
 
 /// Request message types
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -13,8 +18,42 @@ pub enum Request {
         max_results: Option<usize>,
         extensions: Option<Vec<String>>,
         directories: Option<Vec<String>>,
+        #[serde(flatten)]
+        options: SearchOptions,
     },
 
+    /// Count a query's matches per extension (for filter UIs)
+    SearchFacets {
+        query: String,
+        #[serde(flatten)]
+        options: SearchOptions,
+    },
+
+    /// Get the most recently modified files
+    Newest {
+        limit: Option<usize>,
+    },
+
+    /// Get the files modified after a client-chosen baseline (Unix seconds), newest
+    /// first: "what changed since I started working"
+    SessionChanges {
+        since: i64,
+        limit: Option<usize>,
+    },
+
+    /// Get the entries the index has gone longest without refreshing
+    OldestIndexed {
+        limit: Option<usize>,
+    },
+
+    /// Get recent search queries, most recent first (needs `service.record_query_history`)
+    QueryHistory {
+        limit: Option<usize>,
+    },
+
+    /// Forget the recorded search queries
+    ClearQueryHistory,
+
     /// Set indexing mode
     SetMode {
         mode: String,
@@ -43,19 +82,222 @@ pub enum Request {
         path: String,
     },
 
+    /// Count what an exclude pattern would remove from the index, without removing it
+    PreviewExclude {
+        pattern: String,
+        /// Number of matching paths to return (default 10)
+        #[serde(default)]
+        sample: Option<usize>,
+    },
+
     /// Get current configuration
     GetConfig,
 
+    /// List the extension groups searches can filter by
+    ExtensionGroups,
+
+    /// Check that a daemon is answering; replies with its process id
+    Ping,
+
     /// Get index status
     Status,
 
+    /// Get the index breakdown by extension, volume and size (slower than Status)
+    DetailedStatus,
+
+    /// Get the daemon's privilege level and the scan method it allows
+    PrivilegeStatus,
+
+    /// Get the live state of each watch path
+    WatchStatus,
+
     /// Trigger reindex
     Reindex {
         path: Option<String>,
     },
 
+    /// Clear and rescan one Windows drive (a letter such as 'D'), leaving the others
+    ReindexDrive {
+        drive: char,
+    },
+
+    /// Stop the running scan or reindex (the watcher keeps running)
+    CancelReindex,
+
     /// Reload configuration
     ReloadConfig,
+
+    /// Get search backend status (primary/fallback and which is active)
+    BackendStatus,
+
+    /// Re-check primary backend availability, switching back from fallback if possible
+    RefreshBackend,
+
+    /// Write a consistent copy of the database to a file
+    Backup {
+        path: String,
+    },
+
+    /// Replace the database contents with a backup file
+    Restore {
+        path: String,
+    },
+
+    /// Load a JSONL dump into the index
+    Import {
+        path: String,
+        #[serde(default)]
+        policy: ImportPolicy,
+    },
+
+    /// Pull the index into cache ahead of the next search
+    Warmup,
+}
+
+impl Request {
+    /// Whether a client limited to its own files (`per_user_scope`) may send this.
+    /// Searches and detailed status are scoped by the server; status calls that reveal
+    /// no paths are fine.
+    /// Everything else reads or changes daemon-wide state, or files as the service account.
+    pub fn allowed_when_scoped(&self) -> bool {
+        matches!(
+            self,
+            Request::Search { .. }
+                | Request::SearchFacets { .. }
+                | Request::Newest { .. }
+                | Request::SessionChanges { .. }
+                | Request::ExtensionGroups
+                | Request::Ping
+                | Request::Status
+                | Request::DetailedStatus
+                | Request::PrivilegeStatus
+                | Request::GetMode
+                | Request::BackendStatus
+        )
+    }
+}
+
+/// Optional search modifiers, flattened into the Search request.
+/// Every field is optional so older clients can omit them.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SearchOptions {
+    /// How the query is matched against names (default: substring)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<QueryKind>,
+
+    /// Encoding for result paths (default: UTF-8 lossy)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path_encoding: Option<PathEncoding>,
+
+    /// Number of matching results to skip, for paging through large result sets
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset: Option<usize>,
+
+    /// Only return results on this drive or mount point (e.g., "E:", "/mnt/data")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub drive: Option<String>,
+
+    /// Filter expression, e.g. `size > 10MB AND ext IN (pdf, docx)` (see search::filter_dsl)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filter: Option<String>,
+
+    /// false leaves out hidden entries (dotfiles, dot directories); default returns them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub include_hidden: Option<bool>,
+
+    /// Return at most this many results from any one directory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_per_directory: Option<usize>,
+
+    /// Only files in this size class (tiny, small, medium, large, huge)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size_category: Option<SizeCategory>,
+
+    /// Return paths relative to this directory; results outside it keep their full path
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strip_prefix: Option<String>,
+
+    /// true returns the directories holding matches (`containing_dirs`) instead of files
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub return_containing_dirs: Option<bool>,
+
+    /// true lets the daemon stream a large result as a `search_stream` header followed by
+    /// one `search_file` line per file; small results still come as one `search_result`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stream: Option<bool>,
+
+    /// true lets the daemon send a large response compressed: a `compressed` header
+    /// line, then the compressed JSON. A daemon built without the `compression`
+    /// feature ignores it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compress: Option<bool>,
+
+    /// Only files owned by this user: a uid or user name on Unix, a SID or account
+    /// name on Windows (needs `indexing.track_ownership`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+
+    /// true: only entries anyone may write to; false: only entries they can't
+    /// (needs `indexing.track_ownership`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub world_writable: Option<bool>,
+
+    /// Only files with an extension from this `search.extension_groups` entry
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extension_group: Option<String>,
+
+    /// Result order: rank (default: best name matches first, see below), size, name
+    /// or created
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sort: Option<ResultSort>,
+
+    /// With rank order, substring queries list exact name matches (with or without
+    /// extension) first, then names starting with the query. false keeps plain index
+    /// order, which can stop at `max_results` instead of sorting every match
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub boost_name_matches: Option<bool>,
+
+    /// Only entries created at or after this time (Unix seconds). Entries with no
+    /// recorded creation time never match
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_after: Option<i64>,
+
+    /// Only entries created before this time (Unix seconds)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_before: Option<i64>,
+
+    /// Only these exact paths (compared with the `path` of results); the other
+    /// conditions still apply. There is no limit on the list's length
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub restrict_to_paths: Option<Vec<String>>,
+
+    /// true adds `target` (where the link points) to results that are symbolic links.
+    /// SQLite backend only; costs a lookup per result and filesystem calls per link
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolve_symlinks: Option<bool>,
+
+    /// true sets `exists` on results: whether each is still on disk. Checked in
+    /// result order within a time budget; results past it keep `exists` unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verify_existence: Option<bool>,
+
+    /// Budget for `verify_existence` in milliseconds (default: `search.verify_budget_ms`,
+    /// 0 = no limit)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verify_budget_ms: Option<u64>,
+
+    /// true sets `volume` on results: the drive ("C:"), network share or mount point
+    /// each lives on, for grouping results by volume
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub include_volume: Option<bool>,
+}
+
+/// Codec of a compressed response
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Compression {
+    /// zstd at level 1
+    Zstd,
 }
 
 /// Response message types
@@ -67,6 +309,58 @@ pub enum Response {
         files: Vec<IndexedFile>,
         total_found: usize,
         query_time_ms: u64,
+        /// Index generation the results were read at. None when they came from a
+        /// backend the daemon doesn't index itself (e.g. Windows Search)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        generation: Option<u64>,
+        /// Results were cut off at the daemon's result size cap
+        #[serde(default)]
+        truncated: bool,
+    },
+
+    /// Header of a streamed search result; `count` `SearchFile` lines follow
+    SearchStream {
+        count: usize,
+        total_found: usize,
+        query_time_ms: u64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        generation: Option<u64>,
+        #[serde(default)]
+        truncated: bool,
+    },
+
+    /// One file of a streamed search result
+    SearchFile(IndexedFile),
+
+    /// Header of a compressed response; `length` bytes of the compressed JSON of the
+    /// actual response follow (no newline after them)
+    Compressed {
+        encoding: Compression,
+        length: usize,
+    },
+
+    /// Match counts per extension, most common first
+    SearchFacets {
+        facets: Vec<ExtensionCount>,
+    },
+
+    /// Entries by when they were last refreshed, never-refreshed first
+    OldestIndexed {
+        entries: Vec<StaleEntry>,
+    },
+
+    /// Recent distinct search queries, most recent first
+    QueryHistory {
+        entries: Vec<QueryHistoryEntry>,
+    },
+
+    /// Entries an exclude pattern would remove
+    ExcludePreview(ExcludePreview),
+
+    /// Directories holding matching files, most matches first
+    ContainingDirs {
+        directories: Vec<DirectoryCount>,
+        query_time_ms: u64,
     },
 
     /// Status response
@@ -75,9 +369,43 @@ pub enum Response {
         indexed_files: u64,
         indexed_dirs: u64,
         database_size_bytes: u64,
+        #[serde(default)]
+        total_size_bytes: u64,
         is_scanning: bool,
         scan_progress: f64,
         current_scan_path: Option<String>,
+        low_disk_space: bool,
+        #[serde(default)]
+        active_searches: usize,
+        /// Current index generation
+        #[serde(default)]
+        generation: u64,
+        #[serde(default)]
+        privilege: PrivilegeLevel,
+        /// When the daemon started (Unix seconds)
+        #[serde(default)]
+        started_at: i64,
+        /// When the last full scan completed (Unix seconds)
+        #[serde(default)]
+        last_full_scan: Option<i64>,
+        /// Why the last full scan looks incomplete, if it does
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        index_warning: Option<String>,
+    },
+
+    /// Detailed status response
+    DetailedStatus(DetailedStats),
+
+    /// Ping response
+    Pong {
+        pid: u32,
+    },
+
+    /// Privilege status response
+    PrivilegeStatus {
+        privilege: PrivilegeLevel,
+        /// "mft" or "walkdir"
+        scan_method: String,
     },
 
     /// Config response
@@ -90,6 +418,29 @@ pub enum Response {
         include_hidden: bool,
     },
 
+    /// Extension groups, by name
+    ExtensionGroups {
+        groups: Vec<ExtensionGroup>,
+    },
+
+    /// Watch path status
+    WatchStatus {
+        /// Watcher implementation ("native" or "poll"), None until the watcher starts
+        watcher: Option<String>,
+        paths: Vec<WatchPathStatus>,
+    },
+
+    /// Search backend status
+    BackendStatus {
+        primary: String,
+        fallback: Option<String>,
+        active: String,
+        primary_available: bool,
+        using_fallback: bool,
+        /// Set by RefreshBackend when it switched from fallback back to primary
+        switched_to_primary: bool,
+    },
+
     /// Mode response
     Mode {
         mode: String,
@@ -122,24 +473,52 @@ impl Response {
     }
 
     /// Create a search result response
-    pub fn search_result(results: SearchResults) -> Self {
+    pub fn search_result(results: SearchResults, generation: Option<u64>) -> Self {
         Response::SearchResult {
             files: results.files,
             total_found: results.total_found,
             query_time_ms: results.query_time_ms,
+            generation,
+            truncated: results.truncated,
         }
     }
 
     /// Create a status response
-    pub fn status(stats: IndexStats, search_backend: String) -> Self {
+    pub fn status(
+        stats: IndexStats,
+        search_backend: String,
+        active_searches: usize,
+        generation: u64,
+        started_at: i64,
+    ) -> Self {
         Response::Status {
             search_backend,
             indexed_files: stats.indexed_files,
             indexed_dirs: stats.indexed_dirs,
             database_size_bytes: stats.database_size_bytes,
+            total_size_bytes: stats.total_size_bytes,
             is_scanning: stats.is_scanning,
             scan_progress: stats.scan_progress,
             current_scan_path: stats.current_scan_path,
+            low_disk_space: stats.low_disk_space,
+            active_searches,
+            generation,
+            privilege: PrivilegeLevel::detect(),
+            started_at,
+            last_full_scan: stats.last_full_scan,
+            index_warning: stats.index_warning,
+        }
+    }
+
+    /// Create a backend status response
+    pub fn backend_status(manager: &crate::search::SearchManager, switched_to_primary: bool) -> Self {
+        Response::BackendStatus {
+            primary: manager.primary_backend_name().to_string(),
+            fallback: manager.fallback_backend_name().map(|s| s.to_string()),
+            active: manager.active_backend_name().to_string(),
+            primary_available: manager.is_primary_available(),
+            using_fallback: manager.is_using_fallback(),
+            switched_to_primary,
         }
     }
 
@@ -156,6 +535,14 @@ impl Response {
     }
 }
 
+/// One named set of extensions from `search.extension_groups`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExtensionGroup {
+    pub name: String,
+    /// Lowercase, with a leading dot
+    pub extensions: Vec<String>,
+}
+
 /// Config response for IPC client
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConfigResponse {
@@ -174,7 +561,33 @@ pub struct StatusResponse {
     pub indexed_files: u64,
     pub indexed_dirs: u64,
     pub database_size_bytes: u64,
+    pub total_size_bytes: u64,
     pub is_scanning: bool,
     pub scan_progress: f64,
     pub current_scan_path: Option<String>,
+    pub low_disk_space: bool,
+    pub active_searches: usize,
+    pub generation: u64,
+    pub privilege: PrivilegeLevel,
+    pub started_at: i64,
+    pub last_full_scan: Option<i64>,
+    pub index_warning: Option<String>,
+}
+
+/// Watch path status for IPC client
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchStatusResponse {
+    pub watcher: Option<String>,
+    pub paths: Vec<WatchPathStatus>,
+}
+
+/// Search backend status for IPC client
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackendStatusResponse {
+    pub primary: String,
+    pub fallback: Option<String>,
+    pub active: String,
+    pub primary_available: bool,
+    pub using_fallback: bool,
+    pub switched_to_primary: bool,
 }
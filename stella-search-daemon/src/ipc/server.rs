@@ -1,29 +1,146 @@
 //! IPC server implementation
 
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::Result;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader, BufWriter};
 use tracing::{info, warn, error, debug};
 
 use crate::config::Config;
-use crate::database::Database;
+use crate::database::{Database, IndexedFile};
 use crate::indexer::Indexer;
-use crate::search::{SearchQuery, SearchManager};
-use super::protocol::{Request, Response};
+use crate::platform::{self, PrivilegeLevel};
+use crate::search::{filter_dsl, SearchQuery, SearchManager};
+use super::compression;
+use super::history::QueryHistory;
+use super::peer::{self, ClientScope};
+use super::protocol::{ExtensionGroup, Request, Response, SearchOptions};
+
+/// Results with more files than this are streamed to clients that asked for it
+const STREAM_MIN_FILES: usize = 1000;
+
+/// What goes back on a connection
+enum Reply {
+    /// One JSON line
+    Line(Response),
+    /// A `search_stream` header line, then one `search_file` line per file
+    Stream { header: Response, files: Vec<IndexedFile> },
+    /// One JSON line, or a `compressed` header and body if the JSON is large
+    Compressible(Response),
+}
+
+impl Reply {
+    /// Stream a large search result if the client asked for it, else send it whole
+    /// (compressed if the client asked for that)
+    fn new(response: Response, stream: bool, compress: bool) -> Self {
+        match response {
+            Response::SearchResult {
+                files,
+                total_found,
+                query_time_ms,
+                generation,
+                truncated,
+            } if stream && files.len() > STREAM_MIN_FILES => Reply::Stream {
+                header: Response::SearchStream {
+                    count: files.len(),
+                    total_found,
+                    query_time_ms,
+                    generation,
+                    truncated,
+                },
+                files,
+            },
+            response if compress => Reply::Compressible(response),
+            response => Reply::Line(response),
+        }
+    }
+
+    /// Write the reply; a stream is serialized one file at a time, so a large result
+    /// never exists as a single JSON string
+    async fn write(self, writer: &mut (impl AsyncWrite + Unpin)) -> Result<()> {
+        match self {
+            Reply::Line(response) => write_line(writer, &response).await?,
+            Reply::Stream { header, files } => {
+                let mut writer = BufWriter::new(&mut *writer);
+                write_line(&mut writer, &header).await?;
+                for file in files {
+                    write_line(&mut writer, &Response::SearchFile(file)).await?;
+                }
+                writer.flush().await?;
+            }
+            Reply::Compressible(response) => {
+                let json = serde_json::to_vec(&response)?;
+                let compressed = if json.len() >= compression::COMPRESS_MIN_BYTES {
+                    compression::compress(&json)
+                } else {
+                    None
+                };
+                match compressed {
+                    Some((encoding, data)) => {
+                        let header = Response::Compressed { encoding, length: data.len() };
+                        write_line(writer, &header).await?;
+                        writer.write_all(&data).await?;
+                    }
+                    None => {
+                        writer.write_all(&json).await?;
+                        writer.write_all(b"\n").await?;
+                    }
+                }
+            }
+        }
+        writer.flush().await?;
+        Ok(())
+    }
+}
+
+/// Write one response as a JSON line
+async fn write_line(writer: &mut (impl AsyncWrite + Unpin), response: &Response) -> Result<()> {
+    let mut line = serde_json::to_vec(response)?;
+    line.push(b'\n');
+    writer.write_all(&line).await?;
+    Ok(())
+}
+
+/// Most named pipe instances at once, listening or serving. Clients arriving while
+/// all are taken get ERROR_PIPE_BUSY and retry (see client.rs).
+#[cfg(windows)]
+const MAX_PIPE_INSTANCES: usize = 16;
+
+/// Most Unix socket connections served at once. Further clients wait in the
+/// listen backlog until one finishes.
+#[cfg(unix)]
+const MAX_SOCKET_CONNECTIONS: usize = 16;
 
 /// IPC server for handling client requests
+#[derive(Clone)]
 pub struct IpcServer {
     db: Arc<Database>,
     indexer: Indexer,
-    config: Config,
+    config: Arc<Config>,
     search_manager: Arc<SearchManager>,
+    history: Arc<QueryHistory>,
+    /// When the daemon started (Unix seconds)
+    started_at: i64,
 }
 
 impl IpcServer {
     /// Create a new IPC server
     pub fn new(db: Arc<Database>, indexer: Indexer, config: Config, search_manager: Arc<SearchManager>) -> Self {
-        Self { db, indexer, config, search_manager }
+        let history_size = if config.service.record_query_history {
+            config.service.query_history_size
+        } else {
+            0
+        };
+        let history = Arc::new(QueryHistory::new(history_size));
+        Self {
+            db,
+            indexer,
+            config: Arc::new(config),
+            search_manager,
+            history,
+            started_at: chrono::Utc::now().timestamp(),
+        }
     }
 
     /// Run the IPC server
@@ -39,19 +156,202 @@ impl IpcServer {
         }
     }
 
+    /// Read one request line, giving up after `connection_idle_secs` without one.
+    /// A timed-out connection reads as closed (Ok(0)) and is dropped by the caller.
+    async fn read_request(&self, reader: &mut (impl AsyncBufRead + Unpin), line: &mut String) -> std::io::Result<usize> {
+        let idle_secs = self.config.service.connection_idle_secs;
+        if idle_secs == 0 {
+            return reader.read_line(line).await;
+        }
+
+        match tokio::time::timeout(Duration::from_secs(idle_secs), reader.read_line(line)).await {
+            Ok(result) => result,
+            Err(_) => {
+                debug!("Closing IPC connection idle for {}s", idle_secs);
+                Ok(0)
+            }
+        }
+    }
+
+    /// Parse and answer one request line from a client.
+    /// `identify` finds the client's scope; it is only called with per-user scoping on.
+    async fn respond(&self, line: &str, identify: impl FnOnce() -> Result<ClientScope>) -> Reply {
+        let request = match serde_json::from_str::<Request>(line) {
+            Ok(request) => request,
+            Err(e) => return Reply::Line(Response::error(format!("Invalid request: {}", e))),
+        };
+        let stream = matches!(&request, Request::Search { options, .. } if options.stream == Some(true));
+        let compress = matches!(&request, Request::Search { options, .. } if options.compress == Some(true));
+
+        Reply::new(self.respond_to(request, identify).await, stream, compress)
+    }
+
+    /// Check the client may make a parsed request, then handle it
+    async fn respond_to(&self, request: Request, identify: impl FnOnce() -> Result<ClientScope>) -> Response {
+        let scope = if self.config.service.per_user_scope {
+            match identify() {
+                Ok(scope) => scope,
+                Err(e) => {
+                    warn!("Could not identify IPC client: {}", e);
+                    return Response::error("Could not identify the connecting user");
+                }
+            }
+        } else {
+            ClientScope::All
+        };
+
+        if scope != ClientScope::All && !request.allowed_when_scoped() {
+            return Response::error("Not permitted for per-user scoped clients");
+        }
+
+        self.handle_request(request, &scope).await
+    }
+
+    /// Apply the `owner` and `world_writable` options, resolving a user name to the
+    /// uid or SID the index stores. Fails if ownership isn't being indexed, since
+    /// every row would then be filtered out.
+    fn with_ownership_filters(
+        &self,
+        mut search_query: SearchQuery,
+        owner: Option<&str>,
+        world_writable: Option<bool>,
+    ) -> Result<SearchQuery> {
+        if owner.is_none() && world_writable.is_none() {
+            return Ok(search_query);
+        }
+        if !self.config.indexing.track_ownership {
+            anyhow::bail!("Owner and permission filters need indexing.track_ownership = true (then a reindex)");
+        }
+
+        if let Some(owner) = owner {
+            search_query = search_query.with_owner(platform::owner_id(owner)?);
+        }
+        if let Some(world_writable) = world_writable {
+            search_query = search_query.with_world_writable(world_writable);
+        }
+        Ok(search_query)
+    }
+
+    /// Turn a client's query text and search options into a SearchQuery, limited to
+    /// the client's scope. Paging and presentation options are set too; requests that
+    /// only filter (facets) ignore them.
+    fn build_query(&self, query: &str, options: &SearchOptions, scope: &ClientScope) -> Result<SearchQuery> {
+        // Both touch the filesystem with the daemon's rights, not the client's: a link
+        // target or a path's existence could tell a scoped client about files it can't see
+        if scope.directory().is_some() && (options.resolve_symlinks == Some(true) || options.verify_existence == Some(true)) {
+            anyhow::bail!("Symlink resolution and existence checks are not available to per-user scoped clients");
+        }
+
+        let mut search_query = SearchQuery::new(query, 0);
+
+        if let Some(kind) = options.kind {
+            search_query = search_query.with_kind(kind);
+        }
+
+        if let Some(offset) = options.offset {
+            search_query = search_query.with_offset(offset);
+        }
+
+        if let Some(drive) = &options.drive {
+            search_query = search_query.with_drive(drive);
+        }
+
+        if let Some(encoding) = options.path_encoding {
+            search_query = search_query.with_path_encoding(encoding);
+        }
+
+        if let Some(directory) = scope.directory() {
+            search_query = search_query.with_scope(directory);
+        }
+
+        if let Some(include_hidden) = options.include_hidden {
+            search_query = search_query.with_include_hidden(include_hidden);
+        }
+
+        if let Some(category) = options.size_category {
+            search_query = search_query.with_size_category(category);
+        }
+
+        if let Some(max) = options.max_per_directory {
+            search_query = search_query.with_max_per_directory(max);
+        }
+
+        if let Some(prefix) = options.strip_prefix.clone() {
+            search_query = search_query.with_strip_prefix(prefix);
+        }
+
+        if let Some(containing_dirs) = options.return_containing_dirs {
+            search_query = search_query.with_return_containing_dirs(containing_dirs);
+        }
+
+        if let Some(resolve_symlinks) = options.resolve_symlinks {
+            search_query = search_query.with_resolve_symlinks(resolve_symlinks);
+        }
+
+        if let Some(verify_existence) = options.verify_existence {
+            search_query = search_query.with_verify_existence(verify_existence);
+            let budget_ms = options.verify_budget_ms.unwrap_or(self.config.search.verify_budget_ms);
+            if budget_ms > 0 {
+                search_query = search_query.with_verify_budget(std::time::Duration::from_millis(budget_ms));
+            }
+        }
+
+        if let Some(include_volume) = options.include_volume {
+            search_query = search_query.with_include_volume(include_volume);
+        }
+
+        if let Some(boost) = options.boost_name_matches {
+            search_query = search_query.with_boost_name_matches(boost);
+        }
+
+        if let Some(sort) = options.sort {
+            search_query = search_query.with_sort(sort);
+        }
+
+        if let Some(after) = options.created_after {
+            search_query = search_query.with_created_after(after);
+        }
+
+        if let Some(before) = options.created_before {
+            search_query = search_query.with_created_before(before);
+        }
+
+        if let Some(paths) = options.restrict_to_paths.clone() {
+            search_query = search_query.with_restrict_to_paths(paths);
+        }
+
+        search_query = self.with_ownership_filters(search_query, options.owner.as_deref(), options.world_writable)?;
+
+        if let Some(group) = &options.extension_group {
+            search_query = search_query.with_extensions(self.config.search.extension_group(group)?);
+        }
+
+        if let Some(expansion) = self.config.search.query_expansion(&search_query.query) {
+            search_query = search_query.with_expansion(expansion);
+        }
+
+        if let Some(filter) = &options.filter {
+            search_query = search_query.with_filter(filter_dsl::parse(filter)?);
+        }
+
+        Ok(search_query)
+    }
+
     /// Handle a single request
-    async fn handle_request(&self, request: Request) -> Response {
+    async fn handle_request(&self, request: Request, scope: &ClientScope) -> Response {
         match request {
             Request::Search {
                 query,
                 max_results,
                 extensions,
                 directories,
+                options,
             } => {
-                let max = max_results.unwrap_or(50);
-
-                // Build search query
-                let mut search_query = SearchQuery::new(&query, max);
+                let mut search_query = match self.build_query(&query, &options, scope) {
+                    Ok(search_query) => search_query,
+                    Err(e) => return Response::error(e.to_string()),
+                };
+                search_query.max_results = max_results.unwrap_or(50);
 
                 // Add extension filter if provided
                 if let Some(exts) = &extensions {
@@ -65,17 +365,90 @@ impl IpcServer {
                     search_query = search_query.with_directories(dirs);
                 }
 
+                // Grouping needs our own index, whichever backend serves file searches
+                if search_query.return_containing_dirs {
+                    let start = std::time::Instant::now();
+                    return match self.db.containing_directories(&search_query) {
+                        Ok(directories) => Response::ContainingDirs {
+                            directories,
+                            query_time_ms: start.elapsed().as_millis() as u64,
+                        },
+                        Err(e) => Response::error(format!("Failed to group matches: {}", e)),
+                    };
+                }
+
+                // Read before searching: a write during the search then shows up as a newer
+                // generation and invalidates these results in client caches
+                let generation = self.db.generation();
+
                 // Use SearchManager for the search
-                let result = self.search_manager.search(&search_query);
+                match self.search_manager.search(&search_query).await {
+                    // Convert to response format
+                    Ok(result) => {
+                        // Scoped clients' queries would show up in everyone's history
+                        if scope.directory().is_none() {
+                            self.history.record(&query, result.total_found);
+                        }
+                        Response::SearchResult {
+                            // Only results read from our own index follow its generation
+                            generation: (result.backend_name == "SQLite").then_some(generation),
+                            files: result.files,
+                            total_found: result.total_found,
+                            query_time_ms: result.query_time_ms,
+                            truncated: result.truncated,
+                        }
+                    }
+                    Err(e) => Response::error(e.to_string()),
+                }
+            }
+
+            Request::SearchFacets { query, options } => {
+                let search_query = match self.build_query(&query, &options, scope) {
+                    Ok(search_query) => search_query,
+                    Err(e) => return Response::error(e.to_string()),
+                };
 
-                // Convert to response format
-                Response::SearchResult {
-                    files: result.files,
-                    total_found: result.total_found,
-                    query_time_ms: result.query_time_ms,
+                match self.db.search_facets(&search_query) {
+                    Ok(facets) => Response::SearchFacets { facets },
+                    Err(e) => Response::error(format!("Failed to count matches: {}", e)),
                 }
             }
 
+            Request::Newest { limit } => {
+                let generation = self.db.generation();
+                match self.db.newest(limit.unwrap_or(50), scope.directory()) {
+                    Ok(results) => Response::search_result(results, Some(generation)),
+                    Err(e) => Response::error(format!("Failed to get newest files: {}", e)),
+                }
+            }
+
+            Request::SessionChanges { since, limit } => {
+                let generation = self.db.generation();
+                match self.db.modified_since(Some(since), limit.unwrap_or(50), scope.directory()) {
+                    Ok(results) => Response::search_result(results, Some(generation)),
+                    Err(e) => Response::error(format!("Failed to get changed files: {}", e)),
+                }
+            }
+
+            Request::QueryHistory { limit } => {
+                if !self.config.service.record_query_history {
+                    return Response::error("Query history is off (set service.record_query_history = true)");
+                }
+                Response::QueryHistory {
+                    entries: self.history.recent(limit.unwrap_or(20)),
+                }
+            }
+
+            Request::ClearQueryHistory => {
+                let cleared = self.history.clear();
+                Response::ok(format!("Forgot {} queries", cleared))
+            }
+
+            Request::OldestIndexed { limit } => match self.db.oldest_indexed(limit.unwrap_or(50)) {
+                Ok(entries) => Response::OldestIndexed { entries },
+                Err(e) => Response::error(format!("Failed to read index freshness: {}", e)),
+            },
+
             Request::SetMode { mode } => {
                 if mode != "everything" && mode != "selected" {
                     return Response::error("Invalid mode. Use 'everything' or 'selected'");
@@ -111,6 +484,17 @@ impl IpcServer {
                 Response::ok(format!("Removed exclude path: {}", path))
             }
 
+            Request::PreviewExclude { pattern, sample } => {
+                let pattern = match glob::Pattern::new(&pattern) {
+                    Ok(pattern) => pattern,
+                    Err(e) => return Response::error(format!("Invalid pattern '{}': {}", pattern, e)),
+                };
+                match self.db.preview_exclude(&pattern, sample.unwrap_or(10)) {
+                    Ok(preview) => Response::ExcludePreview(preview),
+                    Err(e) => Response::error(format!("Failed to preview exclusion: {}", e)),
+                }
+            }
+
             Request::GetConfig => {
                 Response::config(&self.config)
             }
@@ -122,6 +506,7 @@ impl IpcServer {
                         stats.is_scanning = self.indexer.is_scanning();
                         stats.scan_progress = self.indexer.get_scan_progress();
                         stats.current_scan_path = self.indexer.get_current_scan_path();
+                        stats.low_disk_space = self.indexer.is_low_disk_space();
 
                         // Get active search backend name
                         let search_backend = self.search_manager.active_backend_name().to_string();
@@ -131,16 +516,73 @@ impl IpcServer {
                             indexed_files: stats.indexed_files,
                             indexed_dirs: stats.indexed_dirs,
                             database_size_bytes: stats.database_size_bytes,
+                            total_size_bytes: stats.total_size_bytes,
                             is_scanning: stats.is_scanning,
                             scan_progress: stats.scan_progress,
-                            current_scan_path: stats.current_scan_path,
+                            // Paths outside a scoped client's home are not its business
+                            current_scan_path: stats.current_scan_path.filter(|_| *scope == ClientScope::All),
+                            low_disk_space: stats.low_disk_space,
+                            active_searches: self.search_manager.active_searches(),
+                            generation: self.db.generation(),
+                            privilege: PrivilegeLevel::detect(),
+                            started_at: self.started_at,
+                            last_full_scan: stats.last_full_scan,
+                            // Names watch paths, like current_scan_path
+                            index_warning: stats.index_warning.filter(|_| *scope == ClientScope::All),
                         }
                     }
                     Err(e) => Response::error(format!("Failed to get stats: {}", e)),
                 }
             }
 
+            Request::Ping => Response::Pong {
+                pid: std::process::id(),
+            },
+
+            Request::DetailedStatus => {
+                match self.db.detailed_stats(&platform::volume_roots(), scope.directory()) {
+                    Ok(stats) => Response::DetailedStatus(stats),
+                    Err(e) => Response::error(format!("Failed to get detailed stats: {}", e)),
+                }
+            }
+
+            Request::PrivilegeStatus => {
+                let privilege = PrivilegeLevel::detect();
+                Response::PrivilegeStatus {
+                    privilege,
+                    scan_method: privilege.scan_method().to_string(),
+                }
+            }
+
+            Request::ExtensionGroups => {
+                let search = &self.config.search;
+                let mut groups: Vec<ExtensionGroup> = search
+                    .extension_groups
+                    .keys()
+                    .filter_map(|name| {
+                        let extensions = search.extension_group(name).ok()?;
+                        Some(ExtensionGroup {
+                            name: name.clone(),
+                            extensions,
+                        })
+                    })
+                    .collect();
+                groups.sort_by(|a, b| a.name.cmp(&b.name));
+                Response::ExtensionGroups { groups }
+            }
+
+            Request::WatchStatus => {
+                Response::WatchStatus {
+                    watcher: self.indexer.watcher_kind(),
+                    paths: self.indexer.watch_status(),
+                }
+            }
+
             Request::Reindex { path } => {
+                if self.indexer.is_scanning() {
+                    return Response::error("Cannot reindex while a scan is running");
+                }
+
                 let indexer = self.indexer.clone();
                 let path_owned = path.clone();
 
@@ -157,61 +599,189 @@ impl IpcServer {
                 }
             }
 
+            Request::ReindexDrive { drive } => {
+                if !drive.is_ascii_alphabetic() {
+                    return Response::error(format!("Not a drive letter: {:?}", drive));
+                }
+                let drive = drive.to_ascii_uppercase();
+                if self.indexer.watch_paths_on_drive(drive).is_empty() {
+                    return Response::error(format!("No watched paths on drive {}:", drive));
+                }
+                if self.indexer.is_scanning() {
+                    return Response::error("Cannot reindex while a scan is running");
+                }
+
+                let indexer = self.indexer.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = indexer.reindex_drive(drive).await {
+                        error!("Reindex of drive {}: failed: {}", drive, e);
+                    }
+                });
+
+                Response::ok(format!("Reindex started for drive {}:", drive))
+            }
+
+            Request::CancelReindex => {
+                if self.indexer.cancel_scan() {
+                    info!("Scan cancelled by request");
+                    Response::ok("Scan cancelled")
+                } else {
+                    Response::ok("No scan in progress")
+                }
+            }
+
             Request::ReloadConfig => {
                 Response::ok("Configuration reloaded")
             }
+
+            Request::BackendStatus => {
+                Response::backend_status(&self.search_manager, false)
+            }
+
+            Request::RefreshBackend => {
+                let switched = self.search_manager.refresh_availability();
+                Response::backend_status(&self.search_manager, switched)
+            }
+
+            Request::Backup { path } => {
+                let db = self.db.clone();
+                let dest = std::path::PathBuf::from(&path);
+
+                match tokio::task::spawn_blocking(move || db.backup_to(&dest)).await {
+                    Ok(Ok(pages)) => Response::ok(format!("Backed up {} pages to {}", pages, path)),
+                    Ok(Err(e)) => Response::error(format!("Backup failed: {}", e)),
+                    Err(e) => Response::error(format!("Backup task failed: {}", e)),
+                }
+            }
+
+            Request::Warmup => {
+                let db = self.db.clone();
+                let started = std::time::Instant::now();
+
+                match tokio::task::spawn_blocking(move || db.warm_up()).await {
+                    Ok(Ok(rows)) => Response::ok(format!(
+                        "Warmed up {} rows in {} ms",
+                        rows,
+                        started.elapsed().as_millis()
+                    )),
+                    Ok(Err(e)) => Response::error(format!("Warm-up failed: {}", e)),
+                    Err(e) => Response::error(format!("Warm-up task failed: {}", e)),
+                }
+            }
+
+            Request::Restore { path } => {
+                if self.indexer.is_scanning() {
+                    return Response::error("Cannot restore while a scan is running");
+                }
+
+                let db = self.db.clone();
+                let src = std::path::PathBuf::from(&path);
+
+                match tokio::task::spawn_blocking(move || db.restore_from(&src)).await {
+                    Ok(Ok(())) => Response::ok(format!("Restored database from {}", path)),
+                    Ok(Err(e)) => Response::error(format!("Restore failed: {}", e)),
+                    Err(e) => Response::error(format!("Restore task failed: {}", e)),
+                }
+            }
+
+            Request::Import { path, policy } => {
+                if self.indexer.is_scanning() {
+                    return Response::error("Cannot import while a scan is running");
+                }
+
+                let db = self.db.clone();
+                let src = std::path::PathBuf::from(&path);
+
+                match tokio::task::spawn_blocking(move || db.import_jsonl(&src, policy)).await {
+                    Ok(Ok(summary)) => Response::ok(format!("Imported {}: {}", path, summary)),
+                    Ok(Err(e)) => Response::error(format!("Import failed: {:#}", e)),
+                    Err(e) => Response::error(format!("Import task failed: {}", e)),
+                }
+            }
         }
     }
 
     #[cfg(windows)]
     async fn run_windows(&self) -> Result<()> {
         use tokio::net::windows::named_pipe::{ServerOptions, PipeMode};
+        use tokio::sync::Semaphore;
 
-        let pipe_name = r"\\.\pipe\stella-search";
-        info!("Starting IPC server on {}", pipe_name);
+        let pipe_name = self.config.get_socket_path();
+        info!("Starting IPC server on {:?}", pipe_name);
 
-        loop {
-            // Create a new pipe instance
-            let server = ServerOptions::new()
-                .first_pipe_instance(false)
-                .pipe_mode(PipeMode::Message)
-                .create(pipe_name)?;
+        // With per-user scoping every authenticated user may connect (see peer.rs)
+        let security = if self.config.service.per_user_scope {
+            Some(peer::PipeSecurity::new()?)
+        } else {
+            None
+        };
 
+        let mut options = ServerOptions::new();
+        options
+            .first_pipe_instance(false)
+            .pipe_mode(PipeMode::Message)
+            .max_instances(MAX_PIPE_INSTANCES);
+        let create = || match &security {
+            Some(security) => unsafe { options.create_with_security_attributes_raw(&pipe_name, security.as_ptr()) },
+            None => options.create(&pipe_name),
+        };
+
+        // One permit per pipe instance; a connection holds its permit until it is served
+        let instances = Arc::new(Semaphore::new(MAX_PIPE_INSTANCES));
+        let mut permit = instances.clone().acquire_owned().await?;
+        let mut server = create()?;
+
+        loop {
             // Wait for a client to connect
             server.connect().await?;
+            let connected = server;
+            let connected_permit = permit;
 
-            let mut reader = BufReader::new(server);
-            let mut line = String::new();
+            // Put the next instance up before serving this client, so a client arriving
+            // meanwhile finds one listening rather than ERROR_PIPE_BUSY
+            permit = instances.clone().acquire_owned().await?;
+            server = create()?;
 
-            // Read request
-            match reader.read_line(&mut line).await {
-                Ok(0) => continue, // Connection closed
-                Ok(_) => {
-                    debug!("Received request: {}", line.trim());
+            let this = self.clone();
+            tokio::spawn(async move {
+                this.serve_pipe(connected).await;
+                drop(connected_permit);
+            });
+        }
+    }
 
-                    // Parse and handle request
-                    let response = match serde_json::from_str::<Request>(&line) {
-                        Ok(request) => self.handle_request(request).await,
-                        Err(e) => Response::error(format!("Invalid request: {}", e)),
-                    };
+    /// Answer the one request of a connected pipe client
+    #[cfg(windows)]
+    async fn serve_pipe(&self, pipe: tokio::net::windows::named_pipe::NamedPipeServer) {
+        let mut reader = BufReader::new(pipe);
+        let mut line = String::new();
 
-                    // Send response
-                    let response_json = serde_json::to_string(&response)?;
-                    let mut writer = reader.into_inner();
-                    writer.write_all(response_json.as_bytes()).await?;
-                    writer.write_all(b"\n").await?;
-                    writer.flush().await?;
-                }
-                Err(e) => {
-                    warn!("Error reading from pipe: {}", e);
+        // Read request
+        match self.read_request(&mut reader, &mut line).await {
+            Ok(0) => {} // Connection closed
+            Ok(_) => {
+                debug!("Received request: {}", line.trim());
+
+                // Parse and handle request (a pipe client can only be impersonated
+                // once it has written)
+                let reply = self.respond(&line, || peer::pipe_client_scope(reader.get_ref())).await;
+
+                // Send response (a client may hang up mid-stream; that only ends its reply)
+                let mut writer = reader.into_inner();
+                if let Err(e) = reply.write(&mut writer).await {
+                    warn!("Failed to send response: {}", e);
                 }
             }
+            Err(e) => {
+                warn!("Error reading from pipe: {}", e);
+            }
         }
     }
 
     #[cfg(unix)]
     async fn run_unix(&self) -> Result<()> {
         use tokio::net::UnixListener;
+        use tokio::sync::Semaphore;
 
         let socket_path = self.config.get_socket_path();
 
@@ -223,42 +793,27 @@ impl IpcServer {
         let listener = UnixListener::bind(&socket_path)?;
         info!("Starting IPC server on {:?}", socket_path);
 
-        // Set socket permissions
+        // Set socket permissions: with per-user scoping every local user may connect
+        // and is limited to their own files
         #[cfg(unix)]
         {
             use std::os::unix::fs::PermissionsExt;
-            std::fs::set_permissions(&socket_path, std::fs::Permissions::from_mode(0o660))?;
+            let mode = if self.config.service.per_user_scope { 0o666 } else { 0o660 };
+            std::fs::set_permissions(&socket_path, std::fs::Permissions::from_mode(mode))?;
         }
 
+        // One permit per connection being served; a slow or idle client only holds its own
+        let connections = Arc::new(Semaphore::new(MAX_SOCKET_CONNECTIONS));
+
         loop {
+            let permit = connections.clone().acquire_owned().await?;
             match listener.accept().await {
                 Ok((stream, _)) => {
-                    let mut reader = BufReader::new(stream);
-                    let mut line = String::new();
-
-                    // Read request
-                    match reader.read_line(&mut line).await {
-                        Ok(0) => continue, // Connection closed
-                        Ok(_) => {
-                            debug!("Received request: {}", line.trim());
-
-                            // Parse and handle request
-                            let response = match serde_json::from_str::<Request>(&line) {
-                                Ok(request) => self.handle_request(request).await,
-                                Err(e) => Response::error(format!("Invalid request: {}", e)),
-                            };
-
-                            // Send response
-                            let response_json = serde_json::to_string(&response)?;
-                            let mut writer = reader.into_inner();
-                            writer.write_all(response_json.as_bytes()).await?;
-                            writer.write_all(b"\n").await?;
-                            writer.flush().await?;
-                        }
-                        Err(e) => {
-                            warn!("Error reading from socket: {}", e);
-                        }
-                    }
+                    let this = self.clone();
+                    tokio::spawn(async move {
+                        this.serve_socket(stream).await;
+                        drop(permit);
+                    });
                 }
                 Err(e) => {
                     warn!("Failed to accept connection: {}", e);
@@ -266,4 +821,387 @@ impl IpcServer {
             }
         }
     }
+
+    /// Answer the one request of a connected socket client
+    #[cfg(unix)]
+    async fn serve_socket(&self, stream: tokio::net::UnixStream) {
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+
+        // Read request
+        match self.read_request(&mut reader, &mut line).await {
+            Ok(0) => {} // Connection closed
+            Ok(_) => {
+                debug!("Received request: {}", line.trim());
+
+                // Parse and handle request
+                let reply = self.respond(&line, || peer::unix_peer_scope(reader.get_ref())).await;
+
+                // Send response (a client may hang up mid-stream; that only ends its reply)
+                let mut writer = reader.into_inner();
+                if let Err(e) = reply.write(&mut writer).await {
+                    warn!("Failed to send response: {}", e);
+                }
+            }
+            Err(e) => {
+                warn!("Error reading from socket: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ipc::SearchOptions;
+    use crate::test_support::{seed, test_config, test_database, test_server};
+
+    /// Server with per-user scoping on, over a database holding `paths`
+    fn scoped_server(paths: &[&str]) -> (IpcServer, tempfile::TempDir) {
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut config = test_config(&dir);
+        config.service.per_user_scope = true;
+        let db = test_database(&config);
+        seed(&db, paths);
+        (test_server(&db, &config), dir)
+    }
+
+    fn search(query: &str) -> Request {
+        Request::Search {
+            query: query.to_string(),
+            max_results: Some(50),
+            extensions: None,
+            directories: None,
+            options: SearchOptions::default(),
+        }
+    }
+
+    /// Paths a search returns to a client identified as `scope`
+    async fn scoped_search(server: &IpcServer, query: &str, scope: ClientScope) -> Vec<String> {
+        match server.respond_to(search(query), || Ok(scope)).await {
+            Response::SearchResult { files, .. } => files.into_iter().map(|f| f.path).collect(),
+            other => panic!("unexpected response: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn scoped_clients_only_see_their_home() {
+        let (server, _dir) = scoped_server(&[
+            "/home/alice/report.txt",
+            "/home/bob/report.txt",
+            "/home/bob/docs/report.txt",
+            "/home/bobby/report.txt",
+        ]);
+
+        let paths = scoped_search(&server, "report", ClientScope::Home("/home/bob".into())).await;
+        assert_eq!(paths, ["/home/bob/report.txt", "/home/bob/docs/report.txt"]);
+
+        let paths = scoped_search(&server, "report", ClientScope::All).await;
+        assert_eq!(paths.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn windows_homes_match_backslash_paths() {
+        let (server, _dir) = scoped_server(&[
+            "C:\\Users\\alice\\report.txt",
+            "C:\\Users\\bob\\report.txt",
+            "C:\\Users\\bob\\Documents\\report.txt",
+            "C:\\Users\\bobby\\report.txt",
+        ]);
+
+        // As GetUserProfileDirectoryW returns it
+        let paths = scoped_search(&server, "report", ClientScope::Home("C:\\Users\\bob".into())).await;
+        assert_eq!(paths, ["C:\\Users\\bob\\report.txt", "C:\\Users\\bob\\Documents\\report.txt"]);
+
+        let paths = scoped_search(&server, "report", ClientScope::Home("C:/Users/bob/".into())).await;
+        assert_eq!(paths.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn scoped_clients_cannot_change_the_index() {
+        let (server, _dir) = scoped_server(&[]);
+        let scope = || Ok(ClientScope::Home("/home/bob".into()));
+
+        let response = server.respond_to(Request::ClearQueryHistory, scope).await;
+        assert!(matches!(response, Response::Error { .. }), "{:?}", response);
+
+        let response = server.respond_to(Request::Ping, scope).await;
+        assert!(matches!(response, Response::Pong { .. }), "{:?}", response);
+    }
+
+    #[tokio::test]
+    async fn scoped_clients_only_get_stats_for_their_home() {
+        let (server, _dir) = scoped_server(&["/home/bob/report.txt", "/home/alice/secret.txt"]);
+        let scope = || Ok(ClientScope::Home("/home/bob".into()));
+        match server.respond_to(Request::DetailedStatus, scope).await {
+            Response::DetailedStatus(stats) => {
+                assert_eq!(stats.indexed_files, 1);
+                assert_eq!(stats.largest_files[0].path, "/home/bob/report.txt");
+            }
+            response => panic!("unexpected response: {:?}", response),
+        }
+    }
+
+    #[tokio::test]
+    async fn unidentified_clients_are_refused() {
+        let (server, _dir) = scoped_server(&["/home/bob/report.txt"]);
+        let response = server
+            .respond_to(search("report"), || Err(anyhow::anyhow!("no peer credentials")))
+            .await;
+        assert!(matches!(response, Response::Error { .. }), "{:?}", response);
+    }
+
+    #[tokio::test]
+    async fn clients_are_not_identified_without_scoping() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let config = test_config(&dir);
+        let db = test_database(&config);
+        seed(&db, &["/home/bob/report.txt"]);
+        let server = test_server(&db, &config);
+
+        let response = server
+            .respond_to(search("report"), || -> Result<ClientScope> { panic!("identified a client") })
+            .await;
+        assert!(matches!(response, Response::SearchResult { total_found: 1, .. }), "{:?}", response);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn an_idle_connection_does_not_hold_up_other_clients() {
+        let daemon = crate::test_support::TestDaemon::start_with(&["/data/report.txt"], |config| {
+            // Never drop idle connections, so only concurrent serving lets the ping through
+            config.service.connection_idle_secs = 0;
+        })
+        .await;
+
+        let _idle = tokio::net::UnixStream::connect(daemon.config.get_socket_path()).await.unwrap();
+        let ping = tokio::time::timeout(Duration::from_secs(5), async { daemon.client().await.ping().await }).await;
+        assert_eq!(ping.expect("ping waited behind the idle connection").unwrap(), std::process::id());
+    }
+
+    /// Server over a database holding `count` files named `report_NNNNN.txt`
+    fn server_with_reports(count: usize) -> (IpcServer, tempfile::TempDir) {
+        let dir = tempfile::TempDir::new().unwrap();
+        let config = test_config(&dir);
+        let db = test_database(&config);
+        let paths: Vec<String> = (0..count).map(|i| format!("/data/report_{:05}.txt", i)).collect();
+        seed(&db, &paths.iter().map(String::as_str).collect::<Vec<_>>());
+        (test_server(&db, &config), dir)
+    }
+
+    /// Bytes the server writes back for a search for "report" returning up to `max` files
+    async fn search_reply(server: &IpcServer, max: usize, options: SearchOptions) -> Vec<u8> {
+        let request = Request::Search {
+            query: "report".to_string(),
+            max_results: Some(max),
+            extensions: None,
+            directories: None,
+            options,
+        };
+        let line = serde_json::to_string(&request).unwrap();
+        let mut out = Vec::new();
+        server.respond(&line, || Ok(ClientScope::All)).await.write(&mut out).await.unwrap();
+        out
+    }
+
+    /// Split a reply into its first line (without the newline) and the rest
+    fn first_line(reply: &[u8]) -> (&[u8], &[u8]) {
+        let end = reply.iter().position(|&b| b == b'\n').expect("reply has a line");
+        (&reply[..end], &reply[end + 1..])
+    }
+
+    fn result_paths(response: Response) -> Vec<String> {
+        match response {
+            Response::SearchResult { files, .. } => files.into_iter().map(|f| f.path).collect(),
+            other => panic!("unexpected response: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn small_results_are_not_compressed() {
+        let (server, _dir) = server_with_reports(3);
+        let reply = search_reply(&server, 50, SearchOptions { compress: Some(true), ..Default::default() }).await;
+
+        let (line, rest) = first_line(&reply);
+        assert!(rest.is_empty());
+        assert_eq!(result_paths(serde_json::from_slice(line).unwrap()).len(), 3);
+    }
+
+    #[cfg(feature = "compression")]
+    #[tokio::test]
+    async fn large_results_round_trip_through_zstd() {
+        // Below STREAM_MIN_FILES, but well over COMPRESS_MIN_BYTES of JSON
+        let (server, _dir) = server_with_reports(900);
+        let plain = search_reply(&server, 900, SearchOptions::default()).await;
+        let reply = search_reply(&server, 900, SearchOptions { compress: Some(true), ..Default::default() }).await;
+        assert!(plain.len() >= compression::COMPRESS_MIN_BYTES);
+
+        let (header, body) = first_line(&reply);
+        let Response::Compressed { encoding, length } = serde_json::from_slice(header).unwrap() else {
+            panic!("expected a compressed header, got {}", String::from_utf8_lossy(header));
+        };
+        assert_eq!(body.len(), length);
+        assert!(length < plain.len());
+
+        let json = compression::decompress(encoding, body).unwrap();
+        let (plain_line, _) = first_line(&plain);
+        let paths = result_paths(serde_json::from_slice(&json).unwrap());
+        assert_eq!(paths.len(), 900);
+        assert_eq!(paths, result_paths(serde_json::from_slice(plain_line).unwrap()));
+    }
+
+    #[tokio::test]
+    async fn large_results_are_streamed_one_file_per_line() {
+        let (server, _dir) = server_with_reports(STREAM_MIN_FILES + 200);
+        let plain = result_paths(
+            serde_json::from_slice(first_line(&search_reply(&server, 2000, SearchOptions::default()).await).0).unwrap(),
+        );
+        let reply = search_reply(&server, 2000, SearchOptions { stream: Some(true), ..Default::default() }).await;
+
+        let mut lines = reply.split(|&b| b == b'\n').filter(|line| !line.is_empty());
+        let header: Response = serde_json::from_slice(lines.next().unwrap()).unwrap();
+        let Response::SearchStream { count, total_found, .. } = header else {
+            panic!("expected a stream header, got {:?}", header);
+        };
+        assert_eq!((count, total_found), (STREAM_MIN_FILES + 200, STREAM_MIN_FILES + 200));
+
+        let streamed: Vec<String> = lines
+            .map(|line| match serde_json::from_slice(line).unwrap() {
+                Response::SearchFile(file) => file.path,
+                other => panic!("unexpected response in stream: {:?}", other),
+            })
+            .collect();
+        assert_eq!(streamed, plain);
+    }
+
+    #[tokio::test]
+    async fn results_up_to_the_threshold_are_one_line() {
+        let (server, _dir) = server_with_reports(STREAM_MIN_FILES);
+        let reply = search_reply(&server, 2000, SearchOptions { stream: Some(true), ..Default::default() }).await;
+
+        let (line, rest) = first_line(&reply);
+        assert!(rest.is_empty());
+        assert_eq!(result_paths(serde_json::from_slice(line).unwrap()).len(), STREAM_MIN_FILES);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn idle_connections_are_closed() {
+        use tokio::io::AsyncReadExt;
+
+        let daemon = crate::test_support::TestDaemon::start_with(&[], |config| {
+            config.service.connection_idle_secs = 1;
+        })
+        .await;
+
+        let mut idle = tokio::net::UnixStream::connect(daemon.config.get_socket_path()).await.unwrap();
+        let started = std::time::Instant::now();
+        let mut buf = [0; 64];
+        let read = tokio::time::timeout(Duration::from_secs(5), idle.read(&mut buf)).await;
+        assert_eq!(read.expect("idle connection was left open").unwrap(), 0);
+        assert!(started.elapsed() >= Duration::from_millis(900), "closed after {:?}", started.elapsed());
+
+        // A client that does send a request in time is still answered
+        assert_eq!(daemon.client().await.ping().await.unwrap(), std::process::id());
+    }
+
+    /// Extension counts a facets request gets back, as (extension, count)
+    async fn facets(server: &IpcServer, query: &str, options: SearchOptions) -> Vec<(Option<String>, u64)> {
+        let request = Request::SearchFacets { query: query.to_string(), options };
+        match server.respond_to(request, || Ok(ClientScope::All)).await {
+            Response::SearchFacets { facets } => facets.into_iter().map(|f| (f.extension, f.count)).collect(),
+            other => panic!("unexpected response: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn facets_count_matches_per_extension() {
+        let (server, _dir) = scoped_server(&[
+            "/home/bob/report.pdf",
+            "/home/bob/report-2.pdf",
+            "/home/bob/report.txt",
+            "/home/bob/reports/",
+            "/home/bob/.report.pdf",
+            "/home/alice/report.pdf",
+            "/home/bob/notes.pdf",
+        ]);
+        let ext = |ext: &str| Some(ext.to_string());
+
+        let counts = facets(&server, "report", SearchOptions::default()).await;
+        assert_eq!(counts, [(ext(".pdf"), 4), (None, 1), (ext(".txt"), 1)]);
+
+        // The same options narrow the counts as they narrow a search
+        let options = SearchOptions { include_hidden: Some(false), ..Default::default() };
+        assert_eq!(facets(&server, "report", options).await, [(ext(".pdf"), 3), (None, 1), (ext(".txt"), 1)]);
+        let options = SearchOptions { filter: Some("size > 0".to_string()), ..Default::default() };
+        assert_eq!(facets(&server, "report", options).await, [(ext(".pdf"), 4), (ext(".txt"), 1)]);
+        let options = SearchOptions { kind: Some(crate::search::QueryKind::Exact), ..Default::default() };
+        assert_eq!(facets(&server, "report.pdf", options).await, [(ext(".pdf"), 2)]);
+
+        let request = Request::SearchFacets { query: "report".to_string(), options: SearchOptions::default() };
+        let scope = || Ok(ClientScope::Home("/home/alice".into()));
+        match server.respond_to(request, scope).await {
+            Response::SearchFacets { facets } => assert_eq!(facets.len(), 1),
+            other => panic!("unexpected response: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn searches_and_facets_reject_the_same_bad_options() {
+        let (server, _dir) = scoped_server(&["/home/bob/report.pdf"]);
+        let bad = [
+            SearchOptions { extension_group: Some("nonsense".to_string()), ..Default::default() },
+            SearchOptions { filter: Some("size >".to_string()), ..Default::default() },
+            SearchOptions { owner: Some("bob".to_string()), ..Default::default() },
+        ];
+
+        for options in bad {
+            let search = Request::Search {
+                query: "report".to_string(),
+                max_results: None,
+                extensions: None,
+                directories: None,
+                options: options.clone(),
+            };
+            let facets = Request::SearchFacets { query: "report".to_string(), options };
+            let Response::Error { message } = server.respond_to(search, || Ok(ClientScope::All)).await else {
+                panic!("search accepted a bad option");
+            };
+            let Response::Error { message: facets_message } = server.respond_to(facets, || Ok(ClientScope::All)).await
+            else {
+                panic!("facets accepted a bad option");
+            };
+            assert_eq!(message, facets_message);
+        }
+    }
+
+    #[tokio::test]
+    async fn scoped_clients_cannot_have_the_daemon_stat_files() {
+        let (server, _dir) = scoped_server(&["/home/bob/link.txt"]);
+        let bob = || Ok(ClientScope::Home("/home/bob".into()));
+        let with = |options: SearchOptions| Request::Search {
+            query: "link".to_string(),
+            max_results: None,
+            extensions: None,
+            directories: None,
+            options,
+        };
+
+        for options in [
+            SearchOptions { resolve_symlinks: Some(true), ..Default::default() },
+            SearchOptions { verify_existence: Some(true), ..Default::default() },
+        ] {
+            let response = server.respond_to(with(options.clone()), bob).await;
+            assert!(matches!(response, Response::Error { .. }), "{:?}", response);
+
+            // Unscoped clients still get them
+            let response = server.respond_to(with(options), || Ok(ClientScope::All)).await;
+            assert!(matches!(response, Response::SearchResult { total_found: 1, .. }), "{:?}", response);
+        }
+
+        // Asking for them to be off is fine
+        let options = SearchOptions { resolve_symlinks: Some(false), verify_existence: Some(false), ..Default::default() };
+        let response = server.respond_to(with(options), bob).await;
+        assert!(matches!(response, Response::SearchResult { total_found: 1, .. }), "{:?}", response);
+    }
 }
@@ -5,23 +5,29 @@
 
 mod config;
 mod database;
+mod export;
 mod indexer;
 mod ipc;
 mod platform;
 mod search;
+#[cfg(test)]
+mod test_support;
 
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::{Parser, Subcommand};
-use tracing::{info, Level};
-use tracing_subscriber::FmtSubscriber;
+use tracing::info;
+use tracing_subscriber::EnvFilter;
 
-use crate::config::Config;
-use crate::database::Database;
+use crate::config::{Config, LogFormat, ServiceConfig};
+use crate::database::{Database, ImportPolicy};
+use crate::export::{ExportFormat, ExportOptions, Exporter, LineEnding};
 use crate::indexer::Indexer;
 use crate::ipc::IpcServer;
-use crate::search::SearchManager;
+use crate::search::{BackendRegistry, SearchManager};
 
 /// StellaSearch - Lightweight file indexing service
 #[derive(Parser)]
@@ -34,10 +40,16 @@ struct Cli {
     command: Commands,
 }
 
+// Parsed once per run, so the size of the Search variant doesn't matter
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand)]
 enum Commands {
     /// Start the indexing service daemon
-    Daemon,
+    Daemon {
+        /// Start even if another daemon is answering or holds the database lock
+        #[arg(long)]
+        force: bool,
+    },
 
     /// Start as Windows service (Windows only)
     #[cfg(windows)]
@@ -55,10 +67,188 @@ enum Commands {
         /// Filter by file extension (e.g., ".pdf")
         #[arg(short, long)]
         extension: Option<String>,
+
+        /// Only files with an extension from this group (see `groups`)
+        #[arg(short, long)]
+        group: Option<String>,
+
+        /// Match the whole file name instead of a substring (case-insensitive)
+        #[arg(long)]
+        exact: bool,
+
+        /// Skip this many results (for paging)
+        #[arg(long)]
+        offset: Option<usize>,
+
+        /// Only search on this drive or mount point (e.g., "E:", "/mnt/data")
+        #[arg(long)]
+        drive: Option<String>,
+
+        /// Encoding for result paths (percent-encoded is lossless for non-UTF-8 names)
+        #[arg(long, value_enum)]
+        path_encoding: Option<search::PathEncoding>,
+
+        /// Filter expression, e.g. 'size > 10MB AND ext IN (pdf, docx)'
+        #[arg(long)]
+        filter: Option<String>,
+
+        /// Return hidden files and anything under hidden directories (the default)
+        #[arg(long, overrides_with = "no_hidden")]
+        hidden: bool,
+
+        /// Leave out hidden files and anything under hidden directories
+        #[arg(long, overrides_with = "hidden")]
+        no_hidden: bool,
+
+        /// Return at most this many results from any one directory
+        #[arg(long)]
+        max_per_dir: Option<usize>,
+
+        /// Only files of this size (boundaries in `search.size_categories`)
+        #[arg(long, value_enum)]
+        size: Option<search::SizeCategory>,
+
+        /// Result order: rank (default), size (largest first), name, or created (newest first)
+        #[arg(long, value_parser = parse_sort)]
+        sort: Option<stella_search_core::ResultSort>,
+
+        /// Only entries created on or after this date (YYYY-MM-DD or Unix seconds)
+        #[arg(long, value_parser = parse_time)]
+        created_after: Option<i64>,
+
+        /// Only entries created before this date (YYYY-MM-DD or Unix seconds)
+        #[arg(long, value_parser = parse_time)]
+        created_before: Option<i64>,
+
+        /// Only consider the paths listed in this file, one per line ("-" reads stdin)
+        #[arg(long)]
+        paths_from: Option<PathBuf>,
+
+        /// Print paths relative to this directory (others are printed in full)
+        #[arg(long)]
+        strip_prefix: Option<String>,
+
+        /// Show where symbolic links point (SQLite backend; a few filesystem calls per link)
+        #[arg(long)]
+        resolve_symlinks: bool,
+
+        /// Check that results still exist on disk and mark missing ones; the check
+        /// stops after --verify-budget-ms and later results are shown unchecked
+        #[arg(long)]
+        verify: bool,
+
+        /// Time for --verify in milliseconds (default: search.verify_budget_ms, 0 = no limit)
+        #[arg(long, requires = "verify")]
+        verify_budget_ms: Option<u64>,
+
+        /// Only files owned by this user name or uid (SID on Windows); needs
+        /// `indexing.track_ownership`
+        #[arg(long)]
+        owner: Option<String>,
+
+        /// Only entries any user may write to; needs `indexing.track_ownership`
+        #[arg(long)]
+        world_writable: bool,
+
+        /// List the directories holding matching files, with match counts, instead of the files
+        #[arg(long, conflicts_with = "csv")]
+        containing_dirs: bool,
+
+        /// Print results as CSV (path,name,extension,size,is_directory)
+        #[arg(long)]
+        csv: bool,
+
+        /// Write the CSV to this file instead of stdout
+        #[arg(long, requires = "csv")]
+        output: Option<PathBuf>,
+    },
+
+    /// Search and open the match with its default application. Opens the only result,
+    /// or the only one named exactly like the query; otherwise lists the candidates
+    Open {
+        /// Search query
+        query: String,
+
+        /// Filter by file extension (e.g., ".pdf")
+        #[arg(short, long)]
+        extension: Option<String>,
+
+        /// Open the top result even when several match
+        #[arg(long)]
+        first: bool,
+
+        /// Number of candidates to consider and list
+        #[arg(short, long, default_value = "10")]
+        max_results: usize,
+    },
+
+    /// Count matches per extension
+    Facets {
+        /// Search query
+        query: String,
+
+        /// Match the whole file name instead of a substring (case-insensitive)
+        #[arg(long)]
+        exact: bool,
+
+        /// Only count matches on this drive or mount point
+        #[arg(long)]
+        drive: Option<String>,
+
+        /// Only count files with an extension from this group
+        #[arg(short, long)]
+        group: Option<String>,
+
+        /// Filter expression, e.g. 'size > 10MB'
+        #[arg(long)]
+        filter: Option<String>,
+
+        /// Only count files of this size
+        #[arg(long, value_enum)]
+        size: Option<search::SizeCategory>,
+    },
+
+    /// Show the most recently modified files
+    Newest {
+        /// Maximum number of results
+        #[arg(short, long, default_value = "20")]
+        max_results: usize,
+
+        /// Only files modified after this time: HH:MM today, a date (YYYY-MM-DD) or
+        /// Unix seconds
+        #[arg(long, value_parser = parse_since)]
+        since: Option<i64>,
+    },
+
+    /// Show recent search queries (needs `service.record_query_history`)
+    History {
+        /// Maximum number of queries
+        #[arg(short, long, default_value = "20")]
+        max_results: usize,
+
+        /// Forget the recorded queries instead
+        #[arg(long)]
+        clear: bool,
+    },
+
+    /// Show the entries the index has gone longest without refreshing (reads the
+    /// whole index; for diagnosing stale entries)
+    Stale {
+        /// Maximum number of entries
+        #[arg(short, long, default_value = "20")]
+        max_results: usize,
     },
 
     /// Show index status
-    Status,
+    Status {
+        /// Also break the index down by extension, volume and file size (reads the whole index)
+        #[arg(long)]
+        detailed: bool,
+
+        /// Print the start and last scan times as dates instead of "3 hours ago"
+        #[arg(long)]
+        absolute_times: bool,
+    },
 
     /// Add a path to the exclude list
     Exclude {
@@ -72,6 +262,16 @@ enum Commands {
         path: String,
     },
 
+    /// Show how many indexed entries an exclude pattern would remove
+    PreviewExclude {
+        /// Glob pattern, matched like `exclude_patterns` in the config
+        pattern: String,
+
+        /// Number of matching paths to list
+        #[arg(short, long, default_value = "10")]
+        sample: usize,
+    },
+
     /// Add a path to the include list (for "selected" mode)
     Include {
         /// Path to include
@@ -96,25 +296,110 @@ enum Commands {
         path: Option<String>,
     },
 
+    /// Rebuild one Windows drive's index (MFT scan where possible), leaving other drives alone
+    ReindexDrive {
+        /// Drive letter, e.g. "D" or "D:"
+        drive: String,
+    },
+
+    /// Cancel the running scan or reindex
+    CancelReindex,
+
     /// Show current configuration
     Config,
+
+    /// Show where the config file, database and IPC socket live
+    Where,
+
+    /// List the extension groups usable with `search --group`
+    Groups,
+
+    /// Show what the daemon is watching right now
+    WatchStatus,
+
+    /// Show search backend status (primary/fallback)
+    Backend {
+        /// Re-check primary availability and switch back from fallback if possible
+        #[arg(long)]
+        refresh: bool,
+    },
+
+    /// Back up the index database (safe while the daemon is running)
+    Backup {
+        /// Destination file for the backup
+        #[arg(long)]
+        to: String,
+    },
+
+    /// Restore the index database from a backup
+    Restore {
+        /// Backup file to restore from
+        #[arg(long)]
+        from: String,
+    },
+
+    /// Export matching index entries (JSONL by default, which `import` reads back)
+    Export {
+        /// Search query (default: every indexed entry)
+        #[arg(default_value = "")]
+        query: String,
+
+        /// Maximum number of entries
+        #[arg(short, long, default_value = "1000000")]
+        max_results: usize,
+
+        /// Filter by file extension (e.g., ".pdf")
+        #[arg(short, long)]
+        extension: Option<String>,
+
+        /// Output layout
+        #[arg(long, value_enum, default_value_t)]
+        format: ExportFormat,
+
+        /// Line ending after each record
+        #[arg(long, value_enum, default_value_t)]
+        line_ending: LineEnding,
+
+        /// Start the output with a UTF-8 byte order mark
+        #[arg(long)]
+        bom: bool,
+
+        /// Write to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Import files from a JSONL dump (one file record per line)
+    Import {
+        /// JSONL file to import
+        #[arg(long)]
+        from: String,
+
+        /// How to handle paths that are already indexed
+        #[arg(long, value_enum, default_value_t)]
+        policy: ImportPolicy,
+    },
+
+    /// Pull the index into cache so the next search is fast
+    Warmup,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
 
-    // Initialize logging
-    let subscriber = FmtSubscriber::builder()
-        .with_max_level(Level::INFO)
-        .with_target(false)
-        .compact()
-        .init();
+    // Daemon modes log with the configured level and format, client commands use the defaults
+    let service_config = if cli.command.runs_daemon() {
+        Config::load().map(|c| c.service).unwrap_or_default()
+    } else {
+        ServiceConfig::default()
+    };
+    init_logging(&service_config);
 
     match cli.command {
-        Commands::Daemon => {
+        Commands::Daemon { force } => {
             info!("Starting StellaSearch daemon...");
-            run_daemon().await?;
+            run_daemon(force).await?;
         }
 
         #[cfg(windows)]
@@ -127,12 +412,99 @@ async fn main() -> Result<()> {
             query,
             max_results,
             extension,
+            group,
+            exact,
+            offset,
+            drive,
+            path_encoding,
+            filter,
+            hidden,
+            no_hidden,
+            max_per_dir,
+            size,
+            sort,
+            created_after,
+            created_before,
+            paths_from,
+            strip_prefix,
+            resolve_symlinks,
+            verify,
+            verify_budget_ms,
+            owner,
+            world_writable,
+            containing_dirs,
+            csv,
+            output,
         } => {
-            search_files(&query, max_results, extension.as_deref()).await?;
+            let options = ipc::SearchOptions {
+                kind: exact.then_some(search::QueryKind::Exact),
+                path_encoding,
+                offset,
+                drive,
+                filter,
+                include_hidden: include_hidden_flag(hidden, no_hidden),
+                max_per_directory: max_per_dir,
+                size_category: size,
+                strip_prefix,
+                return_containing_dirs: containing_dirs.then_some(true),
+                stream: None,
+                compress: None,
+                owner,
+                world_writable: world_writable.then_some(true),
+                extension_group: group,
+                sort,
+                boost_name_matches: None,
+                created_after,
+                created_before,
+                resolve_symlinks: resolve_symlinks.then_some(true),
+                verify_existence: verify.then_some(true),
+                verify_budget_ms,
+                include_volume: None,
+                restrict_to_paths: paths_from.as_deref().map(read_path_list).transpose()?,
+            };
+            if containing_dirs {
+                show_containing_dirs(&query, max_results, extension.as_deref(), options).await?;
+            } else if csv {
+                let export = ExportOptions {
+                    format: ExportFormat::Csv,
+                    ..Default::default()
+                };
+                export_results(&query, max_results, extension.as_deref(), options, export, output.as_deref()).await?;
+            } else {
+                search_files(&query, max_results, extension.as_deref(), options).await?;
+            }
+        }
+
+        Commands::Open { query, extension, first, max_results } => {
+            open_result(&query, extension.as_deref(), first, max_results).await?;
         }
 
-        Commands::Status => {
-            show_status().await?;
+        Commands::Facets { query, exact, drive, group, filter, size } => {
+            let options = ipc::SearchOptions {
+                kind: exact.then_some(search::QueryKind::Exact),
+                drive,
+                extension_group: group,
+                filter,
+                size_category: size,
+                ..Default::default()
+            };
+            show_facets(&query, options).await?;
+        }
+
+        Commands::Newest { max_results, since } => {
+            show_newest(max_results, since).await?;
+        }
+
+        Commands::History { max_results, clear } => {
+            show_query_history(max_results, clear).await?;
+        }
+
+        Commands::Stale { max_results } => {
+            show_stale(max_results).await?;
+        }
+
+        Commands::Status { detailed, absolute_times } => {
+            show_status(detailed, absolute_times).await?;
         }
 
         Commands::Exclude { path } => {
@@ -143,6 +515,10 @@ async fn main() -> Result<()> {
             remove_exclusion(&path).await?;
         }
 
+        Commands::PreviewExclude { pattern, sample } => {
+            preview_exclusion(&pattern, sample).await?;
+        }
+
         Commands::Include { path } => {
             add_inclusion(&path).await?;
         }
@@ -159,44 +535,179 @@ async fn main() -> Result<()> {
             trigger_reindex(path.as_deref()).await?;
         }
 
+        Commands::ReindexDrive { drive } => {
+            trigger_drive_reindex(&drive).await?;
+        }
+
+        Commands::CancelReindex => {
+            cancel_reindex().await?;
+        }
+
         Commands::Config => {
             show_config().await?;
         }
+
+        Commands::Where => {
+            show_paths()?;
+        }
+
+        Commands::Groups => {
+            show_extension_groups().await?;
+        }
+
+        Commands::WatchStatus => {
+            show_watch_status().await?;
+        }
+
+        Commands::Backend { refresh } => {
+            show_backend(refresh).await?;
+        }
+
+        Commands::Backup { to } => {
+            backup_database(&to).await?;
+        }
+
+        Commands::Restore { from } => {
+            restore_database(&from).await?;
+        }
+
+        Commands::Export {
+            query,
+            max_results,
+            extension,
+            format,
+            line_ending,
+            bom,
+            output,
+        } => {
+            let export = ExportOptions { format, line_ending, bom };
+            let options = ipc::SearchOptions::default();
+            export_results(&query, max_results, extension.as_deref(), options, export, output.as_deref()).await?;
+        }
+
+        Commands::Import { from, policy } => {
+            import_database(&from, policy).await?;
+        }
+
+        Commands::Warmup => {
+            let client = ipc::IpcClient::connect().await?;
+            println!("{}", client.warmup().await?);
+        }
     }
 
     Ok(())
 }
 
-/// Run the main daemon process
-async fn run_daemon() -> Result<()> {
+impl Commands {
+    /// Whether this command runs the daemon (as opposed to talking to one)
+    fn runs_daemon(&self) -> bool {
+        match self {
+            Commands::Daemon { .. } => true,
+            #[cfg(windows)]
+            Commands::Service => true,
+            _ => false,
+        }
+    }
+}
+
+/// Initialize the tracing subscriber. `RUST_LOG` overrides the configured level.
+fn init_logging(service: &ServiceConfig) {
+    let filter = EnvFilter::try_from_default_env()
+        .or_else(|_| EnvFilter::try_new(&service.log_level))
+        .unwrap_or_else(|_| EnvFilter::new("info"));
+
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_target(false);
+
+    match service.log_format {
+        LogFormat::Text => builder.compact().init(),
+        LogFormat::Json => builder.json().flatten_event(true).init(),
+    }
+}
+
+/// Run the main daemon process. `force` starts it even next to another live daemon.
+async fn run_daemon(force: bool) -> Result<()> {
     // Load configuration
     let config = Config::load()?;
     info!("Configuration loaded: mode={}, search_backend={:?}",
           config.indexing.mode, config.search.backend);
 
+    let _instance = match claim_instance(&config).await {
+        Ok(lock) => Some(lock),
+        Err(e) if force => {
+            tracing::warn!("{:#}; starting anyway (--force)", e);
+            None
+        }
+        Err(e) => anyhow::bail!("{:#}. Stop it first, or pass --force.", e),
+    };
+
+    let privilege = platform::PrivilegeLevel::detect();
+    info!("Privileges: {} (initial scan: {})", privilege, privilege.scan_method());
+    #[cfg(windows)]
+    if privilege == platform::PrivilegeLevel::Standard {
+        tracing::warn!("Not running as admin - using slower walkdir scan; run elevated for fast MFT indexing.");
+    }
+
     // Initialize database
+    info!("Database: {}", config.db_path.display());
     let db = Arc::new(Database::new(&config)?);
     db.init_schema()?;
     info!("Database initialized");
 
-    // Create search manager
-    let search_manager = Arc::new(SearchManager::new(config.search.backend.clone(), db.clone()));
+    if config.performance.warmup_on_start {
+        let db = db.clone();
+        tokio::task::spawn_blocking(move || match db.warm_up() {
+            Ok(rows) => info!("Database warmed up ({} rows read)", rows),
+            Err(e) => tracing::warn!("Database warm-up failed: {}", e),
+        });
+    }
+
+    // Create search manager; extra backends are registered here
+    #[cfg_attr(not(any(windows, target_os = "macos")), allow(unused_mut))]
+    let mut backends = BackendRegistry::with_builtin();
+    #[cfg(windows)]
+    search::everything::register(&mut backends, &config);
+    #[cfg(target_os = "macos")]
+    search::spotlight::register(&mut backends, &config);
+    let search_manager = Arc::new(SearchManager::new(
+        &config.search,
+        &backends,
+        db.clone(),
+        &config.performance,
+    )?);
     info!("Search backend: {}", search_manager.active_backend_name());
 
+    // Keep the primary/fallback choice in step with backend availability
+    let manager = search_manager.clone();
+    let interval = Duration::from_secs(config.search.reprobe_interval_secs);
+    tokio::spawn(async move { manager.run_reprobe(interval).await });
+
     // Create indexer
     let indexer = Indexer::new((*db).clone(), config.clone());
 
-    // Only start indexing if needed (not using Windows Search as primary)
+    // Only start indexing if needed (not with Spotlight as primary)
     if search_manager.needs_indexing() {
         info!("Starting local indexing...");
 
         // Start initial indexing in background
-        let indexer_clone = indexer.clone();
-        tokio::spawn(async move {
-            if let Err(e) = indexer_clone.start_initial_scan().await {
-                tracing::error!("Initial scan failed: {}", e);
-            }
-        });
+        if config.indexing.initial_scan {
+            let indexer_clone = indexer.clone();
+            tokio::spawn(async move {
+                if let Err(e) = indexer_clone.start_initial_scan().await {
+                    tracing::error!("Initial scan failed: {}", e);
+                }
+            });
+        } else {
+            info!("Initial scan disabled; indexing only files that change from now on");
+        }
+
+        // Keep planner statistics current as the watcher changes the index
+        if config.performance.optimize_interval_secs > 0 {
+            let optimize_indexer = indexer.clone();
+            let interval = Duration::from_secs(config.performance.optimize_interval_secs);
+            tokio::spawn(async move { optimize_indexer.run_optimize(interval).await });
+        }
 
         // Start file watcher
         let watcher_indexer = indexer.clone();
@@ -206,43 +717,394 @@ async fn run_daemon() -> Result<()> {
             }
         });
     } else {
-        info!("Using Windows Search - skipping local indexing");
+        info!("{} keeps its own index - skipping local indexing", search_manager.active_backend_name());
+    }
+
+    // Start IPC server (runs until shutdown is requested)
+    let ipc_server = IpcServer::new(db.clone(), indexer.clone(), config, search_manager);
+    tokio::select! {
+        result = ipc_server.run() => result?,
+        _ = shutdown_signal() => info!("Shutting down..."),
     }
 
-    // Start IPC server (blocks until shutdown)
-    let ipc_server = IpcServer::new(db, indexer, config, search_manager);
-    ipc_server.run().await?;
+    // Let the scanner and watcher loops exit, then leave the database tidy
+    indexer.request_stop();
+    if let Err(e) = db.shutdown() {
+        tracing::warn!("Database shutdown failed: {}", e);
+    }
 
     Ok(())
 }
 
+/// How long a running daemon gets to answer the startup ping
+const INSTANCE_PING_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Make sure no other daemon is running: none answers a ping on our socket or pipe,
+/// and (Unix) none holds the lock file next to the database
+async fn claim_instance(config: &Config) -> Result<platform::InstanceLock> {
+    let client = ipc::IpcClient::connect_to(config.get_socket_path()).await?;
+    if let Ok(Ok(pid)) = tokio::time::timeout(INSTANCE_PING_TIMEOUT, client.ping()).await {
+        anyhow::bail!("StellaSearch daemon is already running (pid {})", pid);
+    }
+
+    // A custom db_path's directory may not exist until the database is created
+    if let Some(parent) = config.db_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    platform::InstanceLock::acquire(&config.db_path.with_extension("lock"))
+        .context("Another StellaSearch daemon is using this database")
+}
+
+/// Wait for a termination signal (SIGTERM/SIGINT on Unix, Ctrl+C elsewhere)
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    match platform::linux::setup_signal_handlers() {
+        Ok(mut rx) => {
+            rx.recv().await;
+        }
+        Err(e) => {
+            tracing::warn!("Failed to set up signal handlers: {}", e);
+            std::future::pending::<()>().await;
+        }
+    }
+
+    #[cfg(not(unix))]
+    if let Err(e) = tokio::signal::ctrl_c().await {
+        tracing::warn!("Failed to listen for Ctrl+C: {}", e);
+        std::future::pending::<()>().await;
+    }
+}
+
+/// Tri-state from a `--hidden`/`--no-hidden` flag pair (the last one given wins)
+fn include_hidden_flag(hidden: bool, no_hidden: bool) -> Option<bool> {
+    match (hidden, no_hidden) {
+        (true, _) => Some(true),
+        (_, true) => Some(false),
+        _ => None,
+    }
+}
+
+/// Parse a `--sort` value
+fn parse_sort(value: &str) -> std::result::Result<stella_search_core::ResultSort, String> {
+    stella_search_core::ResultSort::parse(value)
+        .ok_or_else(|| format!("unknown sort '{}' (expected rank, size, name or created)", value))
+}
+
+/// Parse a date option the way filter expressions do
+fn parse_time(value: &str) -> std::result::Result<i64, String> {
+    search::filter_dsl::parse_time(value)
+}
+
+/// `HH:MM` as that local time today, else what `parse_time` accepts
+fn parse_since(value: &str) -> std::result::Result<i64, String> {
+    let Ok(time) = chrono::NaiveTime::parse_from_str(value, "%H:%M") else {
+        return parse_time(value);
+    };
+    chrono::Local::now()
+        .date_naive()
+        .and_time(time)
+        .and_local_timezone(chrono::Local)
+        .earliest()
+        .map(|time| time.timestamp())
+        .ok_or_else(|| format!("'{}' doesn't exist today in the local time zone", value))
+}
+
+/// Read a list of paths, one per line, from a file or "-" (stdin); blank lines are skipped
+fn read_path_list(source: &Path) -> Result<Vec<String>> {
+    let text = if source == Path::new("-") {
+        std::io::read_to_string(std::io::stdin()).context("Failed to read paths from stdin")?
+    } else {
+        std::fs::read_to_string(source).with_context(|| format!("Failed to read path list {:?}", source))?
+    };
+    Ok(text
+        .lines()
+        .map(|line| line.trim_end_matches('\r'))
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
 /// Search files via IPC client
-async fn search_files(query: &str, max_results: usize, extension: Option<&str>) -> Result<()> {
+async fn search_files(
+    query: &str,
+    max_results: usize,
+    extension: Option<&str>,
+    options: ipc::SearchOptions,
+) -> Result<()> {
     let client = ipc::IpcClient::connect().await?;
-    let results = client.search(query, max_results, extension).await?;
+    let mut results = client.search_stream(query, max_results, extension, options).await?;
 
     println!("Found {} files (showing up to {}):", results.total_found, max_results);
     println!();
 
-    for file in &results.files {
-        println!("  {} ({} bytes)", file.path, file.size);
+    while let Some(file) = results.next_file().await? {
+        let missing = if file.exists == Some(false) { " [missing]" } else { "" };
+        match &file.target {
+            Some(target) => println!("  {} -> {} ({} bytes){}", file.path, target, file.size, missing),
+            None => println!("  {} ({} bytes){}", file.path, file.size, missing),
+        }
     }
 
     println!();
+    if results.truncated {
+        println!("Results cut off at the result size limit; narrow the query to see the rest");
+    }
     println!("Query time: {}ms", results.query_time_ms);
 
     Ok(())
 }
 
+/// The result `open` should launch: the only one, the only one named exactly
+/// `query` (ignoring case), or with `first` the top one. None means the choice
+/// is ambiguous (or there are no results).
+fn choose_result<'a>(files: &'a [database::IndexedFile], query: &str, first: bool) -> Option<&'a database::IndexedFile> {
+    if first || files.len() == 1 {
+        return files.first();
+    }
+
+    let query = query.trim().to_lowercase();
+    let mut exact = files.iter().filter(|file| file.name.to_lowercase() == query);
+    match (exact.next(), exact.next()) {
+        (Some(file), None) => Some(file),
+        _ => None,
+    }
+}
+
+/// Search via IPC client and open the chosen result with its default application
+async fn open_result(query: &str, extension: Option<&str>, first: bool, max_results: usize) -> Result<()> {
+    let client = ipc::IpcClient::connect().await?;
+    let results = client
+        .search(query, max_results.max(1), extension, ipc::SearchOptions::default())
+        .await?;
+
+    if results.files.is_empty() {
+        anyhow::bail!("No files match '{}'", query);
+    }
+
+    match choose_result(&results.files, query, first) {
+        Some(file) => {
+            println!("Opening {}", file.path);
+            platform::open_path(Path::new(&file.path))
+        }
+        None => {
+            println!("{} files match '{}':", results.total_found, query);
+            for (i, file) in results.files.iter().enumerate() {
+                println!("  {:>2}. {}", i + 1, file.path);
+            }
+            anyhow::bail!("Refine the query, or pass --first to open the top result")
+        }
+    }
+}
+
+/// Search via IPC client and write the results to `output` or stdout
+async fn export_results(
+    query: &str,
+    max_results: usize,
+    extension: Option<&str>,
+    search_options: ipc::SearchOptions,
+    export: ExportOptions,
+    output: Option<&Path>,
+) -> Result<()> {
+    let client = ipc::IpcClient::connect().await?;
+    let results = client.search(query, max_results, extension, search_options).await?;
+
+    let writer: Box<dyn std::io::Write> = match output {
+        Some(path) => {
+            let file = std::fs::File::create(path)
+                .with_context(|| format!("Failed to create {:?}", path))?;
+            Box::new(std::io::BufWriter::new(file))
+        }
+        None => Box::new(std::io::stdout().lock()),
+    };
+
+    let mut exporter = Exporter::new(writer, export)?;
+    for file in &results.files {
+        exporter.write(file)?;
+    }
+    let written = exporter.finish()?;
+
+    if let Some(path) = output {
+        eprintln!("Wrote {} results to {:?}", written, path);
+    }
+    if results.truncated {
+        eprintln!("Results cut off at the result size limit; narrow the query to see the rest");
+    }
+
+    Ok(())
+}
+
+/// Show per-extension match counts via IPC client
+async fn show_facets(query: &str, options: ipc::SearchOptions) -> Result<()> {
+    let client = ipc::IpcClient::connect().await?;
+    let facets = client.search_facets(query, options).await?;
+
+    for facet in &facets {
+        let label = facet.extension.as_deref().unwrap_or("(none)");
+        println!("  {} ({})", label, facet.count);
+    }
+
+    Ok(())
+}
+
+/// Show the directories holding matches via IPC client
+async fn show_containing_dirs(
+    query: &str,
+    max_results: usize,
+    extension: Option<&str>,
+    options: ipc::SearchOptions,
+) -> Result<()> {
+    let client = ipc::IpcClient::connect().await?;
+    let directories = client.search_containing_dirs(query, max_results, extension, options).await?;
+
+    for entry in &directories {
+        println!("  {} ({})", entry.directory, entry.count);
+    }
+
+    Ok(())
+}
+
+/// Show the newest files via IPC client
+async fn show_newest(max_results: usize, since: Option<i64>) -> Result<()> {
+    let client = ipc::IpcClient::connect().await?;
+    let results = match since {
+        Some(since) => client.session_changes(since, max_results).await?,
+        None => client.newest(max_results).await?,
+    };
+
+    match since.and_then(|since| chrono::DateTime::from_timestamp(since, 0)) {
+        Some(since) => println!(
+            "{} files modified since {}:",
+            results.total_found,
+            since.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M")
+        ),
+        None => println!("Newest {} files:", results.total_found),
+    }
+    println!();
+
+    for file in &results.files {
+        let modified = chrono::DateTime::from_timestamp(file.modified, 0)
+            .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        println!("  {}  {}", modified, file.path);
+    }
+
+    Ok(())
+}
+
+/// Show the least recently refreshed entries via IPC client
+async fn show_stale(max_results: usize) -> Result<()> {
+    let client = ipc::IpcClient::connect().await?;
+    let entries = client.oldest_indexed(max_results).await?;
+
+    let format_time = |secs: i64| {
+        chrono::DateTime::from_timestamp(secs, 0)
+            .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_else(|| "unknown".to_string())
+    };
+
+    println!("{} least recently refreshed entries:", entries.len());
+    println!();
+    println!("  {:<16}  {:<16}  Path", "Indexed", "Modified");
+
+    for entry in &entries {
+        let indexed = entry.indexed_at.map(format_time).unwrap_or_else(|| "never".to_string());
+        // Directories are indexed without a modification time
+        let modified = if entry.is_directory { "-".to_string() } else { format_time(entry.modified) };
+        println!("  {:<16}  {:<16}  {}", indexed, modified, entry.path);
+    }
+
+    Ok(())
+}
+
+/// List (or clear) recent search queries via IPC client
+async fn show_query_history(max_results: usize, clear: bool) -> Result<()> {
+    let client = ipc::IpcClient::connect().await?;
+    if clear {
+        println!("{}", client.clear_query_history().await?);
+        return Ok(());
+    }
+
+    let entries = client.query_history(max_results).await?;
+    println!("{} recent queries:", entries.len());
+    println!();
+    println!("  {:<16}  {:>8}  {:>6}  Query", "Last searched", "Results", "Times");
+
+    for entry in &entries {
+        let last_searched = chrono::DateTime::from_timestamp(entry.last_searched, 0)
+            .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        println!(
+            "  {:<16}  {:>8}  {:>6}  {}",
+            last_searched, entry.result_count, entry.times_searched, entry.query
+        );
+    }
+
+    Ok(())
+}
+
+/// Whole seconds as the largest fitting unit, rounded down: "45 seconds",
+/// "1 minute", "3 hours", "2 days"
+fn format_duration(secs: i64) -> String {
+    let secs = secs.max(0);
+    let (count, unit) = match secs {
+        0..60 => (secs, "second"),
+        60..3600 => (secs / 60, "minute"),
+        3600..86400 => (secs / 3600, "hour"),
+        _ => (secs / 86400, "day"),
+    };
+    format!("{} {}{}", count, unit, if count == 1 { "" } else { "s" })
+}
+
+/// How long before `now` a time was, e.g. "3 hours ago". Within a minute either way
+/// is "just now"; a later time (the clock was set back) reads "in 2 hours".
+fn format_ago(now: i64, then: i64) -> String {
+    let age = now - then;
+    if age.abs() < 60 {
+        "just now".to_string()
+    } else if age < 0 {
+        format!("in {}", format_duration(-age))
+    } else {
+        format!("{} ago", format_duration(age))
+    }
+}
+
+/// Byte count in decimal units, e.g. "1.2 TB"
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["KB", "MB", "GB", "TB", "PB"];
+
+    if bytes < 1000 {
+        return format!("{} bytes", bytes);
+    }
+    let mut size = bytes as f64 / 1000.0;
+    let mut unit = 0;
+    while size >= 1000.0 && unit < UNITS.len() - 1 {
+        size /= 1000.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
 /// Show index status via IPC client
-async fn show_status() -> Result<()> {
+async fn show_status(detailed: bool, absolute_times: bool) -> Result<()> {
     let client = ipc::IpcClient::connect().await?;
     let status = client.get_status().await?;
 
+    let now = chrono::Utc::now().timestamp();
+    let format_when = |secs: i64| {
+        if absolute_times {
+            chrono::DateTime::from_timestamp(secs, 0)
+                .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+                .unwrap_or_else(|| "unknown".to_string())
+        } else {
+            format_ago(now, secs)
+        }
+    };
+
     println!("StellaSearch Status");
     println!("==================");
     println!("Indexed files:    {}", status.indexed_files);
     println!("Indexed dirs:     {}", status.indexed_dirs);
+    println!("Indexed size:     {}", format_size(status.total_size_bytes));
     println!("Database size:    {} MB", status.database_size_bytes / 1_000_000);
     println!("Is scanning:      {}", status.is_scanning);
     if status.is_scanning {
@@ -251,6 +1113,49 @@ async fn show_status() -> Result<()> {
             println!("Current path:     {}", path);
         }
     }
+    println!(
+        "Last full scan:   {}",
+        status.last_full_scan.map(&format_when).unwrap_or_else(|| "never".to_string())
+    );
+    if absolute_times {
+        println!("Started:          {}", format_when(status.started_at));
+    } else {
+        println!("Uptime:           {}", format_duration(now - status.started_at));
+    }
+    println!("Active searches:  {}", status.active_searches);
+    println!("Privileges:       {} ({} scan)", status.privilege, status.privilege.scan_method());
+    if status.low_disk_space {
+        println!("Warning:          indexing paused, database volume is low on disk space");
+    }
+    if let Some(warning) = &status.index_warning {
+        println!("Warning:          index looks incomplete ({}); check the daemon log and run reindex", warning);
+    }
+
+    if detailed {
+        let stats = client.detailed_status().await?;
+
+        println!("Average size:     {} bytes", stats.average_file_size);
+        println!();
+        println!("Top extensions:");
+        for usage in &stats.top_extensions {
+            println!(
+                "  {} ({} files, {} bytes)",
+                usage.extension.as_deref().unwrap_or("(none)"),
+                usage.files,
+                usage.total_size
+            );
+        }
+        println!();
+        println!("Files per volume:");
+        for volume in &stats.volumes {
+            println!("  {} ({})", volume.root, volume.files);
+        }
+        println!();
+        println!("Largest files:");
+        for file in &stats.largest_files {
+            println!("  {} ({} bytes)", file.path, file.size);
+        }
+    }
 
     Ok(())
 }
@@ -271,6 +1176,28 @@ async fn remove_exclusion(path: &str) -> Result<()> {
     Ok(())
 }
 
+/// Show what an exclude pattern would remove via IPC client
+async fn preview_exclusion(pattern: &str, sample: usize) -> Result<()> {
+    let client = ipc::IpcClient::connect().await?;
+    let preview = client.preview_exclude(pattern, sample).await?;
+
+    println!(
+        "Excluding '{}' would remove {} files and {} directories from the index",
+        pattern, preview.files, preview.directories
+    );
+    if !preview.sample.is_empty() {
+        println!();
+        for path in &preview.sample {
+            println!("  {}", path);
+        }
+        let total = preview.files + preview.directories;
+        if total > preview.sample.len() as u64 {
+            println!("  ... and {} more", total - preview.sample.len() as u64);
+        }
+    }
+    Ok(())
+}
+
 /// Add an inclusion path via IPC client
 async fn add_inclusion(path: &str) -> Result<()> {
     let client = ipc::IpcClient::connect().await?;
@@ -306,6 +1233,28 @@ async fn trigger_reindex(path: Option<&str>) -> Result<()> {
     Ok(())
 }
 
+/// Trigger a reindex of one drive ("D", "D:" or "D:\\") via IPC client
+async fn trigger_drive_reindex(drive: &str) -> Result<()> {
+    let trimmed = drive.trim().trim_end_matches(['\\', '/']).trim_end_matches(':');
+    let mut chars = trimmed.chars();
+    let letter = match (chars.next(), chars.next()) {
+        (Some(letter), None) if letter.is_ascii_alphabetic() => letter.to_ascii_uppercase(),
+        _ => anyhow::bail!("Not a drive letter: {}", drive),
+    };
+
+    let client = ipc::IpcClient::connect().await?;
+    client.reindex_drive(letter).await?;
+    println!("Reindex triggered for drive {}:", letter);
+    Ok(())
+}
+
+/// Cancel the running scan via IPC client
+async fn cancel_reindex() -> Result<()> {
+    let client = ipc::IpcClient::connect().await?;
+    println!("{}", client.cancel_reindex().await?);
+    Ok(())
+}
+
 /// Show current configuration via IPC client
 async fn show_config() -> Result<()> {
     let client = ipc::IpcClient::connect().await?;
@@ -334,3 +1283,170 @@ async fn show_config() -> Result<()> {
 
     Ok(())
 }
+
+/// Print the resolved config, database and socket paths (works without a daemon)
+fn show_paths() -> Result<()> {
+    let config = Config::load()?;
+
+    let missing = |path: &Path| if path.exists() { "" } else { " (not created yet)" };
+    println!("Config:   {}{}", config.config_path.display(), missing(&config.config_path));
+    println!("Database: {}{}", config.db_path.display(), missing(&config.db_path));
+    println!("Socket:   {}", config.get_socket_path().display());
+
+    for legacy in config.legacy_db_paths().iter().filter(|path| path.is_file()) {
+        let action = if config.db_path.exists() {
+            "not used"
+        } else if config.service.migrate_legacy_db {
+            "migrated on next daemon start"
+        } else {
+            "not migrated: service.migrate_legacy_db is off"
+        };
+        println!("Older database: {} ({})", legacy.display(), action);
+    }
+
+    Ok(())
+}
+
+/// List the daemon's extension groups via IPC client
+async fn show_extension_groups() -> Result<()> {
+    let client = ipc::IpcClient::connect().await?;
+    let groups = client.extension_groups().await?;
+
+    if groups.is_empty() {
+        println!("(no extension groups; add them under [search.extension_groups])");
+    }
+    for group in &groups {
+        println!("  {}: {}", group.name, group.extensions.join(" "));
+    }
+
+    Ok(())
+}
+
+/// Show watch path status via IPC client
+async fn show_watch_status() -> Result<()> {
+    let client = ipc::IpcClient::connect().await?;
+    let status = client.watch_status().await?;
+
+    println!("Watch Paths");
+    println!("===========");
+    println!("Watcher:  {}", status.watcher.as_deref().unwrap_or("not started"));
+
+    if status.paths.is_empty() {
+        println!("(no watch paths)");
+    }
+    for watch in &status.paths {
+        println!();
+        println!("{}", watch.path);
+        println!("  Watching:      {}", if watch.watching { "yes" } else { "no" });
+        println!("  Initial scan:  {}", if watch.initial_scan_complete { "complete" } else { "pending" });
+        if watch.needs_rescan {
+            println!("  Rescan:        pending (the watcher lost events)");
+        }
+        if let Some(error) = &watch.error {
+            println!("  Error:         {}", error);
+        }
+    }
+
+    Ok(())
+}
+
+/// Show search backend status via IPC client
+async fn show_backend(refresh: bool) -> Result<()> {
+    let client = ipc::IpcClient::connect().await?;
+    let status = if refresh {
+        client.refresh_backend().await?
+    } else {
+        client.backend_status().await?
+    };
+
+    println!("Search Backend");
+    println!("==============");
+    println!("Primary:           {} ({})", status.primary,
+             if status.primary_available { "available" } else { "unavailable" });
+    println!("Fallback:          {}", status.fallback.as_deref().unwrap_or("none"));
+    println!("Active:            {}", status.active);
+    if status.using_fallback {
+        println!("Note:              primary is down, using the local index");
+    }
+    if refresh {
+        println!("Switched back:     {}", status.switched_to_primary);
+    }
+
+    Ok(())
+}
+
+/// Back up the database via IPC client
+async fn backup_database(to: &str) -> Result<()> {
+    // The daemon may run with a different working directory, so send an absolute path
+    let path = std::path::absolute(to)?;
+    let client = ipc::IpcClient::connect().await?;
+    let message = client.backup(&path.to_string_lossy()).await?;
+    println!("{}", message);
+    Ok(())
+}
+
+/// Restore the database via IPC client
+async fn restore_database(from: &str) -> Result<()> {
+    let path = std::path::absolute(from)?;
+    let client = ipc::IpcClient::connect().await?;
+    let message = client.restore(&path.to_string_lossy()).await?;
+    println!("{}", message);
+    Ok(())
+}
+
+/// Import a JSONL dump via IPC client
+async fn import_database(from: &str, policy: ImportPolicy) -> Result<()> {
+    let path = std::path::absolute(from)?;
+    let client = ipc::IpcClient::connect().await?;
+    let message = client.import(&path.to_string_lossy(), policy).await?;
+    println!("{}", message);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: i64 = 60;
+    const HOUR: i64 = 60 * MINUTE;
+    const DAY: i64 = 24 * HOUR;
+
+    #[test]
+    fn durations_use_the_largest_whole_unit() {
+        let cases = [
+            (-5, "0 seconds"),
+            (0, "0 seconds"),
+            (1, "1 second"),
+            (59, "59 seconds"),
+            (MINUTE, "1 minute"),
+            (2 * MINUTE - 1, "1 minute"),
+            (HOUR - 1, "59 minutes"),
+            (HOUR, "1 hour"),
+            (DAY - 1, "23 hours"),
+            (DAY, "1 day"),
+            (400 * DAY, "400 days"),
+        ];
+        for (secs, expected) in cases {
+            assert_eq!(format_duration(secs), expected, "{} seconds", secs);
+        }
+    }
+
+    #[test]
+    fn times_read_relative_to_now() {
+        let now = 1_700_000_000;
+        let cases = [
+            (now, "just now"),
+            (now - 59, "just now"),
+            (now + 59, "just now"),
+            (now - MINUTE, "1 minute ago"),
+            (now - HOUR + 1, "59 minutes ago"),
+            (now - 3 * HOUR, "3 hours ago"),
+            (now - DAY, "1 day ago"),
+            (now - 2 * DAY - HOUR, "2 days ago"),
+            (now + 2 * HOUR, "in 2 hours"),
+        ];
+        for (then, expected) in cases {
+            assert_eq!(format_ago(now, then), expected, "{} seconds ago", now - then);
+        }
+    }
+}
@@ -53,6 +53,162 @@ pub fn get_mount_points() -> Vec<String> {
     Vec::new()
 }
 
+/// Get free space (bytes) available to the current user on the volume containing `path`
+#[cfg(unix)]
+pub fn free_disk_space(path: &std::path::Path) -> Result<u64> {
+    let stats = nix::sys::statvfs::statvfs(path)?;
+    Ok(stats.blocks_available() as u64 * stats.fragment_size() as u64)
+}
+
+#[cfg(not(unix))]
+pub fn free_disk_space(_path: &std::path::Path) -> Result<u64> {
+    anyhow::bail!("statvfs is only available on Unix")
+}
+
+/// Disk holding `path`, looked up from its device number in sysfs
+#[cfg(unix)]
+pub fn disk_of(path: &std::path::Path) -> super::DiskInfo {
+    use std::os::unix::fs::MetadataExt;
+
+    let Ok(metadata) = std::fs::metadata(path) else {
+        return super::DiskInfo::unknown(path);
+    };
+
+    #[cfg(target_os = "linux")]
+    {
+        let dev = metadata.dev();
+        let (major, minor) = (nix::sys::stat::major(dev), nix::sys::stat::minor(dev));
+        if let Some(disk) = sysfs_disk(std::path::Path::new("/sys"), major, minor) {
+            return disk;
+        }
+    }
+
+    // No block device behind it (tmpfs, network, btrfs subvolume ids): key by device number
+    super::DiskInfo {
+        id: format!("dev{}", metadata.dev()),
+        rotational: None,
+    }
+}
+
+/// Resolve a device number to its disk under a sysfs tree (`/sys`, or a fake one)
+#[cfg(target_os = "linux")]
+fn sysfs_disk(sys: &std::path::Path, major: u64, minor: u64) -> Option<super::DiskInfo> {
+    let device = std::fs::canonicalize(sys.join(format!("dev/block/{}:{}", major, minor))).ok()?;
+
+    // A partition is a subdirectory of its disk, marked by a `partition` file;
+    // `queue/rotational` only exists on the whole disk
+    let disk = if device.join("partition").exists() {
+        device.parent()?.to_path_buf()
+    } else {
+        device
+    };
+    let rotational = std::fs::read_to_string(disk.join("queue/rotational"))
+        .ok()
+        .map(|value| value.trim() == "1");
+
+    Some(super::DiskInfo {
+        id: disk.file_name()?.to_string_lossy().into_owned(),
+        rotational,
+    })
+}
+
+/// Owner uid and permission bits of `path`, from `metadata` if the caller already
+/// has it (following symlinks either way)
+#[cfg(unix)]
+pub fn file_ownership(path: &std::path::Path, metadata: Option<&std::fs::Metadata>) -> Option<super::Ownership> {
+    use std::os::unix::fs::MetadataExt;
+
+    let stat;
+    let metadata = match metadata {
+        Some(metadata) => metadata,
+        None => {
+            stat = std::fs::metadata(path).ok()?;
+            &stat
+        }
+    };
+
+    Some(super::Ownership {
+        owner: metadata.uid().to_string(),
+        mode: metadata.mode() & 0o7777,
+    })
+}
+
+/// The uid stored for `user`, which may be a user name or already a uid
+#[cfg(unix)]
+pub fn owner_id(user: &str) -> Result<String> {
+    let user = user.trim();
+    if !user.is_empty() && user.bytes().all(|b| b.is_ascii_digit()) {
+        return Ok(user.to_string());
+    }
+
+    match nix::unistd::User::from_name(user)? {
+        Some(found) => Ok(found.uid.to_string()),
+        None => anyhow::bail!("No user named '{}'", user),
+    }
+}
+
+/// Open a file or folder with the desktop's default handler: `open` on macOS,
+/// `xdg-open` elsewhere
+#[cfg(unix)]
+pub fn open_path(path: &std::path::Path) -> Result<()> {
+    let opener = if cfg!(target_os = "macos") { "open" } else { "xdg-open" };
+    let status = std::process::Command::new(opener)
+        .arg(path)
+        .status()
+        .map_err(|e| anyhow::anyhow!("Failed to run {}: {}", opener, e))?;
+    if !status.success() {
+        anyhow::bail!("{} could not open {} ({})", opener, path.display(), status);
+    }
+    Ok(())
+}
+
+/// Take an exclusive `flock` on `path` and write our pid into it. Fails with the
+/// holder's pid while another process has the lock; the lock ends with the process.
+#[cfg(unix)]
+pub fn lock_instance(path: &std::path::Path) -> Result<nix::fcntl::Flock<std::fs::File>> {
+    use nix::errno::Errno;
+    use nix::fcntl::{Flock, FlockArg};
+    use std::io::{Read, Write};
+
+    let file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)?;
+
+    let mut lock = match Flock::lock(file, FlockArg::LockExclusiveNonblock) {
+        Ok(lock) => lock,
+        Err((mut file, Errno::EWOULDBLOCK)) => {
+            let mut pid = String::new();
+            let _ = file.read_to_string(&mut pid);
+            anyhow::bail!("{} is locked by pid {}", path.display(), pid.trim());
+        }
+        Err((_, errno)) => return Err(errno.into()),
+    };
+
+    lock.set_len(0)?;
+    write!(lock, "{}", std::process::id())?;
+    Ok(lock)
+}
+
+/// Whether the process can read every directory: root, or CAP_DAC_READ_SEARCH
+/// in its effective capability set
+#[cfg(unix)]
+pub fn is_elevated() -> Result<bool> {
+    if nix::unistd::geteuid().is_root() {
+        return Ok(true);
+    }
+
+    // CapEff is a hex bitmask; CAP_DAC_READ_SEARCH is bit 2
+    let status = std::fs::read_to_string("/proc/self/status")?;
+    let Some(cap_eff) = status.lines().find_map(|line| line.strip_prefix("CapEff:")) else {
+        return Ok(false);
+    };
+    let caps = u64::from_str_radix(cap_eff.trim(), 16)?;
+    Ok(caps & (1 << 2) != 0)
+}
+
 /// Setup signal handlers for graceful shutdown
 #[cfg(unix)]
 pub fn setup_signal_handlers() -> Result<tokio::sync::mpsc::Receiver<()>> {
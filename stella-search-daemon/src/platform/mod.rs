@@ -5,3 +5,151 @@ pub mod windows;
 
 #[cfg(unix)]
 pub mod linux;
+
+#[cfg(windows)]
+pub use windows::free_disk_space;
+
+#[cfg(unix)]
+pub use linux::free_disk_space;
+
+#[cfg(windows)]
+pub use windows::disk_of;
+
+#[cfg(unix)]
+pub use linux::disk_of;
+
+#[cfg(windows)]
+pub use windows::{file_ownership, owner_id};
+
+#[cfg(unix)]
+pub use linux::{file_ownership, owner_id};
+
+#[cfg(windows)]
+pub use windows::open_path;
+
+#[cfg(unix)]
+pub use linux::open_path;
+
+#[cfg(windows)]
+use windows::is_elevated;
+
+#[cfg(unix)]
+use linux::is_elevated;
+
+use serde::{Deserialize, Serialize};
+
+/// Roots of the mounted volumes: drive letters on Windows, mount points on Unix
+pub fn volume_roots() -> Vec<String> {
+    #[cfg(windows)]
+    {
+        windows::get_drive_letters()
+    }
+
+    #[cfg(unix)]
+    {
+        // Only some filesystem types are listed; "/" catches files on the rest
+        let mut roots = linux::get_mount_points();
+        if !roots.iter().any(|root| root == "/") {
+            roots.push("/".to_string());
+        }
+        roots
+    }
+}
+
+/// Owner and permissions of a file, indexed with `indexing.track_ownership`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ownership {
+    /// Owner's uid on Unix, or SID string ("S-1-5-21-...") on Windows
+    pub owner: String,
+    /// Permission bits (`mode & 0o7777`) on Unix. Windows has no mode, so only
+    /// 0o002 is set there, when the DACL lets Everyone write.
+    pub mode: u32,
+}
+
+/// Held for the daemon's lifetime so a second daemon can't share its database.
+/// On Unix this is an exclusive `flock` on a lock file next to the database; on
+/// Windows it holds nothing, and the pipe ping at startup is the only guard.
+pub struct InstanceLock {
+    #[cfg(unix)]
+    _lock: nix::fcntl::Flock<std::fs::File>,
+}
+
+impl InstanceLock {
+    /// Lock `path`, failing if another daemon holds it
+    pub fn acquire(_path: &std::path::Path) -> anyhow::Result<Self> {
+        Ok(Self {
+            #[cfg(unix)]
+            _lock: linux::lock_instance(_path)?,
+        })
+    }
+}
+
+/// Physical disk a path lives on, used to schedule scans
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiskInfo {
+    /// Paths with the same id are on the same drive ("sda", "PhysicalDrive0")
+    pub id: String,
+    /// Spinning disk with a seek penalty; `None` if the type can't be determined
+    pub rotational: Option<bool>,
+}
+
+impl DiskInfo {
+    /// A path whose disk can't be identified: a disk of its own, type unknown
+    fn unknown(path: &std::path::Path) -> Self {
+        Self {
+            id: path.to_string_lossy().into_owned(),
+            rotational: None,
+        }
+    }
+}
+
+/// Privileges of the daemon process, which decide the scan method
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PrivilegeLevel {
+    /// Administrator (elevated token) on Windows; root or CAP_DAC_READ_SEARCH on Unix
+    Elevated,
+    /// Regular user
+    Standard,
+    /// The check itself failed
+    #[default]
+    Unknown,
+}
+
+impl PrivilegeLevel {
+    /// Check the current process
+    pub fn detect() -> Self {
+        Self::from_check(is_elevated())
+    }
+
+    fn from_check(elevated: anyhow::Result<bool>) -> Self {
+        match elevated {
+            Ok(true) => PrivilegeLevel::Elevated,
+            Ok(false) => PrivilegeLevel::Standard,
+            Err(e) => {
+                tracing::debug!("Privilege check failed: {}", e);
+                PrivilegeLevel::Unknown
+            }
+        }
+    }
+
+    /// Scan method the initial scan will use: the MFT reader needs an elevated
+    /// process on Windows, everything else walks directories
+    pub fn scan_method(self) -> &'static str {
+        if cfg!(windows) && self == PrivilegeLevel::Elevated {
+            "mft"
+        } else {
+            "walkdir"
+        }
+    }
+}
+
+impl std::fmt::Display for PrivilegeLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PrivilegeLevel::Elevated => write!(f, "elevated"),
+            PrivilegeLevel::Standard => write!(f, "standard"),
+            PrivilegeLevel::Unknown => write!(f, "unknown"),
+        }
+    }
+}
@@ -87,7 +87,7 @@ fn run_service_main() -> Result<()> {
 
     // Run the daemon
     rt.block_on(async {
-        crate::run_daemon().await
+        crate::run_daemon(false).await
     })?;
 
     // Tell Windows we've stopped
@@ -120,6 +120,220 @@ pub fn get_drive_letters() -> Vec<String> {
     drives
 }
 
+/// Get free space (bytes) available to the current user on the volume containing `path`
+#[cfg(windows)]
+pub fn free_disk_space(path: &std::path::Path) -> Result<u64> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+
+    let path_wide: Vec<u16> = path
+        .as_os_str()
+        .encode_wide()
+        .chain(std::iter::once(0))
+        .collect();
+
+    let mut free_bytes: u64 = 0;
+    let result = unsafe {
+        GetDiskFreeSpaceExW(
+            path_wide.as_ptr(),
+            &mut free_bytes,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+    };
+
+    if result == 0 {
+        anyhow::bail!("GetDiskFreeSpaceExW failed: {}", std::io::Error::last_os_error());
+    }
+
+    Ok(free_bytes)
+}
+
+/// Open a file or folder with its default handler, as double-clicking it in
+/// Explorer would
+#[cfg(windows)]
+pub fn open_path(path: &std::path::Path) -> Result<()> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::UI::Shell::ShellExecuteW;
+    use windows_sys::Win32::UI::WindowsAndMessaging::SW_SHOWNORMAL;
+
+    let path_wide: Vec<u16> = path
+        .as_os_str()
+        .encode_wide()
+        .chain(std::iter::once(0))
+        .collect();
+
+    // No verb: the file type's default action
+    let result = unsafe {
+        ShellExecuteW(
+            std::ptr::null_mut(),
+            std::ptr::null(),
+            path_wide.as_ptr(),
+            std::ptr::null(),
+            std::ptr::null(),
+            SW_SHOWNORMAL,
+        )
+    };
+
+    // Values up to 32 are error codes
+    if result as isize <= 32 {
+        anyhow::bail!("ShellExecuteW failed for {} (error {})", path.display(), result as isize);
+    }
+    Ok(())
+}
+
+/// Whether the process token is elevated (administrator with UAC approval)
+#[cfg(windows)]
+pub fn is_elevated() -> Result<bool> {
+    use windows_sys::Win32::Foundation::CloseHandle;
+    use windows_sys::Win32::Security::{GetTokenInformation, TokenElevation, TOKEN_ELEVATION, TOKEN_QUERY};
+    use windows_sys::Win32::System::Threading::{GetCurrentProcess, OpenProcessToken};
+
+    let mut token = std::ptr::null_mut();
+    if unsafe { OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token) } == 0 {
+        anyhow::bail!("OpenProcessToken failed: {}", std::io::Error::last_os_error());
+    }
+
+    let mut elevation = TOKEN_ELEVATION { TokenIsElevated: 0 };
+    let mut len = 0u32;
+    let result = unsafe {
+        GetTokenInformation(
+            token,
+            TokenElevation,
+            (&mut elevation as *mut TOKEN_ELEVATION).cast(),
+            size_of::<TOKEN_ELEVATION>() as u32,
+            &mut len,
+        )
+    };
+    let error = std::io::Error::last_os_error();
+    unsafe { CloseHandle(token) };
+
+    if result == 0 {
+        anyhow::bail!("GetTokenInformation failed: {}", error);
+    }
+
+    Ok(elevation.TokenIsElevated != 0)
+}
+
+/// Owner SID of `path`, with mode 0o002 if its DACL lets Everyone write data.
+/// `metadata` is unused: Windows metadata carries no security information.
+#[cfg(windows)]
+pub fn file_ownership(path: &std::path::Path, _metadata: Option<&std::fs::Metadata>) -> Option<super::Ownership> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Foundation::{LocalFree, ERROR_SUCCESS};
+    use windows_sys::Win32::Security::Authorization::{
+        BuildTrusteeWithSidW, GetEffectiveRightsFromAclW, GetNamedSecurityInfoW, SE_FILE_OBJECT, TRUSTEE_W,
+    };
+    use windows_sys::Win32::Security::{
+        CreateWellKnownSid, WinWorldSid, DACL_SECURITY_INFORMATION, OWNER_SECURITY_INFORMATION,
+        SECURITY_MAX_SID_SIZE,
+    };
+    use windows_sys::Win32::Storage::FileSystem::FILE_WRITE_DATA;
+
+    let path_wide: Vec<u16> = path
+        .as_os_str()
+        .encode_wide()
+        .chain(std::iter::once(0))
+        .collect();
+
+    // The owner and DACL point into the descriptor, which is freed last
+    let mut owner = std::ptr::null_mut();
+    let mut dacl = std::ptr::null_mut();
+    let mut descriptor = std::ptr::null_mut();
+    let status = unsafe {
+        GetNamedSecurityInfoW(
+            path_wide.as_ptr(),
+            SE_FILE_OBJECT,
+            OWNER_SECURITY_INFORMATION | DACL_SECURITY_INFORMATION,
+            &mut owner,
+            std::ptr::null_mut(),
+            &mut dacl,
+            std::ptr::null_mut(),
+            &mut descriptor,
+        )
+    };
+    if status != ERROR_SUCCESS {
+        return None;
+    }
+
+    let owner = sid_to_string(owner);
+
+    // A null DACL grants everyone full access
+    let world_writable = dacl.is_null() || unsafe {
+        let mut world = [0u32; SECURITY_MAX_SID_SIZE as usize / 4];
+        let mut len = SECURITY_MAX_SID_SIZE;
+        let mut trustee: TRUSTEE_W = std::mem::zeroed();
+        let mut rights = 0u32;
+        CreateWellKnownSid(WinWorldSid, std::ptr::null_mut(), world.as_mut_ptr().cast(), &mut len) != 0 && {
+            BuildTrusteeWithSidW(&mut trustee, world.as_mut_ptr().cast());
+            GetEffectiveRightsFromAclW(dacl, &trustee, &mut rights) == ERROR_SUCCESS
+                && rights & FILE_WRITE_DATA != 0
+        }
+    };
+
+    unsafe { LocalFree(descriptor) };
+
+    Some(super::Ownership {
+        owner: owner?,
+        mode: if world_writable { 0o002 } else { 0 },
+    })
+}
+
+/// The SID string stored for `user`: a SID is kept as is, an account name
+/// ("alice", "DOMAIN\\alice") is looked up
+#[cfg(windows)]
+pub fn owner_id(user: &str) -> Result<String> {
+    use windows_sys::Win32::Security::{LookupAccountNameW, SECURITY_MAX_SID_SIZE};
+
+    let user = user.trim();
+    if user.len() > 2 && user[..2].eq_ignore_ascii_case("S-") {
+        return Ok(user.to_ascii_uppercase());
+    }
+
+    let name: Vec<u16> = user.encode_utf16().chain(std::iter::once(0)).collect();
+    let mut sid = [0u32; SECURITY_MAX_SID_SIZE as usize / 4];
+    let mut sid_len = SECURITY_MAX_SID_SIZE;
+    let mut domain = [0u16; 256];
+    let mut domain_len = domain.len() as u32;
+    let mut sid_use = 0;
+    let found = unsafe {
+        LookupAccountNameW(
+            std::ptr::null(),
+            name.as_ptr(),
+            sid.as_mut_ptr().cast(),
+            &mut sid_len,
+            domain.as_mut_ptr(),
+            &mut domain_len,
+            &mut sid_use,
+        )
+    };
+    if found == 0 {
+        anyhow::bail!("No account named '{}': {}", user, std::io::Error::last_os_error());
+    }
+
+    sid_to_string(sid.as_mut_ptr().cast())
+        .ok_or_else(|| anyhow::anyhow!("Failed to convert the SID of '{}'", user))
+}
+
+/// "S-1-5-..." form of a SID
+#[cfg(windows)]
+fn sid_to_string(sid: windows_sys::Win32::Security::PSID) -> Option<String> {
+    use windows_sys::Win32::Foundation::LocalFree;
+    use windows_sys::Win32::Security::Authorization::ConvertSidToStringSidW;
+
+    let mut raw = std::ptr::null_mut();
+    if sid.is_null() || unsafe { ConvertSidToStringSidW(sid, &mut raw) } == 0 {
+        return None;
+    }
+
+    let text = unsafe {
+        let len = (0..).take_while(|&i| *raw.add(i) != 0).count();
+        String::from_utf16_lossy(std::slice::from_raw_parts(raw, len))
+    };
+    unsafe { LocalFree(raw.cast()) };
+    Some(text)
+}
+
 #[cfg(not(windows))]
 pub fn run_service() -> Result<()> {
     anyhow::bail!("Windows service mode is only available on Windows")
@@ -129,3 +343,101 @@ pub fn run_service() -> Result<()> {
 pub fn get_drive_letters() -> Vec<String> {
     Vec::new()
 }
+
+/// Disk holding `path`: the physical drive behind its volume, and whether it
+/// reports a seek penalty (spinning disk)
+#[cfg(windows)]
+pub fn disk_of(path: &std::path::Path) -> super::DiskInfo {
+    use std::os::windows::io::{AsRawHandle, FromRawHandle, OwnedHandle};
+    use std::path::Component;
+    use windows_sys::Win32::Foundation::INVALID_HANDLE_VALUE;
+    use windows_sys::Win32::Storage::FileSystem::{
+        CreateFileW, FILE_SHARE_READ, FILE_SHARE_WRITE, IOCTL_VOLUME_GET_VOLUME_DISK_EXTENTS,
+        OPEN_EXISTING,
+    };
+    use windows_sys::Win32::System::IO::DeviceIoControl;
+    use windows_sys::Win32::System::Ioctl::{
+        PropertyStandardQuery, StorageDeviceSeekPenaltyProperty, DEVICE_SEEK_PENALTY_DESCRIPTOR,
+        IOCTL_STORAGE_QUERY_PROPERTY, STORAGE_PROPERTY_QUERY, VOLUME_DISK_EXTENTS,
+    };
+
+    // Drive letter of the path ("C:"); UNC shares have no local disk to query
+    let volume = match path.components().next() {
+        Some(Component::Prefix(prefix)) => prefix.as_os_str().to_string_lossy().to_uppercase(),
+        _ => return super::DiskInfo::unknown(path),
+    };
+    let unknown = || super::DiskInfo {
+        id: volume.clone(),
+        rotational: None,
+    };
+
+    // Zero access rights are enough for these queries and don't need elevation
+    let device: Vec<u16> = format!(r"\\.\{}", volume).encode_utf16().chain(std::iter::once(0)).collect();
+    let raw = unsafe {
+        CreateFileW(
+            device.as_ptr(),
+            0,
+            FILE_SHARE_READ | FILE_SHARE_WRITE,
+            std::ptr::null(),
+            OPEN_EXISTING,
+            0,
+            std::ptr::null_mut(),
+        )
+    };
+    if raw == INVALID_HANDLE_VALUE {
+        return unknown();
+    }
+    let handle = unsafe { OwnedHandle::from_raw_handle(raw) };
+
+    let query = STORAGE_PROPERTY_QUERY {
+        PropertyId: StorageDeviceSeekPenaltyProperty,
+        QueryType: PropertyStandardQuery,
+        AdditionalParameters: [0],
+    };
+    let mut penalty: DEVICE_SEEK_PENALTY_DESCRIPTOR = unsafe { std::mem::zeroed() };
+    let mut returned = 0u32;
+    let rotational = unsafe {
+        DeviceIoControl(
+            handle.as_raw_handle(),
+            IOCTL_STORAGE_QUERY_PROPERTY,
+            (&query as *const STORAGE_PROPERTY_QUERY).cast(),
+            size_of::<STORAGE_PROPERTY_QUERY>() as u32,
+            (&mut penalty as *mut DEVICE_SEEK_PENALTY_DESCRIPTOR).cast(),
+            size_of::<DEVICE_SEEK_PENALTY_DESCRIPTOR>() as u32,
+            &mut returned,
+            std::ptr::null_mut(),
+        )
+    } != 0;
+    let rotational = rotational.then_some(penalty.IncursSeekPenalty != 0);
+
+    // Partitions of one physical disk share its disk number. A volume spanning
+    // several disks fails with ERROR_MORE_DATA and keeps its drive letter as id.
+    let mut extents: VOLUME_DISK_EXTENTS = unsafe { std::mem::zeroed() };
+    let spans_one_disk = unsafe {
+        DeviceIoControl(
+            handle.as_raw_handle(),
+            IOCTL_VOLUME_GET_VOLUME_DISK_EXTENTS,
+            std::ptr::null(),
+            0,
+            (&mut extents as *mut VOLUME_DISK_EXTENTS).cast(),
+            size_of::<VOLUME_DISK_EXTENTS>() as u32,
+            &mut returned,
+            std::ptr::null_mut(),
+        )
+    } != 0
+        && extents.NumberOfDiskExtents == 1;
+
+    super::DiskInfo {
+        id: if spans_one_disk {
+            format!("PhysicalDrive{}", extents.Extents[0].DiskNumber)
+        } else {
+            volume
+        },
+        rotational,
+    }
+}
+
+#[cfg(not(windows))]
+pub fn free_disk_space(_path: &std::path::Path) -> Result<u64> {
+    anyhow::bail!("GetDiskFreeSpaceExW is only available on Windows")
+}
@@ -0,0 +1,463 @@
+//! Everything (voidtools) search backend, Windows only
+//!
+//! Everything keeps its own instant file name index. With `search.backend =
+//! "everything"` searches are sent to it through its SDK DLL (`Everything64.dll`,
+//! from the Everything SDK), which talks to the running Everything process over
+//! IPC. The DLL is loaded at startup from `search.everything_dll`, or else from
+//! the daemon's directory and the system directories; the daemon never links to it.
+//!
+//! A `SearchQuery` is translated to Everything's search syntax. Filters it has no
+//! faithful equivalent for (creation time, ownership, filter expressions, path
+//! lists, per-directory caps, symlink targets) fail the search, so the manager
+//! answers it from the SQLite index instead. The local index is kept up to date
+//! either way, so switching backends never needs a rescan.
+
+use std::path::Path;
+use std::sync::Mutex;
+
+use anyhow::{bail, Context, Result};
+use windows_sys::Win32::Foundation::{FreeLibrary, FILETIME, HMODULE};
+use windows_sys::Win32::System::LibraryLoader::{GetProcAddress, LoadLibraryExW, LOAD_LIBRARY_SEARCH_DEFAULT_DIRS};
+
+use stella_search_core::{collect_capped, IndexedFile, ResultSort};
+use crate::config::{Config, SizeCategoryBounds};
+use super::path_encoding::percent_encode_str;
+use super::registry::BackendRegistry;
+use super::{fill_volumes, verify_existence, PathEncoding, QueryKind, SearchBackend, SearchError, SearchQuery, SearchResult};
+
+#[cfg(target_pointer_width = "64")]
+const SDK_DLL: &str = "Everything64.dll";
+#[cfg(not(target_pointer_width = "64"))]
+const SDK_DLL: &str = "Everything32.dll";
+
+// Everything_SetRequestFlags
+const REQUEST_FILE_NAME: u32 = 0x0000_0001;
+const REQUEST_FULL_PATH_AND_FILE_NAME: u32 = 0x0000_0004;
+const REQUEST_EXTENSION: u32 = 0x0000_0008;
+const REQUEST_SIZE: u32 = 0x0000_0010;
+const REQUEST_DATE_CREATED: u32 = 0x0000_0020;
+const REQUEST_DATE_MODIFIED: u32 = 0x0000_0040;
+
+// Everything_SetSort
+const SORT_NAME_ASCENDING: u32 = 1;
+const SORT_SIZE_DESCENDING: u32 = 6;
+const SORT_DATE_CREATED_DESCENDING: u32 = 12;
+
+/// Everything_GetLastError: Everything isn't running
+const ERROR_IPC: u32 = 2;
+
+/// Longest path the SDK returns, in UTF-16 units
+const MAX_PATH_UNITS: usize = 32_768;
+
+/// Seconds between 1601-01-01 (FILETIME epoch) and 1970-01-01
+const FILETIME_UNIX_OFFSET: u64 = 11_644_473_600;
+
+/// Register the backend as "everything", built from `config` when selected
+pub fn register(registry: &mut BackendRegistry, config: &Config) {
+    let dll = config.search.everything_dll.clone();
+    let size_bounds = config.search.size_categories;
+    let max_result_bytes = config.performance.max_result_mb.saturating_mul(1024 * 1024);
+
+    registry.register("everything", move |_| {
+        let backend = EverythingSearchBackend::load(dll.as_deref(), size_bounds, max_result_bytes)?;
+        Ok(Box::new(backend))
+    });
+}
+
+/// Search backend answering from a running Everything instance
+pub struct EverythingSearchBackend {
+    /// The SDK keeps one query state per process, so calls are serialized
+    sdk: Mutex<Sdk>,
+    size_bounds: SizeCategoryBounds,
+    max_result_bytes: usize,
+}
+
+impl EverythingSearchBackend {
+    /// Load the SDK DLL from `dll`, or by name from the default DLL directories
+    pub fn load(dll: Option<&Path>, size_bounds: SizeCategoryBounds, max_result_bytes: usize) -> Result<Self> {
+        let sdk = match dll {
+            Some(path) => Sdk::load(path),
+            None => Sdk::load(Path::new(SDK_DLL)),
+        }?;
+
+        Ok(Self {
+            sdk: Mutex::new(sdk),
+            size_bounds,
+            max_result_bytes,
+        })
+    }
+
+    /// Run one query and read back its results
+    fn query(&self, search: &str, query: &SearchQuery) -> Result<(Vec<IndexedFile>, usize), SearchError> {
+        let sdk = self.sdk.lock().unwrap();
+        let search = to_wide(search);
+
+        let sort = match query.sort {
+            // Everything has no relevance rank; its own default order is by name
+            ResultSort::Rank | ResultSort::Name => SORT_NAME_ASCENDING,
+            ResultSort::Size => SORT_SIZE_DESCENDING,
+            ResultSort::Created => SORT_DATE_CREATED_DESCENDING,
+        };
+
+        let results = unsafe {
+            (sdk.set_search)(search.as_ptr());
+            (sdk.set_match_case)(0);
+            (sdk.set_match_path)(0);
+            (sdk.set_regex)(0);
+            (sdk.set_max)(u32::try_from(query.max_results).unwrap_or(u32::MAX));
+            (sdk.set_offset)(u32::try_from(query.offset).unwrap_or(u32::MAX));
+            (sdk.set_sort)(sort);
+            (sdk.set_request_flags)(
+                REQUEST_FILE_NAME
+                    | REQUEST_FULL_PATH_AND_FILE_NAME
+                    | REQUEST_EXTENSION
+                    | REQUEST_SIZE
+                    | REQUEST_DATE_CREATED
+                    | REQUEST_DATE_MODIFIED,
+            );
+
+            if (sdk.query)(1) == 0 {
+                return Err(match (sdk.get_last_error)() {
+                    ERROR_IPC => SearchError::NotAvailable,
+                    code => SearchError::QueryFailed(format!("Everything query failed (error {})", code)),
+                });
+            }
+
+            let count = (sdk.get_num_results)();
+            let mut buffer = vec![0u16; MAX_PATH_UNITS];
+            (0..count).map(|index| sdk.read_result(index, &mut buffer)).collect::<Vec<_>>()
+        };
+        let total_found = unsafe { (sdk.get_tot_results)() } as usize;
+
+        Ok((results, total_found))
+    }
+}
+
+impl SearchBackend for EverythingSearchBackend {
+    fn is_available(&self) -> bool {
+        // Zero when the IPC call fails, i.e. Everything isn't running
+        let sdk = self.sdk.lock().unwrap();
+        unsafe { (sdk.get_major_version)() != 0 }
+    }
+
+    fn search(&self, query: &SearchQuery) -> Result<SearchResult, SearchError> {
+        let start = std::time::Instant::now();
+
+        let search = everything_search(query, &self.size_bounds)?;
+        let (files, total_found) = self.query(&search, query)?;
+        let (mut files, truncated) = collect_capped(files, self.max_result_bytes);
+
+        if query.verify_existence {
+            verify_existence(&mut files, query.verify_budget);
+        }
+        if query.include_volume {
+            fill_volumes(&mut files);
+        }
+
+        if query.path_encoding == PathEncoding::PercentEncoded {
+            for file in &mut files {
+                file.path = percent_encode_str(&file.path);
+            }
+        }
+
+        if let Some(prefix) = &query.strip_prefix {
+            let prefix = match query.path_encoding {
+                PathEncoding::PercentEncoded => percent_encode_str(prefix),
+                PathEncoding::Utf8Lossy => prefix.clone(),
+            };
+            for file in &mut files {
+                file.strip_path_prefix(&prefix);
+            }
+        }
+
+        Ok(SearchResult {
+            files,
+            total_found,
+            query_time_ms: start.elapsed().as_millis() as u64,
+            backend_name: self.name().to_string(),
+            truncated,
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "Everything"
+    }
+}
+
+/// Everything search string for a query. Terms separated by spaces must all match;
+/// everything user-supplied is quoted, so it matches literally.
+fn everything_search(query: &SearchQuery, size_bounds: &SizeCategoryBounds) -> Result<String, SearchError> {
+    let unsupported = |what: &str| SearchError::QueryFailed(format!("The Everything backend doesn't support {}", what));
+
+    if query.created_after.is_some() || query.created_before.is_some() {
+        return Err(unsupported("creation time filters"));
+    }
+    if query.owner.is_some() || query.world_writable.is_some() {
+        return Err(unsupported("owner or permission filters"));
+    }
+    if query.filter.is_some() {
+        return Err(unsupported("filter expressions"));
+    }
+    if query.restrict_to_paths.is_some() {
+        return Err(unsupported("path lists"));
+    }
+    if query.max_per_directory.is_some() {
+        return Err(unsupported("per-directory result caps"));
+    }
+    if query.resolve_symlinks {
+        return Err(unsupported("symlink resolution"));
+    }
+    // Windows names can't contain these, and Everything would read them as syntax
+    if query.query.contains(['"', '*', '?']) {
+        return Err(unsupported("quotes or wildcards in the query"));
+    }
+
+    let mut terms = Vec::new();
+
+    if !query.query.is_empty() {
+        let text = quoted(&query.query);
+        let name_term = match query.kind {
+            QueryKind::Substring => text,
+            QueryKind::Exact => format!("wfn:{}", text),
+        };
+
+        match query.expansion.as_ref().filter(|_| query.kind == QueryKind::Substring) {
+            Some(expansion) => {
+                let mut alternatives = vec![name_term];
+                if !expansion.extensions.is_empty() {
+                    alternatives.push(extension_term(&expansion.extensions));
+                }
+                alternatives.extend(expansion.names.iter().map(|name| quoted(name)));
+                terms.push(format!("<{}>", alternatives.join(" | ")));
+            }
+            None => terms.push(name_term),
+        }
+    }
+
+    if let Some(extension) = &query.extension {
+        terms.push(extension_term(std::slice::from_ref(extension)));
+    }
+    if let Some(extensions) = &query.extensions {
+        terms.push(extension_term(extensions));
+    }
+
+    if let Some(directories) = &query.directories {
+        let alternatives: Vec<String> = directories.iter().map(|dir| path_term(dir)).collect();
+        if !alternatives.is_empty() {
+            terms.push(format!("<{}>", alternatives.join(" | ")));
+        }
+    }
+    if let Some(drive) = &query.drive {
+        terms.push(path_term(drive));
+    }
+    if let Some(scope) = &query.scope {
+        terms.push(path_term(scope));
+    }
+
+    // A dot at the start of any path component marks the entry hidden, as in the index
+    if query.include_hidden == Some(false) {
+        terms.push("!path:\"\\.\"".to_string());
+    }
+
+    if let Some(category) = query.size_category {
+        let (low, high) = category.size_range(size_bounds);
+        terms.push("file:".to_string());
+        terms.push(format!("size:>={}", low));
+        if let Some(high) = high {
+            terms.push(format!("size:<{}", high));
+        }
+    }
+
+    Ok(terms.join(" "))
+}
+
+fn quoted(text: &str) -> String {
+    format!("\"{}\"", text)
+}
+
+/// `ext:pdf;docx` from extensions with or without their leading dot
+fn extension_term(extensions: &[String]) -> String {
+    let extensions: Vec<&str> = extensions.iter().map(|ext| ext.trim().trim_start_matches('.')).collect();
+    format!("ext:{}", extensions.join(";"))
+}
+
+/// Entries under a directory. The path is matched as a substring, but a drive or
+/// UNC prefix can only occur at the start of a full path, so this anchors it.
+fn path_term(directory: &str) -> String {
+    let directory = directory.trim().replace('/', "\\");
+    format!("path:{}", quoted(&format!("{}\\", directory.trim_end_matches('\\'))))
+}
+
+fn to_wide(text: &str) -> Vec<u16> {
+    text.encode_utf16().chain(std::iter::once(0)).collect()
+}
+
+/// Unix seconds from a FILETIME value; None for the SDK's "unknown" markers
+fn unix_time(filetime: &FILETIME) -> Option<i64> {
+    let ticks = ((filetime.dwHighDateTime as u64) << 32) | filetime.dwLowDateTime as u64;
+    if ticks == 0 || ticks == u64::MAX {
+        return None;
+    }
+    Some((ticks / 10_000_000) as i64 - FILETIME_UNIX_OFFSET as i64)
+}
+
+/// String the SDK returned a pointer to (owned by the SDK until the next query)
+unsafe fn wide_str(ptr: *const u16) -> String {
+    if ptr.is_null() {
+        return String::new();
+    }
+    let mut len = 0;
+    while unsafe { *ptr.add(len) } != 0 {
+        len += 1;
+    }
+    String::from_utf16_lossy(unsafe { std::slice::from_raw_parts(ptr, len) })
+}
+
+type SetStringFn = unsafe extern "system" fn(*const u16);
+type SetFlagFn = unsafe extern "system" fn(u32);
+type QueryFn = unsafe extern "system" fn(i32) -> i32;
+type GetFn = unsafe extern "system" fn() -> u32;
+type ResultFlagFn = unsafe extern "system" fn(u32) -> i32;
+type ResultStringFn = unsafe extern "system" fn(u32) -> *const u16;
+type ResultPathFn = unsafe extern "system" fn(u32, *mut u16, u32) -> u32;
+type ResultSizeFn = unsafe extern "system" fn(u32, *mut i64) -> i32;
+type ResultDateFn = unsafe extern "system" fn(u32, *mut FILETIME) -> i32;
+type CleanUpFn = unsafe extern "system" fn();
+
+/// The SDK functions used, resolved from the loaded DLL
+struct Sdk {
+    module: HMODULE,
+    set_search: SetStringFn,
+    set_match_case: SetFlagFn,
+    set_match_path: SetFlagFn,
+    set_regex: SetFlagFn,
+    set_max: SetFlagFn,
+    set_offset: SetFlagFn,
+    set_sort: SetFlagFn,
+    set_request_flags: SetFlagFn,
+    query: QueryFn,
+    get_last_error: GetFn,
+    get_num_results: GetFn,
+    get_tot_results: GetFn,
+    get_major_version: GetFn,
+    is_folder_result: ResultFlagFn,
+    get_result_file_name: ResultStringFn,
+    get_result_extension: ResultStringFn,
+    get_result_full_path_name: ResultPathFn,
+    get_result_size: ResultSizeFn,
+    get_result_date_modified: ResultDateFn,
+    get_result_date_created: ResultDateFn,
+    clean_up: CleanUpFn,
+}
+
+// The module handle is only used to free the DLL; the SDK is called under a mutex
+unsafe impl Send for Sdk {}
+
+impl Sdk {
+    fn load(path: &Path) -> Result<Self> {
+        let wide: Vec<u16> = path
+            .as_os_str()
+            .to_string_lossy()
+            .encode_utf16()
+            .chain(std::iter::once(0))
+            .collect();
+        // Default directories only (the exe's, System32, added ones), never the
+        // working directory; a full path is loaded as given
+        let module = unsafe { LoadLibraryExW(wide.as_ptr(), std::ptr::null_mut(), LOAD_LIBRARY_SEARCH_DEFAULT_DIRS) };
+        if module.is_null() {
+            bail!(
+                "Failed to load the Everything SDK ({:?}): {}",
+                path,
+                std::io::Error::last_os_error()
+            );
+        }
+
+        let resolve = || -> Result<Self> {
+            unsafe {
+                Ok(Self {
+                    module,
+                    set_search: proc(module, "Everything_SetSearchW\0")?,
+                    set_match_case: proc(module, "Everything_SetMatchCase\0")?,
+                    set_match_path: proc(module, "Everything_SetMatchPath\0")?,
+                    set_regex: proc(module, "Everything_SetRegex\0")?,
+                    set_max: proc(module, "Everything_SetMax\0")?,
+                    set_offset: proc(module, "Everything_SetOffset\0")?,
+                    set_sort: proc(module, "Everything_SetSort\0")?,
+                    set_request_flags: proc(module, "Everything_SetRequestFlags\0")?,
+                    query: proc(module, "Everything_QueryW\0")?,
+                    get_last_error: proc(module, "Everything_GetLastError\0")?,
+                    get_num_results: proc(module, "Everything_GetNumResults\0")?,
+                    get_tot_results: proc(module, "Everything_GetTotResults\0")?,
+                    get_major_version: proc(module, "Everything_GetMajorVersion\0")?,
+                    is_folder_result: proc(module, "Everything_IsFolderResult\0")?,
+                    get_result_file_name: proc(module, "Everything_GetResultFileNameW\0")?,
+                    get_result_extension: proc(module, "Everything_GetResultExtensionW\0")?,
+                    get_result_full_path_name: proc(module, "Everything_GetResultFullPathNameW\0")?,
+                    get_result_size: proc(module, "Everything_GetResultSize\0")?,
+                    get_result_date_modified: proc(module, "Everything_GetResultDateModified\0")?,
+                    get_result_date_created: proc(module, "Everything_GetResultDateCreated\0")?,
+                    clean_up: proc(module, "Everything_CleanUp\0")?,
+                })
+            }
+        };
+
+        resolve()
+            .inspect_err(|_| unsafe {
+                FreeLibrary(module);
+            })
+            .with_context(|| format!("{:?} is not a usable Everything SDK", path))
+    }
+
+    /// Read one result of the last query; `buffer` holds the full path
+    unsafe fn read_result(&self, index: u32, buffer: &mut [u16]) -> IndexedFile {
+        unsafe {
+            let len = (self.get_result_full_path_name)(index, buffer.as_mut_ptr(), buffer.len() as u32) as usize;
+            let path = String::from_utf16_lossy(&buffer[..len.min(buffer.len())]);
+            let is_directory = (self.is_folder_result)(index) != 0;
+
+            let extension = wide_str((self.get_result_extension)(index));
+            let mut size = 0i64;
+            if is_directory || (self.get_result_size)(index, &mut size) == 0 {
+                size = 0;
+            }
+
+            let mut modified = FILETIME { dwLowDateTime: 0, dwHighDateTime: 0 };
+            let mut created = FILETIME { dwLowDateTime: 0, dwHighDateTime: 0 };
+            (self.get_result_date_modified)(index, &mut modified);
+            (self.get_result_date_created)(index, &mut created);
+
+            IndexedFile {
+                id: 0,
+                path,
+                name: wide_str((self.get_result_file_name)(index)),
+                extension: (!is_directory && !extension.is_empty()).then(|| format!(".{}", extension)),
+                size: size.max(0),
+                is_directory,
+                modified: unix_time(&modified).unwrap_or(0),
+                created: unix_time(&created),
+                full_path: None,
+                target: None,
+                exists: None,
+                volume: None,
+            }
+        }
+    }
+}
+
+impl Drop for Sdk {
+    fn drop(&mut self) {
+        unsafe {
+            (self.clean_up)();
+            FreeLibrary(self.module);
+        }
+    }
+}
+
+/// Look up an exported function; `name` must end in a NUL
+unsafe fn proc<T: Copy>(module: HMODULE, name: &str) -> Result<T> {
+    let Some(address) = (unsafe { GetProcAddress(module, name.as_ptr()) }) else {
+        bail!("Missing export {}", name.trim_end_matches('\0'));
+    };
+    // Every T is an `extern "system" fn` pointer, the same size as `address`
+    Ok(unsafe { std::mem::transmute_copy(&address) })
+}
@@ -0,0 +1,62 @@
+//! Abbreviation expansion for search queries
+//!
+//! With `search.expand_queries` on, a query that equals a key of
+//! `search.query_expansions` ("doc", "pic", ...) also matches the key's mapped
+//! extensions and name terms. Entries starting with a dot are extensions (".docx");
+//! anything else is a name substring ("screenshot").
+//!
+//! Expansion only ever adds matches: files whose names contain the query literally
+//! still match. Exact-name searches and empty queries are never expanded, and the
+//! query has to equal the key (ignoring case and surrounding spaces), so "docs" or
+//! "my doc" are searched literally.
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+
+/// Alternatives a query expands to, beside its literal match
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QueryExpansion {
+    /// Extensions, lowercased with a leading dot (".docx")
+    pub extensions: Vec<String>,
+    /// Name substrings
+    pub names: Vec<String>,
+}
+
+/// Expansion of `query` under `expansions`, if its key is mapped
+pub fn expand(query: &str, expansions: &HashMap<String, Vec<String>>) -> Option<QueryExpansion> {
+    let query = query.trim();
+    if query.is_empty() {
+        return None;
+    }
+
+    let (_, targets) = expansions.iter().find(|(key, _)| key.trim().eq_ignore_ascii_case(query))?;
+
+    let mut expansion = QueryExpansion::default();
+    for target in targets.iter().map(|target| target.trim()) {
+        if let Some(ext) = target.strip_prefix('.') {
+            if !ext.is_empty() {
+                expansion.extensions.push(format!(".{}", ext.to_lowercase()));
+            }
+        } else if !target.is_empty() {
+            expansion.names.push(target.to_string());
+        }
+    }
+    Some(expansion)
+}
+
+/// Reject empty keys, keys that clash ignoring case, and keys without targets
+pub fn validate(expansions: &HashMap<String, Vec<String>>) -> Result<()> {
+    for (key, targets) in expansions {
+        if key.trim().is_empty() {
+            anyhow::bail!("Query expansion with an empty key");
+        }
+        if expansions.keys().filter(|other| other.trim().eq_ignore_ascii_case(key.trim())).count() > 1 {
+            anyhow::bail!("Query expansion keys differ only in case: '{}'", key);
+        }
+        if targets.iter().all(|target| target.trim().trim_start_matches('.').is_empty()) {
+            anyhow::bail!("Query expansion '{}' has no extensions or names", key);
+        }
+    }
+    Ok(())
+}
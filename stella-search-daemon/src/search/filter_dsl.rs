@@ -0,0 +1,700 @@
+//! Structured filter expressions for searches
+//!
+//! A small whitelisted grammar for predicates the typed query fields don't cover:
+//!
+//! ```text
+//! size > 10MB AND ext IN (pdf, docx) AND NOT name ~ "draft"
+//! (modified >= 2024-01-01 OR path ~ "/projects/") AND dir = false
+//! ```
+//!
+//! ```text
+//! expr       := and ("OR" and)*
+//! and        := unary ("AND" unary)*
+//! unary      := "NOT" unary | "(" expr ")" | comparison
+//! comparison := field op value | field ["NOT"] "IN" "(" value ("," value)* ")"
+//! op         := "=" | "!=" | "<" | "<=" | ">" | ">=" | "~"
+//! value      := "quoted string" | bare word
+//! ```
+//!
+//! Fields and operators map to fixed SQL fragments and every value becomes a bound
+//! parameter, so user input never reaches the SQL text. Keywords are case-insensitive.
+
+use std::fmt;
+
+use chrono::{Local, NaiveDate, TimeZone};
+use rusqlite::types::Value;
+
+use crate::database::escape_like;
+
+/// Longest accepted expression, in bytes
+const MAX_FILTER_LEN: usize = 4096;
+
+/// Deepest accepted nesting of NOT and parentheses
+const MAX_DEPTH: usize = 32;
+
+/// A compiled filter: a parenthesized WHERE fragment with `?` placeholders
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter {
+    pub sql: String,
+    pub values: Vec<Value>,
+}
+
+/// Why a filter expression was rejected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilterError {
+    /// Byte offset in the expression
+    pub position: usize,
+    pub message: String,
+}
+
+impl fmt::Display for FilterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid filter at position {}: {}", self.position, self.message)
+    }
+}
+
+impl std::error::Error for FilterError {}
+
+/// Parse and compile a filter expression
+pub fn parse(input: &str) -> Result<Filter, FilterError> {
+    if input.len() > MAX_FILTER_LEN {
+        return Err(FilterError {
+            position: MAX_FILTER_LEN,
+            message: format!("filter is longer than {} bytes", MAX_FILTER_LEN),
+        });
+    }
+
+    let tokens = tokenize(input)?;
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        end: input.len(),
+    };
+    let filter = parser.expr(0)?;
+
+    match parser.peek() {
+        None => Ok(filter),
+        Some(token) => Err(parser.error_at(token.position, format!("unexpected {}", token.kind))),
+    }
+}
+
+/// Fields a filter may test, each bound to one column
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Name,
+    Path,
+    Ext,
+    Size,
+    Modified,
+    Created,
+    Indexed,
+    Dir,
+}
+
+impl Field {
+    fn parse(word: &str) -> Option<Self> {
+        match word.to_ascii_lowercase().as_str() {
+            "name" => Some(Field::Name),
+            "path" => Some(Field::Path),
+            "ext" | "extension" => Some(Field::Ext),
+            "size" => Some(Field::Size),
+            "modified" => Some(Field::Modified),
+            "created" => Some(Field::Created),
+            "indexed" | "indexed_at" => Some(Field::Indexed),
+            "dir" | "is_dir" | "is_directory" => Some(Field::Dir),
+            _ => None,
+        }
+    }
+
+    fn column(self) -> &'static str {
+        match self {
+            Field::Name => "name",
+            Field::Path => "path",
+            Field::Ext => "extension",
+            Field::Size => "size",
+            Field::Modified => "modified",
+            Field::Created => "created",
+            // Rows never stamped (imported, or indexed before the column existed) count
+            // as seen at the epoch, so they match "not refreshed since X"
+            Field::Indexed => "COALESCE(indexed_at, 0)",
+            Field::Dir => "is_directory",
+        }
+    }
+
+    /// Text fields compare case-insensitively, like name searches
+    fn is_text(self) -> bool {
+        matches!(self, Field::Name | Field::Path | Field::Ext)
+    }
+}
+
+/// Comparison operators, each with a fixed SQL spelling
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Contains,
+}
+
+impl Op {
+    fn sql(self) -> &'static str {
+        match self {
+            Op::Eq => "=",
+            Op::Ne => "!=",
+            Op::Lt => "<",
+            Op::Le => "<=",
+            Op::Gt => ">",
+            Op::Ge => ">=",
+            Op::Contains => "LIKE",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    /// Bare word: field, keyword or unquoted value
+    Word(String),
+    /// Quoted string value
+    Str(String),
+    Op(Op),
+    LParen,
+    RParen,
+    Comma,
+}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenKind::Word(word) => write!(f, "'{}'", word),
+            TokenKind::Str(_) => write!(f, "string"),
+            TokenKind::Op(op) => write!(f, "'{}'", if *op == Op::Contains { "~" } else { op.sql() }),
+            TokenKind::LParen => write!(f, "'('"),
+            TokenKind::RParen => write!(f, "')'"),
+            TokenKind::Comma => write!(f, "','"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    position: usize,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, FilterError> {
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+
+    while let Some(&(position, c)) = chars.peek() {
+        let kind = match c {
+            c if c.is_whitespace() => {
+                chars.next();
+                continue;
+            }
+            '(' | ')' | ',' | '~' | '=' => {
+                chars.next();
+                match c {
+                    '(' => TokenKind::LParen,
+                    ')' => TokenKind::RParen,
+                    ',' => TokenKind::Comma,
+                    '~' => TokenKind::Op(Op::Contains),
+                    _ => TokenKind::Op(Op::Eq),
+                }
+            }
+            '!' | '<' | '>' => {
+                chars.next();
+                let with_eq = chars.next_if(|&(_, next)| next == '=').is_some();
+                match (c, with_eq) {
+                    ('!', true) => TokenKind::Op(Op::Ne),
+                    ('<', false) => TokenKind::Op(Op::Lt),
+                    ('<', true) => TokenKind::Op(Op::Le),
+                    ('>', false) => TokenKind::Op(Op::Gt),
+                    ('>', true) => TokenKind::Op(Op::Ge),
+                    _ => {
+                        return Err(FilterError {
+                            position,
+                            message: "expected '!='".to_string(),
+                        });
+                    }
+                }
+            }
+            '"' | '\'' => {
+                chars.next();
+                TokenKind::Str(quoted(&mut chars, c, position)?)
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some((_, c)) = chars.next_if(|&(_, c)| is_word_char(c)) {
+                    word.push(c);
+                }
+                if word.is_empty() {
+                    return Err(FilterError {
+                        position,
+                        message: format!("unexpected character '{}'", c),
+                    });
+                }
+                TokenKind::Word(word)
+            }
+        };
+        tokens.push(Token { kind, position });
+    }
+
+    Ok(tokens)
+}
+
+/// Characters of bare words: enough for numbers with units, dates and extensions
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '.' | '-' | ':' | '+')
+}
+
+/// Read a quoted string after its opening quote; `\` escapes the next character
+fn quoted(
+    chars: &mut std::iter::Peekable<std::str::CharIndices<'_>>,
+    quote: char,
+    start: usize,
+) -> Result<String, FilterError> {
+    let mut value = String::new();
+    while let Some((_, c)) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some((_, escaped)) => value.push(escaped),
+                None => break,
+            },
+            c if c == quote => return Ok(value),
+            c => value.push(c),
+        }
+    }
+    Err(FilterError {
+        position: start,
+        message: "unterminated string".to_string(),
+    })
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    /// Input length, reported for errors at the end of input
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn error_at(&self, position: usize, message: impl Into<String>) -> FilterError {
+        FilterError {
+            position,
+            message: message.into(),
+        }
+    }
+
+    /// Error at the current token, or at the end of input
+    fn error_here(&self, message: impl Into<String>) -> FilterError {
+        let position = self.peek().map_or(self.end, |token| token.position);
+        self.error_at(position, message)
+    }
+
+    /// Consume the next token if it is the given keyword
+    fn keyword(&mut self, keyword: &str) -> bool {
+        let matched = matches!(
+            self.peek(),
+            Some(Token { kind: TokenKind::Word(word), .. }) if word.eq_ignore_ascii_case(keyword)
+        );
+        if matched {
+            self.pos += 1;
+        }
+        matched
+    }
+
+    fn expect(&mut self, kind: TokenKind) -> Result<(), FilterError> {
+        match self.peek() {
+            Some(token) if token.kind == kind => {
+                self.pos += 1;
+                Ok(())
+            }
+            Some(token) => Err(self.error_at(token.position, format!("expected {}, found {}", kind, token.kind))),
+            None => Err(self.error_here(format!("expected {}", kind))),
+        }
+    }
+
+    fn expr(&mut self, depth: usize) -> Result<Filter, FilterError> {
+        let mut parts = vec![self.and(depth)?];
+        while self.keyword("OR") {
+            parts.push(self.and(depth)?);
+        }
+        Ok(join(parts, " OR "))
+    }
+
+    fn and(&mut self, depth: usize) -> Result<Filter, FilterError> {
+        let mut parts = vec![self.unary(depth)?];
+        while self.keyword("AND") {
+            parts.push(self.unary(depth)?);
+        }
+        Ok(join(parts, " AND "))
+    }
+
+    fn unary(&mut self, depth: usize) -> Result<Filter, FilterError> {
+        if depth >= MAX_DEPTH {
+            return Err(self.error_here("filter is nested too deeply"));
+        }
+
+        if self.keyword("NOT") {
+            let inner = self.unary(depth + 1)?;
+            return Ok(Filter {
+                sql: format!("(NOT {})", inner.sql),
+                values: inner.values,
+            });
+        }
+
+        if matches!(self.peek(), Some(Token { kind: TokenKind::LParen, .. })) {
+            self.pos += 1;
+            let inner = self.expr(depth + 1)?;
+            self.expect(TokenKind::RParen)?;
+            return Ok(inner);
+        }
+
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Filter, FilterError> {
+        let field = match self.next() {
+            Some(Token { kind: TokenKind::Word(word), position }) => Field::parse(&word).ok_or_else(|| {
+                self.error_at(
+                    position,
+                    format!(
+                        "unknown field '{}' (expected name, path, ext, size, modified, created, indexed or dir)",
+                        word
+                    ),
+                )
+            })?,
+            Some(token) => return Err(self.error_at(token.position, format!("expected a field, found {}", token.kind))),
+            None => return Err(self.error_here("expected a field")),
+        };
+
+        let negated = self.keyword("NOT");
+        if self.keyword("IN") {
+            return self.in_list(field, negated);
+        }
+        if negated {
+            return Err(self.error_here("expected IN after NOT"));
+        }
+
+        let op_position = self.peek().map_or(self.end, |token| token.position);
+        let op = match self.next() {
+            Some(Token { kind: TokenKind::Op(op), .. }) => op,
+            _ => return Err(self.error_at(op_position, "expected an operator (=, !=, <, <=, >, >=, ~ or IN)")),
+        };
+
+        let value_position = self.peek().map_or(self.end, |token| token.position);
+        let raw = self.value()?;
+        let value = convert(field, op, &raw).map_err(|message| self.error_at(value_position, message))?;
+
+        let sql = if op == Op::Contains {
+            format!("({} LIKE ? ESCAPE '\\')", field.column())
+        } else if field.is_text() {
+            format!("({} {} ? COLLATE NOCASE)", field.column(), op.sql())
+        } else {
+            format!("({} {} ?)", field.column(), op.sql())
+        };
+
+        Ok(Filter {
+            sql,
+            values: vec![value],
+        })
+    }
+
+    fn in_list(&mut self, field: Field, negated: bool) -> Result<Filter, FilterError> {
+        self.expect(TokenKind::LParen)?;
+
+        let mut values = Vec::new();
+        loop {
+            let position = self.peek().map_or(self.end, |token| token.position);
+            let raw = self.value()?;
+            values.push(convert(field, Op::Eq, &raw).map_err(|message| self.error_at(position, message))?);
+
+            if matches!(self.peek(), Some(Token { kind: TokenKind::Comma, .. })) {
+                self.pos += 1;
+                continue;
+            }
+            self.expect(TokenKind::RParen)?;
+            break;
+        }
+
+        let placeholders = vec!["?"; values.len()].join(", ");
+        let collate = if field.is_text() { " COLLATE NOCASE" } else { "" };
+        let not = if negated { "NOT " } else { "" };
+
+        Ok(Filter {
+            sql: format!("({}{} {}IN ({}))", field.column(), collate, not, placeholders),
+            values,
+        })
+    }
+
+    /// A value: quoted string or bare word
+    fn value(&mut self) -> Result<String, FilterError> {
+        match self.next() {
+            Some(Token { kind: TokenKind::Str(value), .. }) | Some(Token { kind: TokenKind::Word(value), .. }) => Ok(value),
+            Some(token) => Err(self.error_at(token.position, format!("expected a value, found {}", token.kind))),
+            None => Err(self.error_here("expected a value")),
+        }
+    }
+}
+
+/// Combine fragments with AND/OR, keeping their placeholder order
+fn join(parts: Vec<Filter>, separator: &str) -> Filter {
+    if parts.len() == 1 {
+        return parts.into_iter().next().unwrap();
+    }
+
+    let mut sql = Vec::with_capacity(parts.len());
+    let mut values = Vec::new();
+    for part in parts {
+        sql.push(part.sql);
+        values.extend(part.values);
+    }
+    Filter {
+        sql: format!("({})", sql.join(separator)),
+        values,
+    }
+}
+
+/// Turn a raw value into the bound parameter for a field and operator
+fn convert(field: Field, op: Op, raw: &str) -> Result<Value, String> {
+    if op == Op::Contains {
+        if !matches!(field, Field::Name | Field::Path) {
+            return Err("'~' only applies to name and path".to_string());
+        }
+        return Ok(Value::Text(format!("%{}%", escape_like(raw))));
+    }
+
+    match field {
+        Field::Name | Field::Path => Ok(Value::Text(raw.to_string())),
+        Field::Ext => {
+            if !matches!(op, Op::Eq | Op::Ne) {
+                return Err("ext only supports =, != and IN".to_string());
+            }
+            // Extensions are stored with their dot: "pdf" and ".pdf" are the same filter
+            Ok(Value::Text(format!(".{}", raw.trim_start_matches('.'))))
+        }
+        Field::Size => parse_size(raw).map(Value::Integer),
+        Field::Modified | Field::Created | Field::Indexed => parse_time(raw).map(Value::Integer),
+        Field::Dir => {
+            if !matches!(op, Op::Eq | Op::Ne) {
+                return Err("dir only supports = and !=".to_string());
+            }
+            match raw.to_ascii_lowercase().as_str() {
+                "true" | "yes" | "1" => Ok(Value::Integer(1)),
+                "false" | "no" | "0" => Ok(Value::Integer(0)),
+                _ => Err(format!("expected true or false, found '{}'", raw)),
+            }
+        }
+    }
+}
+
+/// Size in bytes with an optional binary unit: `1500`, `10KB`, `2.5M`, `1gb`
+fn parse_size(raw: &str) -> Result<i64, String> {
+    let lower = raw.to_ascii_lowercase();
+    let split = lower
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(lower.len());
+    let (number, unit) = lower.split_at(split);
+
+    let multiplier: f64 = match unit {
+        "" | "b" => 1.0,
+        "k" | "kb" => 1024.0,
+        "m" | "mb" => 1024.0 * 1024.0,
+        "g" | "gb" => 1024.0 * 1024.0 * 1024.0,
+        "t" | "tb" => 1024.0 * 1024.0 * 1024.0 * 1024.0,
+        _ => return Err(format!("unknown size unit in '{}'", raw)),
+    };
+    let number: f64 = number.parse().map_err(|_| format!("expected a size, found '{}'", raw))?;
+
+    let bytes = number * multiplier;
+    if !bytes.is_finite() || bytes > i64::MAX as f64 {
+        return Err(format!("size '{}' is too large", raw));
+    }
+    Ok(bytes as i64)
+}
+
+/// Unix seconds, or a `YYYY-MM-DD` date taken as local midnight
+pub fn parse_time(raw: &str) -> Result<i64, String> {
+    if let Ok(secs) = raw.parse::<i64>() {
+        return Ok(secs);
+    }
+
+    let date = NaiveDate::parse_from_str(raw, "%Y-%m-%d")
+        .map_err(|_| format!("expected a date (YYYY-MM-DD) or Unix seconds, found '{}'", raw))?;
+    let midnight = date.and_hms_opt(0, 0, 0).expect("midnight is a valid time");
+    Local
+        .from_local_datetime(&midnight)
+        .earliest()
+        .map(|time| time.timestamp())
+        .ok_or_else(|| format!("'{}' has no local midnight", raw))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(value: &str) -> Value {
+        Value::Text(value.to_string())
+    }
+
+    #[test]
+    fn quotes_and_comment_payloads_stay_bound_parameters() {
+        let payloads = [
+            "x'; DROP TABLE files; --",
+            "x\" OR 1=1 --",
+            "'); DELETE FROM files WHERE ('1'='1",
+            "a /* b */ c",
+        ];
+        for payload in payloads {
+            let quoted = format!("\"{}\"", payload.replace('\\', "\\\\").replace('"', "\\\""));
+
+            let filter = parse(&format!("name = {}", quoted)).unwrap();
+            assert_eq!(filter.sql, "(name = ? COLLATE NOCASE)");
+            assert_eq!(filter.values, [text(payload)]);
+
+            let filter = parse(&format!("path ~ {}", quoted)).unwrap();
+            assert_eq!(filter.sql, "(path LIKE ? ESCAPE '\\')");
+            assert_eq!(filter.values, [text(&format!("%{}%", escape_like(payload)))]);
+
+            let filter = parse(&format!("name NOT IN ({}, safe)", quoted)).unwrap();
+            assert_eq!(filter.sql, "(name COLLATE NOCASE NOT IN (?, ?))");
+            assert_eq!(filter.values, [text(payload), text("safe")]);
+        }
+
+        // Outside quotes the SQL punctuation isn't part of the grammar at all
+        assert!(parse("name = x; DROP TABLE files").is_err());
+        assert!(parse("name = x' OR '1'='1").is_err());
+        assert_eq!(parse("name = a--b").unwrap().values, [text("a--b")]);
+    }
+
+    #[test]
+    fn payloads_match_literally_in_a_search() {
+        use crate::search::SearchQuery;
+        use crate::test_support::{seed, test_config, test_database};
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let db = test_database(&test_config(&dir));
+        seed(&db, &["/data/x'; DROP TABLE files; --.txt", "/data/report.txt"]);
+
+        let filter = parse(r#"name = "x'; DROP TABLE files; --.txt""#).unwrap();
+        let results = db.search(&SearchQuery::new("", 10).with_filter(filter)).unwrap();
+        assert_eq!(results.total_found, 1);
+
+        let filter = parse(r#"name ~ "' OR 1=1 --""#).unwrap();
+        let results = db.search(&SearchQuery::new("", 10).with_filter(filter)).unwrap();
+        assert_eq!(results.total_found, 0);
+
+        let results = db.search(&SearchQuery::new("report", 10)).unwrap();
+        assert_eq!(results.total_found, 1, "the files table is intact");
+    }
+
+    #[test]
+    fn nesting_is_limited_to_max_depth() {
+        let nested = |depth: usize| format!("{}size > 1{}", "(".repeat(depth), ")".repeat(depth));
+        assert!(parse(&nested(MAX_DEPTH - 1)).is_ok());
+        let error = parse(&nested(MAX_DEPTH)).unwrap_err();
+        assert!(error.message.contains("nested too deeply"), "{}", error);
+
+        let negated = |depth: usize| format!("{}dir = true", "NOT ".repeat(depth));
+        assert!(parse(&negated(MAX_DEPTH - 1)).is_ok());
+        assert!(parse(&negated(MAX_DEPTH)).is_err());
+
+        // Far past the limit fails the same way rather than overflowing the stack
+        assert!(parse(&nested(1_000)).is_err());
+    }
+
+    #[test]
+    fn length_is_limited_to_max_filter_len() {
+        let padded = |len: usize| format!("name = \"{}\"", "a".repeat(len - "name = \"\"".len()));
+        assert_eq!(padded(MAX_FILTER_LEN).len(), MAX_FILTER_LEN);
+        assert!(parse(&padded(MAX_FILTER_LEN)).is_ok());
+
+        let error = parse(&padded(MAX_FILTER_LEN + 1)).unwrap_err();
+        assert_eq!(error.position, MAX_FILTER_LEN);
+        assert!(error.message.contains("longer than"), "{}", error);
+    }
+
+    #[test]
+    fn not_binds_tighter_than_and_and_and_tighter_than_or() {
+        let filter = parse("name = a OR name = b AND dir = false").unwrap();
+        assert_eq!(
+            filter.sql,
+            "((name = ? COLLATE NOCASE) OR ((name = ? COLLATE NOCASE) AND (is_directory = ?)))"
+        );
+        assert_eq!(filter.values, [text("a"), text("b"), Value::Integer(0)]);
+
+        let filter = parse("NOT dir = true AND size > 1KB").unwrap();
+        assert_eq!(filter.sql, "((NOT (is_directory = ?)) AND (size > ?))");
+        assert_eq!(filter.values, [Value::Integer(1), Value::Integer(1024)]);
+
+        let filter = parse("(name = a OR name = b) and DIR = 0").unwrap();
+        assert_eq!(
+            filter.sql,
+            "(((name = ? COLLATE NOCASE) OR (name = ? COLLATE NOCASE)) AND (is_directory = ?))"
+        );
+    }
+
+    #[test]
+    fn malformed_filters_are_errors() {
+        let malformed = [
+            "",
+            "   ",
+            "(",
+            ")",
+            "()",
+            "name",
+            "name =",
+            "name = a b",
+            "name = a AND",
+            "OR name = a",
+            "NOT",
+            "name ! a",
+            "name NOT = a",
+            "name IN",
+            "name IN ()",
+            "name IN (a,",
+            "name IN (a b)",
+            "(name = a",
+            "name = a)",
+            "name = \"unterminated",
+            "name = 'unterminated\\",
+            "owner = bob",
+            "size > huge",
+            "size > 10XB",
+            "size > 1e400",
+            "modified > 2024-13-45",
+            "ext > pdf",
+            "dir = maybe",
+            "dir > true",
+            "size ~ 10",
+            "name = #",
+            "name == a",
+        ];
+        for input in malformed {
+            let error = parse(input).expect_err(input);
+            assert!(error.position <= input.len(), "{:?}: {}", input, error);
+        }
+    }
+
+    #[test]
+    fn every_prefix_of_a_filter_parses_or_fails_cleanly() {
+        let input = r#"(size >= 1.5MB OR ext IN (pdf, "docx")) AND NOT name ~ "dräft \"v2\"" AND modified < 2024-01-01"#;
+        assert!(parse(input).is_ok());
+        for (end, _) in input.char_indices() {
+            let _ = parse(&input[..end]);
+        }
+    }
+}
@@ -1,64 +1,291 @@
-//! Search manager for SQLite backend
+//! Search manager with automatic fallback
 //!
-//! The daemon only provides SQLite search.
-//! Windows Search is handled by the native DLL (stella-search-native).
+//! The daemon's own backend is SQLite. Windows Search is handled by the
+//! native DLL (stella-search-native), but the manager keeps the primary/fallback
+//! arrangement so a primary backend can fall back to the local index. Other
+//! backends are picked by name from a `BackendRegistry`. With
+//! `search.union_with_local` set, searches on a primary other than SQLite also
+//! read the local index and merge the two result sets.
 
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
+use anyhow::Result;
+use stella_search_core::TopK;
+use tokio::sync::{Semaphore, SemaphorePermit};
 use tracing::{debug, info, warn};
 
-use crate::config::SearchBackendType;
+use crate::config::{PerformanceConfig, SearchBackendType, SearchConfig};
 use crate::database::Database;
 use super::{SearchBackend, SearchError, SearchQuery, SearchResult};
+use super::registry::BackendRegistry;
 use super::sqlite_search::SqliteSearchBackend;
 
-/// Search manager that handles SQLite search backend
+/// Search manager that handles backend selection and fallback
 pub struct SearchManager {
-    /// SQLite search backend
-    backend: SqliteSearchBackend,
+    /// Primary search backend
+    primary: Box<dyn SearchBackend>,
+    /// Fallback search backend (always SQLite)
+    fallback: Option<SqliteSearchBackend>,
+    /// Whether we're currently using fallback
+    using_fallback: AtomicBool,
+    /// Also search the fallback while the primary is active, and merge the results
+    union_with_local: bool,
+    /// Size cap for merged results
+    max_result_bytes: usize,
     /// Reference to database
     db: Arc<Database>,
+    /// Number of searches currently running
+    active_searches: AtomicUsize,
+    /// One permit per concurrent search (`None` = unlimited)
+    search_slots: Option<Semaphore>,
+    /// How long a search may wait for a free slot
+    queue_timeout: Duration,
+}
+
+/// Holds a search slot for the duration of one search
+struct SearchSlot<'a> {
+    manager: &'a SearchManager,
+    _permit: Option<SemaphorePermit<'a>>,
+}
+
+impl Drop for SearchSlot<'_> {
+    fn drop(&mut self) {
+        self.manager.active_searches.fetch_sub(1, Ordering::AcqRel);
+    }
 }
 
 impl SearchManager {
-    /// Create a new search manager
-    pub fn new(_backend_type: SearchBackendType, db: Arc<Database>) -> Self {
-        info!("Using SQLite as search backend (daemon mode)");
-        let backend = SqliteSearchBackend::new(db.clone());
+    /// Create a search manager for the configured backend. A registered backend
+    /// becomes the primary with SQLite as fallback; one that fails to build, or a
+    /// name nothing is registered under, is replaced by SQLite.
+    pub fn new(
+        search: &SearchConfig,
+        registry: &BackendRegistry,
+        db: Arc<Database>,
+        performance: &PerformanceConfig,
+    ) -> Result<Self> {
+        let backend_type = &search.backend;
+        let sqlite = || Box::new(SqliteSearchBackend::new(db.clone())) as Box<dyn SearchBackend>;
+
+        let (primary, fallback) = match backend_type.registry_name() {
+            Some(name) => match registry.create(name, &db) {
+                Some(Ok(backend)) => {
+                    info!("Using {} as search backend, SQLite as fallback", backend.name());
+                    (backend, Some(SqliteSearchBackend::new(db.clone())))
+                }
+                Some(Err(e)) => {
+                    warn!("Search backend '{}' is not usable here, using SQLite: {:#}", name, e);
+                    (sqlite(), None)
+                }
+                // Windows Search is served by the native library, not the daemon
+                None if *backend_type == SearchBackendType::Windows => {
+                    info!("Using SQLite as search backend (daemon mode)");
+                    (sqlite(), None)
+                }
+                None if *backend_type == SearchBackendType::Everything => {
+                    warn!("The Everything backend is only available on Windows, using SQLite");
+                    (sqlite(), None)
+                }
+                None if *backend_type == SearchBackendType::Spotlight => {
+                    warn!("The Spotlight backend is only available on macOS, using SQLite");
+                    (sqlite(), None)
+                }
+                None => {
+                    warn!(
+                        "Unknown search backend '{}' (available: auto, windows, {}), using SQLite",
+                        name,
+                        registry.names().join(", ")
+                    );
+                    (sqlite(), None)
+                }
+            },
+            None => {
+                info!("Using SQLite as search backend (daemon mode)");
+                (sqlite(), None)
+            }
+        };
+
+        Ok(Self {
+            primary,
+            fallback,
+            using_fallback: AtomicBool::new(false),
+            union_with_local: search.union_with_local,
+            max_result_bytes: performance.max_result_mb.saturating_mul(1024 * 1024),
+            db,
+            active_searches: AtomicUsize::new(0),
+            search_slots: (performance.max_concurrent_searches > 0)
+                .then(|| Semaphore::new(performance.max_concurrent_searches)),
+            queue_timeout: Duration::from_millis(performance.search_queue_timeout_ms),
+        })
+    }
+
+    /// Perform a search, with automatic fallback if primary fails.
+    /// Fails with `QueryFailed("busy")` when no search slot frees up in time.
+    pub async fn search(&self, query: &SearchQuery) -> Result<SearchResult, SearchError> {
+        let Some(_slot) = self.acquire_slot().await else {
+            debug!(
+                "Rejecting search, {} searches already in flight",
+                self.active_searches()
+            );
+            return Err(SearchError::QueryFailed("busy".to_string()));
+        };
+
+        // If already using fallback, go straight to it
+        if self.is_using_fallback() {
+            return Ok(self.search_with_fallback(query));
+        }
+
+        if self.merges_local() {
+            return Ok(self.search_with_union(query));
+        }
+
+        Ok(self.search_with_primary(query))
+    }
+
+    /// Whether searches on the primary are merged with the local index
+    fn merges_local(&self) -> bool {
+        self.union_with_local && self.fallback.is_some()
+    }
 
-        Self { backend, db }
+    /// Number of searches currently in flight
+    pub fn active_searches(&self) -> usize {
+        self.active_searches.load(Ordering::Acquire)
     }
 
-    /// Perform a search
-    pub fn search(&self, query: &SearchQuery) -> SearchResult {
-        match self.backend.search(query) {
+    /// Take a search slot, waiting up to the queue timeout if all are in use.
+    /// The wait yields to the runtime, so the searches holding slots can finish.
+    async fn acquire_slot(&self) -> Option<SearchSlot<'_>> {
+        let permit = match &self.search_slots {
+            Some(slots) => Some(tokio::time::timeout(self.queue_timeout, slots.acquire()).await.ok()?.ok()?),
+            None => None,
+        };
+        self.active_searches.fetch_add(1, Ordering::AcqRel);
+        Some(SearchSlot { manager: self, _permit: permit })
+    }
+
+    /// Search using the primary backend, falling back on failure
+    fn search_with_primary(&self, query: &SearchQuery) -> SearchResult {
+        match self.primary.search(query) {
             Ok(result) => {
                 debug!(
-                    "SQLite search returned {} results in {}ms",
-                    result.total_found, result.query_time_ms
+                    backend = %result.backend_name,
+                    query = %query.query,
+                    results = result.total_found,
+                    duration_ms = result.query_time_ms,
+                    "Search completed on primary backend"
                 );
                 result
             }
+            Err(SearchError::NotAvailable) if self.fallback.is_some() => {
+                warn!(
+                    "Primary backend ({}) not available, switching to fallback",
+                    self.primary.name()
+                );
+                self.using_fallback.store(true, Ordering::Relaxed);
+                self.search_with_fallback(query)
+            }
             Err(e) => {
-                warn!("SQLite search failed: {}", e);
-                SearchResult {
-                    files: Vec::new(),
-                    total_found: 0,
-                    query_time_ms: 0,
-                    backend_name: "SQLite".to_string(),
-                }
+                warn!(backend = self.primary.name(), query = %query.query, error = %e, "Search failed on primary backend");
+                self.search_with_fallback(query)
+            }
+        }
+    }
+
+    /// Search the primary and the local index, and merge the results. Both are asked
+    /// for everything up to the end of the requested page, so the merged page is
+    /// cut from the combined list rather than from two separately paged ones.
+    fn search_with_union(&self, query: &SearchQuery) -> SearchResult {
+        let mut window = query.clone();
+        window.offset = 0;
+        window.max_results = query.offset.saturating_add(query.max_results);
+
+        let primary = match self.primary.search(&window) {
+            Ok(result) => result,
+            Err(SearchError::NotAvailable) => {
+                warn!(
+                    "Primary backend ({}) not available, switching to fallback",
+                    self.primary.name()
+                );
+                self.using_fallback.store(true, Ordering::Relaxed);
+                return self.search_with_fallback(query);
+            }
+            Err(e) => {
+                warn!(backend = self.primary.name(), query = %query.query, error = %e, "Search failed on primary backend");
+                return self.search_with_fallback(query);
+            }
+        };
+        let local = self.search_with_fallback(&window);
+
+        let result = merge_results(primary, local, query, self.max_result_bytes);
+        debug!(
+            backend = %result.backend_name,
+            query = %query.query,
+            results = result.total_found,
+            duration_ms = result.query_time_ms,
+            "Search completed on primary and local index"
+        );
+        result
+    }
+
+    /// Search using fallback backend
+    fn search_with_fallback(&self, query: &SearchQuery) -> SearchResult {
+        let Some(fallback) = &self.fallback else {
+            return empty_result(self.primary.name());
+        };
+
+        match fallback.search(query) {
+            Ok(result) => {
+                debug!(
+                    backend = %result.backend_name,
+                    query = %query.query,
+                    results = result.total_found,
+                    duration_ms = result.query_time_ms,
+                    "Search completed on fallback backend"
+                );
+                result
+            }
+            Err(e) => {
+                warn!(query = %query.query, error = %e, "Search failed on fallback backend");
+                empty_result("none")
             }
         }
     }
 
-    /// Get the name of the active backend
+    /// Check if we're currently using the fallback backend
+    pub fn is_using_fallback(&self) -> bool {
+        self.using_fallback.load(Ordering::Relaxed)
+    }
+
+    /// Get the name of the currently active backend
     pub fn active_backend_name(&self) -> &'static str {
-        "SQLite"
+        match &self.fallback {
+            Some(fallback) if self.is_using_fallback() => fallback.name(),
+            _ => self.primary.name(),
+        }
+    }
+
+    /// Get the name of the primary backend
+    pub fn primary_backend_name(&self) -> &'static str {
+        self.primary.name()
+    }
+
+    /// Get the name of the fallback backend, if one is configured
+    pub fn fallback_backend_name(&self) -> Option<&'static str> {
+        self.fallback.as_ref().map(|f| f.name())
+    }
+
+    /// Check if the primary backend is currently available
+    pub fn is_primary_available(&self) -> bool {
+        self.primary.is_available()
     }
 
-    /// Check if indexing is needed (always true for SQLite daemon)
+    /// Check if indexing is needed: false only while the primary backend keeps
+    /// its own index and is available to answer from it, and isn't merged with ours
     pub fn needs_indexing(&self) -> bool {
-        true
+        self.primary.uses_local_index() || !self.primary.is_available() || self.merges_local()
     }
 
     /// Get reference to the database
@@ -66,13 +293,424 @@ impl SearchManager {
         &self.db
     }
 
-    /// Get status description
+    /// Get status description of all backends
     pub fn status_description(&self) -> String {
-        self.backend.status_description()
+        let primary_status = self.primary.status_description();
+
+        match &self.fallback {
+            Some(fallback) => {
+                let active = if self.is_using_fallback() {
+                    "fallback"
+                } else if self.merges_local() {
+                    "primary + fallback"
+                } else {
+                    "primary"
+                };
+                format!(
+                    "Primary: {}, Fallback: {}, Active: {}",
+                    primary_status,
+                    fallback.status_description(),
+                    active
+                )
+            }
+            None => format!("Backend: {}", primary_status),
+        }
+    }
+
+    /// Re-check primary availability and switch back to it if it has recovered.
+    /// Returns true if the manager switched from fallback to primary.
+    pub fn refresh_availability(&self) -> bool {
+        if self.is_using_fallback() && self.primary.is_available() {
+            info!("Primary backend ({}) became available, switching back", self.primary.name());
+            self.using_fallback.store(false, Ordering::Relaxed);
+            return true;
+        }
+        false
+    }
+
+    /// Re-check the primary backend and switch in whichever direction its availability
+    /// changed: back to primary once it recovers, or to fallback once it goes away.
+    /// Returns true if the active backend changed.
+    pub fn reprobe(&self) -> bool {
+        if self.is_using_fallback() {
+            return self.refresh_availability();
+        }
+
+        if self.fallback.is_some() && !self.primary.is_available() {
+            warn!(
+                "Primary backend ({}) became unavailable, switching to fallback",
+                self.primary.name()
+            );
+            self.using_fallback.store(true, Ordering::Relaxed);
+            return true;
+        }
+        false
+    }
+
+    /// Reprobe every `interval` until the daemon exits, so a restarted primary
+    /// (e.g. the WSearch service) is picked up again without a daemon restart.
+    /// A zero interval means reprobing only on request (`backend --refresh`), and
+    /// returns at once.
+    pub async fn run_reprobe(&self, interval: Duration) {
+        if interval.is_zero() {
+            return;
+        }
+
+        let mut ticker = tokio::time::interval(interval);
+        // The first tick completes immediately; startup already probed
+        ticker.tick().await;
+
+        loop {
+            ticker.tick().await;
+            if self.reprobe() {
+                info!("Active search backend is now {}", self.active_backend_name());
+            }
+        }
+    }
+}
+
+/// Merge a page of results from the primary with one from the local index. A file
+/// both returned is kept once, in the primary's version; paths are compared with
+/// `/` for `\` and without case, since the two indexes may spell them differently.
+/// Files keep the query's sort order, with the primary's rows ahead on ties (and so
+/// throughout for rank order).
+fn merge_results(
+    primary: SearchResult,
+    local: SearchResult,
+    query: &SearchQuery,
+    max_result_bytes: usize,
+) -> SearchResult {
+    let mut seen = HashSet::new();
+    let mut top = TopK::new(query.sort, query.offset.saturating_add(query.max_results), max_result_bytes);
+    let mut unique = 0;
+    for file in primary.files.into_iter().chain(local.files) {
+        if seen.insert(dedup_key(&file.path)) {
+            unique += 1;
+            top.push(file);
+        }
+    }
+
+    let (files, capped) = top.into_sorted();
+    let files = files.into_iter().skip(query.offset).collect();
+
+    SearchResult {
+        files,
+        // The overlap past the fetched window isn't known, so this is a lower bound
+        total_found: primary.total_found.max(local.total_found).max(unique),
+        query_time_ms: primary.query_time_ms + local.query_time_ms,
+        backend_name: format!("{}+{}", primary.backend_name, local.backend_name),
+        truncated: primary.truncated || local.truncated || capped,
+    }
+}
+
+/// Path as compared when merging: forward slashes, no trailing slash, lowercase
+fn dedup_key(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    path.trim_end_matches('/').to_lowercase()
+}
+
+/// Empty result returned when every backend failed
+fn empty_result(backend_name: &str) -> SearchResult {
+    SearchResult {
+        files: Vec::new(),
+        total_found: 0,
+        query_time_ms: 0,
+        backend_name: backend_name.to_string(),
+        truncated: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicBool;
+
+    use stella_search_core::{IndexedFile, ResultSort, DEFAULT_MAX_RESULT_BYTES};
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::config::Config;
+    use crate::test_support::{seed, test_config, test_database};
+
+    /// Primary backend answering with fixed `files` while `available` is set
+    struct StubBackend {
+        available: Arc<AtomicBool>,
+        files: Vec<IndexedFile>,
+    }
+
+    impl SearchBackend for StubBackend {
+        fn is_available(&self) -> bool {
+            self.available.load(Ordering::SeqCst)
+        }
+
+        fn search(&self, _query: &SearchQuery) -> Result<SearchResult, SearchError> {
+            if !self.is_available() {
+                return Err(SearchError::NotAvailable);
+            }
+            Ok(SearchResult {
+                files: self.files.clone(),
+                total_found: self.files.len(),
+                query_time_ms: 0,
+                backend_name: "Stub".to_string(),
+                truncated: false,
+            })
+        }
+
+        fn name(&self) -> &'static str {
+            "Stub"
+        }
+    }
+
+    /// Manager with a `StubBackend` registered as "stub" for its primary, and SQLite
+    /// over a database seeded with `local` as its fallback
+    fn stub_manager(
+        available: &Arc<AtomicBool>,
+        files: &[IndexedFile],
+        local: &[&str],
+        configure: impl FnOnce(&mut Config),
+    ) -> (SearchManager, TempDir) {
+        let dir = TempDir::new().unwrap();
+        let mut config = test_config(&dir);
+        config.search.backend = SearchBackendType::Custom("stub".to_string());
+        configure(&mut config);
+
+        let db = test_database(&config);
+        seed(&db, local);
+
+        let mut registry = BackendRegistry::with_builtin();
+        let (available, files) = (available.clone(), files.to_vec());
+        registry.register("stub", move |_| {
+            Ok(Box::new(StubBackend {
+                available: available.clone(),
+                files: files.clone(),
+            }))
+        });
+        let manager = SearchManager::new(&config.search, &registry, db, &config.performance).unwrap();
+        (manager, dir)
+    }
+
+    /// A result row for `path`
+    fn file(path: &str) -> IndexedFile {
+        IndexedFile {
+            id: 0,
+            path: path.to_string(),
+            name: path.rsplit(['/', '\\']).next().unwrap().to_string(),
+            extension: None,
+            size: 100,
+            is_directory: false,
+            modified: 0,
+            created: None,
+            full_path: None,
+            target: None,
+            exists: None,
+            volume: None,
+        }
+    }
+
+    /// SQLite-backed manager allowing `max_concurrent` searches, each waiting up to
+    /// `queue_timeout_ms` for a slot
+    fn limited_manager(max_concurrent: usize, queue_timeout_ms: u64) -> (SearchManager, TempDir) {
+        let dir = TempDir::new().unwrap();
+        let mut config = test_config(&dir);
+        config.performance.max_concurrent_searches = max_concurrent;
+        config.performance.search_queue_timeout_ms = queue_timeout_ms;
+
+        let db = test_database(&config);
+        seed(&db, &["/data/report.txt"]);
+        let manager =
+            SearchManager::new(&config.search, &BackendRegistry::with_builtin(), db, &config.performance).unwrap();
+        (manager, dir)
+    }
+
+    fn busy(result: Result<SearchResult, SearchError>) -> bool {
+        matches!(result, Err(SearchError::QueryFailed(reason)) if reason == "busy")
+    }
+
+    #[tokio::test]
+    async fn searches_past_the_limit_are_refused_after_the_queue_timeout() {
+        let (manager, _dir) = limited_manager(2, 50);
+        let query = SearchQuery::new("report", 10);
+
+        let first = manager.acquire_slot().await.unwrap();
+        let second = manager.acquire_slot().await.unwrap();
+        assert_eq!(manager.active_searches(), 2);
+        assert!(busy(manager.search(&query).await));
+
+        drop(first);
+        assert_eq!(manager.search(&query).await.unwrap().total_found, 1);
+        drop(second);
+        assert_eq!(manager.active_searches(), 0);
+    }
+
+    // A single-threaded runtime: the queued search must yield while it waits, or the
+    // slot holder below never gets to release
+    #[tokio::test(flavor = "current_thread")]
+    async fn queued_searches_run_once_a_slot_is_released() {
+        let (manager, _dir) = limited_manager(1, 5_000);
+        let query = SearchQuery::new("report", 10);
+
+        let slot = manager.acquire_slot().await.unwrap();
+        let release = async {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            drop(slot);
+        };
+        let (result, ()) = tokio::join!(manager.search(&query), release);
+
+        assert_eq!(result.unwrap().total_found, 1);
+        assert_eq!(manager.active_searches(), 0);
+    }
+
+    #[tokio::test]
+    async fn no_limit_never_queues() {
+        let (manager, _dir) = limited_manager(0, 0);
+        let query = SearchQuery::new("report", 10);
+
+        let mut slots = Vec::new();
+        for _ in 0..64 {
+            slots.push(manager.acquire_slot().await.unwrap());
+        }
+        assert_eq!(manager.active_searches(), 64);
+        assert_eq!(manager.search(&query).await.unwrap().total_found, 1);
+    }
+
+    #[tokio::test]
+    async fn refresh_switches_back_once_the_primary_recovers() {
+        let available = Arc::new(AtomicBool::new(true));
+        let (manager, _dir) = stub_manager(&available, &[file("/remote/report.txt")], &["/data/report.txt"], |_| {});
+        let query = SearchQuery::new("report", 10);
+        assert_eq!(manager.search(&query).await.unwrap().backend_name, "Stub");
+
+        // The primary goes away: the next search falls back, and refreshing keeps it there
+        available.store(false, Ordering::SeqCst);
+        assert_eq!(manager.search(&query).await.unwrap().backend_name, "SQLite");
+        assert!(manager.is_using_fallback());
+        assert!(!manager.refresh_availability());
+        assert_eq!(manager.active_backend_name(), "SQLite");
+
+        // It comes back: refreshing switches to it
+        available.store(true, Ordering::SeqCst);
+        assert!(manager.refresh_availability());
+        assert!(!manager.is_using_fallback());
+        assert_eq!(manager.active_backend_name(), "Stub");
+        assert_eq!(manager.search(&query).await.unwrap().backend_name, "Stub");
+        assert!(!manager.refresh_availability(), "nothing to switch back from");
+
+        // And away again
+        available.store(false, Ordering::SeqCst);
+        assert_eq!(manager.search(&query).await.unwrap().backend_name, "SQLite");
+        available.store(true, Ordering::SeqCst);
+        assert!(manager.refresh_availability());
+    }
+
+    /// Wait for the active backend to become `name`, failing after a few seconds
+    async fn wait_for_backend(manager: &SearchManager, name: &str) {
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+        while manager.active_backend_name() != name {
+            assert!(tokio::time::Instant::now() < deadline, "still on {}", manager.active_backend_name());
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
     }
 
-    /// No-op for daemon (no fallback switching)
-    pub fn refresh_availability(&self) {
-        // No-op - daemon always uses SQLite
+    #[tokio::test]
+    async fn periodic_reprobes_follow_primary_availability() {
+        let available = Arc::new(AtomicBool::new(true));
+        let (manager, _dir) = stub_manager(&available, &[], &[], |_| {});
+        let manager = Arc::new(manager);
+        let reprobe = {
+            let manager = manager.clone();
+            tokio::spawn(async move { manager.run_reprobe(Duration::from_millis(10)).await })
+        };
+
+        // No search is needed to notice either change
+        available.store(false, Ordering::SeqCst);
+        wait_for_backend(&manager, "SQLite").await;
+        available.store(true, Ordering::SeqCst);
+        wait_for_backend(&manager, "Stub").await;
+
+        reprobe.abort();
+    }
+
+    #[tokio::test]
+    async fn a_zero_reprobe_interval_only_reprobes_on_request() {
+        let available = Arc::new(AtomicBool::new(true));
+        let (manager, _dir) = stub_manager(&available, &[], &[], |_| {});
+
+        available.store(false, Ordering::SeqCst);
+        tokio::time::timeout(Duration::from_secs(1), manager.run_reprobe(Duration::ZERO))
+            .await
+            .expect("a zero interval returns at once");
+        assert_eq!(manager.active_backend_name(), "Stub");
+
+        assert!(manager.reprobe());
+        assert_eq!(manager.active_backend_name(), "SQLite");
+    }
+
+    fn result(backend_name: &str, files: Vec<IndexedFile>) -> SearchResult {
+        SearchResult {
+            total_found: files.len(),
+            files,
+            query_time_ms: 1,
+            backend_name: backend_name.to_string(),
+            truncated: false,
+        }
+    }
+
+    fn merged_paths(result: &SearchResult) -> Vec<&str> {
+        result.files.iter().map(|file| file.path.as_str()).collect()
+    }
+
+    #[test]
+    fn merging_keeps_one_copy_of_each_path_in_rank_order() {
+        let primary = || {
+            let mut report = file("C:\\Data\\Report.txt");
+            report.size = 700;
+            result("Stub", vec![report, file("C:\\Data\\b.txt"), file("C:\\Data\\z.txt")])
+        };
+        // The local index spells the same files with other separators and case
+        let local = || {
+            let mut big = file("D:\\big.iso");
+            big.size = 900;
+            result(
+                "SQLite",
+                vec![file("c:/data/report.txt"), file("C:/Data/B.TXT/"), file("/local/a.txt"), big],
+            )
+        };
+
+        let query = SearchQuery::new("", 10);
+        let merged = merge_results(primary(), local(), &query, DEFAULT_MAX_RESULT_BYTES);
+        assert_eq!(
+            merged_paths(&merged),
+            ["C:\\Data\\Report.txt", "C:\\Data\\b.txt", "C:\\Data\\z.txt", "/local/a.txt", "D:\\big.iso"]
+        );
+        assert_eq!(merged.files[0].size, 700, "the primary's copy is kept");
+        assert_eq!(merged.total_found, 5);
+        assert_eq!(merged.backend_name, "Stub+SQLite");
+
+        // The page is cut from the merged list, not from either input
+        let query = SearchQuery::new("", 2).with_offset(2);
+        let merged = merge_results(primary(), local(), &query, DEFAULT_MAX_RESULT_BYTES);
+        assert_eq!(merged_paths(&merged), ["C:\\Data\\z.txt", "/local/a.txt"]);
+        assert_eq!(merged.total_found, 5);
+
+        // Other orders apply across both inputs
+        let query = SearchQuery::new("", 2).with_sort(ResultSort::Size);
+        let merged = merge_results(primary(), local(), &query, DEFAULT_MAX_RESULT_BYTES);
+        assert_eq!(merged_paths(&merged), ["D:\\big.iso", "C:\\Data\\Report.txt"]);
+    }
+
+    #[tokio::test]
+    async fn union_searches_merge_the_primary_with_the_local_index() {
+        let available = Arc::new(AtomicBool::new(true));
+        let (manager, _dir) = stub_manager(
+            &available,
+            &[file("/data/Report.txt"), file("/remote/report.txt")],
+            &["/data/report.txt", "/data/report-old.txt"],
+            |config| config.search.union_with_local = true,
+        );
+
+        let result = manager.search(&SearchQuery::new("report", 10)).await.unwrap();
+        assert_eq!(result.backend_name, "Stub+SQLite");
+        let mut paths = merged_paths(&result);
+        paths.sort();
+        assert_eq!(paths, ["/data/Report.txt", "/data/report-old.txt", "/remote/report.txt"]);
     }
 }
@@ -5,11 +5,24 @@
 
 pub mod sqlite_search;
 pub mod manager;
+pub mod path_encoding;
+pub mod filter_dsl;
+pub mod expansion;
+pub mod registry;
+#[cfg(windows)]
+pub mod everything;
+#[cfg(target_os = "macos")]
+pub mod spotlight;
 
 // Re-export main types
 pub use manager::SearchManager;
+pub use registry::BackendRegistry;
+pub use path_encoding::PathEncoding;
 
-use stella_search_core::IndexedFile;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use stella_search_core::{IndexedFile, ResultSort};
 use thiserror::Error;
 
 /// Search backend errors
@@ -25,38 +38,360 @@ pub enum SearchError {
     DatabaseError(#[from] anyhow::Error),
 }
 
+/// How the query text is matched against file names
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryKind {
+    /// Name contains the query (default)
+    #[default]
+    Substring,
+    /// Name equals the query, ignoring ASCII case (an index seek, not a scan)
+    Exact,
+}
+
+/// Everyday size classes, for users who don't think in bytes.
+/// Boundaries come from `search.size_categories`; defaults shown.
+/// Categories only match files, never directories.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum SizeCategory {
+    /// Under 100 KB
+    Tiny,
+    /// 100 KB up to 1 MB
+    Small,
+    /// 1 MB up to 100 MB
+    Medium,
+    /// 100 MB up to 1 GB
+    Large,
+    /// 1 GB and over
+    Huge,
+}
+
+impl SizeCategory {
+    /// Size range in bytes: at least the first value, below the second if any
+    pub fn size_range(self, bounds: &crate::config::SizeCategoryBounds) -> (u64, Option<u64>) {
+        let tiny = bounds.tiny_below_kb.saturating_mul(1024);
+        let small = bounds.small_below_mb.saturating_mul(1024 * 1024);
+        let medium = bounds.medium_below_mb.saturating_mul(1024 * 1024);
+        let large = bounds.large_below_mb.saturating_mul(1024 * 1024);
+
+        match self {
+            SizeCategory::Tiny => (0, Some(tiny)),
+            SizeCategory::Small => (tiny, Some(small)),
+            SizeCategory::Medium => (small, Some(medium)),
+            SizeCategory::Large => (medium, Some(large)),
+            SizeCategory::Huge => (large, None),
+        }
+    }
+}
+
 /// Search query parameters
 #[derive(Debug, Clone)]
 pub struct SearchQuery {
     /// The search term (filename pattern)
     pub query: String,
+    /// How the search term is matched
+    pub kind: QueryKind,
     /// Maximum number of results to return
     pub max_results: usize,
+    /// Number of matching results to skip (for pagination)
+    pub offset: usize,
     /// Optional extension filter (e.g., ".pdf", ".exe")
     pub extension: Option<String>,
+    /// Only files with one of these extensions, ignoring case (an extension group)
+    pub extensions: Option<Vec<String>>,
+    /// Extra extensions and names the query matches (an abbreviation from
+    /// `search.query_expansions`); substring queries only
+    pub expansion: Option<expansion::QueryExpansion>,
+    /// Result order; ties keep index order
+    pub sort: ResultSort,
+    /// With `ResultSort::Rank` and a substring query: names equal to the query (with
+    /// or without extension) first, then names starting with it, then the rest.
+    /// On by default; SQLite backend only
+    pub boost_name_matches: bool,
+    /// Only entries created at or after this time (Unix seconds)
+    pub created_after: Option<i64>,
+    /// Only entries created before this time (Unix seconds)
+    pub created_before: Option<i64>,
+    /// Only these exact paths (as stored, i.e. the `path` of results); for checking an
+    /// external file list against the index
+    pub restrict_to_paths: Option<Vec<String>>,
     /// Optional directory filter
     pub directories: Option<Vec<String>>,
+    /// Optional drive or mount point filter (e.g., "E:", "/mnt/data")
+    pub drive: Option<String>,
+    /// Encoding applied to result paths
+    pub path_encoding: PathEncoding,
+    /// Only match files under this directory (per-user scoping, not user-controlled)
+    pub scope: Option<String>,
+    /// Extra predicate from a filter expression
+    pub filter: Option<filter_dsl::Filter>,
+    /// Hidden entries (dotfiles and anything under a dot directory): Some(false) leaves
+    /// them out, Some(true) or None returns them if they are indexed
+    pub include_hidden: Option<bool>,
+    /// Cap on results from any one parent directory, so a single large folder
+    /// can't fill the whole result list
+    pub max_per_directory: Option<usize>,
+    /// Only files in this size class
+    pub size_category: Option<SizeCategory>,
+    /// Directory to cut from the front of result paths (output only; the full
+    /// path is kept in `IndexedFile::full_path`)
+    pub strip_prefix: Option<String>,
+    /// Return the directories holding matching files (with counts) instead of the files
+    pub return_containing_dirs: bool,
+    /// Fill in `target` for results that are symbolic links (a lookup per result,
+    /// filesystem calls per link)
+    pub resolve_symlinks: bool,
+    /// Only files owned by this uid (Unix) or SID string (Windows); needs
+    /// `indexing.track_ownership`
+    pub owner: Option<String>,
+    /// Some(true): only entries anyone may write to; Some(false): only those they
+    /// can't. Needs `indexing.track_ownership`
+    pub world_writable: Option<bool>,
+    /// Check that each result is still on disk and fill in `exists`
+    pub verify_existence: bool,
+    /// Time allowed for the whole check; results after it ran out are left
+    /// unchecked. None = no limit
+    pub verify_budget: Option<Duration>,
+    /// Fill in `volume`: the drive, share or mount point holding each result
+    pub include_volume: bool,
 }
 
 impl SearchQuery {
     pub fn new(query: impl Into<String>, max_results: usize) -> Self {
         Self {
             query: query.into(),
+            kind: QueryKind::default(),
             max_results,
+            offset: 0,
             extension: None,
+            extensions: None,
+            expansion: None,
+            sort: ResultSort::default(),
+            boost_name_matches: true,
+            created_after: None,
+            created_before: None,
+            restrict_to_paths: None,
             directories: None,
+            drive: None,
+            path_encoding: PathEncoding::default(),
+            scope: None,
+            filter: None,
+            include_hidden: None,
+            max_per_directory: None,
+            size_category: None,
+            strip_prefix: None,
+            return_containing_dirs: false,
+            resolve_symlinks: false,
+            owner: None,
+            world_writable: None,
+            verify_existence: false,
+            verify_budget: None,
+            include_volume: false,
         }
     }
 
+    pub fn with_kind(mut self, kind: QueryKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    pub fn with_offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+
     pub fn with_extension(mut self, ext: impl Into<String>) -> Self {
         self.extension = Some(ext.into());
         self
     }
 
+    pub fn with_extensions(mut self, extensions: Vec<String>) -> Self {
+        self.extensions = Some(extensions);
+        self
+    }
+
+    pub fn with_expansion(mut self, expansion: expansion::QueryExpansion) -> Self {
+        self.expansion = Some(expansion);
+        self
+    }
+
+    pub fn with_sort(mut self, sort: ResultSort) -> Self {
+        self.sort = sort;
+        self
+    }
+
+    pub fn with_boost_name_matches(mut self, boost: bool) -> Self {
+        self.boost_name_matches = boost;
+        self
+    }
+
+    pub fn with_created_after(mut self, time: i64) -> Self {
+        self.created_after = Some(time);
+        self
+    }
+
+    pub fn with_created_before(mut self, time: i64) -> Self {
+        self.created_before = Some(time);
+        self
+    }
+
+    pub fn with_restrict_to_paths(mut self, paths: Vec<String>) -> Self {
+        self.restrict_to_paths = Some(paths);
+        self
+    }
+
     pub fn with_directories(mut self, dirs: Vec<String>) -> Self {
         self.directories = Some(dirs);
         self
     }
+
+    pub fn with_drive(mut self, drive: impl Into<String>) -> Self {
+        self.drive = Some(drive.into());
+        self
+    }
+
+    pub fn with_path_encoding(mut self, encoding: PathEncoding) -> Self {
+        self.path_encoding = encoding;
+        self
+    }
+
+    pub fn with_scope(mut self, scope: impl Into<String>) -> Self {
+        self.scope = Some(scope.into());
+        self
+    }
+
+    pub fn with_filter(mut self, filter: filter_dsl::Filter) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    pub fn with_include_hidden(mut self, include_hidden: bool) -> Self {
+        self.include_hidden = Some(include_hidden);
+        self
+    }
+
+    pub fn with_max_per_directory(mut self, max: usize) -> Self {
+        self.max_per_directory = Some(max);
+        self
+    }
+
+    pub fn with_size_category(mut self, category: SizeCategory) -> Self {
+        self.size_category = Some(category);
+        self
+    }
+
+    pub fn with_strip_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.strip_prefix = Some(prefix.into());
+        self
+    }
+
+    pub fn with_return_containing_dirs(mut self, containing_dirs: bool) -> Self {
+        self.return_containing_dirs = containing_dirs;
+        self
+    }
+
+    pub fn with_resolve_symlinks(mut self, resolve_symlinks: bool) -> Self {
+        self.resolve_symlinks = resolve_symlinks;
+        self
+    }
+
+    pub fn with_owner(mut self, owner: impl Into<String>) -> Self {
+        self.owner = Some(owner.into());
+        self
+    }
+
+    pub fn with_world_writable(mut self, world_writable: bool) -> Self {
+        self.world_writable = Some(world_writable);
+        self
+    }
+
+    pub fn with_verify_existence(mut self, verify_existence: bool) -> Self {
+        self.verify_existence = verify_existence;
+        self
+    }
+
+    pub fn with_verify_budget(mut self, budget: Duration) -> Self {
+        self.verify_budget = Some(budget);
+        self
+    }
+
+    pub fn with_include_volume(mut self, include_volume: bool) -> Self {
+        self.include_volume = include_volume;
+        self
+    }
+}
+
+/// Fill in `exists` for results in order, until `budget` runs out. Everything
+/// after that keeps `exists: None`, so a large result set gets its first results
+/// verified and an unverified tail rather than an unbounded wait. Paths must
+/// still be the ones on disk (before encoding or prefix stripping).
+pub fn verify_existence(files: &mut [IndexedFile], budget: Option<Duration>) {
+    let start = Instant::now();
+    for file in files {
+        if budget.is_some_and(|budget| start.elapsed() >= budget) {
+            break;
+        }
+        // An unreadable parent says nothing either way
+        file.exists = match std::fs::symlink_metadata(&file.path) {
+            Ok(_) => Some(true),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Some(false),
+            Err(_) => None,
+        };
+    }
+}
+
+/// Fill in `volume` for every result. Paths must still be the ones on disk.
+pub fn fill_volumes(files: &mut [IndexedFile]) {
+    let mount_points = crate::platform::volume_roots();
+    for file in files {
+        file.volume = volume_of(&file.path, &mount_points);
+    }
+}
+
+/// Volume a path lives on, whatever platform stored it: the drive for "C:\a" ("C:"),
+/// the share for "\\server\share\a" ("\\server\share", also from the `\\?\` forms),
+/// otherwise the deepest of `mount_points` holding the path. None for relative paths.
+pub fn volume_of(path: &str, mount_points: &[String]) -> Option<String> {
+    let is_separator = |c: char| c == '\\' || c == '/';
+
+    // Verbatim paths: \\?\C:\a and \\?\UNC\server\share\a
+    let (path, unc) = match path.strip_prefix(r"\\?\") {
+        Some(rest) => match rest.strip_prefix(r"UNC\") {
+            Some(share_path) => (share_path, true),
+            None => (rest, false),
+        },
+        None => match path.strip_prefix(r"\\").or_else(|| path.strip_prefix("//")) {
+            Some(share_path) => (share_path, true),
+            None => (path, false),
+        },
+    };
+
+    if unc {
+        let mut parts = path.split(is_separator).filter(|part| !part.is_empty());
+        let server = parts.next()?;
+        return Some(match parts.next() {
+            Some(share) => format!(r"\\{}\{}", server, share),
+            None => format!(r"\\{}", server),
+        });
+    }
+
+    let bytes = path.as_bytes();
+    if bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' {
+        return Some(path[..2].to_ascii_uppercase());
+    }
+
+    if !path.starts_with('/') {
+        return None;
+    }
+    let deepest = mount_points
+        .iter()
+        .map(|root| root.trim_end_matches('/'))
+        .filter(|root| {
+            root.is_empty() || path == *root || path.strip_prefix(root).is_some_and(|rest| rest.starts_with('/'))
+        })
+        .max_by_key(|root| root.len())?;
+    Some(if deepest.is_empty() { "/".to_string() } else { deepest.to_string() })
 }
 
 /// Search results with timing information
@@ -70,6 +405,8 @@ pub struct SearchResult {
     pub query_time_ms: u64,
     /// Which backend produced these results
     pub backend_name: String,
+    /// Cut short by the result size cap
+    pub truncated: bool,
 }
 
 /// Trait for search backends
@@ -87,6 +424,12 @@ pub trait SearchBackend: Send + Sync {
     /// Get the name of this backend for logging/status
     fn name(&self) -> &'static str;
 
+    /// Whether searches read the daemon's own index. A backend with an index of
+    /// its own (Spotlight) returns false, and the daemon then skips indexing.
+    fn uses_local_index(&self) -> bool {
+        true
+    }
+
     /// Get a description of this backend's status
     fn status_description(&self) -> String {
         if self.is_available() {
@@ -96,3 +439,78 @@ pub trait SearchBackend: Send + Sync {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SizeCategoryBounds;
+
+    const CATEGORIES: [SizeCategory; 5] = [
+        SizeCategory::Tiny,
+        SizeCategory::Small,
+        SizeCategory::Medium,
+        SizeCategory::Large,
+        SizeCategory::Huge,
+    ];
+    const KB: u64 = 1024;
+    const MB: u64 = 1024 * KB;
+
+    /// Categories whose range holds `size`
+    fn categories_of(size: u64, bounds: &SizeCategoryBounds) -> Vec<SizeCategory> {
+        CATEGORIES
+            .into_iter()
+            .filter(|category| {
+                let (low, high) = category.size_range(bounds);
+                size >= low && high.is_none_or(|high| size < high)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn default_ranges_are_half_open_and_adjacent() {
+        let bounds = SizeCategoryBounds::default();
+        let edges = [
+            (0, SizeCategory::Tiny),
+            (100 * KB - 1, SizeCategory::Tiny),
+            (100 * KB, SizeCategory::Small),
+            (MB - 1, SizeCategory::Small),
+            (MB, SizeCategory::Medium),
+            (100 * MB - 1, SizeCategory::Medium),
+            (100 * MB, SizeCategory::Large),
+            (1024 * MB - 1, SizeCategory::Large),
+            (1024 * MB, SizeCategory::Huge),
+            (u64::MAX, SizeCategory::Huge),
+        ];
+        for (size, category) in edges {
+            assert_eq!(categories_of(size, &bounds), [category], "{} bytes", size);
+        }
+    }
+
+    #[test]
+    fn custom_bounds_move_every_edge() {
+        let bounds = SizeCategoryBounds {
+            tiny_below_kb: 4,
+            small_below_mb: 2,
+            medium_below_mb: 3,
+            large_below_mb: 4,
+        };
+        assert_eq!(SizeCategory::Tiny.size_range(&bounds), (0, Some(4 * KB)));
+        assert_eq!(SizeCategory::Small.size_range(&bounds), (4 * KB, Some(2 * MB)));
+        assert_eq!(SizeCategory::Medium.size_range(&bounds), (2 * MB, Some(3 * MB)));
+        assert_eq!(SizeCategory::Large.size_range(&bounds), (3 * MB, Some(4 * MB)));
+        assert_eq!(SizeCategory::Huge.size_range(&bounds), (4 * MB, None));
+        for size in [0, 4 * KB - 1, 4 * KB, 2 * MB - 1, 2 * MB, 3 * MB, 4 * MB - 1, 4 * MB] {
+            assert_eq!(categories_of(size, &bounds).len(), 1, "{} bytes", size);
+        }
+    }
+
+    #[test]
+    fn oversized_bounds_saturate_instead_of_overflowing() {
+        let bounds = SizeCategoryBounds {
+            large_below_mb: u64::MAX,
+            ..Default::default()
+        };
+        assert_eq!(SizeCategory::Huge.size_range(&bounds), (u64::MAX, None));
+        assert_eq!(categories_of(u64::MAX - 1, &bounds), [SizeCategory::Large]);
+    }
+}
@@ -0,0 +1,40 @@
+//! Result path encodings
+//!
+//! Paths are indexed as UTF-8 text, but Unix filenames are arbitrary bytes.
+//! Non-UTF-8 paths keep their raw bytes in the database so clients that need
+//! byte-exact round-tripping can ask for percent-encoded results.
+//!
+//! Percent-encoded scheme: valid UTF-8 characters are emitted as-is, except `%`
+//! which becomes `%25`; every byte that is not part of valid UTF-8 becomes `%XX`
+//! (uppercase hex). To decode, replace each `%XX` with the byte 0xXX and leave all
+//! other bytes unchanged. UTF-8 paths without `%` are identical in both encodings.
+
+use serde::{Deserialize, Serialize};
+
+/// How result paths are encoded
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum PathEncoding {
+    /// UTF-8 with invalid bytes replaced by U+FFFD (default)
+    #[default]
+    Utf8Lossy,
+    /// Lossless percent-encoding of the raw path bytes
+    PercentEncoded,
+}
+
+/// Percent-encode raw path bytes
+pub fn percent_encode_bytes(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len());
+    for chunk in bytes.utf8_chunks() {
+        encoded.push_str(&percent_encode_str(chunk.valid()));
+        for byte in chunk.invalid() {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+/// Percent-encode a UTF-8 path (only `%` itself needs escaping)
+pub fn percent_encode_str(path: &str) -> String {
+    path.replace('%', "%25")
+}
@@ -0,0 +1,143 @@
+//! Named search backends the manager can be configured to use
+//!
+//! `search.backend` names a backend; "auto", "sqlite" and "windows" are built in,
+//! anything else is looked up here. Backends are registered at startup with a
+//! factory that builds them over the daemon's database, so adding one (a platform
+//! search service, a remote index) doesn't touch the manager.
+//!
+//! A custom backend runs as the primary with the SQLite index as its fallback, so
+//! the daemon keeps indexing whichever backend is selected, unless the backend
+//! answers from an index of its own (`SearchBackend::uses_local_index`).
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use anyhow::Result;
+
+use super::SearchBackend;
+use super::sqlite_search::SqliteSearchBackend;
+use crate::database::Database;
+
+/// Builds a backend; an error means it can't run on this machine
+pub type BackendFactory = Box<dyn Fn(&Arc<Database>) -> Result<Box<dyn SearchBackend>> + Send + Sync>;
+
+/// Backend factories by lowercase name
+#[derive(Default)]
+pub struct BackendRegistry {
+    factories: BTreeMap<String, BackendFactory>,
+}
+
+impl BackendRegistry {
+    /// A registry holding the backends the daemon ships with
+    pub fn with_builtin() -> Self {
+        let mut registry = Self::default();
+        registry.register("sqlite", |db| Ok(Box::new(SqliteSearchBackend::new(db.clone()))));
+        registry
+    }
+
+    /// Register a backend under `name` (case-insensitive), replacing any earlier one
+    pub fn register<F>(&mut self, name: &str, factory: F)
+    where
+        F: Fn(&Arc<Database>) -> Result<Box<dyn SearchBackend>> + Send + Sync + 'static,
+    {
+        self.factories.insert(name.trim().to_lowercase(), Box::new(factory));
+    }
+
+    /// Registered names, sorted
+    pub fn names(&self) -> Vec<&str> {
+        self.factories.keys().map(String::as_str).collect()
+    }
+
+    /// Build the backend registered under `name`, if any
+    pub fn create(&self, name: &str, db: &Arc<Database>) -> Option<Result<Box<dyn SearchBackend>>> {
+        let factory = self.factories.get(&name.trim().to_lowercase())?;
+        Some(factory(db))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::config::{SearchBackendType, SearchConfig};
+    use crate::search::{SearchError, SearchManager, SearchQuery, SearchResult};
+    use crate::test_support::{test_config, test_database};
+
+    /// Backend that finds nothing, under its own name
+    struct MockBackend;
+
+    impl SearchBackend for MockBackend {
+        fn is_available(&self) -> bool {
+            true
+        }
+
+        fn search(&self, _query: &SearchQuery) -> Result<SearchResult, SearchError> {
+            Ok(SearchResult {
+                files: Vec::new(),
+                total_found: 0,
+                query_time_ms: 0,
+                backend_name: "Mock".to_string(),
+                truncated: false,
+            })
+        }
+
+        fn name(&self) -> &'static str {
+            "Mock"
+        }
+    }
+
+    fn registry() -> BackendRegistry {
+        let mut registry = BackendRegistry::with_builtin();
+        registry.register("Mock", |_| Ok(Box::new(MockBackend)));
+        registry.register("broken", |_| anyhow::bail!("not on this machine"));
+        registry
+    }
+
+    /// Manager for `search` over an empty database
+    fn manager(search: &SearchConfig) -> (SearchManager, TempDir) {
+        let dir = TempDir::new().unwrap();
+        let config = test_config(&dir);
+        let db = test_database(&config);
+        let manager = SearchManager::new(search, &registry(), db, &config.performance).unwrap();
+        (manager, dir)
+    }
+
+    #[test]
+    fn names_are_case_insensitive_and_sorted() {
+        assert_eq!(registry().names(), ["broken", "mock", "sqlite"]);
+
+        let search = SearchConfig {
+            backend: SearchBackendType::Custom(" MOCK ".to_string()),
+            ..SearchConfig::default()
+        };
+        assert_eq!(manager(&search).0.primary_backend_name(), "Mock");
+    }
+
+    #[tokio::test]
+    async fn a_registered_backend_is_selected_by_name() {
+        let search: SearchConfig = toml::from_str(r#"backend = "mock""#).unwrap();
+        assert_eq!(search.backend, SearchBackendType::Custom("mock".to_string()));
+
+        let (manager, _dir) = manager(&search);
+        assert_eq!(manager.primary_backend_name(), "Mock");
+        assert_eq!(manager.fallback_backend_name(), Some("SQLite"));
+        let result = manager.search(&SearchQuery::new("report", 10)).await.unwrap();
+        assert_eq!(result.backend_name, "Mock");
+    }
+
+    #[tokio::test]
+    async fn unknown_and_unusable_backends_fall_back_to_sqlite() {
+        for name in ["nonexistent", "broken"] {
+            let search = SearchConfig {
+                backend: SearchBackendType::Custom(name.to_string()),
+                ..SearchConfig::default()
+            };
+            let (manager, _dir) = manager(&search);
+            assert_eq!(manager.primary_backend_name(), "SQLite", "{}", name);
+            assert_eq!(manager.fallback_backend_name(), None);
+            let result = manager.search(&SearchQuery::new("report", 10)).await.unwrap();
+            assert_eq!(result.backend_name, "SQLite");
+        }
+    }
+}
@@ -0,0 +1,377 @@
+//! Spotlight search backend, macOS only
+//!
+//! macOS already keeps a file index for Spotlight, so with `search.backend =
+//! "spotlight"` (or "auto" on macOS) searches run `mdfind` instead of reading the
+//! daemon's own index, and the daemon skips indexing while Spotlight is up at
+//! startup.
+//!
+//! The name, extension, size and creation-time parts of a `SearchQuery` become a
+//! Spotlight query (the `MDQuery` syntax `mdfind` takes). Extensions are matched as
+//! name suffixes rather than content types, so ".log" means exactly that. Path
+//! filters, hidden entries, sorting, paging and per-directory caps are applied to
+//! `mdfind`'s output, and metadata is read from disk for the results returned (for
+//! every match when sorting by size or creation time). Filter expressions and
+//! ownership filters need the local index and fail the search.
+
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsStr;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{bail, Result};
+use stella_search_core::{collect_capped, IndexedFile, ResultSort};
+
+use crate::config::{Config, SizeCategoryBounds};
+use super::path_encoding::{percent_encode_bytes, percent_encode_str};
+use super::registry::BackendRegistry;
+use super::{volume_of, PathEncoding, QueryKind, SearchBackend, SearchError, SearchQuery, SearchResult};
+
+const MDFIND: &str = "/usr/bin/mdfind";
+const MDUTIL: &str = "/usr/bin/mdutil";
+
+/// Register the backend as "spotlight", built from `config` when selected
+pub fn register(registry: &mut BackendRegistry, config: &Config) {
+    let size_bounds = config.search.size_categories;
+    let max_result_bytes = config.performance.max_result_mb.saturating_mul(1024 * 1024);
+
+    registry.register("spotlight", move |_| {
+        let backend = SpotlightSearchBackend::new(size_bounds, max_result_bytes)?;
+        Ok(Box::new(backend))
+    });
+}
+
+/// Search backend answering from the Spotlight index
+pub struct SpotlightSearchBackend {
+    size_bounds: SizeCategoryBounds,
+    max_result_bytes: usize,
+}
+
+impl SpotlightSearchBackend {
+    pub fn new(size_bounds: SizeCategoryBounds, max_result_bytes: usize) -> Result<Self> {
+        if !Path::new(MDFIND).exists() {
+            bail!("{} not found", MDFIND);
+        }
+        Ok(Self {
+            size_bounds,
+            max_result_bytes,
+        })
+    }
+
+    /// Paths matching a Spotlight query, optionally only under `only_in`
+    fn find(&self, predicate: &str, only_in: Option<&str>) -> Result<Vec<PathBuf>, SearchError> {
+        let mut command = Command::new(MDFIND);
+        // NUL-separated, so no path can be misread
+        command.arg("-0");
+        if let Some(directory) = only_in {
+            command.arg("-onlyin").arg(directory);
+        }
+        command.arg(predicate);
+
+        let output = command.output().map_err(|_| SearchError::NotAvailable)?;
+        if !output.status.success() {
+            return Err(SearchError::QueryFailed(format!(
+                "mdfind failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+
+        Ok(output
+            .stdout
+            .split(|&byte| byte == 0)
+            .filter(|path| !path.is_empty())
+            .map(|path| PathBuf::from(OsStr::from_bytes(path)))
+            .collect())
+    }
+}
+
+impl SearchBackend for SpotlightSearchBackend {
+    fn is_available(&self) -> bool {
+        // "Indexing enabled." unless Spotlight is turned off for the boot volume
+        Command::new(MDUTIL)
+            .args(["-s", "/"])
+            .output()
+            .map(|output| String::from_utf8_lossy(&output.stdout).contains("Indexing enabled"))
+            .unwrap_or(false)
+    }
+
+    fn search(&self, query: &SearchQuery) -> Result<SearchResult, SearchError> {
+        let start = std::time::Instant::now();
+
+        let predicate = spotlight_predicate(query, &self.size_bounds)?;
+        let only_in = query.scope.as_deref().or(query.drive.as_deref());
+        let mut paths = self.find(&predicate, only_in)?;
+
+        let restrict: Option<HashSet<&str>> = query
+            .restrict_to_paths
+            .as_ref()
+            .map(|paths| paths.iter().map(String::as_str).collect());
+        paths.retain(|path| matches_path_filters(path, query, restrict.as_ref()));
+
+        let total_found;
+        let files: Vec<IndexedFile> = match query.sort {
+            // Spotlight has no relevance order to offer; keep the order mdfind gave
+            ResultSort::Rank | ResultSort::Name => {
+                if query.sort == ResultSort::Name {
+                    paths.sort_by_cached_key(|path| file_name(path).to_lowercase());
+                }
+                let paths = cap_per_directory(paths, query.max_per_directory, |path| path);
+                total_found = paths.len();
+                paths
+                    .iter()
+                    .skip(query.offset)
+                    .take(query.max_results)
+                    .filter_map(|path| indexed_file(path))
+                    .collect()
+            }
+            // Sorting by metadata means reading it for every match
+            ResultSort::Size | ResultSort::Created => {
+                let mut files: Vec<(PathBuf, IndexedFile)> = paths
+                    .into_iter()
+                    .filter_map(|path| indexed_file(&path).map(|file| (path, file)))
+                    .collect();
+                match query.sort {
+                    ResultSort::Size => files.sort_by_key(|(_, file)| std::cmp::Reverse(file.size)),
+                    _ => files.sort_by_key(|(_, file)| std::cmp::Reverse(file.created)),
+                }
+                let files = cap_per_directory(files, query.max_per_directory, |(path, _)| path);
+                total_found = files.len();
+                files
+                    .into_iter()
+                    .skip(query.offset)
+                    .take(query.max_results)
+                    .map(|(_, file)| file)
+                    .collect()
+            }
+        };
+
+        let (mut files, truncated) = collect_capped(files, self.max_result_bytes);
+
+        let mount_points = query.include_volume.then(crate::platform::volume_roots);
+        for file in &mut files {
+            let path = PathBuf::from(&file.full_path.take().unwrap_or_default());
+            if query.resolve_symlinks && path.is_symlink() {
+                file.target = std::fs::canonicalize(&path)
+                    .or_else(|_| std::fs::read_link(&path))
+                    .ok()
+                    .map(|target| encode_path(&target, query.path_encoding));
+            }
+            // Just read from disk
+            if query.verify_existence {
+                file.exists = Some(true);
+            }
+            if let Some(mount_points) = &mount_points {
+                file.volume = volume_of(&path.to_string_lossy(), mount_points);
+            }
+            file.path = encode_path(&path, query.path_encoding);
+        }
+
+        if let Some(prefix) = &query.strip_prefix {
+            let prefix = match query.path_encoding {
+                PathEncoding::PercentEncoded => percent_encode_str(prefix),
+                PathEncoding::Utf8Lossy => prefix.clone(),
+            };
+            for file in &mut files {
+                file.strip_path_prefix(&prefix);
+            }
+        }
+
+        Ok(SearchResult {
+            files,
+            total_found,
+            query_time_ms: start.elapsed().as_millis() as u64,
+            backend_name: self.name().to_string(),
+            truncated,
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "Spotlight"
+    }
+
+    fn uses_local_index(&self) -> bool {
+        false
+    }
+}
+
+/// Spotlight query for the parts of a search Spotlight can evaluate itself.
+/// Clauses are joined with `&&`; user text is escaped so it matches literally.
+fn spotlight_predicate(query: &SearchQuery, size_bounds: &SizeCategoryBounds) -> Result<String, SearchError> {
+    if query.filter.is_some() {
+        return Err(SearchError::QueryFailed(
+            "The Spotlight backend doesn't support filter expressions".to_string(),
+        ));
+    }
+    if query.owner.is_some() || query.world_writable.is_some() {
+        return Err(SearchError::QueryFailed(
+            "The Spotlight backend doesn't support owner or permission filters".to_string(),
+        ));
+    }
+
+    let mut clauses = Vec::new();
+
+    let name_clause = match query.kind {
+        _ if query.query.is_empty() => name_matches("*"),
+        QueryKind::Substring => name_matches(&format!("*{}*", escape(&query.query))),
+        QueryKind::Exact => name_matches(&escape(&query.query)),
+    };
+    match query.expansion.as_ref().filter(|_| query.kind == QueryKind::Substring) {
+        Some(expansion) => {
+            let mut alternatives = vec![name_clause];
+            alternatives.extend(expansion.extensions.iter().map(|ext| extension_clause(ext)));
+            alternatives.extend(expansion.names.iter().map(|name| name_matches(&format!("*{}*", escape(name)))));
+            clauses.push(format!("({})", alternatives.join(" || ")));
+        }
+        None => clauses.push(name_clause),
+    }
+
+    if let Some(extension) = &query.extension {
+        clauses.push(extension_clause(extension));
+    }
+    if let Some(extensions) = &query.extensions {
+        let alternatives: Vec<String> = extensions.iter().map(|ext| extension_clause(ext)).collect();
+        clauses.push(format!("({})", alternatives.join(" || ")));
+    }
+
+    if let Some(category) = query.size_category {
+        let (low, high) = category.size_range(size_bounds);
+        clauses.push("kMDItemContentType != \"public.folder\"".to_string());
+        clauses.push(format!("kMDItemFSSize >= {}", low));
+        if let Some(high) = high {
+            clauses.push(format!("kMDItemFSSize < {}", high));
+        }
+    }
+
+    if let Some(after) = query.created_after {
+        clauses.push(format!("kMDItemFSCreationDate >= {}", spotlight_time(after)));
+    }
+    if let Some(before) = query.created_before {
+        clauses.push(format!("kMDItemFSCreationDate < {}", spotlight_time(before)));
+    }
+
+    Ok(clauses.join(" && "))
+}
+
+/// Case-insensitive match of the file name against a wildcard pattern
+fn name_matches(pattern: &str) -> String {
+    format!("kMDItemFSName == \"{}\"c", pattern)
+}
+
+/// Names ending in the extension, given with or without its leading dot
+fn extension_clause(extension: &str) -> String {
+    let extension = extension.trim().trim_start_matches('.');
+    name_matches(&format!("*.{}", escape(extension)))
+}
+
+/// Quote, backslash and wildcard escaped for a Spotlight string
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '"' | '*') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Spotlight date literal for a Unix time
+fn spotlight_time(secs: i64) -> String {
+    let time = chrono::DateTime::from_timestamp(secs, 0).unwrap_or_default();
+    format!("$time.iso({})", time.format("%Y-%m-%dT%H:%M:%SZ"))
+}
+
+/// Filters Spotlight can't express, checked against each path it returned
+fn matches_path_filters(path: &Path, query: &SearchQuery, restrict: Option<&HashSet<&str>>) -> bool {
+    let under = |directory: &str| {
+        let directory = Path::new(directory.trim());
+        path.starts_with(directory) && path != directory
+    };
+
+    if let Some(scope) = &query.scope
+        && !under(scope)
+    {
+        return false;
+    }
+    if let Some(drive) = &query.drive
+        && !under(drive)
+    {
+        return false;
+    }
+    if let Some(directories) = &query.directories
+        && !directories.iter().any(|directory| under(directory))
+    {
+        return false;
+    }
+    // A dot at the start of any path component marks it (or a parent) hidden
+    if query.include_hidden == Some(false) && path.as_os_str().as_bytes().windows(2).any(|w| w == b"/.") {
+        return false;
+    }
+    if let Some(restrict) = restrict
+        && !restrict.contains(path.to_string_lossy().as_ref())
+    {
+        return false;
+    }
+    true
+}
+
+/// Keep at most `max` items from each parent directory, in order
+fn cap_per_directory<T>(items: Vec<T>, max: Option<usize>, path: impl Fn(&T) -> &Path) -> Vec<T> {
+    let Some(max) = max else {
+        return items;
+    };
+    let mut counts: HashMap<PathBuf, usize> = HashMap::new();
+    items
+        .into_iter()
+        .filter(|item| {
+            let parent = path(item).parent().map(Path::to_path_buf).unwrap_or_default();
+            let count = counts.entry(parent).or_default();
+            *count += 1;
+            *count <= max
+        })
+        .collect()
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default()
+}
+
+/// Result entry read from disk (None if the path is gone). The on-disk path is
+/// parked in `full_path` until the results are encoded.
+fn indexed_file(path: &Path) -> Option<IndexedFile> {
+    let metadata = std::fs::symlink_metadata(path).ok()?;
+    let is_directory = metadata.is_dir();
+    let unix_secs = |time: std::io::Result<SystemTime>| {
+        time.ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|duration| duration.as_secs() as i64)
+    };
+
+    Some(IndexedFile {
+        id: 0,
+        path: String::new(),
+        name: file_name(path),
+        extension: if is_directory {
+            None
+        } else {
+            path.extension().map(|ext| format!(".{}", ext.to_string_lossy()))
+        },
+        size: if is_directory { 0 } else { metadata.len() as i64 },
+        is_directory,
+        modified: unix_secs(metadata.modified()).unwrap_or(0),
+        created: unix_secs(metadata.created()),
+        full_path: Some(path.to_string_lossy().into_owned()),
+        target: None,
+        exists: None,
+        volume: None,
+    })
+}
+
+/// Path in the requested result encoding
+fn encode_path(path: &Path, encoding: PathEncoding) -> String {
+    match encoding {
+        PathEncoding::PercentEncoded => percent_encode_bytes(path.as_os_str().as_bytes()),
+        PathEncoding::Utf8Lossy => path.to_string_lossy().into_owned(),
+    }
+}
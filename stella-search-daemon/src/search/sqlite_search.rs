@@ -6,7 +6,8 @@
 use std::sync::Arc;
 
 use crate::database::Database;
-use super::{SearchBackend, SearchError, SearchQuery, SearchResult};
+use super::path_encoding::percent_encode_str;
+use super::{fill_volumes, verify_existence, PathEncoding, SearchBackend, SearchError, SearchQuery, SearchResult};
 
 /// SQLite search backend using the existing database infrastructure
 pub struct SqliteSearchBackend {
@@ -35,17 +36,45 @@ impl SearchBackend for SqliteSearchBackend {
         let start = std::time::Instant::now();
 
         // Use existing database search
-        let results = self.db.search(
-            &query.query,
-            query.max_results,
-            query.extension.as_deref(),
-        )?;
+        let mut results = self.db.search(query)?;
+
+        // Before encoding, while paths are still the ones on disk
+        if query.resolve_symlinks {
+            self.db.resolve_symlink_targets(&mut results.files)?;
+        }
+        if query.verify_existence {
+            verify_existence(&mut results.files, query.verify_budget);
+        }
+        if query.include_volume {
+            fill_volumes(&mut results.files);
+        }
+
+        if query.path_encoding == PathEncoding::PercentEncoded {
+            self.db.percent_encode_paths(&mut results.files)?;
+            for file in &mut results.files {
+                if let Some(target) = &mut file.target {
+                    *target = percent_encode_str(target);
+                }
+            }
+        }
+
+        if let Some(prefix) = &query.strip_prefix {
+            // Compare in the same encoding as the result paths
+            let prefix = match query.path_encoding {
+                PathEncoding::PercentEncoded => percent_encode_str(prefix),
+                PathEncoding::Utf8Lossy => prefix.clone(),
+            };
+            for file in &mut results.files {
+                file.strip_path_prefix(&prefix);
+            }
+        }
 
         Ok(SearchResult {
             files: results.files,
             total_found: results.total_found,
             query_time_ms: start.elapsed().as_millis() as u64,
             backend_name: self.name().to_string(),
+            truncated: results.truncated,
         })
     }
 
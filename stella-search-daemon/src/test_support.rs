@@ -0,0 +1,178 @@
+//! Test support: a temporary database and an IPC server to run clients against
+//!
+//! `TestDaemon` starts the real `IpcServer` on a socket (or pipe) of its own, backed
+//! by a fresh database in a temp directory. Nothing is scanned; tests seed the rows
+//! they need with `seed`.
+
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tempfile::TempDir;
+use tokio::task::JoinHandle;
+
+use crate::config::Config;
+use crate::database::{Database, FileMetadata};
+use crate::indexer::Indexer;
+use crate::ipc::{IpcClient, IpcServer};
+use crate::search::{BackendRegistry, SearchManager};
+
+/// Longest `TestDaemon::start` waits for the server to answer a ping
+const START_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Config whose database and socket live in `dir`
+pub fn test_config(dir: &TempDir) -> Config {
+    let mut config = Config {
+        config_path: dir.path().join("config.toml"),
+        db_path: dir.path().join("index.db"),
+        ..Config::default()
+    };
+    config.service.socket_path = Some(socket_name(dir));
+    config
+}
+
+/// Config watching only `roots`, which may live in the temp directory: the default
+/// `/tmp` exclusion is lifted and no free disk space is required
+pub fn watch_config(dir: &TempDir, roots: &[&Path]) -> Config {
+    let mut config = test_config(dir);
+    config.indexing.mode = "selected".to_string();
+    config.watch.include = roots.iter().map(|root| root.to_string_lossy().into_owned()).collect();
+    config.watch.exclude.clear();
+    config.performance.min_free_disk_mb = 0;
+    config
+}
+
+/// Directory of files for a test to index. `TempDir::new` names start with ".tmp",
+/// which the scanners skip as hidden.
+pub fn scan_root() -> TempDir {
+    tempfile::Builder::new().prefix("stella-test-").tempdir().expect("create scan root")
+}
+
+/// Create `files` (relative paths, parents included) under `root`
+pub fn create_files(root: &Path, files: &[&str]) {
+    for file in files {
+        let path = root.join(file);
+        std::fs::create_dir_all(path.parent().unwrap()).expect("create parent directory");
+        std::fs::write(&path, b"test").expect("create test file");
+    }
+}
+
+/// Socket path in `dir`, or on Windows a pipe name unique to it
+fn socket_name(dir: &TempDir) -> String {
+    #[cfg(windows)]
+    {
+        let id = dir.path().file_name().unwrap_or_default().to_string_lossy();
+        format!(r"\\.\pipe\stella-search-test-{}", id)
+    }
+
+    #[cfg(unix)]
+    {
+        dir.path().join("stella-search.sock").to_string_lossy().into_owned()
+    }
+}
+
+/// Database with the schema in place, opened from `config`
+pub fn test_database(config: &Config) -> Arc<Database> {
+    let db = Database::new(config).expect("open test database");
+    db.init_schema().expect("create test schema");
+    Arc::new(db)
+}
+
+/// Index rows for `paths`, stored exactly as given. A trailing separator marks a
+/// directory, so "C:\\Users\\" and "/home/" seed directories.
+pub fn seed(db: &Database, paths: &[&str]) {
+    let files: Vec<FileMetadata> = paths
+        .iter()
+        .map(|path| {
+            let is_directory = path.len() > 1 && path.ends_with(['/', '\\']);
+            let path = if is_directory { &path[..path.len() - 1] } else { path };
+            FileMetadata {
+                path: path.to_string(),
+                name: path.rsplit(['/', '\\']).next().unwrap_or(path).to_string(),
+                size: if is_directory { 0 } else { 100 },
+                is_directory,
+                modified: 1_700_000_000,
+                created: None,
+                is_symlink: false,
+            }
+        })
+        .collect();
+    db.batch_upsert_files_with_metadata(&files).expect("seed test database");
+}
+
+/// Indexer over a fresh database opened from `config`
+pub fn test_indexer(config: Config) -> Indexer {
+    let db = test_database(&config);
+    Indexer::new((*db).clone(), config)
+}
+
+/// IPC server over `db` with the SQLite backend, not yet listening
+pub fn test_server(db: &Arc<Database>, config: &Config) -> IpcServer {
+    let search_manager = Arc::new(
+        SearchManager::new(&config.search, &BackendRegistry::with_builtin(), db.clone(), &config.performance)
+            .expect("create search manager"),
+    );
+    let indexer = Indexer::new((**db).clone(), config.clone());
+    IpcServer::new(db.clone(), indexer, config.clone(), search_manager)
+}
+
+/// An `IpcServer` serving a temporary database until dropped
+pub struct TestDaemon {
+    pub db: Arc<Database>,
+    pub config: Config,
+    server: JoinHandle<()>,
+    // Removed last: the database and socket live in here
+    _dir: TempDir,
+}
+
+impl TestDaemon {
+    /// Start a server on a database seeded with `paths` (see `seed`)
+    pub async fn start(paths: &[&str]) -> Self {
+        Self::start_with(paths, |_| {}).await
+    }
+
+    /// Start a server, adjusting its config first
+    pub async fn start_with(paths: &[&str], configure: impl FnOnce(&mut Config)) -> Self {
+        let dir = TempDir::new().expect("create temp dir");
+        let mut config = test_config(&dir);
+        configure(&mut config);
+
+        let db = test_database(&config);
+        seed(&db, paths);
+
+        let server = test_server(&db, &config);
+        let server = tokio::spawn(async move {
+            if let Err(e) = server.run().await {
+                panic!("test IPC server failed: {:#}", e);
+            }
+        });
+
+        let daemon = Self { db, config, server, _dir: dir };
+        daemon.wait_until_ready().await;
+        daemon
+    }
+
+    /// Client connected to this server
+    pub async fn client(&self) -> IpcClient {
+        IpcClient::connect_to(self.config.get_socket_path())
+            .await
+            .expect("connect to test daemon")
+    }
+
+    /// Ping until the server answers
+    async fn wait_until_ready(&self) {
+        let client = self.client().await;
+        let deadline = tokio::time::Instant::now() + START_TIMEOUT;
+        while client.ping().await.is_err() {
+            assert!(!self.server.is_finished(), "test IPC server exited during startup");
+            assert!(tokio::time::Instant::now() < deadline, "test IPC server did not start");
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+}
+
+impl Drop for TestDaemon {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
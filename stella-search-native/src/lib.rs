@@ -5,6 +5,9 @@
 
 use std::ffi::{c_char, CStr, CString};
 use std::ptr;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use stella_search_core::{ResultSort, DEFAULT_MAX_RESULT_BYTES};
 
 #[cfg(windows)]
 mod windows_search;
@@ -12,6 +15,60 @@ mod windows_search;
 #[cfg(unix)]
 mod linux_search;
 
+/// Cap on the estimated size of one result set, see `stella_set_max_result_bytes`
+static MAX_RESULT_BYTES: AtomicU64 = AtomicU64::new(DEFAULT_MAX_RESULT_BYTES as u64);
+
+/// Current result size cap in bytes
+#[cfg(windows)]
+pub(crate) fn max_result_bytes() -> usize {
+    usize::try_from(MAX_RESULT_BYTES.load(Ordering::Relaxed)).unwrap_or(usize::MAX)
+}
+
+/// Cap the estimated serialized size of each result set, regardless of `max_results`.
+/// Once the cap is reached, collection stops and the JSON has `"truncated": true`.
+/// Pass 0 to restore the default (64 MiB).
+#[unsafe(no_mangle)]
+pub extern "C" fn stella_set_max_result_bytes(max_bytes: u64) {
+    let max_bytes = if max_bytes == 0 {
+        DEFAULT_MAX_RESULT_BYTES as u64
+    } else {
+        max_bytes
+    };
+    MAX_RESULT_BYTES.store(max_bytes, Ordering::Relaxed);
+}
+
+/// Bumped by `stella_cancel_all`; a search started under an older value is cancelled
+static CANCEL_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// Cancellation state of one search, taken when it starts
+#[cfg(windows)]
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct CancelToken {
+    generation: u64,
+}
+
+#[cfg(windows)]
+impl CancelToken {
+    pub(crate) fn new() -> Self {
+        Self {
+            generation: CANCEL_GENERATION.load(Ordering::Acquire),
+        }
+    }
+
+    /// Whether `stella_cancel_all` has run since this search started
+    pub(crate) fn should_stop(&self) -> bool {
+        CANCEL_GENERATION.load(Ordering::Acquire) != self.generation
+    }
+}
+
+/// Cancel every search currently running on any thread.
+/// Each stops before reading its next row, releases its COM objects and returns null.
+/// Searches started after this call are not affected.
+#[unsafe(no_mangle)]
+pub extern "C" fn stella_cancel_all() {
+    CANCEL_GENERATION.fetch_add(1, Ordering::AcqRel);
+}
+
 /// Check if system search is available.
 /// Returns 1 if available, 0 if not.
 #[unsafe(no_mangle)]
@@ -60,10 +117,246 @@ pub unsafe extern "C" fn stella_search(
     };
 
     #[cfg(windows)]
-    let result = windows_search::search(query_str, max_results, ext.as_deref());
+    let result = windows_search::search(query_str, max_results, ext.as_deref().as_slice(), None, ResultSort::Rank, None);
+
+    #[cfg(unix)]
+    let result = linux_search::search(query_str, max_results, ext.as_deref().as_slice(), None, ResultSort::Rank, None);
+
+    match result {
+        Ok(json) => match CString::new(json) {
+            Ok(cstr) => cstr.into_raw(),
+            Err(_) => ptr::null_mut(),
+        },
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Search for files matching the query on a single drive (e.g., "E:").
+/// Returns a JSON string that must be freed with stella_free.
+/// Returns null on error.
+///
+/// # Safety
+/// - `query` and `drive` must be valid null-terminated UTF-8 strings
+/// - `extension` can be null, otherwise must be a valid null-terminated UTF-8 string
+/// - Caller must free the returned pointer with stella_free
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn stella_search_drive(
+    query: *const c_char,
+    max_results: u32,
+    extension: *const c_char,
+    drive: *const c_char,
+) -> *mut c_char {
+    if query.is_null() || drive.is_null() {
+        return ptr::null_mut();
+    }
+
+    let (query_str, drive_str) = match (
+        unsafe { CStr::from_ptr(query) }.to_str(),
+        unsafe { CStr::from_ptr(drive) }.to_str(),
+    ) {
+        (Ok(q), Ok(d)) => (q, d),
+        _ => return ptr::null_mut(),
+    };
+
+    let ext = if extension.is_null() {
+        None
+    } else {
+        match unsafe { CStr::from_ptr(extension) }.to_str() {
+            Ok(s) => Some(s.to_string()),
+            Err(_) => None,
+        }
+    };
+
+    #[cfg(windows)]
+    let result = windows_search::search(query_str, max_results, ext.as_deref().as_slice(), Some(drive_str), ResultSort::Rank, None);
+
+    #[cfg(unix)]
+    let result = linux_search::search(query_str, max_results, ext.as_deref().as_slice(), Some(drive_str), ResultSort::Rank, None);
+
+    match result {
+        Ok(json) => match CString::new(json) {
+            Ok(cstr) => cstr.into_raw(),
+            Err(_) => ptr::null_mut(),
+        },
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Search for files matching the query, returned in the given order.
+/// `sort` is "rank" (index relevance, same as stella_search), "size" (largest first),
+/// "name" or "created" (newest first); null means "rank". Non-rank orders are applied locally while matches
+/// stream in, holding at most `max_results` rows.
+/// Returns a JSON string that must be freed with stella_free.
+/// Returns null on error or an unknown sort.
+///
+/// # Safety
+/// - `query` must be a valid null-terminated UTF-8 string
+/// - `extension`, `drive` and `sort` can be null, otherwise must be valid null-terminated UTF-8 strings
+/// - Caller must free the returned pointer with stella_free
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn stella_search_sorted(
+    query: *const c_char,
+    max_results: u32,
+    extension: *const c_char,
+    drive: *const c_char,
+    sort: *const c_char,
+) -> *mut c_char {
+    if query.is_null() {
+        return ptr::null_mut();
+    }
+
+    let query_str = match unsafe { CStr::from_ptr(query) }.to_str() {
+        Ok(s) => s,
+        Err(_) => return ptr::null_mut(),
+    };
+
+    let ext = unsafe { optional_str(extension) };
+    let drive_str = unsafe { optional_str(drive) };
+
+    let sort = match unsafe { optional_str(sort) } {
+        None => ResultSort::Rank,
+        Some(s) => match ResultSort::parse(s) {
+            Some(sort) => sort,
+            None => return ptr::null_mut(),
+        },
+    };
+
+    #[cfg(windows)]
+    let result = windows_search::search(query_str, max_results, ext.as_slice(), drive_str, sort, None);
+
+    #[cfg(unix)]
+    let result = linux_search::search(query_str, max_results, ext.as_slice(), drive_str, sort, None);
+
+    match result {
+        Ok(json) => match CString::new(json) {
+            Ok(cstr) => cstr.into_raw(),
+            Err(_) => ptr::null_mut(),
+        },
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Search for files matching the query with any of several extensions.
+/// `extensions` points to `ext_count` null-terminated UTF-8 strings (e.g. ".pdf");
+/// a zero count means no extension filter, and `extensions` may then be null.
+/// Returns a JSON string that must be freed with stella_free.
+/// Returns null on error, including a null or non-UTF-8 extension entry.
+///
+/// # Safety
+/// - `query` must be a valid null-terminated UTF-8 string
+/// - If `ext_count` is non-zero, `extensions` must point to `ext_count` readable pointers
+/// - Caller must free the returned pointer with stella_free
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn stella_search_multi(
+    query: *const c_char,
+    max_results: u32,
+    extensions: *const *const c_char,
+    ext_count: usize,
+) -> *mut c_char {
+    if query.is_null() || (ext_count > 0 && extensions.is_null()) {
+        return ptr::null_mut();
+    }
+
+    let query_str = match unsafe { CStr::from_ptr(query) }.to_str() {
+        Ok(s) => s,
+        Err(_) => return ptr::null_mut(),
+    };
+
+    let ext_ptrs = if ext_count == 0 {
+        &[][..]
+    } else {
+        unsafe { std::slice::from_raw_parts(extensions, ext_count) }
+    };
+    let mut exts = Vec::with_capacity(ext_count);
+    for &ext in ext_ptrs {
+        if ext.is_null() {
+            return ptr::null_mut();
+        }
+        match unsafe { CStr::from_ptr(ext) }.to_str() {
+            Ok(s) => exts.push(s),
+            Err(_) => return ptr::null_mut(),
+        }
+    }
+
+    #[cfg(windows)]
+    let result = windows_search::search(query_str, max_results, &exts, None, ResultSort::Rank, None);
+
+    #[cfg(unix)]
+    let result = linux_search::search(query_str, max_results, &exts, None, ResultSort::Rank, None);
+
+    match result {
+        Ok(json) => match CString::new(json) {
+            Ok(cstr) => cstr.into_raw(),
+            Err(_) => ptr::null_mut(),
+        },
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Search for files matching the query, with result paths made relative to a directory.
+/// Paths under `strip_prefix` lose that prefix (either separator matches, whole components
+/// only, ASCII case ignored on Windows) and carry the original in `full_path`; other paths are returned
+/// unchanged. Null `strip_prefix` behaves like stella_search.
+/// Returns a JSON string that must be freed with stella_free.
+/// Returns null on error.
+///
+/// # Safety
+/// - `query` must be a valid null-terminated UTF-8 string
+/// - `extension` and `strip_prefix` can be null, otherwise must be valid null-terminated UTF-8 strings
+/// - Caller must free the returned pointer with stella_free
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn stella_search_relative(
+    query: *const c_char,
+    max_results: u32,
+    extension: *const c_char,
+    strip_prefix: *const c_char,
+) -> *mut c_char {
+    if query.is_null() {
+        return ptr::null_mut();
+    }
+
+    let query_str = match unsafe { CStr::from_ptr(query) }.to_str() {
+        Ok(s) => s,
+        Err(_) => return ptr::null_mut(),
+    };
+
+    let ext = unsafe { optional_str(extension) };
+    let prefix = unsafe { optional_str(strip_prefix) };
+
+    #[cfg(windows)]
+    let result = windows_search::search(query_str, max_results, ext.as_slice(), None, ResultSort::Rank, prefix);
+
+    #[cfg(unix)]
+    let result = linux_search::search(query_str, max_results, ext.as_slice(), None, ResultSort::Rank, prefix);
+
+    match result {
+        Ok(json) => match CString::new(json) {
+            Ok(cstr) => cstr.into_raw(),
+            Err(_) => ptr::null_mut(),
+        },
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Optional C string argument: null and invalid UTF-8 are treated as absent
+unsafe fn optional_str<'a>(value: *const c_char) -> Option<&'a str> {
+    if value.is_null() {
+        None
+    } else {
+        unsafe { CStr::from_ptr(value) }.to_str().ok()
+    }
+}
+
+/// Get the most recently modified files, newest first.
+/// Returns a JSON string that must be freed with stella_free.
+/// Returns null on error.
+#[unsafe(no_mangle)]
+pub extern "C" fn stella_newest(max_results: u32) -> *mut c_char {
+    #[cfg(windows)]
+    let result = windows_search::newest(max_results);
 
     #[cfg(unix)]
-    let result = linux_search::search(query_str, max_results, ext.as_deref());
+    let result = linux_search::newest(max_results);
 
     match result {
         Ok(json) => match CString::new(json) {
@@ -3,6 +3,8 @@
 //! TODO: Implement Tracker3 SPARQL queries over D-Bus.
 //! For now, returns not available so daemon (SQLite) is used.
 
+use stella_search_core::ResultSort;
+
 /// Check if Tracker3 is available
 pub fn is_available() -> bool {
     // TODO: Check if Tracker3 is running via D-Bus
@@ -14,7 +16,15 @@ pub fn is_available() -> bool {
 pub fn search(
     _query: &str,
     _max_results: u32,
-    _extension: Option<&str>,
+    _extensions: &[&str],
+    _drive: Option<&str>,
+    _sort: ResultSort,
+    _strip_prefix: Option<&str>,
 ) -> Result<String, Box<dyn std::error::Error>> {
     Err("Tracker3 search not implemented yet".into())
 }
+
+/// Newest files using Tracker3 (placeholder)
+pub fn newest(_max_results: u32) -> Result<String, Box<dyn std::error::Error>> {
+    Err("Tracker3 search not implemented yet".into())
+}
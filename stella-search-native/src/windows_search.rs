@@ -3,7 +3,7 @@
 //! Queries the Windows Search Index using ADO via COM.
 //! No PowerShell, no process spawning, no window flashing.
 
-use stella_search_core::{IndexedFile, SearchResults};
+use stella_search_core::{IndexedFile, ResultSort, SearchResults, TopK};
 use std::time::Instant;
 use windows::{
     core::*,
@@ -12,6 +12,12 @@ use windows::{
     Win32::System::Services::*,
 };
 
+use crate::CancelToken;
+
+/// Columns read by `read_recordset`
+const SELECT_COLUMNS: &str =
+    "System.ItemPathDisplay, System.FileName, System.ItemType, System.Size, System.DateModified, System.DateCreated";
+
 // ADODB.Connection CLSID (not in windows-rs, define manually)
 const CLSID_ADODB_CONNECTION: GUID = GUID::from_u128(0x00000514_0000_0010_8000_00aa006d2ea4);
 
@@ -42,31 +48,68 @@ pub fn is_available() -> bool {
     }
 }
 
-/// Search for files using Windows Search via direct COM
+/// Search for files using Windows Search via direct COM.
+/// `ResultSort::Rank` keeps the index's own ranking; other orders read every match
+/// and keep the top `max_results` locally.
 pub fn search(
     query: &str,
     max_results: u32,
-    extension: Option<&str>,
+    extensions: &[&str],
+    drive: Option<&str>,
+    sort: ResultSort,
+    strip_prefix: Option<&str>,
 ) -> std::result::Result<String, Box<dyn std::error::Error + Send + Sync>> {
     let start = Instant::now();
+    let cancel = CancelToken::new();
+
+    let sql = build_search_sql(query, max_results, extensions, drive, sort);
+    let (mut files, truncated) = unsafe { search_via_com(&sql, sort, max_results as usize, cancel)? };
+
+    if let Some(prefix) = strip_prefix {
+        for file in &mut files {
+            file.strip_path_prefix(prefix);
+        }
+    }
+
+    let search_results = SearchResults {
+        total_found: files.len(),
+        files,
+        query_time_ms: start.elapsed().as_millis() as u64,
+        truncated,
+    };
+
+    Ok(serde_json::to_string(&search_results)?)
+}
+
+/// Get the most recently modified files using Windows Search via direct COM
+pub fn newest(max_results: u32) -> std::result::Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    let start = Instant::now();
+    let cancel = CancelToken::new();
 
-    let files = unsafe { search_via_com(query, max_results, extension)? };
+    let sql = build_newest_sql(max_results);
+    let (files, truncated) = unsafe { search_via_com(&sql, ResultSort::Rank, max_results as usize, cancel)? };
 
     let search_results = SearchResults {
-        files: files.clone(),
         total_found: files.len(),
+        files,
         query_time_ms: start.elapsed().as_millis() as u64,
+        truncated,
     };
 
     Ok(serde_json::to_string(&search_results)?)
 }
 
-/// Execute the COM-based search
+/// Execute a SystemIndex query via COM.
+/// Rows are kept in recordset order for `ResultSort::Rank`, otherwise the best `limit`
+/// under `sort` are kept as they stream in.
+/// Returns the files and whether the result size cap cut them short, or an error once
+/// `cancel` is set.
 unsafe fn search_via_com(
-    query: &str,
-    max_results: u32,
-    extension: Option<&str>,
-) -> std::result::Result<Vec<IndexedFile>, Box<dyn std::error::Error + Send + Sync>> {
+    sql: &str,
+    sort: ResultSort,
+    limit: usize,
+    cancel: CancelToken,
+) -> std::result::Result<(Vec<IndexedFile>, bool), Box<dyn std::error::Error + Send + Sync>> {
     // Initialize COM (apartment-threaded for ADO)
     let _com = ComInitializer::new()?;
 
@@ -77,22 +120,51 @@ unsafe fn search_via_com(
     let conn_string = "Provider=Search.CollatorDSO;Extended Properties='Application=Windows'";
     unsafe { invoke_method(&conn, "Open", &[VARIANT::from(conn_string)])? };
 
-    // Build and execute SQL query
-    let sql = build_search_sql(query, max_results, extension);
-    let rs_variant = unsafe { invoke_method(&conn, "Execute", &[VARIANT::from(sql.as_str())])? };
+    // Execute SQL query (blocking; cancellation is only seen between rows)
+    if cancel.should_stop() {
+        let _ = unsafe { invoke_method(&conn, "Close", &[]) };
+        return Err("Search cancelled".into());
+    }
+    let rs_variant = unsafe { invoke_method(&conn, "Execute", &[VARIANT::from(sql)])? };
 
     // Get IDispatch for recordset
     let rs: IDispatch = IDispatch::try_from(&rs_variant)
         .map_err(|e| format!("Failed to get recordset IDispatch: {}", e))?;
 
     // Read results
-    let files = unsafe { read_recordset(&rs)? };
+    let max_bytes = crate::max_result_bytes();
+    let result = if sort == ResultSort::Rank {
+        let mut files = Vec::new();
+        let mut total_bytes = 0usize;
+        let mut truncated = false;
+        unsafe {
+            read_recordset(&rs, cancel, |file| {
+                total_bytes += file.estimated_size();
+                if total_bytes > max_bytes {
+                    truncated = true;
+                    return false;
+                }
+                files.push(file);
+                true
+            })
+        }
+        .map(|()| (files, truncated))
+    } else {
+        let mut top = TopK::new(sort, limit, max_bytes);
+        unsafe {
+            read_recordset(&rs, cancel, |file| {
+                top.push(file);
+                true
+            })
+        }
+        .map(|()| top.into_sorted())
+    };
 
-    // Close recordset and connection (ignore errors)
+    // Close recordset and connection (ignore errors), also after a failed or cancelled read
     let _ = unsafe { invoke_method(&rs, "Close", &[]) };
     let _ = unsafe { invoke_method(&conn, "Close", &[]) };
 
-    Ok(files)
+    result
 }
 
 /// RAII wrapper for COM initialization
@@ -213,13 +285,18 @@ unsafe fn get_property(disp: &IDispatch, name: &str) -> windows::core::Result<VA
     Ok(result)
 }
 
-/// Read recordset rows into IndexedFile vec
+/// Stream recordset rows as IndexedFiles to `on_row`, which returns false to stop early.
+/// Fails once `cancel` is set.
 unsafe fn read_recordset(
     rs: &IDispatch,
-) -> std::result::Result<Vec<IndexedFile>, Box<dyn std::error::Error + Send + Sync>> {
-    let mut files = Vec::new();
-
+    cancel: CancelToken,
+    mut on_row: impl FnMut(IndexedFile) -> bool,
+) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
     loop {
+        if cancel.should_stop() {
+            return Err("Search cancelled".into());
+        }
+
         // Check EOF
         let eof_variant = unsafe { get_property(rs, "EOF")? };
         // Try to convert to bool - if it fails or is true, we're done
@@ -238,6 +315,8 @@ unsafe fn read_recordset(
         let name = unsafe { get_field_string(&fields, "System.FileName").unwrap_or_default() };
         let item_type = unsafe { get_field_string(&fields, "System.ItemType").ok() };
         let size = unsafe { get_field_i64(&fields, "System.Size").unwrap_or(0) };
+        let modified = unsafe { get_field_unix_time(&fields, "System.DateModified").unwrap_or(0) };
+        let created = unsafe { get_field_unix_time(&fields, "System.DateCreated").ok() };
 
         // Skip if path is empty
         if !path.is_empty() {
@@ -246,21 +325,31 @@ unsafe fn read_recordset(
                 .map(|t| t == "Directory" || t == "Folder" || t.is_empty())
                 .unwrap_or(true);
 
-            files.push(IndexedFile {
+            let file = IndexedFile {
                 id: 0,
                 path,
                 name,
                 extension: if is_dir { None } else { item_type },
                 size,
                 is_directory: is_dir,
-            });
+                modified,
+                created,
+                full_path: None,
+                target: None,
+                exists: None,
+                volume: None,
+            };
+
+            if !on_row(file) {
+                return Ok(());
+            }
         }
 
         // MoveNext
         unsafe { invoke_method(rs, "MoveNext", &[])? };
     }
 
-    Ok(files)
+    Ok(())
 }
 
 /// Get field value as string
@@ -324,21 +413,116 @@ unsafe fn get_field_i64(
     Ok(0)
 }
 
-/// Build SQL query for Windows Search SystemIndex
-fn build_search_sql(query: &str, max_results: u32, extension: Option<&str>) -> String {
+/// Get a VT_DATE field value as Unix seconds
+unsafe fn get_field_unix_time(
+    fields: &IDispatch,
+    field_name: &str,
+) -> std::result::Result<i64, Box<dyn std::error::Error + Send + Sync>> {
+    let field_variant = unsafe { invoke_method(fields, "Item", &[VARIANT::from(field_name)])? };
+    let field: IDispatch = IDispatch::try_from(&field_variant)
+        .map_err(|e| format!("Failed to get field IDispatch: {}", e))?;
+    let value = unsafe { get_property(&field, "Value")? };
+
+    // OLE automation date: days since 1899-12-30, Unix epoch is day 25569
+    let ole_date = f64::try_from(&value)?;
+    Ok(((ole_date - 25569.0) * 86400.0) as i64)
+}
+
+/// Build SQL query for Windows Search SystemIndex.
+/// Rank order comes from the index itself (`System.Search.Rank`) and is capped with TOP;
+/// for local sorts every match is streamed unordered, since the best rows by size or
+/// name may sit anywhere in the match set.
+fn build_search_sql(
+    query: &str,
+    max_results: u32,
+    extensions: &[&str],
+    drive: Option<&str>,
+    sort: ResultSort,
+) -> String {
     let mut conditions = Vec::new();
-    let escaped_query = query.replace('\'', "''");
-    conditions.push(format!("System.FileName LIKE '%{}%'", escaped_query));
+    conditions.push(format!("System.FileName LIKE '%{}%'", escape_like(query)));
+
+    // Any of the extensions (SystemIndex SQL has no IN, so OR the comparisons)
+    if !extensions.is_empty() {
+        let any_of: Vec<String> = extensions
+            .iter()
+            .map(|ext| format!("System.ItemType = '{}'", ext.replace('\'', "''")))
+            .collect();
+        conditions.push(format!("({})", any_of.join(" OR ")));
+    }
+
+    if let Some(drive) = drive {
+        conditions.push(format!("SCOPE = '{}'", drive_scope(drive)));
+    }
 
-    if let Some(ext) = extension {
-        let escaped_ext = ext.replace('\'', "''");
-        conditions.push(format!("System.ItemType = '{}'", escaped_ext));
+    if sort != ResultSort::Rank {
+        return format!("SELECT {} FROM SystemIndex WHERE {}", SELECT_COLUMNS, conditions.join(" AND "));
     }
 
     format!(
-        "SELECT TOP {} System.ItemPathDisplay, System.FileName, System.ItemType, System.Size \
-         FROM SystemIndex WHERE {} ORDER BY System.Search.Rank DESC",
+        "SELECT TOP {} {} FROM SystemIndex WHERE {} ORDER BY System.Search.Rank DESC, System.ItemPathDisplay",
         max_results,
+        SELECT_COLUMNS,
         conditions.join(" AND ")
     )
 }
+
+/// Build SQL query for the most recently modified files (no search term)
+fn build_newest_sql(max_results: u32) -> String {
+    format!(
+        "SELECT TOP {} {} FROM SystemIndex WHERE System.ItemType <> 'Directory' \
+         ORDER BY System.DateModified DESC, System.ItemPathDisplay",
+        max_results, SELECT_COLUMNS
+    )
+}
+
+/// SCOPE URL for a drive filter: `E:`, `e:\` and `E:/` all become `file:E:/`
+fn drive_scope(drive: &str) -> String {
+    let drive = drive.trim().replace('\\', "/").replace('\'', "''");
+    let root = drive.trim_end_matches('/');
+    let root = if root.len() == 2 && root.ends_with(':') {
+        root.to_ascii_uppercase()
+    } else {
+        root.to_string()
+    };
+    format!("file:{}/", root)
+}
+
+/// Escape a user string for use inside a Windows Search `LIKE '...'` literal.
+/// The wildcards `%`, `_` and `[` are wrapped in brackets so SystemIndex matches
+/// them literally (e.g. "50%_off" no longer matches "50Xoff"); single quotes are doubled.
+fn escape_like(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '[' => escaped.push_str("[[]"),
+            '%' => escaped.push_str("[%]"),
+            '_' => escaped.push_str("[_]"),
+            '\'' => escaped.push_str("''"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn like_wildcards_are_matched_literally() {
+        assert_eq!(escape_like("50%_off"), "50[%][_]off");
+        assert_eq!(escape_like("[draft]"), "[[]draft]");
+        assert_eq!(escape_like("bob's"), "bob''s");
+        assert_eq!(escape_like("report"), "report");
+    }
+
+    #[test]
+    fn search_sql_escapes_the_file_name_pattern() {
+        let sql = build_search_sql("50%_off", 10, &[], None, ResultSort::Rank);
+        assert!(sql.contains("System.FileName LIKE '%50[%][_]off%'"), "{}", sql);
+
+        let sql = build_search_sql("it's", 10, &[], None, ResultSort::Rank);
+        assert!(sql.contains("System.FileName LIKE '%it''s%'"), "{}", sql);
+    }
+}